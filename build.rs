@@ -13,11 +13,16 @@ fn main() {
     generated_code.push_str("pub fn get_error_docs() -> HashMap<&'static str, &'static str> {\n");
     generated_code.push_str("    let mut docs = HashMap::new();\n");
 
-    // Iterate over all .md files in the error_codes folder
-    for entry in fs::read_dir(error_codes_dir).expect("Failed to read error_codes directory") {
-        let entry = entry.expect("Failed to read directory entry");
-        let path = entry.path();
+    // Collect all files in the error_codes folder, sorted so that the generated file does not
+    // depend on the order the file system lists them in
+    let mut paths: Vec<_> = fs::read_dir(error_codes_dir)
+        .expect("Failed to read error_codes directory")
+        .map(|entry| entry.expect("Failed to read directory entry").path())
+        .collect();
+    paths.sort();
 
+    // Iterate over all .md files in the error_codes folder
+    for path in paths {
         // Only process .md files
         if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("md") {
             // Get the file stem (e.g., "E0001" from "E0001.md")
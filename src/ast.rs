@@ -13,7 +13,10 @@ pub use token::{
     BinOpToken, CommentKind, Delimiter, DocStyle, IdentIsRaw, Lit, LitKind, Token, TokenKind,
 };
 
-use crate::span_encoding::{Span, Spanned};
+use crate::{
+    kw::{self, Keyword},
+    span_encoding::{Span, Spanned},
+};
 
 #[derive(Debug)]
 pub struct Ast {
@@ -54,8 +57,14 @@ pub enum StmtKind {
     While(Box<Expr>, Box<Stmt>),
     /// A for loop: 'for' ident 'in' expr block_stmt
     For(Ident, Box<Expr>, Box<Stmt>),
-    /// An import statement: 'import' ident ';'
-    Import(Ident),
+    /// An import statement: 'import' ident ('.' ident)* ('as' ident | '.' '{' ident (',' ident)* '}')? ';'
+    Import(Box<Import>),
+    /// A struct declaration: 'struct' ident '{' (ident ':' type ','?)* '}'
+    StructDecl(Box<StructDef>),
+    /// An enum declaration: 'enum' ident '{' (ident ('(' type (',' type)* ')')? ','?)* '}'
+    EnumDecl(Box<EnumDef>),
+    /// A match statement: 'match' expr '{' (pattern '=>' block_stmt ','?)* '}'
+    Match(Box<Expr>, Vec<Arm>),
     /// An empty statement: ';'.
     Empty,
 }
@@ -80,6 +89,28 @@ impl Display for Ty {
             TyKind::Named(ident) => {
                 write!(f, "{}", ident.name)
             }
+            TyKind::Tuple(tys) => {
+                let tys = tys.iter().map(|ty| ty.to_string()).collect::<Vec<_>>();
+                if tys.len() == 1 {
+                    write!(f, "({},)", tys[0])
+                } else {
+                    write!(f, "({})", tys.join(", "))
+                }
+            }
+            TyKind::Fun(inputs, output) => {
+                let inputs = inputs
+                    .iter()
+                    .map(|(ty, is_mut)| match is_mut {
+                        true => format!("{} {}", kw::to_string(Keyword::Mut), ty),
+                        false => ty.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                let fun = kw::to_string(Keyword::Fun);
+                match output {
+                    Some(output) => write!(f, "{}({}) -> {}", fun, inputs.join(", "), output),
+                    None => write!(f, "{}({})", fun, inputs.join(", ")),
+                }
+            }
         }
     }
 }
@@ -92,18 +123,57 @@ pub enum TyKind {
     Array(Box<Ty>, Option<Box<Expr>>),
     /// A named type.
     Named(Ident),
+    /// A tuple type.
+    ///
+    /// E.g., `(int, str)`. The empty tuple `()` is the unit type.
+    Tuple(Vec<Ty>),
+    /// A function type.
+    ///
+    /// E.g., `fun(int, int) -> int`. Without a return type, the function returns `()`. Each
+    /// parameter comes with whether it is `mut`, e.g. `fun(mut [int])`.
+    Fun(Vec<(Ty, bool)>, Option<Box<Ty>>),
 }
 
-/// Local represents a `var` statement. e.g. `var mut <ident>:<ty> = <expr>;`.
+/// Local represents a `var` statement. e.g. `var mut <pat>:<ty> = <expr>;`.
 #[derive(Debug, Clone)]
 pub struct Local {
     pub is_mut: bool,
-    pub ident: Ident,
-    pub ty: Ty,
+    pub pat: Pat,
+    /// The type annotation. It can only be omitted when destructuring a tuple.
+    pub ty: Option<Ty>,
     pub kind: LocalKind,
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct Pat {
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum PatKind {
+    /// A wildcard pattern (`_`).
+    Wild,
+    /// A binding (e.g. `x`).
+    Ident(Ident),
+    /// A tuple pattern (e.g. `(a, b)`).
+    Tuple(Vec<Pat>),
+    /// A literal pattern (e.g. `1`, `-1`, `"foo"`, `true`).
+    Lit(Box<Expr>),
+    /// An enum variant pattern (e.g. `Shape::Circle(r)` or `Shape::Empty`).
+    Variant(Ident, Ident, Vec<Pat>),
+}
+
+/// An arm of a `match` statement.
+/// E.g., `Shape::Circle(r) => { ... }`.
+#[derive(Debug, Clone)]
+pub struct Arm {
+    pub pat: Pat,
+    pub body: Box<Stmt>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum LocalKind {
     /// Local declaration.
@@ -157,6 +227,47 @@ pub enum ExprKind {
     /// E.g., `[1; 5]`. The left expression is the element to be
     /// repeated; the right expression is the number of times to repeat it.
     Repeat(Box<Expr>, Box<Expr>),
+    /// A range (e.g., `0..n` or `0..=n`).
+    Range(Box<Expr>, Box<Expr>, RangeLimits),
+    /// A tuple (e.g., `(a, b, c)`). The empty tuple `()` is the unit value.
+    Tuple(Vec<Box<Expr>>),
+    /// Access of a tuple field (e.g., `foo.0`).
+    /// The span represents the span of the field index.
+    TupleField(Box<Expr>, usize, Span),
+    /// A struct literal (e.g., `Point { x: 1, y: 2 }`).
+    Struct(Ident, Vec<ExprField>),
+    /// An enum variant (e.g., `Shape::Circle(1.0)` or `Shape::Empty`).
+    /// The first field is the enum, the second one is the variant.
+    Variant(Ident, Ident, Vec<Box<Expr>>),
+    /// An anonymous function (e.g., `fun(x: int) -> int { yeet x + 1; }`).
+    Closure(Box<Closure>),
+}
+
+/// Whether a range includes its end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeLimits {
+    /// `..`, the end is excluded.
+    HalfOpen,
+    /// `..=`, the end is included.
+    Closed,
+}
+
+impl Display for RangeLimits {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RangeLimits::HalfOpen => write!(f, ".."),
+            RangeLimits::Closed => write!(f, "..="),
+        }
+    }
+}
+
+/// A field in a struct literal.
+/// E.g., `x: 1` as in `Point { x: 1, y: 2 }`.
+#[derive(Debug, Clone)]
+pub struct ExprField {
+    pub ident: Ident,
+    pub expr: Box<Expr>,
+    pub span: Span,
 }
 
 impl Display for Expr {
@@ -298,9 +409,45 @@ impl BinOpKind {
     }
 }
 
+/// The library added by an import statement.
+/// E.g., `utils.strings` as in `add utils.strings;`, for the file `utils/strings.boxx`.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: Vec<Ident>,
+    pub kind: ImportKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportKind {
+    /// `add geometry;`, used as `geometry`.
+    Library,
+    /// `add math as m;`, used as `m`.
+    Alias(Ident),
+    /// `add geometry.{area, perimeter};`, whose functions are called without the library name.
+    Select(Vec<Ident>),
+}
+
+impl Import {
+    /// Returns the name the library is used with: its alias, or the last segment of its path.
+    pub fn name(&self) -> &Ident {
+        match &self.kind {
+            ImportKind::Alias(alias) => alias,
+            _ => self.path.last().expect("an import path is never empty"),
+        }
+    }
+
+    /// Returns the segments of the path, e.g. `["utils", "strings"]`.
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.iter().map(|ident| ident.name.as_str()).collect()
+    }
+}
+
 /// A function definition.
 #[derive(Debug, Clone)]
 pub struct Fun {
+    /// Whether the function is hidden from the programs adding the library it is declared in.
+    pub is_private: bool,
     pub sig: FunSig,
     pub body: Box<Stmt>,
 }
@@ -315,6 +462,48 @@ pub struct FunSig {
     pub span: Span,
 }
 
+/// An anonymous function, which captures the variables of the scope it is created in.
+#[derive(Debug, Clone)]
+pub struct Closure {
+    pub inputs: Vec<FunParam>,
+    pub output: Option<Ty>,
+    pub body: Box<Stmt>,
+    /// The span of the signature, from `fun` to the return type.
+    pub span: Span,
+}
+
+/// A struct definition.
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: Ident,
+    pub fields: Vec<FieldDef>,
+}
+
+/// An enum definition.
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: Ident,
+    pub variants: Vec<VariantDef>,
+}
+
+/// A variant in an enum definition.
+/// E.g., `Circle(float)` as in `enum Shape { Circle(float), Empty }`.
+#[derive(Debug, Clone)]
+pub struct VariantDef {
+    pub ident: Ident,
+    pub fields: Vec<Ty>,
+    pub span: Span,
+}
+
+/// A field in a struct definition.
+/// E.g., `x: int` as in `struct Point { x: int, y: int }`.
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub ident: Ident,
+    pub ty: Ty,
+    pub span: Span,
+}
+
 /// A parameter in a function header.
 /// E.g., `bar: usize` as in `fn foo(bar: usize)`.
 #[derive(Debug, Clone)]
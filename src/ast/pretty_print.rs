@@ -1,131 +1,618 @@
-use super::{Expr, ExprKind, Fun, FunSig, Ident, Local, LocalKind, Stmt, Ty};
-use crate::visitor::Visitor;
+//! Prints the syntax tree back as source code, in the canonical layout used by `unbox fmt`.
+//!
+//! The syntax tree does not hold comments, so they are read from the source code again and
+//! put back between the statements they were found between. A comment inside a statement
+//! stays before the expression or parameter that follows it if it is a block comment on one
+//! line. Otherwise it cannot be put back there, so it is moved to the end of the line of the
+//! statement, or to the end of the line that opens its block, e.g. after `{` in a function.
+//! Literals and identifiers are copied from the source code, so that e.g. the base of a number
+//! or an escape in a string is kept, and keywords are printed in the dialect in use.
+
+use super::{
+    Arm, BinOpKind, EnumDef, Expr, ExprKind, Fun, FunParam, FunSig, Ident, Import, ImportKind,
+    Local, LocalKind, Pat, PatKind, Stmt, StmtKind, StructDef, Ty, TyKind, UnOp,
+};
+use crate::{
+    kw::{self, Keyword},
+    lexer::{self, Cursor},
+    span_encoding::Span,
+    visitor::Visitor,
+};
+
+/// The precedence of an assignment, which is the lowest one.
+const PREC_ASSIGN: usize = 1;
+/// The precedence of a range, which binds looser than every binary operator.
+const PREC_RANGE: usize = 2;
+/// The precedence of a cast, which binds tighter than every binary operator.
+const PREC_CAST: usize = 12;
+const PREC_PREFIX: usize = 13;
+/// The precedence of calls, indexing and field accesses.
+const PREC_POSTFIX: usize = 14;
+/// The precedence of literals, identifiers and every expression with delimiters around it.
+const PREC_ATOM: usize = 15;
+
+struct Comment {
+    start: usize,
+    end: usize,
+}
 
-pub struct Printer {
+pub struct Printer<'src> {
     pub output: String,
     pub indent: usize,
     pub indent_spaces: usize,
+    src: &'src str,
+    /// The comments of the source code, in order.
+    comments: Vec<Comment>,
+    /// The index of the first comment not printed yet.
+    next_comment: usize,
+    /// The end of the last statement or comment printed, in the source code.
+    last_end: usize,
 }
 
-impl Printer {
-    pub fn new() -> Self {
+impl<'src> Printer<'src> {
+    pub fn new(src: &'src str) -> Self {
         Printer {
             output: String::new(),
             indent: 0,
-            indent_spaces: 2,
+            indent_spaces: 4,
+            src,
+            comments: collect_comments(src),
+            next_comment: 0,
+            last_end: 0,
         }
     }
 
-    pub fn print_stmts(&mut self, stmts: &Vec<Box<Stmt>>) {
+    /// Prints the statements of a whole file, followed by the comments after them.
+    pub fn print_file(mut self, stmts: &Vec<Box<Stmt>>) -> String {
+        self.print_stmts(stmts, self.src.len());
+        self.print_comments_before(self.src.len(), true);
+        self.output
+    }
+
+    /// Prints each statement on its own line, with the comments before each of them, and then
+    /// the comments before `end`. At most one blank line between two statements is kept.
+    pub fn print_stmts(&mut self, stmts: &Vec<Box<Stmt>>, end: usize) {
+        let mut is_first = true;
         for stmt in stmts {
-            self.print_stmt(&stmt);
+            // A lone `;` does nothing.
+            if let StmtKind::Empty = stmt.kind {
+                continue;
+            }
+
+            let start = stmt.span.offset as usize;
+            is_first = self.print_comments_before(start, is_first);
+            self.start_line(start, is_first);
+            is_first = false;
+
+            self.print_stmt(stmt);
+            self.last_end = self.last_end.max(span_end(stmt.span));
+            self.print_trailing_comments();
+            self.output.push('\n');
         }
+        self.print_comments_before(end, is_first);
     }
 
     pub fn print_stmt(&mut self, stmt: &Stmt) {
         self.visit_stmt(stmt);
     }
-}
 
-impl<'ast> Visitor<'ast> for Printer {
-    fn visit_stmt_import(&mut self, ident: &'ast Ident) {
-        self.output.push_str(&format!(
-            "{}Import statement: {}\n",
-            space(self.indent),
-            ident.span
-        ));
+    /// Prints the comments starting before `pos`, each on its own line. Returns whether the
+    /// next line is still the first one of its block.
+    fn print_comments_before(&mut self, pos: usize, mut is_first: bool) -> bool {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.start >= pos {
+                break;
+            }
+
+            let (start, end) = (comment.start, comment.end);
+            self.next_comment += 1;
+            self.start_line(start, is_first);
+            is_first = false;
+            self.output.push_str(self.src[start..end].trim_end());
+            self.output.push('\n');
+            self.last_end = end;
+        }
+        is_first
+    }
+
+    /// Prints the comments on the same line as the end of the last statement, and the ones
+    /// inside of it.
+    fn print_trailing_comments(&mut self) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let between = &self.src[self.last_end.min(comment.start)..comment.start];
+            if between.contains('\n') {
+                break;
+            }
+
+            self.output.push(' ');
+            self.output
+                .push_str(self.src[comment.start..comment.end].trim_end());
+            // A comment may be inside the statement, which ends after it.
+            self.last_end = self.last_end.max(comment.end);
+            self.next_comment += 1;
+        }
+    }
+
+    /// Prints the comments starting before `pos`, which is in the middle of a line, at the end
+    /// of the output. With `is_inline`, only the block comments on one line are printed, each
+    /// followed by a space, since the code after `pos` goes on the same line. Returns whether a
+    /// comment was printed.
+    fn print_comments_in_line(&mut self, pos: usize, is_inline: bool) -> bool {
+        let mut is_printed = false;
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let text = self.src[comment.start..comment.end].trim_end();
+            if comment.start >= pos || (is_inline && !is_inline_comment(text)) {
+                break;
+            }
+
+            if is_inline {
+                self.output.push_str(text);
+                self.output.push(' ');
+            } else {
+                self.output.push(' ');
+                self.output.push_str(text);
+            }
+            self.last_end = self.last_end.max(comment.end);
+            self.next_comment += 1;
+            is_printed = true;
+        }
+        is_printed
+    }
+
+    /// Indents a new line, after a blank line if there is one in the source code between the
+    /// last printed line and `pos`.
+    fn start_line(&mut self, pos: usize, is_first: bool) {
+        let between = &self.src[self.last_end.min(pos)..pos];
+        if !is_first && between.matches('\n').count() > 1 {
+            self.output.push('\n');
+        }
+        self.output.push_str(&" ".repeat(self.indent));
+    }
+
+    fn print_block(&mut self, stmt: &Stmt) {
+        let stmts = match &stmt.kind {
+            StmtKind::Block(stmts) => stmts,
+            // A statement is parsed where a block is expected, e.g. after the condition of
+            // `when`, and reported as an error only when the program runs.
+            _ => return self.visit_stmt(stmt),
+        };
+
+        // The span of a block ends with its closing brace.
+        let end = span_end(stmt.span).saturating_sub(1);
+        self.output.push('{');
+        // The comments left in the code before the block, e.g. in the parameters of a
+        // function, end the line of its brace, with the ones after the brace on that line.
+        let first_comment = self.next_comment;
+        self.print_comments_in_line(stmt.span.offset as usize, false);
+        self.last_end = self.last_end.max(stmt.span.offset as usize + 1);
+        self.print_trailing_comments();
+        let has_comment = self.next_comment > first_comment;
+        if stmts.is_empty() && !has_comment && !self.has_comment_before(end) {
+            self.output.push('}');
+            return;
+        }
+
+        self.output.push('\n');
         self.indent += self.indent_spaces;
-        self.output.push_str(&format!(
-            "{}Library name: {} {}\n",
-            space(self.indent),
-            ident.name,
-            ident.span
-        ));
+        self.print_stmts(stmts, end);
         self.indent -= self.indent_spaces;
+        self.output.push_str(&" ".repeat(self.indent));
+        self.output.push('}');
     }
 
-    fn visit_stmt_func_decl(&mut self, fun: &'ast Fun) {
-        let Fun { sig, body } = fun;
+    fn has_comment_before(&self, pos: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.start < pos)
+    }
+
+    fn print_keyword(&mut self, keyword: Keyword) {
+        self.output.push_str(&kw::to_string(keyword));
+    }
+
+    /// Prints the text of a span as it is in the source code.
+    fn print_source(&mut self, span: Span) {
+        self.output.push_str(&self.src[span_range(span)]);
+    }
+
+    /// Prints an identifier as it is in the source code, e.g. with the `r#` of a raw
+    /// identifier.
+    fn print_ident(&mut self, ident: &Ident) {
+        let name = ident.name.as_str();
+        match self.src.get(span_range(ident.span)) {
+            Some(text) if text.ends_with(name) => self.output.push_str(text),
+            _ => self.output.push_str(name),
+        }
+    }
+
+    /// Prints the signature of a function, e.g. `fun add(a: int, b: int) -> int`.
+    pub fn print_fun_sig(&mut self, sig: &FunSig) {
         let FunSig {
             name,
             inputs,
             output,
-            span,
+            ..
         } = sig;
 
-        self.output.push_str(&format!(
-            "{}Function declaration: {}\n",
-            space(self.indent),
-            span
-        ));
-        self.indent += self.indent_spaces;
-
-        self.output.push_str(&format!(
-            "{}Name: {} {}\n",
-            space(self.indent),
-            name.name,
-            name.span
-        ));
+        self.print_keyword(Keyword::Fun);
+        self.output.push(' ');
+        self.print_ident(name);
+        self.print_fun_params(inputs);
+        self.print_fun_output(output);
+    }
 
-        self.output
-            .push_str(&format!("{}Inputs:\n", space(self.indent)));
-        self.indent += self.indent_spaces;
-        for input in inputs {
-            self.visit_ty(&input.ty);
+    fn print_fun_params(&mut self, inputs: &Vec<FunParam>) {
+        self.output.push('(');
+        for (i, param) in inputs.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.print_comments_in_line(param.span.offset as usize, true);
+            if param.is_mut {
+                self.print_keyword(Keyword::Mut);
+                self.output.push(' ');
+            }
+            self.print_ident(&param.ident);
+            self.output.push_str(": ");
+            self.visit_ty(&param.ty);
         }
-        self.indent -= self.indent_spaces;
+        self.output.push(')');
+    }
 
+    fn print_fun_output(&mut self, output: &Option<Ty>) {
         if let Some(output) = output {
-            self.output
-                .push_str(&format!("{}Output:\n", space(self.indent)));
-            self.indent += self.indent_spaces;
+            self.output.push_str(" -> ");
             self.visit_ty(output);
-            self.indent -= self.indent_spaces;
         }
+    }
 
-        self.output
-            .push_str(&format!("{}Body:\n", space(self.indent)));
-        self.indent += self.indent_spaces;
-        self.visit_stmt(body);
-        self.indent -= self.indent_spaces;
+    /// Prints an expression, with parentheses around it if it binds looser than `min_prec`.
+    fn print_expr(&mut self, expr: &Expr, min_prec: usize) {
+        self.print_comments_in_line(expr.span.offset as usize, true);
+        let needs_parens = precedence(expr) < min_prec;
+        if needs_parens {
+            self.output.push('(');
+        }
+
+        match &expr.kind {
+            ExprKind::Binary(op, lhs, rhs) => {
+                let prec = binop_precedence(&op.node);
+                self.print_expr(lhs, prec);
+                self.output.push_str(&format!(" {} ", op.node));
+                self.print_expr(rhs, prec + 1);
+            }
+            ExprKind::Unary(op, operand) => {
+                self.output.push_str(&op.to_string());
+                // `--x` would be lexed as one token.
+                let is_double_minus = matches!(
+                    (op, &operand.kind),
+                    (UnOp::Ne, ExprKind::Unary(UnOp::Ne, _))
+                );
+                let prec = if is_double_minus {
+                    PREC_ATOM
+                } else {
+                    PREC_PREFIX
+                };
+                self.print_expr(operand, prec);
+            }
+            ExprKind::Literal(_) => self.print_source(expr.span),
+            ExprKind::Assign(lhs, rhs, _) => {
+                self.print_expr(lhs, PREC_ASSIGN + 1);
+                self.output.push_str(" = ");
+                self.print_expr(rhs, PREC_ASSIGN);
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                self.print_expr(lhs, PREC_ASSIGN + 1);
+                self.output.push_str(&format!(" {}= ", op.node));
+                self.print_expr(rhs, PREC_ASSIGN);
+            }
+            ExprKind::Identifier(ident) => self.print_ident(ident),
+            ExprKind::Cast(operand, ty) => {
+                self.print_expr(operand, PREC_CAST);
+                self.output.push(' ');
+                self.print_keyword(Keyword::As);
+                self.output.push(' ');
+                self.visit_ty(ty);
+            }
+            ExprKind::FunCall(fun, args) | ExprKind::LibFunCall(fun, args) => {
+                self.print_expr(fun, PREC_POSTFIX);
+                self.output.push('(');
+                self.print_exprs(args);
+                self.output.push(')');
+            }
+            ExprKind::LibAccess(lib, ident) => {
+                self.print_expr(lib, PREC_POSTFIX);
+                self.output.push('.');
+                self.print_ident(ident);
+            }
+            ExprKind::Array(elements) => {
+                self.output.push('[');
+                self.print_exprs(elements);
+                self.output.push(']');
+            }
+            ExprKind::Index(array, index, _) => {
+                self.print_expr(array, PREC_POSTFIX);
+                self.output.push('[');
+                self.print_expr(index, 0);
+                self.output.push(']');
+            }
+            ExprKind::Repeat(element, count) => {
+                self.output.push('[');
+                self.print_expr(element, 0);
+                self.output.push_str("; ");
+                self.print_expr(count, 0);
+                self.output.push(']');
+            }
+            ExprKind::Range(start, end, limits) => {
+                // Ranges cannot be chained, so both ends bind tighter.
+                self.print_expr(start, PREC_RANGE + 1);
+                self.output.push_str(&limits.to_string());
+                self.print_expr(end, PREC_RANGE + 1);
+            }
+            ExprKind::Tuple(elements) => {
+                self.output.push('(');
+                self.print_exprs(elements);
+                if elements.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
+            }
+            ExprKind::TupleField(tuple, index, _) => {
+                self.print_expr(tuple, PREC_POSTFIX);
+                self.output.push_str(&format!(".{}", index));
+            }
+            ExprKind::Struct(name, fields) => {
+                self.print_ident(name);
+                if fields.is_empty() {
+                    self.output.push_str(" {}");
+                } else {
+                    self.output.push_str(" { ");
+                    for (i, field) in fields.iter().enumerate() {
+                        if i > 0 {
+                            self.output.push_str(", ");
+                        }
+                        self.print_ident(&field.ident);
+                        // The shorthand `Point { x }` has no expression of its own.
+                        if field.expr.span != field.ident.span {
+                            self.output.push_str(": ");
+                            self.print_expr(&field.expr, 0);
+                        }
+                    }
+                    self.output.push_str(" }");
+                }
+            }
+            ExprKind::Variant(enum_name, variant, args) => {
+                self.print_ident(enum_name);
+                self.output.push_str("::");
+                self.print_ident(variant);
+                if !args.is_empty() {
+                    self.output.push('(');
+                    self.print_exprs(args);
+                    self.output.push(')');
+                }
+            }
+            ExprKind::Closure(closure) => {
+                self.print_keyword(Keyword::Fun);
+                self.print_fun_params(&closure.inputs);
+                self.print_fun_output(&closure.output);
+                self.output.push(' ');
+                self.print_block(&closure.body);
+            }
+        }
+
+        if needs_parens {
+            self.output.push(')');
+        }
     }
 
-    fn visit_stmt_for(&mut self, ident: &'ast Ident, expr: &'ast Expr, block: &'ast Stmt) {
-        self.output
-            .push_str(&format!("{}For statement:\n", space(self.indent)));
-        self.indent += self.indent_spaces;
-        self.output.push_str(&format!(
-            "{}Identifier: {}\n",
-            space(self.indent),
-            ident.name
-        ));
+    fn print_exprs(&mut self, exprs: &Vec<Box<Expr>>) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.print_expr(expr, 0);
+        }
+    }
 
-        self.output
-            .push_str(&format!("{}Expression:\n", space(self.indent)));
-        self.visit_expr(expr);
+    /// Prints the expression before the block of `when`, `during`, `for` or `match`, where a
+    /// struct literal must be put in parentheses since its `{` would start the block.
+    fn print_expr_no_struct(&mut self, expr: &Expr) {
+        if has_bare_struct(expr) {
+            self.output.push('(');
+            self.print_expr(expr, 0);
+            self.output.push(')');
+        } else {
+            self.print_expr(expr, 0);
+        }
+    }
 
-        self.output
-            .push_str(&format!("{}Block:\n", space(self.indent)));
+    fn print_pats(&mut self, pats: &Vec<Pat>) {
+        for (i, pat) in pats.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.visit_pat(pat);
+        }
+    }
+}
+
+impl<'ast, 'src> Visitor<'ast> for Printer<'src> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(_) => self.print_block(stmt),
+            StmtKind::Break => {
+                self.print_keyword(Keyword::Br);
+                self.output.push(';');
+            }
+            StmtKind::Continue => {
+                self.print_keyword(Keyword::Skip);
+                self.output.push(';');
+            }
+            _ => crate::visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_stmt_import(&mut self, import: &'ast Import) {
+        self.print_keyword(Keyword::Add);
+        self.output.push(' ');
+        for (i, ident) in import.path.iter().enumerate() {
+            if i > 0 {
+                self.output.push('.');
+            }
+            self.print_ident(ident);
+        }
+        match &import.kind {
+            ImportKind::Library => {}
+            ImportKind::Alias(alias) => {
+                self.output.push(' ');
+                self.print_keyword(Keyword::As);
+                self.output.push(' ');
+                self.print_ident(alias);
+            }
+            ImportKind::Select(names) => {
+                self.output.push_str(".{");
+                for (i, name) in names.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.print_ident(name);
+                }
+                self.output.push('}');
+            }
+        }
+        self.output.push(';');
+    }
+
+    fn visit_stmt_struct_decl(&mut self, def: &'ast StructDef) {
+        let StructDef { name, fields } = def;
+        self.print_keyword(Keyword::Struct);
+        self.output.push(' ');
+        self.print_ident(name);
+        if fields.is_empty() {
+            self.output.push_str(" {}");
+            return;
+        }
+
+        self.output.push_str(" {\n");
         self.indent += self.indent_spaces;
-        self.visit_stmt(block);
-        self.indent -= self.indent_spaces;
+        for (i, field) in fields.iter().enumerate() {
+            let start = field.span.offset as usize;
+            let is_first = self.print_comments_before(start, i == 0);
+            self.start_line(start, is_first);
+            self.print_ident(&field.ident);
+            self.output.push_str(": ");
+            self.visit_ty(&field.ty);
+            self.output.push(',');
+            self.last_end = span_end(field.span);
+            self.print_trailing_comments();
+            self.output.push('\n');
+        }
         self.indent -= self.indent_spaces;
+        self.output.push_str(&" ".repeat(self.indent));
+        self.output.push('}');
     }
 
-    fn visit_stmt_while(&mut self, condition: &'ast Expr, block: &'ast Stmt) {
-        self.output
-            .push_str(&format!("{}While statement:\n", space(self.indent),));
-        self.indent += self.indent_spaces;
-        self.output
-            .push_str(&format!("{}Condition:\n", space(self.indent)));
-        self.visit_expr(condition);
+    fn visit_stmt_enum_decl(&mut self, def: &'ast EnumDef) {
+        let EnumDef { name, variants } = def;
+        self.print_keyword(Keyword::Enum);
+        self.output.push(' ');
+        self.print_ident(name);
+        if variants.is_empty() {
+            self.output.push_str(" {}");
+            return;
+        }
 
-        self.output
-            .push_str(&format!("{}Block:\n", space(self.indent)));
+        self.output.push_str(" {\n");
         self.indent += self.indent_spaces;
-        self.visit_stmt(block);
+        for (i, variant) in variants.iter().enumerate() {
+            let start = variant.span.offset as usize;
+            let is_first = self.print_comments_before(start, i == 0);
+            self.start_line(start, is_first);
+            self.print_ident(&variant.ident);
+            if !variant.fields.is_empty() {
+                self.output.push('(');
+                for (i, ty) in variant.fields.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.visit_ty(ty);
+                }
+                self.output.push(')');
+            }
+            self.output.push(',');
+            self.last_end = span_end(variant.span);
+            self.print_trailing_comments();
+            self.output.push('\n');
+        }
         self.indent -= self.indent_spaces;
+        self.output.push_str(&" ".repeat(self.indent));
+        self.output.push('}');
+    }
+
+    fn visit_stmt_match(&mut self, scrutinee: &'ast Expr, arms: &'ast Vec<Arm>) {
+        self.print_keyword(Keyword::Match);
+        self.output.push(' ');
+        self.print_expr_no_struct(scrutinee);
+        if arms.is_empty() {
+            self.output.push_str(" {}");
+            return;
+        }
+
+        self.output.push_str(" {\n");
+        self.indent += self.indent_spaces;
+        for (i, arm) in arms.iter().enumerate() {
+            let start = arm.span.offset as usize;
+            let is_first = self.print_comments_before(start, i == 0);
+            self.start_line(start, is_first);
+            self.visit_arm(arm);
+            self.last_end = span_end(arm.span);
+            self.print_trailing_comments();
+            self.output.push('\n');
+        }
         self.indent -= self.indent_spaces;
+        self.output.push_str(&" ".repeat(self.indent));
+        self.output.push('}');
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.visit_pat(&arm.pat);
+        self.output.push_str(" => ");
+        self.print_block(&arm.body);
+    }
+
+    fn visit_stmt_func_decl(&mut self, fun: &'ast Fun) {
+        let Fun {
+            is_private,
+            sig,
+            body,
+        } = fun;
+        if *is_private {
+            self.print_keyword(Keyword::Priv);
+            self.output.push(' ');
+        }
+        self.print_fun_sig(sig);
+        self.output.push(' ');
+        self.print_block(body);
+    }
+
+    fn visit_stmt_for(&mut self, ident: &'ast Ident, expr: &'ast Expr, block: &'ast Stmt) {
+        self.print_keyword(Keyword::For);
+        self.output.push(' ');
+        self.print_ident(ident);
+        self.output.push(' ');
+        self.print_keyword(Keyword::In);
+        self.output.push(' ');
+        self.print_expr_no_struct(expr);
+        self.output.push(' ');
+        self.print_block(block);
+    }
+
+    fn visit_stmt_while(&mut self, condition: &'ast Expr, block: &'ast Stmt) {
+        self.print_keyword(Keyword::During);
+        self.output.push(' ');
+        self.print_expr_no_struct(condition);
+        self.output.push(' ');
+        self.print_block(block);
     }
 
     fn visit_stmt_if(
@@ -134,269 +621,232 @@ impl<'ast> Visitor<'ast> for Printer {
         block: &'ast Stmt,
         optional_else: Option<&'ast Stmt>,
     ) {
-        self.output
-            .push_str(&format!("{}If statement:\n", space(self.indent),));
-        self.indent += self.indent_spaces;
-        self.output
-            .push_str(&format!("{}Condition:\n", space(self.indent)));
-        self.visit_expr(condition);
-
-        self.output
-            .push_str(&format!("{}Block:\n", space(self.indent)));
-        self.indent += self.indent_spaces;
-        self.visit_stmt(block);
-        self.indent -= self.indent_spaces;
+        self.print_keyword(Keyword::When);
+        self.output.push(' ');
+        self.print_expr_no_struct(condition);
+        self.output.push(' ');
+        self.print_block(block);
 
         if let Some(else_stmt) = optional_else {
-            self.output
-                .push_str(&format!("{}Else:\n", space(self.indent)));
-            self.indent += self.indent_spaces;
+            self.output.push(' ');
+            self.print_keyword(Keyword::Alt);
+            self.output.push(' ');
+            // Either a block or another `when` statement.
             self.visit_stmt(else_stmt);
-            self.indent -= self.indent_spaces;
         }
-        self.indent -= self.indent_spaces;
     }
 
     fn visit_stmt_expr(&mut self, expr: &'ast Expr) {
-        self.output.push_str(&format!(
-            "{}Expr statement: {}\n",
-            space(self.indent),
-            expr.span
-        ));
-        self.visit_expr(expr);
+        self.print_expr(expr, 0);
+        self.output.push(';');
     }
 
     fn visit_stmt_var(&mut self, local: &'ast Local) {
-        self.output.push_str(&format!(
-            "{}Var statement: {}\n",
-            space(self.indent),
-            local.span
-        ));
-
-        self.indent += self.indent_spaces;
-
         let Local {
             kind,
-            ident,
+            pat,
             is_mut,
             ty,
             ..
         } = local;
 
-        self.output.push_str(&format!(
-            "{}Mutability: {} {}\n",
-            space(self.indent),
-            is_mut,
-            ident.span
-        ));
-        self.output.push_str(&format!(
-            "{}Identifier: {} {}\n",
-            space(self.indent),
-            ident.name,
-            ident.span
-        ));
-        self.visit_ty(ty);
-
-        match kind {
-            LocalKind::Init(expr) => {
-                self.output
-                    .push_str(&format!("{}Init expression:\n", space(self.indent)));
-                self.visit_expr(expr);
-            }
-            LocalKind::Decl => {}
+        self.print_keyword(Keyword::Set);
+        self.output.push(' ');
+        if *is_mut {
+            self.print_keyword(Keyword::Mut);
+            self.output.push(' ');
         }
-
-        self.indent -= self.indent_spaces;
+        self.visit_pat(pat);
+        if let Some(ty) = ty {
+            self.output.push_str(": ");
+            self.visit_ty(ty);
+        }
+        if let LocalKind::Init(expr) = kind {
+            self.output.push_str(" = ");
+            self.print_expr(expr, 0);
+        }
+        self.output.push(';');
     }
 
     fn visit_stmt_return(&mut self, expr: Option<&'ast Expr>) {
-        self.output
-            .push_str(&format!("{}Return statement:\n", space(self.indent),));
-        if expr.is_some() {
-            self.visit_expr(expr.unwrap());
+        self.print_keyword(Keyword::Yeet);
+        if let Some(expr) = expr {
+            self.output.push(' ');
+            self.print_expr(expr, 0);
         }
+        self.output.push(';');
     }
 
     fn visit_expr(&mut self, expr: &'ast Expr) {
-        let Expr { kind, span } = expr;
+        self.print_expr(expr, 0);
+    }
 
-        self.indent += self.indent_spaces;
-        match kind {
-            ExprKind::Binary(op, left_expression, right_expression) => {
-                self.output.push_str(&format!(
-                    "{}Binary operation: {} {}\n",
-                    space(self.indent),
-                    op,
-                    span
-                ));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}Left:\n", space(self.indent)));
-                self.visit_expr(left_expression);
-                self.output
-                    .push_str(&format!("{}Right:\n", space(self.indent)));
-                self.visit_expr(right_expression);
-                self.indent -= self.indent_spaces;
-            }
-
-            ExprKind::Unary(op, subexpression) => {
-                self.output.push_str(&format!(
-                    "{}Unary operation: {} {}\n",
-                    space(self.indent),
-                    op,
-                    span
-                ));
-                self.visit_expr(subexpression);
-            }
-
-            ExprKind::Assign(lhs, rhs, span) => {
-                self.output
-                    .push_str(&format!("{}Assignment: {}\n", space(self.indent), span));
-                self.visit_expr(lhs);
-                self.visit_expr(rhs);
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        match &pat.kind {
+            PatKind::Ident(ident) => self.print_ident(ident),
+            PatKind::Tuple(pats) => {
+                self.output.push('(');
+                self.print_pats(pats);
+                if pats.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
             }
-            ExprKind::AssignOp(op, lhs, rhs) => {
-                self.output.push_str(&format!(
-                    "{}Assignment operation: {} {}\n",
-                    space(self.indent),
-                    op,
-                    span
-                ));
-                self.visit_expr(lhs);
-                self.visit_expr(rhs);
-            }
-            ExprKind::Literal(token) => {
-                self.output.push_str(&format!(
-                    "{}{} {}\n",
-                    space(self.indent),
-                    token.symbol,
-                    span
-                ));
-            }
-            ExprKind::Identifier(ident) => {
-                self.output.push_str(&format!(
-                    "{}Identifier: {} {}\n",
-                    space(self.indent),
-                    ident.name,
-                    ident.span
-                ));
-            }
-            ExprKind::Cast(expr, ty) => {
-                self.output
-                    .push_str(&format!("{}Cast:\n", space(self.indent)));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}From:\n", space(self.indent),));
-                self.visit_expr(expr);
-                self.output
-                    .push_str(&format!("{}To:\n", space(self.indent),));
-                self.indent += self.indent_spaces;
-                self.visit_ty(ty);
-                self.indent -= self.indent_spaces;
-            }
-            ExprKind::FunCall(fun, args) => {
-                self.output.push_str(&format!(
-                    "{}Function call: {} {}\n",
-                    space(self.indent),
-                    fun.span,
-                    span
-                ));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}Function name:\n", space(self.indent)));
-                self.visit_expr(fun);
-                self.output
-                    .push_str(&format!("{}Arguments:\n", space(self.indent)));
-                for arg in args {
-                    self.visit_expr(arg);
+            PatKind::Wild => self.output.push('_'),
+            PatKind::Lit(expr) => self.print_expr(expr, 0),
+            PatKind::Variant(enum_name, variant, pats) => {
+                self.print_ident(enum_name);
+                self.output.push_str("::");
+                self.print_ident(variant);
+                if !pats.is_empty() {
+                    self.output.push('(');
+                    self.print_pats(pats);
+                    self.output.push(')');
                 }
-                self.indent -= self.indent_spaces;
             }
-            ExprKind::LibAccess(lib, ident) => {
-                self.output.push_str(&format!(
-                    "{}Library access: {}\n",
-                    space(self.indent),
-                    ident.span
-                ));
-                self.indent += self.indent_spaces;
-                self.visit_expr(lib);
-                self.indent -= self.indent_spaces;
-                self.output.push_str(&format!(
-                    "{}Item: {} {}\n",
-                    space(self.indent),
-                    ident.name,
-                    ident.span
-                ));
-            }
-            ExprKind::LibFunCall(lib_func, args) => {
-                self.output.push_str(&format!(
-                    "{}Library function call: {} {}\n",
-                    space(self.indent),
-                    lib_func.span,
-                    span
-                ));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}Function name:\n", space(self.indent)));
-                self.visit_expr(lib_func);
-                self.output
-                    .push_str(&format!("{}Arguments:\n", space(self.indent)));
-                for arg in args {
-                    self.visit_expr(arg);
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        match &ty.kind {
+            TyKind::Array(elem, len) => {
+                self.output.push('[');
+                self.visit_ty(elem);
+                if let Some(len) = len {
+                    self.output.push_str("; ");
+                    self.print_expr(len, 0);
                 }
-                self.indent -= self.indent_spaces;
-            }
-            ExprKind::Array(exprs) => {
-                self.output
-                    .push_str(&format!("{}Array: {}\n", space(self.indent), span));
-                self.indent += self.indent_spaces;
-                for expr in exprs {
-                    self.visit_expr(expr);
+                self.output.push(']');
+            }
+            TyKind::Named(ident) => self.print_ident(ident),
+            TyKind::Tuple(tys) => {
+                self.output.push('(');
+                for (i, ty) in tys.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.visit_ty(ty);
+                }
+                if tys.len() == 1 {
+                    self.output.push(',');
                 }
-                self.indent -= self.indent_spaces;
-            }
-            ExprKind::Index(expr, index, span) => {
-                self.output.push_str(&format!(
-                    "{}Indexing: {} {}\n",
-                    space(self.indent),
-                    span,
-                    span
-                ));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}Array:\n", space(self.indent)));
-                self.visit_expr(expr);
-                self.output
-                    .push_str(&format!("{}Index:\n", space(self.indent)));
-                self.visit_expr(index);
-                self.indent -= self.indent_spaces;
+                self.output.push(')');
             }
-            ExprKind::Repeat(element, count) => {
-                self.output.push_str(&format!(
-                    "{}Repeat: {} {}\n",
-                    space(self.indent),
-                    span,
-                    span
-                ));
-                self.indent += self.indent_spaces;
-                self.output
-                    .push_str(&format!("{}Element:\n", space(self.indent)));
-                self.visit_expr(element);
-                self.output
-                    .push_str(&format!("{}Count:\n", space(self.indent)));
-                self.visit_expr(count);
-                self.indent -= self.indent_spaces;
+            TyKind::Fun(inputs, output) => {
+                self.print_keyword(Keyword::Fun);
+                self.output.push('(');
+                for (i, (ty, is_mut)) in inputs.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    if *is_mut {
+                        self.print_keyword(Keyword::Mut);
+                        self.output.push(' ');
+                    }
+                    self.visit_ty(ty);
+                }
+                self.output.push(')');
+                if let Some(output) = output {
+                    self.output.push_str(" -> ");
+                    self.visit_ty(output);
+                }
             }
         }
-        self.indent -= self.indent_spaces;
     }
+}
 
-    fn visit_ty(&mut self, ty: &'ast Ty) {
-        self.output
-            .push_str(&format!("{}Type: {} {}\n", space(self.indent), ty, ty.span));
+/// Whether a comment can be followed by code on the same line, i.e. it is a block comment
+/// without a newline.
+fn is_inline_comment(text: &str) -> bool {
+    text.starts_with("/*") && !text.contains('\n')
+}
+
+/// Finds the comments of the source code with the lexer, which skips them otherwise.
+fn collect_comments(src: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut cursor = Cursor::new(src);
+    let mut pos = 0;
+    loop {
+        let token = cursor.advance_token();
+        let start = pos;
+        pos += token.len as usize;
+        match token.kind {
+            lexer::TokenKind::LineComment { .. } | lexer::TokenKind::BlockComment { .. } => {
+                comments.push(Comment { start, end: pos })
+            }
+            lexer::TokenKind::Eof => break,
+            _ => {}
+        }
+    }
+    comments
+}
+
+fn precedence(expr: &Expr) -> usize {
+    match &expr.kind {
+        ExprKind::Binary(op, ..) => binop_precedence(&op.node),
+        ExprKind::Assign(..) | ExprKind::AssignOp(..) => PREC_ASSIGN,
+        ExprKind::Range(..) => PREC_RANGE,
+        ExprKind::Cast(..) => PREC_CAST,
+        ExprKind::Unary(..) => PREC_PREFIX,
+        ExprKind::FunCall(..)
+        | ExprKind::LibFunCall(..)
+        | ExprKind::LibAccess(..)
+        | ExprKind::Index(..)
+        | ExprKind::TupleField(..) => PREC_POSTFIX,
+        ExprKind::Literal(_)
+        | ExprKind::Identifier(_)
+        | ExprKind::Array(_)
+        | ExprKind::Repeat(..)
+        | ExprKind::Tuple(_)
+        | ExprKind::Struct(..)
+        | ExprKind::Variant(..)
+        | ExprKind::Closure(_) => PREC_ATOM,
+    }
+}
+
+/// The same precedences as the ones the parser uses.
+fn binop_precedence(op: &BinOpKind) -> usize {
+    match op {
+        BinOpKind::Mul | BinOpKind::Div | BinOpKind::Mod => 11,
+        BinOpKind::Add | BinOpKind::Sub => 10,
+        BinOpKind::Shl | BinOpKind::Shr => 9,
+        BinOpKind::BitAnd => 8,
+        BinOpKind::BitXor => 7,
+        BinOpKind::BitOr => 6,
+        BinOpKind::Eq
+        | BinOpKind::Ne
+        | BinOpKind::Lt
+        | BinOpKind::Le
+        | BinOpKind::Gt
+        | BinOpKind::Ge => 5,
+        BinOpKind::And => 4,
+        BinOpKind::Or => 3,
+    }
+}
+
+/// Whether a struct literal would be printed outside of any delimiter.
+fn has_bare_struct(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Struct(..) => true,
+        ExprKind::Binary(_, lhs, rhs)
+        | ExprKind::Range(lhs, rhs, _)
+        | ExprKind::Assign(lhs, rhs, _)
+        | ExprKind::AssignOp(_, lhs, rhs) => has_bare_struct(lhs) || has_bare_struct(rhs),
+        ExprKind::Unary(_, expr)
+        | ExprKind::Cast(expr, _)
+        | ExprKind::FunCall(expr, _)
+        | ExprKind::LibFunCall(expr, _)
+        | ExprKind::LibAccess(expr, _)
+        | ExprKind::Index(expr, ..)
+        | ExprKind::TupleField(expr, ..) => has_bare_struct(expr),
+        _ => false,
     }
 }
 
-fn space(n: usize) -> String {
-    " ".repeat(n)
+fn span_range(span: Span) -> std::ops::Range<usize> {
+    span.offset as usize..span_end(span)
+}
+
+fn span_end(span: Span) -> usize {
+    span.offset as usize + span.length
 }
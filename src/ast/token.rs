@@ -54,6 +54,10 @@ pub enum TokenKind {
     /* Structural symbols */
     /// `.`
     Dot,
+    /// `..`
+    DotDot,
+    /// `..=`
+    DotDotEq,
     /// `,`
     Comma,
     /// `;`
@@ -64,6 +68,8 @@ pub enum TokenKind {
     PathSep,
     /// `->`
     RArrow,
+    /// `=>`
+    FatArrow,
     /// `?`
     Question,
     /// An opening delimiter (e.g., `{`).
@@ -101,11 +107,14 @@ impl Display for TokenKind {
             BinOp(op) => write!(f, "{}", op),
             BinOpEq(op) => write!(f, "{}=", op),
             Dot => write!(f, "."),
+            DotDot => write!(f, ".."),
+            DotDotEq => write!(f, "..="),
             Comma => write!(f, ","),
             Semicolon => write!(f, ";"),
             Colon => write!(f, ":"),
             PathSep => write!(f, "::"),
             RArrow => write!(f, "->"),
+            FatArrow => write!(f, "=>"),
             Question => write!(f, "?"),
             OpenDelim(delim) => {
                 let s = match delim {
@@ -273,6 +282,7 @@ impl Token {
         let kind = match self.kind {
             Eq => match joint.kind {
                 Eq => EqEq,
+                Gt => FatArrow,
                 _ => return None,
             },
             Not => match joint.kind {
@@ -302,9 +312,17 @@ impl Token {
                 Colon => PathSep,
                 _ => return None,
             },
-            Le | EqEq | Ne | Ge | AndAnd | OrOr | Tilde | BinOpEq(_) | Dot | Comma | Semicolon
-            | Question | OpenDelim(_) | CloseDelim(_) | Literal(_) | Ident(..) | DocComment(..)
-            | PathSep | RArrow | Eof => return None,
+            Dot => match joint.kind {
+                Dot => DotDot,
+                _ => return None,
+            },
+            DotDot => match joint.kind {
+                Eq => DotDotEq,
+                _ => return None,
+            },
+            Le | EqEq | Ne | Ge | AndAnd | OrOr | Tilde | BinOpEq(_) | DotDotEq | Comma
+            | Semicolon | Question | OpenDelim(_) | CloseDelim(_) | Literal(_) | Ident(..)
+            | DocComment(..) | PathSep | RArrow | FatArrow | Eof => return None,
         };
 
         Some(Token {
@@ -312,6 +330,7 @@ impl Token {
             span: Span {
                 offset: self.span.offset,
                 length: self.span.length + joint.span.length,
+                file: self.span.file,
             },
         })
     }
@@ -319,10 +338,10 @@ impl Token {
     pub fn is_punct(&self) -> bool {
         match self.kind {
             Eq | Lt | Le | EqEq | Ne | Ge | Gt | AndAnd | OrOr | Not | Tilde | BinOp(_)
-            | BinOpEq(_) | Dot | Comma | Semicolon | Colon | Question => true,
+            | BinOpEq(_) | Dot | DotDot | DotDotEq | Comma | Semicolon | Colon | Question => true,
 
             PathSep | OpenDelim(..) | CloseDelim(..) | Literal(..) | DocComment(..) | Ident(..)
-            | Eof | RArrow => false,
+            | Eof | RArrow | FatArrow => false,
         }
     }
 
@@ -338,6 +357,15 @@ impl Token {
         }
     }
 
+    /// Returns `true` if the token can begin a literal, possibly a negative one (e.g. `-1`).
+    pub fn can_begin_literal_maybe_minus(&self) -> bool {
+        match self.kind {
+            Literal(..) | BinOp(Minus) => true,
+            Ident(name, IdentIsRaw::No) => name.is_bool_lit(),
+            _ => false,
+        }
+    }
+
     /// Returns `true` if the token is a non-raw identifier for which `pred` holds.
     pub fn is_non_raw_ident_where(&self, pred: impl FnOnce(Ident) -> bool) -> bool {
         match self.ident() {
@@ -387,7 +415,7 @@ pub fn ident_can_begin_expr(name: Symbol, span: Span, is_raw: IdentIsRaw) -> boo
     is_raw == IdentIsRaw::Yes
         || ident_token.is_non_raw_ident_where(|ident| match kw::from_str(ident.name.as_str()) {
             Ok(keyword) => match keyword {
-                Keyword::True | Keyword::False => true,
+                Keyword::True | Keyword::False | Keyword::Fun => true,
                 _ => false,
             },
             Err(_) => true,
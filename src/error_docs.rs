@@ -74,5 +74,23 @@ pub fn get_error_docs() -> HashMap<&'static str, &'static str> {
     docs.insert("E0248", include_str!("../error_codes/E0248.md"));
     docs.insert("E0249", include_str!("../error_codes/E0249.md"));
     docs.insert("E0250", include_str!("../error_codes/E0250.md"));
+    docs.insert("E0251", include_str!("../error_codes/E0251.md"));
+    docs.insert("E0252", include_str!("../error_codes/E0252.md"));
+    docs.insert("E0253", include_str!("../error_codes/E0253.md"));
+    docs.insert("E0254", include_str!("../error_codes/E0254.md"));
+    docs.insert("E0255", include_str!("../error_codes/E0255.md"));
+    docs.insert("E0256", include_str!("../error_codes/E0256.md"));
+    docs.insert("E0257", include_str!("../error_codes/E0257.md"));
+    docs.insert("E0258", include_str!("../error_codes/E0258.md"));
+    docs.insert("E0259", include_str!("../error_codes/E0259.md"));
+    docs.insert("E0260", include_str!("../error_codes/E0260.md"));
+    docs.insert("E0261", include_str!("../error_codes/E0261.md"));
+    docs.insert("E0262", include_str!("../error_codes/E0262.md"));
+    docs.insert("E0263", include_str!("../error_codes/E0263.md"));
+    docs.insert("E0264", include_str!("../error_codes/E0264.md"));
+    docs.insert("E0265", include_str!("../error_codes/E0265.md"));
+    docs.insert("E0266", include_str!("../error_codes/E0266.md"));
+    docs.insert("E0267", include_str!("../error_codes/E0267.md"));
+    docs.insert("E0268", include_str!("../error_codes/E0268.md"));
     docs
 }
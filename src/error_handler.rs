@@ -1,11 +1,12 @@
 use crate::{
     ast::{Delimiter, TokenKind},
+    interpreter::CallFrame,
     parse::parser::TokenType,
-    session::SourceFile,
-    span_encoding::Span,
+    session::SourceMap,
+    span_encoding::{split_source_offset, Span},
 };
-use miette::{Diagnostic, LabeledSpan, Report, SourceSpan};
-use std::sync::Arc;
+use miette::{Diagnostic, LabeledSpan, Report, Severity, SourceSpan};
+use std::{cell::RefCell, fmt, rc::Rc};
 use thiserror::Error;
 
 pub const ERROR_CODE_URL: &str =
@@ -13,10 +14,145 @@ pub const ERROR_CODE_URL: &str =
 
 #[derive(Debug, Clone)]
 pub struct ErrorHandler {
-    pub file: Arc<SourceFile>,
+    /// The files whose code is shown in the reports.
+    pub source_map: SourceMap,
+    /// If set, the reports are collected here instead of being printed, e.g. for the language
+    /// server, which turns them into diagnostics.
+    pub collected: Option<Rc<RefCell<Vec<Report>>>>,
 }
 
 impl ErrorHandler {
+    pub fn build_pass_immutable_as_mutable_error(
+        &self,
+        param_name: Option<String>,
+        mut_kw: String,
+        var_name: Option<String>,
+        arg_span: Span,
+        param_span: Span,
+        help_span: Option<Span>,
+    ) -> PassImmutableAsMutable {
+        let arg_label = match &var_name {
+            Some(var_name) => format!("`{}` is not declared as mutable", var_name),
+            None => "expected a mutable variable".to_string(),
+        };
+        let (param, param_label) = match param_name {
+            Some(param_name) => (
+                format!("the `{}` parameter `{}`", mut_kw, param_name),
+                format!("parameter declared as `{}` here", mut_kw),
+            ),
+            None => (
+                format!("a `{}` parameter", mut_kw),
+                format!("the type of this function has a `{}` parameter", mut_kw),
+            ),
+        };
+        PassImmutableAsMutable {
+            param,
+            param_label,
+            mut_kw,
+            var_name: var_name.unwrap_or_default(),
+            arg_label,
+            arg_span: arg_span.to_source_span(),
+            param_span: param_span.to_source_span(),
+            help_span: help_span.map(|span| span.to_source_span()),
+        }
+    }
+
+    pub fn build_non_exhaustive_patterns_error(
+        &self,
+        witness: String,
+        ty: String,
+        span: Span,
+    ) -> NonExhaustivePatterns {
+        NonExhaustivePatterns {
+            witness,
+            ty,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_no_variant_in_enum_error(
+        &self,
+        variant: String,
+        enum_name: String,
+        span: Span,
+    ) -> NoVariantInEnum {
+        NoVariantInEnum {
+            variant,
+            enum_name,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_variant_declared_more_than_once_error(
+        &self,
+        variant: String,
+        first_span: Span,
+        second_span: Span,
+    ) -> VariantDeclaredMoreThanOnce {
+        VariantDeclaredMoreThanOnce {
+            variant,
+            first_span: first_span.to_source_span(),
+            second_span: second_span.to_source_span(),
+        }
+    }
+
+    pub fn build_type_already_declared_in_scope_error(
+        &self,
+        type_name: String,
+        first_decl_span: Span,
+        second_decl_span: Span,
+    ) -> TypeAlreadyDeclaredInScope {
+        TypeAlreadyDeclaredInScope {
+            type_name,
+            first_decl_span: first_decl_span.to_source_span(),
+            second_decl_span: second_decl_span.to_source_span(),
+        }
+    }
+
+    pub fn build_field_specified_more_than_once_error(
+        &self,
+        field: String,
+        first_span: Span,
+        second_span: Span,
+    ) -> FieldSpecifiedMoreThanOnce {
+        FieldSpecifiedMoreThanOnce {
+            field,
+            first_span: first_span.to_source_span(),
+            second_span: second_span.to_source_span(),
+        }
+    }
+
+    pub fn build_missing_struct_fields_error(
+        &self,
+        struct_name: String,
+        fields: Vec<String>,
+        span: Span,
+    ) -> MissingStructFields {
+        let fields = fields
+            .iter()
+            .map(|field| format!("`{}`", field))
+            .collect::<Vec<String>>()
+            .join(", ");
+        MissingStructFields {
+            struct_name,
+            fields,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_no_field_on_type_error(
+        &self,
+        field: String,
+        ty: String,
+        span: Span,
+    ) -> NoFieldOnType {
+        NoFieldOnType {
+            field,
+            ty,
+            span: span.to_source_span(),
+        }
+    }
+
     pub fn build_neg_array_size_error(&self, span: Span, size: String) -> NegArraySize {
         NegArraySize {
             size,
@@ -24,6 +160,53 @@ impl ErrorHandler {
         }
     }
 
+    pub fn build_traced_error(&self, error: Report, trace: Vec<CallFrame>) -> Traced {
+        // A recursive function makes the same call many times, so the calls are counted.
+        let mut calls: Vec<(CallFrame, usize)> = vec![];
+        for frame in trace {
+            let same_call = calls.iter_mut().find(|(call, _)| {
+                call.func_name == frame.func_name && call.call_span == frame.call_span
+            });
+            match same_call {
+                Some((_, count)) => *count += 1,
+                None => calls.push((frame, 1)),
+            }
+        }
+
+        // The calls in the file of the error are shown with it, the others after it.
+        let error_file = error
+            .labels()
+            .and_then(|mut labels| labels.next())
+            .map(|label| split_source_offset(label.offset()).0);
+        let mut labels = vec![];
+        let mut related = vec![];
+        for (call, count) in calls {
+            let label = if count == 1 {
+                format!("`{}` called here", call.func_name)
+            } else {
+                format!("`{}` called here ({} times)", call.func_name, count)
+            };
+            if Some(call.call_span.file) == error_file {
+                labels.push(LabeledSpan::new_with_span(
+                    Some(label),
+                    call.call_span.to_source_span(),
+                ));
+            } else {
+                related.push(CalledFrom {
+                    func_name: call.func_name,
+                    label,
+                    span: call.call_span.to_source_span(),
+                });
+            }
+        }
+
+        Traced {
+            error,
+            labels,
+            related,
+        }
+    }
+
     pub fn build_predefined_error(&self, span: Span, message: String) -> PredefinedError {
         PredefinedError {
             span: span.to_source_span(),
@@ -52,6 +235,24 @@ impl ErrorHandler {
         }
     }
 
+    pub fn build_arithmetic_overflow_error(
+        &self,
+        op: String,
+        lhs: Option<String>,
+        rhs: String,
+        op_span: Span,
+    ) -> ArithmeticOverflow {
+        let expr = match lhs {
+            Some(lhs) => format!("{} {} {}", lhs, op, rhs),
+            None if rhs.starts_with('-') => format!("{}({})", op, rhs),
+            None => format!("{}{}", op, rhs),
+        };
+        ArithmeticOverflow {
+            expr,
+            op_span: op_span.to_source_span(),
+        }
+    }
+
     pub fn build_function_in_library_not_found_error(
         &self,
         func_name: String,
@@ -65,17 +266,47 @@ impl ErrorHandler {
         }
     }
 
-    pub fn build_invalid_library_name_error(&self, span: Span) -> InvalidLibraryName {
-        InvalidLibraryName {
+    pub fn build_private_function_in_library_error(
+        &self,
+        func_name: String,
+        lib_name: String,
+        span: Span,
+    ) -> PrivateFunctionInLibrary {
+        PrivateFunctionInLibrary {
+            func_name,
+            lib_name,
             span: span.to_source_span(),
         }
     }
 
-    pub fn build_non_function_declared_in_external_library_error(
+    pub fn build_mutable_variable_in_library_error(
         &self,
+        var_name: String,
+        lib_name: String,
         span: Span,
-    ) -> NonFunctionDeclaredInExternalLibrary {
-        NonFunctionDeclaredInExternalLibrary {
+    ) -> MutableVariableInLibrary {
+        MutableVariableInLibrary {
+            var_name,
+            lib_name,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_call_depth_exceeded_error(
+        &self,
+        func_name: String,
+        limit: usize,
+        span: Span,
+    ) -> CallDepthExceeded {
+        CallDepthExceeded {
+            func_name,
+            limit,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_invalid_library_name_error(&self, span: Span) -> InvalidLibraryName {
+        InvalidLibraryName {
             span: span.to_source_span(),
         }
     }
@@ -115,8 +346,15 @@ impl ErrorHandler {
         lib_name: String,
         span: Span,
     ) -> ExternalLibraryNotFound {
+        let extension = if crate::is_genz_mode() {
+            "unbxx"
+        } else {
+            "boxx"
+        };
+        let lib_file = format!("{}.{}", lib_name.replace('.', "/"), extension);
         ExternalLibraryNotFound {
             lib_name,
+            lib_file,
             span: span.to_source_span(),
         }
     }
@@ -191,7 +429,7 @@ impl ErrorHandler {
 
     pub fn build_function_param_mismatch(
         &self,
-        func_decl_span: Span,
+        func_decl_span: Option<Span>,
         args: usize,
         mismatch_params: Vec<(Span, String, String)>,
         missing_param_tys: Vec<String>, // this and unexpected_params should never be non empty at the
@@ -285,7 +523,7 @@ impl ErrorHandler {
         FunctionParamMismatch {
             message,
             missing_span,
-            func_decl_span: func_decl_span.to_source_span(),
+            func_decl_span: func_decl_span.map(|span| span.to_source_span()),
             mismatch_spans: param_labels,
             unexpected_spans,
         }
@@ -335,6 +573,74 @@ impl ErrorHandler {
             span: span.to_source_span(),
         }
     }
+    pub fn build_no_method_on_type_error(
+        &self,
+        method: String,
+        ty: String,
+        help: Option<String>,
+        span: Span,
+    ) -> NoMethodOnType {
+        NoMethodOnType {
+            method,
+            ty,
+            help,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_array_length_is_fixed_error(
+        &self,
+        method: String,
+        ty: String,
+        growable_ty: String,
+        span: Span,
+    ) -> ArrayLengthIsFixed {
+        ArrayLengthIsFixed {
+            method,
+            ty,
+            growable_ty,
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_pop_from_empty_array_error(&self, span: Span) -> PopFromEmptyArray {
+        PopFromEmptyArray {
+            span: span.to_source_span(),
+        }
+    }
+
+    pub fn build_pass_fixed_array_as_growable_error(
+        &self,
+        param_name: Option<String>,
+        ty: String,
+        param_ty: String,
+        arg_span: Span,
+        param_span: Span,
+    ) -> PassFixedArrayAsGrowable {
+        let param = match param_name {
+            Some(param_name) => format!("the parameter `{}`", param_name),
+            None => "a parameter".to_string(),
+        };
+        PassFixedArrayAsGrowable {
+            param,
+            ty,
+            param_ty,
+            arg_span: arg_span.to_source_span(),
+            param_span: param_span.to_source_span(),
+        }
+    }
+
+    pub fn build_range_step_not_positive_error(
+        &self,
+        step: i64,
+        span: Span,
+    ) -> RangeStepNotPositive {
+        RangeStepNotPositive {
+            step,
+            span: span.to_source_span(),
+        }
+    }
+
     pub fn build_neg_repeat_count_error(&self, count: i64, span: Span) -> NegRepeatCount {
         NegRepeatCount {
             count,
@@ -668,7 +974,7 @@ impl ErrorHandler {
         // If the found token is an EOF token, we want to display the error at the end of the file
         let span = match found {
             TokenType::Token(tok) => match tok {
-                TokenKind::Eof => Span::new(prev_span.end() - 1, prev_span.end()),
+                TokenKind::Eof => Span::new(prev_span.end() - 1, prev_span.end(), prev_span.file),
                 _ => span,
             },
             _ => span,
@@ -754,7 +1060,7 @@ impl ErrorHandler {
         // If the found token is an EOF token, we want to display the error at the end of the file
         let span = match found {
             TokenType::Token(tok) => match tok {
-                TokenKind::Eof => Span::new(prev_span.end() - 1, prev_span.end()),
+                TokenKind::Eof => Span::new(prev_span.end() - 1, prev_span.end(), prev_span.file),
                 _ => span,
             },
             _ => span,
@@ -919,6 +1225,36 @@ impl ErrorHandler {
         }
     }
 
+    pub fn build_circular_import_error(
+        &self,
+        lib_name: String,
+        mut chain: Vec<(String, Span)>,
+        cycle: Vec<(String, String)>,
+    ) -> CircularImport {
+        // Each library of the cycle adds the next one, and the last adds the first again.
+        let mut diagram = String::new();
+        let adds = cycle.iter().skip(1).map(|(_, name)| name.as_str());
+        for ((file, _), added) in cycle.iter().zip(adds.chain([lib_name.as_str()])) {
+            diagram.push_str(&format!("\n    {}\n    │ adds `{}`\n    ▼", file, added));
+        }
+        diagram.push_str(&format!("\n    {} (already being added)", cycle[0].0));
+
+        let (_, span) = chain.pop().unwrap();
+        let related = chain
+            .into_iter()
+            .map(|(lib_name, span)| AddedFrom {
+                lib_name,
+                span: span.to_source_span(),
+            })
+            .collect();
+        CircularImport {
+            lib_name,
+            diagram,
+            span: span.to_source_span(),
+            related,
+        }
+    }
+
     pub fn build_unterminated_string_literal_error(
         &self,
         span: Span,
@@ -928,12 +1264,34 @@ impl ErrorHandler {
         }
     }
 
-    pub fn new(file: Arc<SourceFile>) -> Self {
-        Self { file }
+    pub fn new(source_map: SourceMap) -> Self {
+        Self {
+            source_map,
+            collected: None,
+        }
+    }
+
+    pub fn new_collecting(source_map: SourceMap) -> Self {
+        Self {
+            source_map,
+            collected: Some(Rc::new(RefCell::new(vec![]))),
+        }
     }
 
     pub fn report_err(&self, build: Report) {
-        println!("{:?}", build.with_source_code(Arc::clone(&self.file)));
+        let report = build.with_source_code(self.source_map.files());
+        match &self.collected {
+            Some(reports) => reports.borrow_mut().push(report),
+            None => println!("{:?}", report),
+        }
+    }
+
+    /// Takes the reports collected so far.
+    pub fn take_reports(&self) -> Vec<Report> {
+        match &self.collected {
+            Some(reports) => reports.take(),
+            None => vec![],
+        }
     }
 }
 
@@ -1320,10 +1678,7 @@ pub struct FunctionAlreadyDeclaredInScope {
 }
 
 #[derive(Error, Debug, Diagnostic)]
-#[error(
-    "there are multiple libraries with the same name `{}` in the same scope",
-    lib_name
-)]
+#[error("`{}` is added multiple times in the same scope", lib_name)]
 #[diagnostic(
     code(E0209),
     url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
@@ -1580,6 +1935,82 @@ pub struct IndexOutOfBounds {
     span: SourceSpan,
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("no method named `{}` found for type `{}`", method, ty)]
+#[diagnostic(
+    code(E0262),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct NoMethodOnType {
+    method: String,
+    ty: String,
+    #[help]
+    help: Option<String>,
+    #[label("method not found")]
+    span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("cannot change the length of an array of type `{}`", ty)]
+#[diagnostic(
+    code(E0263),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("declare the array as `{}` to let its length change", growable_ty)
+)]
+pub struct ArrayLengthIsFixed {
+    method: String,
+    ty: String,
+    growable_ty: String,
+    #[label("`{}` changes the length of the array", method)]
+    span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("cannot pop from an empty array")]
+#[diagnostic(
+    code(E0264),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct PopFromEmptyArray {
+    #[label("the array is empty")]
+    span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error(
+    "cannot pass an array of type `{}` to {} of type `{}`",
+    ty,
+    param,
+    param_ty
+)]
+#[diagnostic(
+    code(E0266),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("declare the array as `{}` to let its length change", param_ty)
+)]
+pub struct PassFixedArrayAsGrowable {
+    param: String,
+    ty: String,
+    param_ty: String,
+    #[label("the length of this array is fixed")]
+    arg_span: SourceSpan,
+    #[label("the length of the array can change through this parameter")]
+    param_span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("the step of a range must be positive")]
+#[diagnostic(
+    code(E0265),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("use `rev()` to go through the range backwards")
+)]
+pub struct RangeStepNotPositive {
+    step: i64,
+    #[label("the step is `{}`", step)]
+    span: SourceSpan,
+}
+
 #[derive(Error, Debug, Diagnostic)]
 #[error("cannot index into a value of type `{}`", ty)]
 #[diagnostic(
@@ -1647,7 +2078,7 @@ pub struct FunctionNotInScope {
 pub struct FunctionParamMismatch {
     message: String,
     #[label("function defined here")]
-    func_decl_span: SourceSpan,
+    func_decl_span: Option<SourceSpan>,
     #[label(collection)]
     mismatch_spans: Vec<LabeledSpan>,
     #[label(collection)]
@@ -1745,9 +2176,11 @@ pub struct DirectoryNotFound();
 #[diagnostic(
     code(E0212),
     url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("`{}` is looked up in the directory of the program, the directories given with `--lib-path` and the ones in `PANDORA_PATH`", lib_file),
 )]
 pub struct ExternalLibraryNotFound {
     lib_name: String,
+    lib_file: String,
     #[label("library not found")]
     span: SourceSpan,
 }
@@ -1774,17 +2207,6 @@ pub struct ParseLibraryFileFailed {
     path: String,
 }
 
-#[derive(Error, Debug, Diagnostic)]
-#[error("only function declarations are allowed in external libraries")]
-#[diagnostic(
-    code(E0215),
-    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
-)]
-pub struct NonFunctionDeclaredInExternalLibrary {
-    #[label("this library contains a non-function declaration")]
-    span: SourceSpan,
-}
-
 #[derive(Error, Debug, Diagnostic)]
 #[error("invalid library name")]
 #[diagnostic(
@@ -1809,6 +2231,48 @@ pub struct FunctionInLibraryNotFound {
     span: SourceSpan,
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("function `{}` is private to library `{}`", func_name, lib_name)]
+#[diagnostic(
+    code(E0260),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("only the functions of `{}` can call it", lib_name)
+)]
+pub struct PrivateFunctionInLibrary {
+    lib_name: String,
+    func_name: String,
+    #[label("private function")]
+    span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("`{}` is a mutable variable of library `{}`", var_name, lib_name)]
+#[diagnostic(
+    code(E0267),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("only the constants of `{}`, declared without `mut`, can be read outside of it", lib_name)
+)]
+pub struct MutableVariableInLibrary {
+    lib_name: String,
+    var_name: String,
+    #[label("mutable variable")]
+    span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("too many nested calls")]
+#[diagnostic(
+    code(E0268),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("a function cannot be called while {} calls are running, check that the recursion ends", limit)
+)]
+pub struct CallDepthExceeded {
+    func_name: String,
+    limit: usize,
+    #[label("call to `{}` exceeds the limit", func_name)]
+    span: SourceSpan,
+}
+
 #[derive(Error, Debug, Diagnostic)]
 #[error("cannot divide by zero")]
 #[diagnostic(
@@ -1836,6 +2300,19 @@ pub struct ModdedByZero {
     pub span: SourceSpan,
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("attempt to compute `{}`, which would overflow", expr)]
+#[diagnostic(
+    code(E0261),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("an `int` holds values from `{}` to `{}`", i64::MIN, i64::MAX)
+)]
+pub struct ArithmeticOverflow {
+    pub expr: String,
+    #[label("arithmetic operation overflowed")]
+    pub op_span: SourceSpan,
+}
+
 #[derive(Error, Debug, Diagnostic)]
 #[error("integer literal is too large")]
 #[diagnostic(
@@ -1860,6 +2337,93 @@ pub struct NegArraySize {
     pub span: SourceSpan,
 }
 
+/// An error raised inside function calls. It is shown like the error itself, with the calls
+/// pointed at too.
+#[derive(Debug)]
+pub struct Traced {
+    pub error: Report,
+    /// The calls written in the same file as the error.
+    pub labels: Vec<LabeledSpan>,
+    /// The calls written in other files.
+    pub related: Vec<CalledFrom>,
+}
+
+impl fmt::Display for Traced {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Traced {}
+
+impl Diagnostic for Traced {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.code()
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.error.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.url()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let labels = self.error.labels().into_iter().flatten();
+        Some(Box::new(labels.chain(self.labels.iter().cloned())))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        if self.related.is_empty() {
+            return None;
+        }
+        Some(Box::new(
+            self.related.iter().map(|call| call as &dyn Diagnostic),
+        ))
+    }
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("cycle detected when adding `{}`", lib_name)]
+#[diagnostic(
+    code(E0259),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("remove one of the `add` statements of the cycle:{}", diagram)
+)]
+pub struct CircularImport {
+    pub lib_name: String,
+    pub diagram: String,
+    #[label("`{}` is already being added", lib_name)]
+    pub span: SourceSpan,
+    /// The `add` statements leading to this one, outermost first.
+    #[related]
+    pub related: Vec<AddedFrom>,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("while adding `{}`", lib_name)]
+#[diagnostic(severity(Advice))]
+pub struct AddedFrom {
+    pub lib_name: String,
+    #[label("`{}` added here", lib_name)]
+    pub span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("in the call to `{}`", func_name)]
+#[diagnostic(severity(Advice))]
+pub struct CalledFrom {
+    pub func_name: String,
+    pub label: String,
+    #[label("{}", label)]
+    pub span: SourceSpan,
+}
+
 #[derive(Error, Debug, Diagnostic)]
 #[error("{}", message)]
 #[diagnostic(
@@ -1872,6 +2436,125 @@ pub struct PredefinedError {
     pub message: String,
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("no field `{}` on type `{}`", field, ty)]
+#[diagnostic(
+    code(E0251),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct NoFieldOnType {
+    pub field: String,
+    pub ty: String,
+    #[label("unknown field")]
+    pub span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("missing fields {} in initializer of `{}`", fields, struct_name)]
+#[diagnostic(
+    code(E0252),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct MissingStructFields {
+    pub struct_name: String,
+    pub fields: String,
+    #[label("missing {}", fields)]
+    pub span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("field `{}` specified more than once", field)]
+#[diagnostic(
+    code(E0253),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct FieldSpecifiedMoreThanOnce {
+    pub field: String,
+    #[label("first use of `{}`", field)]
+    pub first_span: SourceSpan,
+    #[label("used more than once")]
+    pub second_span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("type with name `{}` already exists in this scope", type_name)]
+#[diagnostic(
+    code(E0254),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct TypeAlreadyDeclaredInScope {
+    pub type_name: String,
+    #[label("first declared here")]
+    pub first_decl_span: SourceSpan,
+    #[label("help: remove this")]
+    pub second_decl_span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("cannot pass an immutable value to {}", param)]
+#[diagnostic(
+    code(E0258),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct PassImmutableAsMutable {
+    pub param: String,
+    pub param_label: String,
+    pub mut_kw: String,
+    pub var_name: String,
+    pub arg_label: String,
+    #[label("{}", arg_label)]
+    pub arg_span: SourceSpan,
+    #[label("{}", param_label)]
+    pub param_span: SourceSpan,
+    #[label(
+        "help: consider making this binding mutable: `{} {}`",
+        mut_kw,
+        var_name
+    )]
+    pub help_span: Option<SourceSpan>,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("non-exhaustive patterns: `{}` not covered", witness)]
+#[diagnostic(
+    code(E0255),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+    help("ensure that all possible cases are being handled by adding a match arm with a wildcard pattern or an explicit pattern for `{}`", witness)
+)]
+pub struct NonExhaustivePatterns {
+    pub witness: String,
+    pub ty: String,
+    #[label("pattern `{}` of type `{}` not covered", witness, ty)]
+    pub span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("no variant named `{}` found for enum `{}`", variant, enum_name)]
+#[diagnostic(
+    code(E0256),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct NoVariantInEnum {
+    pub variant: String,
+    pub enum_name: String,
+    #[label("variant not found in `{}`", enum_name)]
+    pub span: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("variant `{}` declared more than once", variant)]
+#[diagnostic(
+    code(E0257),
+    url("{}/{}.md", ERROR_CODE_URL, self.code().unwrap()),
+)]
+pub struct VariantDeclaredMoreThanOnce {
+    pub variant: String,
+    #[label("first declared here")]
+    pub first_span: SourceSpan,
+    #[label("help: remove this")]
+    pub second_span: SourceSpan,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ErrorType {
     Recoverable,
@@ -0,0 +1,157 @@
+//! The source formatter, started with `unbox fmt [--check] <files...>`.
+
+use std::{fs, process, sync::Arc};
+
+use crate::{
+    ast::pretty_print::Printer,
+    parse::parser,
+    session::{Session, SourceFile},
+};
+
+/// Formats the given files in place. With `--check`, the files are left untouched, and the
+/// process exits with an error if one of them is not formatted.
+pub fn run(args: &[String]) {
+    let is_check = args.contains(&String::from("--check"));
+    let files: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    if files.is_empty() {
+        eprintln!("Error: fmt needs at least one file to format");
+        process::exit(1);
+    }
+
+    let extensions: &[&str] = if crate::is_genz_mode() {
+        &[".unbx", ".unbxx"]
+    } else {
+        &[".box", ".boxx"]
+    };
+
+    let mut has_error = false;
+    let mut has_unformatted = false;
+    for filename in files {
+        if !extensions.iter().any(|ext| filename.ends_with(ext)) {
+            eprintln!(
+                "Error: File '{}' must have a {} extension",
+                filename,
+                extensions.join(" or ")
+            );
+            has_error = true;
+            continue;
+        }
+
+        let contents = match fs::read_to_string(filename) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Error: Failed to read file '{}': {e}", filename);
+                has_error = true;
+                continue;
+            }
+        };
+
+        let formatted = match format_source(filename, contents.clone()) {
+            Some(formatted) => formatted,
+            None => {
+                has_error = true;
+                continue;
+            }
+        };
+
+        if formatted == contents {
+            continue;
+        }
+
+        if is_check {
+            println!("Would reformat: {}", filename);
+            has_unformatted = true;
+        } else if let Err(e) = fs::write(filename, formatted) {
+            eprintln!("Error: Failed to write file '{}': {e}", filename);
+            has_error = true;
+        } else {
+            println!("Formatted: {}", filename);
+        }
+    }
+
+    if has_error || has_unformatted {
+        process::exit(1);
+    }
+    process::exit(0);
+}
+
+/// Returns the formatted source code, or `None` if it cannot be parsed, in which case the
+/// errors are reported.
+fn format_source(filename: &str, contents: String) -> Option<String> {
+    let contents = Arc::new(contents);
+    let file = SourceFile::new(filename, Arc::clone(&contents));
+    let mut session = Session::new(Arc::new(file));
+
+    let ast = parser::parse(contents.as_str(), &mut session)?;
+    Some(Printer::new(contents.as_str()).print_file(&ast.stmts))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    /// Returns the source files under a directory, including those of its subdirectories.
+    fn source_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(source_files(&path));
+            } else if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("box" | "boxx")
+            ) {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    /// Formats source code, or returns `None` if it cannot be parsed.
+    fn format(contents: &str) -> Option<String> {
+        let contents = Arc::new(contents.to_string());
+        let file = SourceFile::new("<test>", Arc::clone(&contents));
+        let mut session = Session::new_collecting(Arc::new(file));
+        let ast = parser::parse(contents.as_str(), &mut session)?;
+        Some(Printer::new(contents.as_str()).print_file(&ast.stmts))
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+        let mut formatted_files = 0;
+        for path in source_files(&examples) {
+            // Some examples of errors are meant not to parse.
+            let Some(once) = format(&fs::read_to_string(&path).unwrap()) else {
+                continue;
+            };
+            let twice = format(&once).expect("formatted code should parse");
+            assert_eq!(once, twice, "{}", path.display());
+            formatted_files += 1;
+        }
+        assert!(formatted_files > 0);
+    }
+
+    #[test]
+    fn comments_inside_statements_stay_on_their_line() {
+        let src = "fun f(a: int, // first
+    b: int) -> int {
+    yeet a + b;
+}
+set xs: [int; 3] = [1, // one
+    2, 3];
+set y: int = 1 + /* mid */ 2;
+";
+        let expected = "fun f(a: int, b: int) -> int { // first
+    yeet a + b;
+}
+set xs: [int; 3] = [1, 2, 3]; // one
+set y: int = 1 + /* mid */ 2;
+";
+        let formatted = format(src).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), expected);
+    }
+}
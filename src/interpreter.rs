@@ -1,43 +1,1673 @@
+mod array;
 pub mod environment;
 mod errors;
 pub mod eval;
+mod exhaustiveness;
 mod expr;
 mod ident;
 mod libs;
+mod range;
+pub mod resolver;
 mod stmt;
 mod ty;
+mod typeck;
+mod vm;
 
-use std::process::exit;
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use environment::Environment;
+pub(crate) use errors::CallFrame;
 use errors::IError;
 use eval::*;
 use expr::*;
+use libs::{math::MathLib, std::StdLib, Library};
+pub use libs::{NativeLibrary, ParamTy};
+pub use range::Range;
+use resolver::ModuleResolver;
 use stmt::*;
+pub use ty::TyKind;
 use ty::*;
 
-use crate::{ast::Ast, session::Session};
+use crate::{
+    ast::{Ast, Expr, StmtKind},
+    error_handler::ErrorHandler,
+    lexer::{Cursor, TokenKind},
+    parse::parser,
+    session::{BytePos, FileId, Session, SourceFile, SourceMap},
+    span_encoding::Span,
+};
+
+/// Checks the types of the whole program, reporting every type error found. Returns `true` if
+/// there is none, in which case the program can be interpreted.
+pub fn typecheck(ast: &Ast, session: &Session, resolver: &ModuleResolver) -> bool {
+    let errors = typeck::check(ast, resolver.clone());
+    let is_ok = errors.is_empty();
+    for error in errors {
+        let report = error.to_report(&session.error_handler);
+        session.error_handler.report_err(report);
+    }
+
+    is_ok
+}
+
+/// Compiles the program to bytecode and runs it on the VM, with its libraries found by
+/// `resolver`. Returns `false` if a runtime error occurred, in which case it is reported.
+pub fn run(ast: &Ast, session: &Session, resolver: ModuleResolver, is_verbose: bool) -> bool {
+    let program = vm::compile(ast);
+    if is_verbose {
+        println!("\x1b[90m[DEBUG] Compiled program:\n{}\x1b[0m", program);
+    }
 
-pub fn interpret(ast: &Ast, session: &Session, is_verbose: bool) {
     let mut env = Environment::new();
+    env.resolver = resolver;
+    env.error_handler = Some(session.error_handler.clone());
+    if let Err(errors) = vm::Vm::new(&program, env, is_verbose).run() {
+        report_errors(errors, session);
+        return false;
+    }
+
+    true
+}
+
+/// Runs the program with the tree walker, which evaluates the syntax tree directly. Returns
+/// `false` if a runtime error occurred, in which case it is reported.
+pub fn interpret(ast: &Ast, session: &Session, resolver: ModuleResolver, is_verbose: bool) -> bool {
+    let mut env = Environment::new();
+    env.resolver = resolver;
+    env.error_handler = Some(session.error_handler.clone());
     for stmt in &ast.stmts {
         if is_verbose {
             println!("\x1b[90m[DEBUG] Interpreting: {:?}\x1b[0m", stmt);
         }
-        let result = interpret_stmt(&mut env, stmt, false, is_verbose);
-        match result {
-            Err(errors) => {
-                for error in errors {
-                    let report = error.to_report(&session.error_handler);
-                    session.error_handler.report_err(report);
+        if let Err(errors) = interpret_stmt(&mut env, stmt, false, is_verbose) {
+            report_errors(errors, session);
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Returns the names of the functions of an embedded library, or `None` if there is no such
+/// library.
+pub fn embedded_library_functions(name: &str) -> Option<Vec<String>> {
+    let lib: Box<dyn Library> = match name {
+        "std" => Box::new(StdLib::new()),
+        "math" => Box::new(MathLib::new()),
+        _ => return None,
+    };
+    Some(lib.function_names())
+}
+
+/// The state of the REPL, kept alive between its inputs. Inputs are run by the tree walker, so
+/// that the values declared by one input can be used by the next ones.
+pub struct Repl {
+    env: Environment,
+    types: typeck::Context,
+    is_verbose: bool,
+}
+
+impl Repl {
+    /// Creates a session whose libraries are found by `resolver`.
+    pub fn new(resolver: ModuleResolver, is_verbose: bool) -> Self {
+        let mut env = Environment::new();
+        env.resolver = resolver;
+        let file = SourceFile::new("<repl>", Arc::new(String::new()));
+        env.error_handler = Some(ErrorHandler::new(SourceMap::new(Arc::new(file))));
+        Repl {
+            env,
+            types: typeck::Context::new(),
+            is_verbose,
+        }
+    }
+
+    /// Creates a session for the given input, with everything typed before it. The libraries
+    /// added by the previous inputs stay in its source map.
+    pub fn session(&self, file: SourceFile) -> Session {
+        let error_handler = self.env.error_handler.clone().unwrap();
+        error_handler.source_map.set_main_file(Arc::new(file));
+        Session::with_error_handler(error_handler, FileId::MAIN)
+    }
+
+    /// Checks and runs an input, writing the value of each of its expression statements to
+    /// `out`. Errors are reported without ending the session. If a runtime error occurs, the
+    /// statements run before it keep their effects.
+    pub fn eval(&mut self, ast: &Ast, session: &Session, out: &mut impl Write) -> io::Result<()> {
+        self.types.use_libraries_of(&self.env);
+        let errors = typeck::check_input(ast, &mut self.types);
+        if !errors.is_empty() {
+            report_errors(errors, session);
+            return Ok(());
+        }
+
+        for stmt in &ast.stmts {
+            if self.is_verbose {
+                println!("\x1b[90m[DEBUG] Interpreting: {:?}\x1b[0m", stmt);
+            }
+            let result = match &stmt.kind {
+                StmtKind::Expr(expr) => {
+                    interpret_expr(&mut self.env, expr, false, self.is_verbose).map(Some)
+                }
+                _ => interpret_stmt(&mut self.env, stmt, false, self.is_verbose).map(|_| None),
+            };
+
+            match result {
+                Ok(Some(value)) if !matches!(value.kind, ValueKind::Unit) => {
+                    writeln!(out, "{}", value.kind)?;
+                }
+                Ok(_) => {}
+                Err(errors) => {
+                    // The error may have occurred inside a block, whose scope was not popped.
+                    self.env.scopes.truncate(1);
+                    report_errors(errors, session);
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the type of an expression without evaluating it.
+    pub fn print_type(
+        &mut self,
+        expr: &Expr,
+        session: &Session,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.types.use_libraries_of(&self.env);
+        match typeck::check_input_expr(expr, &mut self.types) {
+            Ok(Some(ty)) => writeln!(out, "{}", ty)?,
+            // E.g. the value returned by a function of a library written in Pandora, which is
+            // only known when the function is called.
+            Ok(None) => writeln!(out, "<unknown>")?,
+            Err(errors) => report_errors(errors, session),
+        }
+        Ok(())
+    }
+
+    /// Writes the libraries, types, functions and variables declared so far.
+    pub fn print_env(&self, out: &mut impl Write) -> io::Result<()> {
+        let scope = self.env.scopes.first().unwrap();
+
+        let mut libraries: Vec<&String> = scope.libraries.keys().collect();
+        libraries.sort();
+        for name in libraries {
+            writeln!(out, "add {};", name)?;
+        }
+
+        let mut types: Vec<(&String, &TypeDef)> = scope
+            .types
+            .iter()
+            .map(|(name, (_, def))| (name, def))
+            .collect();
+        types.sort_by_key(|(name, _)| *name);
+        for (name, def) in types {
+            match def {
+                TypeDef::Struct(_) => writeln!(out, "struct {}", name)?,
+                TypeDef::Enum(_) => writeln!(out, "enum {}", name)?,
+            }
+        }
+
+        let mut functions: Vec<(&String, &ValueKind)> = scope
+            .functions
+            .iter()
+            .map(|(name, (_, value))| (name, value))
+            .collect();
+        functions.sort_by_key(|(name, _)| *name);
+        for (name, value) in functions {
+            writeln!(out, "{}: {}", name, value.to_ty_kind())?;
+        }
+
+        for (index, var) in scope.variables.iter().enumerate() {
+            let var = var.borrow();
+            let name = var.ident.name.as_str();
+            // Skip the variables shadowed by a later one.
+            if scope.variables[index + 1..]
+                .iter()
+                .any(|other| other.borrow().ident.name == name)
+            {
+                continue;
+            }
+
+            let is_mut = if var.is_mut { "mut " } else { "" };
+            match &var.val {
+                Some(value) => {
+                    writeln!(out, "{}{}: {} = {}", is_mut, name, var.ty.kind, value.kind)?
                 }
-                exit(1);
+                None => writeln!(out, "{}{}: {}", is_mut, name, var.ty.kind)?,
             }
-            _ => {}
         }
+        Ok(())
+    }
+}
+
+/// The errors that stopped a program, e.g. a syntax error or a division by zero.
+#[derive(Debug)]
+pub struct Diagnostics {
+    reports: Vec<miette::Report>,
+}
+
+impl Diagnostics {
+    /// Returns the errors, which can be rendered with their source code by `miette`.
+    pub fn reports(&self) -> &[miette::Report] {
+        &self.reports
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for report in &self.reports {
+            writeln!(f, "{:?}", report)?;
+        }
+        Ok(())
     }
+}
+
+impl std::error::Error for Diagnostics {}
 
-    exit(0);
+/// Runs Pandora programs in-process. Declarations are kept between the programs run by an
+/// interpreter, so a script can be evaluated once and its functions called many times.
+///
+/// Programs are run by the tree walker, and their errors are returned instead of being printed.
+///
+/// ```
+/// use pandora_interpreter::{Interpreter, ValueKind};
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter
+///     .eval_str("fun double(x: int) -> int { yeet x * 2; }")
+///     .unwrap();
+/// let value = interpreter
+///     .call_function("double", vec![ValueKind::Int(21).into()])
+///     .unwrap();
+/// assert!(matches!(value.kind, ValueKind::Int(42)));
+/// ```
+pub struct Interpreter {
+    env: Environment,
+    types: typeck::Context,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut env = Environment::new();
+        // The libraries of a program run from a string are looked up in the working directory.
+        env.resolver = ModuleResolver::new(Some(".".into()), vec![]);
+        // Each program run is a file of its own. The arguments of the calls made by the host are
+        // not in any of them, so the errors about them point into an empty file.
+        let file = SourceFile::new("<call>", Arc::new(String::new()));
+        env.error_handler = Some(ErrorHandler::new_collecting(SourceMap::new(Arc::new(file))));
+        Interpreter {
+            env,
+            types: typeck::Context::new(),
+        }
+    }
+
+    /// Adds a library of functions written in Rust, which programs import with `add <name>;`.
+    /// It takes precedence over an embedded library with the same name.
+    pub fn add_library(&mut self, name: &str, lib: NativeLibrary) {
+        self.env.native_libs.insert(name.to_string(), lib);
+    }
+
+    /// Adds a directory where libraries are looked up, after the directory of the program and
+    /// the ones added before, but before those of the `PANDORA_PATH` environment variable.
+    pub fn add_lib_path(&mut self, dir: impl Into<PathBuf>) {
+        self.env.resolver.add_lib_path(dir);
+    }
+
+    /// Runs a program, and returns the value of its last statement if it is an expression, e.g.
+    /// `3` for `1 + 2`, or `()` otherwise.
+    pub fn eval_str(&mut self, src: &str) -> Result<Value, Diagnostics> {
+        self.eval("<string>", src)
+    }
+
+    /// Runs the program of a file, like [`Interpreter::eval_str`]. Its libraries are looked up
+    /// in the directory of the file.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, Diagnostics> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path).map_err(|e| Diagnostics {
+            reports: vec![miette::miette!(
+                "failed to read file '{}': {}",
+                path.display(),
+                e
+            )],
+        })?;
+
+        // The libraries of the programs run after it are looked up where they were before.
+        let resolver = self.env.resolver.clone();
+        if let Some(dir) = path.parent() {
+            self.env.resolver.set_program_dir(dir);
+        }
+        let result = self.eval(&path.display().to_string(), &src);
+        self.env.resolver = resolver;
+        result
+    }
+
+    /// Calls a function declared by a program run before, e.g. a callback defined by a script.
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Diagnostics> {
+        let call_span = Span::new(0, 0, FileId::MAIN);
+
+        let function = match self.env.lookup_function(name) {
+            Some(function) => Some(function),
+            // A closure stored in a variable can be called as well.
+            None => self.env.lookup_variable(name).and_then(|var| {
+                let var = var.borrow();
+                match &var.val {
+                    Some(value) if matches!(value.kind, ValueKind::Function(_)) => {
+                        Some((var.ident.span, value.kind.clone()))
+                    }
+                    _ => None,
+                }
+            }),
+        };
+        let (span, function) = function.ok_or_else(|| {
+            let error = IError::FunctionNotInScope {
+                function: name.to_string(),
+                span: call_span,
+            };
+            to_diagnostics(vec![error], &self.call_session())
+        })?;
+
+        // The arguments are not in the source code, so the errors about their types say which
+        // one is wrong instead of pointing at it.
+        if let ValueKind::Function(func) = &function {
+            let reports: Vec<miette::Report> = func
+                .sig
+                .inputs
+                .iter()
+                .zip(&args)
+                .enumerate()
+                .filter(|(_, (param, arg))| param.ty.kind != arg.kind.to_ty_kind())
+                .map(|(i, (param, arg))| {
+                    miette::miette!(
+                        code = "E0240",
+                        "argument {} of `{}` has type `{}`, but `{}` was expected",
+                        i + 1,
+                        name,
+                        arg.kind.to_ty_kind(),
+                        param.ty.kind,
+                    )
+                })
+                .collect();
+            if !reports.is_empty() {
+                return Err(Diagnostics { reports });
+            }
+        }
+
+        let args = args
+            .into_iter()
+            .map(|arg| {
+                let arg = Value {
+                    kind: arg.kind,
+                    span: call_span,
+                };
+                (arg, None)
+            })
+            .collect();
+        let kind = Value::evaluate_function(&self.env, span, function, args, false)
+            .map_err(|errors| to_diagnostics(errors, &self.call_session()))?;
+        Ok(Value { kind, span })
+    }
+
+    fn eval(&mut self, name: &str, src: &str) -> Result<Value, Diagnostics> {
+        let mut contents = src.to_string();
+        terminate_last_statement(&mut contents, 0);
+        let contents = Arc::new(contents);
+
+        // The spans of the declarations of the program point into its file, which stays in the
+        // source map for the errors raised when they are used later.
+        let error_handler = self.env.error_handler.clone().unwrap();
+        let file = SourceFile::new(name, Arc::clone(&contents));
+        let file = error_handler.source_map.add_file(Arc::new(file));
+        let mut session = Session::with_error_handler(error_handler, file);
+        let ast = parser::parse(&contents, &mut session);
+        let ast = match ast {
+            Some(ast) if !session.has_error() => ast,
+            _ => {
+                return Err(Diagnostics {
+                    reports: session.error_handler.take_reports(),
+                })
+            }
+        };
+
+        self.types.use_libraries_of(&self.env);
+        let errors = typeck::check_input(&ast, &mut self.types);
+        if !errors.is_empty() {
+            return Err(to_diagnostics(errors, &session));
+        }
+
+        let mut value = Value {
+            kind: ValueKind::Unit,
+            span: Span::new(0, contents.len() as BytePos, file),
+        };
+        for stmt in &ast.stmts {
+            let result = match &stmt.kind {
+                StmtKind::Expr(expr) => interpret_expr(&mut self.env, expr, false, false),
+                _ => interpret_stmt(&mut self.env, stmt, false, false).map(|_| Value {
+                    kind: ValueKind::Unit,
+                    span: stmt.span,
+                }),
+            };
+
+            match result {
+                Ok(result) => value = result,
+                Err(errors) => {
+                    // The error may have occurred inside a block, whose scope was not popped.
+                    self.env.scopes.truncate(1);
+                    return Err(to_diagnostics(errors, &session));
+                }
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Creates a session to report the errors of a call made by the host.
+    fn call_session(&self) -> Session {
+        let error_handler = self.env.error_handler.clone().unwrap();
+        Session::with_error_handler(error_handler, FileId::MAIN)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Ends the source code from `start` with a semicolon unless its last token is one or a closing
+/// brace, as an expression alone does not need one, e.g. `1 + 2`. The semicolon is put on a line
+/// of its own, so that it is not part of a comment ending the code.
+pub(crate) fn terminate_last_statement(src: &mut String, start: usize) {
+    let mut cursor = Cursor::new(&src[start..]);
+    let mut last = None;
+    loop {
+        match cursor.advance_token().kind {
+            TokenKind::Whitespace
+            | TokenKind::LineComment { .. }
+            | TokenKind::BlockComment { .. } => {}
+            TokenKind::Eof => break,
+            kind => last = Some(kind),
+        }
+    }
+    if !matches!(
+        last,
+        None | Some(TokenKind::Semicolon | TokenKind::CloseBrace)
+    ) {
+        src.push_str("\n;\n");
+    }
+}
+
+fn to_diagnostics(errors: Vec<IError>, session: &Session) -> Diagnostics {
+    report_errors(errors, session);
+    Diagnostics {
+        reports: session.error_handler.take_reports(),
+    }
+}
+
+fn report_errors(errors: Vec<IError>, session: &Session) {
+    for error in errors {
+        let report = error.to_report(&session.error_handler);
+        session.error_handler.report_err(report);
+    }
 }
 
 pub type IResult = Result<EvalResult, Vec<IError>>;
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    /// Runs a program with the tree walker, then with the VM, and returns the errors of each.
+    fn run_both(src: &str) -> [Result<(), Vec<IError>>; 2] {
+        let contents = Arc::new(src.to_string());
+        let file = SourceFile::new("<test>", Arc::clone(&contents));
+        let mut session = Session::new_collecting(Arc::new(file));
+        let ast = parser::parse(&contents, &mut session).expect("the program should parse");
+
+        let mut env = Environment::new();
+        let tree_walk = ast
+            .stmts
+            .iter()
+            .try_for_each(|stmt| interpret_stmt(&mut env, stmt, false, false).map(|_| ()));
+        let program = vm::compile(&ast);
+        let vm = vm::Vm::new(&program, Environment::new(), false).run();
+        [tree_walk, vm]
+    }
+
+    /// Runs a program with an `Interpreter`, then with the VM, and returns the value of its
+    /// `result` variable with each.
+    fn results(src: &str) -> [ValueKind; 2] {
+        let mut interpreter = Interpreter::new();
+        let tree_walk = match interpreter
+            .eval_str(src)
+            .and_then(|_| interpreter.eval_str("result"))
+        {
+            Ok(value) => value.kind,
+            Err(diagnostics) => panic!("{}: {}", src, diagnostics),
+        };
+
+        let contents = Arc::new(src.to_string());
+        let file = SourceFile::new("<test>", Arc::clone(&contents));
+        let mut session = Session::new_collecting(Arc::new(file));
+        let ast = parser::parse(&contents, &mut session).expect("the program should parse");
+        let mut vm = vm::Vm::new(&vm::compile(&ast), Environment::new(), false);
+        if let Err(errors) = vm.run() {
+            panic!("{}: {:?}", src, errors);
+        }
+        let vm = vm
+            .globals()
+            .filter(|(info, _)| info.name == "result")
+            .last()
+            .and_then(|(_, value)| value)
+            .expect("the program should set `result`")
+            .kind
+            .clone();
+        [tree_walk, vm]
+    }
+
+    /// Asserts that a program fails with a single error, the one `is_expected` accepts, with
+    /// each backend.
+    fn assert_fails(src: &str, is_expected: impl Fn(&IError) -> bool) {
+        for result in run_both(src) {
+            assert!(
+                matches!(result.as_ref().map_err(Vec::as_slice), Err([error]) if is_expected(error)),
+                "{}: {:?}",
+                src,
+                result
+            );
+        }
+    }
+
+    /// Returns the codes of the errors of a program run by an `Interpreter`, e.g. `["E0240"]`.
+    fn report_codes(diagnostics: &Diagnostics) -> Vec<String> {
+        diagnostics
+            .reports()
+            .iter()
+            .filter_map(|report| report.code().map(|code| code.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn and_skips_rhs_when_lhs_is_false() {
+        let src = r#"
+set a: [int; 3] = [1, 2, 3];
+set i: int = 3;
+set in_bounds: bool = i < 3 && a[i] > 0;
+set result: bool = in_bounds || false && 1 / 0 == 0;
+"#;
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(false)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn or_skips_rhs_when_lhs_is_true() {
+        let src = r#"
+set a: [int; 3] = [1, 2, 3];
+set i: int = 3;
+set result: bool = i >= 3 || a[i] > 0;
+"#;
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn rhs_is_evaluated_when_lhs_does_not_decide() {
+        for src in [
+            "set b: bool = true && 1 / 0 == 0;",
+            "set b: bool = false || 1 / 0 == 0;",
+        ] {
+            assert_fails(src, |error| matches!(error, IError::DividedByZero { .. }));
+        }
+    }
+
+    #[test]
+    fn operands_are_evaluated_from_left_to_right() {
+        let src = r#"
+set a: [int; 3] = [1, 2, 3];
+set b: bool = a[3] > 0 && 1 / 0 == 0;
+"#;
+        assert_fails(src, |error| {
+            matches!(error, IError::IndexOutOfBounds { .. })
+        });
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // `true || (false && <error>)`: the right operand of `||` is skipped as a whole.
+        let src = "set result: bool = true || false && 1 / 0 == 0;";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+
+        // `(false && <error>) || <error>`: only the right operand of `&&` is skipped.
+        let src = "set b: bool = false && 1 / 0 == 0 || [1][1] == 0;";
+        assert_fails(src, |error| {
+            matches!(error, IError::IndexOutOfBounds { .. })
+        });
+    }
+
+    #[test]
+    fn int_overflow_is_an_error() {
+        for expr in [
+            "max + 1", "min - 1", "max * 2", "min / -1", "min % -1", "-min", "1 << 64", "1 >> -1",
+        ] {
+            let src = format!(
+                "set max: int = 9223372036854775807;\nset min: int = -max - 1;\nset x: int = {};",
+                expr
+            );
+            assert_fails(&src, |error| {
+                matches!(error, IError::ArithmeticOverflow { .. })
+            });
+        }
+    }
+
+    #[test]
+    fn empty_arrays_can_be_iterated_and_compared() {
+        let src = "set xs: [int] = [];
+set mut n: int = 0;
+for x in xs { n += 1; }
+set result: bool = n == 0 && xs == [] && [0; 0] == xs && [1, 2] != [1, 2, 3] && [1, 2] == [1, 2];";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn array_methods_change_the_length() {
+        let src = "set mut xs: [int] = [];
+xs.push(2);
+xs.insert(0, 1);
+xs.extend([3, 4]);
+set last: int = xs.pop();
+set first: int = xs.remove(0);
+set is_shrunk: bool = xs == [2, 3];
+xs.clear();
+set result: bool = is_shrunk && last == 4 && first == 1 && xs == [];";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn array_methods_check_the_array() {
+        let cases: [(&str, fn(&IError) -> bool); 4] = [
+            ("set xs: [int] = [1];\nxs.push(2);", |error| {
+                matches!(error, IError::MutateImmutableVariable { .. })
+            }),
+            ("set mut xs: [int; 1] = [1];\nxs.push(2);", |error| {
+                matches!(error, IError::ArrayLengthIsFixed { .. })
+            }),
+            ("set mut xs: [int] = [];\nset x: int = xs.pop();", |error| {
+                matches!(error, IError::PopFromEmptyArray { .. })
+            }),
+            ("set mut xs: [int] = [1];\nxs.remove(1);", |error| {
+                matches!(error, IError::IndexOutOfBounds { .. })
+            }),
+        ];
+        for (src, is_expected) in cases {
+            assert_fails(src, is_expected);
+        }
+    }
+
+    #[test]
+    fn ranges_count_with_step_and_rev() {
+        // A range this long could not be iterated if its elements were built up front.
+        let src = "set mut xs: [int] = [];
+for i in 0..9223372036854775807 {
+    when i == 3 { br; }
+    xs.push(i);
+}
+for i in 1..=2 { xs.push(i); }
+for i in (0..10).step(4) { xs.push(i); }
+for i in (0..10).step(3).rev() { xs.push(i); }
+for i in 5..5 { xs.push(i); }
+set result: bool = xs == [0, 1, 2, 1, 2, 0, 4, 8, 9, 6, 3, 0] && (0..3).rev() == (0..3).rev();";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+
+        assert_fails("for i in (0..3).step(0) {}", |error| {
+            matches!(error, IError::RangeStepNotPositive { step: 0, .. })
+        });
+    }
+
+    #[test]
+    fn nested_functions_shadow_outer_ones() {
+        let src = "fun f() -> int { yeet 1; }
+fun g() -> int {
+    fun f() -> int { yeet 2; }
+    yeet f();
+}
+set mut n: int = 0;
+{
+    fun f() -> int { yeet 3; }
+    fun h() -> int { yeet f(); }
+    n = h();
+}
+set result: int = n * 100 + g() * 10 + f();";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(321)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn tuples_are_destructured_by_set() {
+        let src = "fun divmod(a: int, b: int) -> (int, int) { yeet (a / b, a % b); }
+set (q, r) = divmod(17, 5);
+set pair: (int, (int, bool)) = (4, (2, true));
+set (a, (b, _)) = pair;
+set result: int = q * 1000 + r * 100 + a * 10 + b + pair.1.0 - 2;";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(3242)), "{:?}", result);
+        }
+
+        let errors = check_types("set (a, b) = (1, 2, 3);");
+        assert!(
+            matches!(errors.as_slice(), [IError::MismatchedType { .. }]),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn struct_fields_are_read_and_assigned() {
+        let src = "struct Point { x: int, y: int }
+struct Rect { top_left: Point, bottom_right: Point }
+fun area(r: Rect) -> int {
+    yeet (r.bottom_right.x - r.top_left.x) * (r.bottom_right.y - r.top_left.y);
+}
+set mut r: Rect = Rect { top_left: Point { x: 0, y: 0 }, bottom_right: Point { x: 2, y: 3 } };
+set before: int = area(r);
+r.bottom_right.x = 4;
+set result: int = before * 100 + area(r);";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(612)), "{:?}", result);
+        }
+
+        let src = "struct Point { x: int, y: int }\nset p: Point = Point { x: 1, y: 2 };\np.x = 3;";
+        assert_fails(src, |error| {
+            matches!(error, IError::MutateImmutableVariable { .. })
+        });
+    }
+
+    #[test]
+    fn match_binds_payloads_and_must_be_exhaustive() {
+        let src = "enum Shape { Circle(int), Rect(int, int), Empty }
+fun area(s: Shape) -> int {
+    match s {
+        Shape::Circle(r) => { yeet 3 * r * r; }
+        Shape::Rect(w, h) => { yeet w * h; }
+        Shape::Empty => { yeet 0; }
+    }
+}
+fun sign(pair: (int, bool)) -> int {
+    match pair {
+        (0, _) => { yeet 0; }
+        (n, true) => { yeet n; }
+        (n, false) => { yeet -n; }
+    }
+}
+set result: int = area(Shape::Circle(2)) * 100 + area(Shape::Rect(2, 3)) + area(Shape::Empty)
+    + sign((0, true)) + sign((4, false)) * 1000;";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(-2794)), "{:?}", result);
+        }
+
+        let src = "enum Shape { Circle(int), Rect(int, int) }
+set s: Shape = Shape::Circle(1);
+match s { Shape::Circle(_) => {} }";
+        let errors = check_types(src);
+        assert!(
+            matches!(errors.as_slice(), [IError::NonExhaustivePatterns { .. }]),
+            "{:?}",
+            errors
+        );
+        assert_fails(src, |error| {
+            matches!(error, IError::NonExhaustivePatterns { .. })
+        });
+    }
+
+    #[test]
+    fn closures_capture_their_scope() {
+        let src = "fun make_adder(n: int) -> fun(int) -> int {
+    yeet fun(x: int) -> int { yeet x + n; };
+}
+fun apply(f: fun(int) -> int, x: int) -> int { yeet f(x); }
+set add5: fun(int) -> int = make_adder(5);
+set mut count: int = 0;
+set inc: fun() = fun() { count += 1; };
+set also_inc: fun() = inc;
+inc();
+also_inc();
+set result: int = apply(add5, 10) * 100 + make_adder(1)(2) * 10 + count;";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(1532)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn backends_report_the_same_errors() {
+        for src in [
+            "fun f() {}\nfun f() {}",
+            "fun f() -> int { yeet 1 / 0; }\nfun g() -> int { fun f() -> int { yeet 1; } yeet f(); }\nset x: int = g() + f();",
+            "fun f(n: int) -> int { when n == 0 { yeet [0][1]; } yeet f(n - 1); }\nf(3);",
+            "set x: int = undefined;",
+            "struct P { x: int }\nset p: P = P { x: 1 };\nset y: int = p.x / 0;",
+        ] {
+            let [tree_walk, vm] = run_both(src);
+            let kinds = |result: &Result<(), Vec<IError>>| {
+                result
+                    .as_ref()
+                    .err()
+                    .map(|errors| errors.iter().map(std::mem::discriminant).collect::<Vec<_>>())
+            };
+            assert!(tree_walk.is_err(), "{}", src);
+            assert_eq!(kinds(&tree_walk), kinds(&vm), "{}: {:?} {:?}", src, tree_walk, vm);
+        }
+    }
+
+    #[test]
+    fn imported_functions_conflict_with_declared_ones() {
+        for src in [
+            "add math.{sqrt};\nfun sqrt(x: float) -> float { yeet x; }",
+            "fun sqrt(x: float) -> float { yeet x; }\nadd math.{sqrt};",
+        ] {
+            assert_fails(src, |error| {
+                matches!(error, IError::FunctionAlreadyDeclaredInScope { .. })
+            });
+        }
+
+        // A function declared in an inner block shadows the imported one.
+        let src = "add math.{sqrt};
+set mut inner: float = 0.0;
+{
+    fun sqrt(x: float) -> float { yeet x; }
+    inner = sqrt(4.0);
+}
+set result: float = inner * 10.0 + sqrt(4.0);";
+        for result in results(src) {
+            assert!(
+                matches!(result, ValueKind::Float(x) if x == 42.0),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    /// Parses a program and returns the errors the type checker finds in it.
+    fn check_types(src: &str) -> Vec<IError> {
+        let contents = Arc::new(src.to_string());
+        let file = SourceFile::new("<test>", Arc::clone(&contents));
+        let mut session = Session::new_collecting(Arc::new(file));
+        let ast = parser::parse(&contents, &mut session).expect("the program should parse");
+        typeck::check(&ast, ModuleResolver::default())
+    }
+
+    #[test]
+    fn typeck_reports_errors_in_branches_that_are_not_taken() {
+        let src = "when false { set x: int = \"a\"; } alt { set y: str = 1; }
+during false { set z: bool = 1.0; }";
+        let errors = check_types(src);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error, IError::MismatchedType { .. })),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn typeck_reports_mismatched_return_types() {
+        for src in [
+            "fun f() -> int { yeet \"a\"; }",
+            "fun f() -> int { when false { yeet true; } yeet 1; }",
+            "fun f() -> [int; 2] { yeet [1]; }",
+        ] {
+            let errors = check_types(src);
+            assert!(
+                matches!(
+                    errors.as_slice(),
+                    [IError::MismatchedFunctionReturnType { .. }]
+                ),
+                "{}: {:?}",
+                src,
+                errors
+            );
+        }
+        assert!(check_types("fun f() -> int { yeet 1; }").is_empty());
+    }
+
+    #[test]
+    fn typeck_reports_bodies_that_may_not_return() {
+        for src in [
+            "fun f() -> int { set x: int = 1; }",
+            "fun f(b: bool) -> int { when b { yeet 1; } }",
+            "fun f(b: bool) -> int { when b { yeet 1; } alt when !b { yeet 2; } }",
+            "fun f(x: int) -> int { match x { 0 => { yeet 1; } _ => {} } }",
+            "fun f(b: bool) -> int { during b { yeet 1; } }",
+            "set f: fun(bool) -> int = fun(b: bool) -> int { when b { yeet 1; } };",
+        ] {
+            let errors = check_types(src);
+            assert!(
+                matches!(errors.as_slice(), [IError::MissingReturnStatement { .. }]),
+                "{}: {:?}",
+                src,
+                errors
+            );
+        }
+        for src in [
+            "fun f(b: bool) -> int { when b { yeet 1; } alt { yeet 2; } }",
+            "fun f(x: int) -> int { match x { 0 => { yeet 1; } _ => { yeet 2; } } }",
+            "fun f() -> int { { yeet 1; } }",
+            "fun f() { when false { yeet; } }",
+            "set f: fun() -> int = fun() -> int { yeet 1; };",
+        ] {
+            let errors = check_types(src);
+            assert!(errors.is_empty(), "{}: {:?}", src, errors);
+        }
+    }
+
+    #[test]
+    fn typeck_reports_calls_to_libraries_that_were_not_added() {
+        let errors = check_types("when false { math.sqrt(1.0); }");
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [IError::LibraryNotFound { library, .. }] if library == "math"
+            ),
+            "{:?}",
+            errors
+        );
+        assert!(check_types("add math;\nwhen false { math.sqrt(1.0); }").is_empty());
+    }
+
+    #[test]
+    fn typeck_reports_bad_binary_operand_types() {
+        let cases: [(&str, fn(&IError) -> bool); 4] = [
+            ("set x: int = 1 + \"a\";", |error| {
+                matches!(error, IError::CannotAdd { .. })
+            }),
+            ("set x: float = 1.0 - 1;", |error| {
+                matches!(error, IError::CannotSubtract { .. })
+            }),
+            ("set b: bool = true < 1;", |error| {
+                matches!(error, IError::CannotCompare { .. })
+            }),
+            ("set b: bool = 1 && true;", |error| {
+                matches!(error, IError::NoImplForOp { .. })
+            }),
+        ];
+        for (src, is_expected) in cases {
+            let errors = check_types(src);
+            assert!(
+                matches!(errors.as_slice(), [error] if is_expected(error)),
+                "{}: {:?}",
+                src,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn typeck_reports_names_not_in_scope() {
+        let cases: [(&str, fn(&IError) -> bool); 5] = [
+            (
+                "when false { println(nosuchvar); }",
+                |error| matches!(error, IError::CannotFindVariableInScope { var_name, .. } if var_name == "nosuchvar"),
+            ),
+            (
+                "when false { nosuch(1); }",
+                |error| matches!(error, IError::FunctionNotInScope { function, .. } if function == "nosuch"),
+            ),
+            (
+                "set x: int = 5;\nfun f(a: int) -> int { yeet a + x; }",
+                |error| matches!(error, IError::CannotFindVariableInScope { var_name, .. } if var_name == "x"),
+            ),
+            ("when false { nosuchvar = 1; }", |error| {
+                matches!(error, IError::CannotFindVariableInScope { .. })
+            }),
+            ("when false { println(nosuchlib.SIZE); }", |error| {
+                matches!(error, IError::CannotFindVariableInScope { .. })
+            }),
+        ];
+        for (src, is_expected) in cases {
+            let errors = check_types(src);
+            assert!(
+                matches!(errors.as_slice(), [error] if is_expected(error)),
+                "{}: {:?}",
+                src,
+                errors
+            );
+        }
+
+        // Named functions, closures and the standard library are still found.
+        let src = "set x: int = 5;
+fun g() -> int { yeet 1; }
+fun f(a: int) -> int { yeet a + g() + h(); }
+fun h() -> int { yeet strlen(\"a\"); }
+set k: fun() -> int = fun() -> int { yeet x; };
+set y: int = f(x) + k();";
+        assert!(check_types(src).is_empty(), "{:?}", check_types(src));
+    }
+
+    #[test]
+    fn fixed_arrays_cannot_grow_through_mut_params() {
+        for src in [
+            "fun grow(mut a: [int]) { a.push(1); }\nset mut x: [int; 2] = [1, 2];\ngrow(x);",
+            "fun grow(mut a: [int]) { a.push(1); }\nset mut x: [[int; 2]] = [[1, 2]];\ngrow(x[0]);",
+            "fun grow(mut a: [[int]]) { a[0].push(1); }\nset mut x: [[int; 2]] = [[1, 2]];\ngrow(x);",
+        ] {
+            let errors = check_types(src);
+            assert!(
+                matches!(errors.as_slice(), [IError::PassFixedArrayAsGrowable { .. }]),
+                "{}: {:?}",
+                src,
+                errors
+            );
+            assert_fails(src, |error| {
+                matches!(error, IError::PassFixedArrayAsGrowable { .. })
+            });
+        }
+
+        let src = "fun grow(mut a: [int]) { a.push(1); }
+fun set_first(mut a: [int; 2]) { a[0] = 0; }
+set mut x: [int] = [1, 2];
+set mut y: [int; 2] = [1, 2];
+grow(x);
+set_first(y);
+set result: bool = x == [1, 2, 1] && y == [0, 2];";
+        assert!(check_types(src).is_empty());
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Bool(true)), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn typeck_reports_immutable_args_of_mut_params() {
+        for src in [
+            "fun g(mut x: int) {}\nwhen false { g(5); }",
+            "fun g(mut x: int) {}\nset y: int = 1;\ng(y);",
+            "set g: fun(mut int) = fun(mut x: int) {};\nset y: int = 1;\ng(y);",
+            "fun g(f: fun(mut int)) { f(1); }",
+        ] {
+            let errors = check_types(src);
+            assert!(
+                matches!(errors.as_slice(), [IError::PassImmutableAsMutable { .. }]),
+                "{}: {:?}",
+                src,
+                errors
+            );
+        }
+
+        // A function with a `mut` parameter does not have the type of one without.
+        let errors = check_types("set f: fun(int) = fun(mut x: int) {};");
+        assert!(
+            matches!(errors.as_slice(), [IError::MismatchedType { .. }]),
+            "{:?}",
+            errors
+        );
+        let src = "set g: fun(mut int) = fun(mut x: int) { x += 1; };
+set mut n: int = 1;
+g(n);";
+        assert!(check_types(src).is_empty());
+    }
+
+    #[test]
+    fn mut_params_refer_to_the_places_passed() {
+        let src = "fun h(mut x: int, mut y: int) { x += 1; y += 10; }
+set mut n: int = 3;
+h(n, n);
+set mut m: int = 0;
+set bump: fun() = fun() { m += 100; };
+fun call_with(mut x: int, f: fun()) { x += 1; f(); x += 1; }
+call_with(m, bump);
+struct P { x: int, y: int }
+fun swap(mut a: int, mut b: int) { set t: int = a; a = b; b = t; }
+set mut p: P = P { x: 1, y: 2 };
+swap(p.x, p.y);
+fun double(mut v: int) { v *= 2; }
+fun double_second(mut arr: [int]) { double(arr[1]); }
+set mut arr: [int] = [1, 2, 3];
+double_second(arr);
+set result: [int] = [n, m, p.x, p.y, arr[1]];";
+        for result in results(src) {
+            let values = match result {
+                ValueKind::Array(values, _) => values,
+                kind => panic!("{:?}", kind),
+            };
+            let ints: Vec<_> = values
+                .iter()
+                .map(|value| match value.kind {
+                    ValueKind::Int(x) => x,
+                    _ => unreachable!("The elements are integers"),
+                })
+                .collect();
+            assert_eq!(ints, [14, 102, 2, 1, 4]);
+        }
+
+        // A closure capturing the parameter keeps referring to the place after the call.
+        let src = "fun keep(mut x: int) -> fun() -> int {
+    x += 1;
+    yeet fun() -> int { x += 10; yeet x; };
+}
+set mut get: fun() -> int = fun() -> int { yeet 0; };
+set mut total: int = 0;
+for i in 0..3 {
+    set mut n: int = i;
+    get = keep(n);
+    total += get() + n;
+}
+set result: int = total * 100 + get();";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(7223)), "{:?}", result);
+        }
+    }
+
+    /// Writes the files of a test to a temporary directory unique to the test, and returns it.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pandora-{}-{}", test, std::process::id()));
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn call_function_checks_the_number_of_arguments() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str("fun sum(x: int, y: int) -> int { yeet x + y; }")
+            .unwrap();
+
+        for args in [vec![ValueKind::Int(1).into()], vec![]] {
+            let diagnostics = interpreter.call_function("sum", args).unwrap_err();
+            assert_eq!(report_codes(&diagnostics), ["E0240"], "{}", diagnostics);
+        }
+
+        let args = vec![ValueKind::Int(1).into(), ValueKind::Int(2).into()];
+        let value = interpreter.call_function("sum", args).unwrap();
+        assert!(matches!(value.kind, ValueKind::Int(3)));
+    }
+
+    #[test]
+    fn call_function_names_the_argument_of_the_wrong_type() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str("fun sum(x: int, y: int) -> int { yeet x + y; }")
+            .unwrap();
+
+        let args = vec![ValueKind::Int(1).into(), ValueKind::Bool(true).into()];
+        let diagnostics = interpreter.call_function("sum", args).unwrap_err();
+        assert_eq!(report_codes(&diagnostics), ["E0240"], "{}", diagnostics);
+        let rendered = diagnostics.to_string();
+        assert!(
+            rendered.contains("argument 2 of `sum` has type `bool`, but `int` was expected"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn eval_str_returns_an_expression_ending_with_a_comment() {
+        let mut interpreter = Interpreter::new();
+        for src in [
+            "1 + 2 // comment",
+            "1 + /* two */ 2 /* end */",
+            "1 + 2; // done",
+        ] {
+            match interpreter.eval_str(src) {
+                Ok(value) => assert!(matches!(value.kind, ValueKind::Int(3)), "{}", src),
+                Err(diagnostics) => panic!("{}: {}", src, diagnostics),
+            }
+        }
+    }
+
+    #[test]
+    fn errors_point_into_the_program_that_declared_the_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str("fun div(x: int, y: int) -> int {\n    yeet x / y;\n}")
+            .unwrap();
+        assert!(interpreter.eval_str("set x: int = \"a\";").is_err());
+        interpreter.eval_str("div(4, 2)").unwrap();
+
+        let args = vec![ValueKind::Int(1).into(), ValueKind::Int(0).into()];
+        let diagnostics = interpreter.call_function("div", args).unwrap_err();
+        let rendered = diagnostics.to_string();
+        assert!(rendered.contains("yeet x / y;"), "{}", rendered);
+        assert!(!rendered.contains("\"a\""), "{}", rendered);
+    }
+
+    #[test]
+    fn gcd_of_min_int_overflows_only_when_it_does_not_fit() {
+        let src = "add math;
+set min: int = -9223372036854775807 - 1;
+set result: int = math.gcd(min, 2) * 100 + math.gcd(6, min) * 10 + math.gcd(-4, -6);";
+        for result in results(src) {
+            assert!(matches!(result, ValueKind::Int(222)), "{:?}", result);
+        }
+
+        for call in ["math.gcd(min, 0)", "math.gcd(min, min)"] {
+            let src = format!(
+                "add math;\nset min: int = -9223372036854775807 - 1;\nset x: int = {};",
+                call
+            );
+            assert_fails(&src, |error| {
+                matches!(error, IError::ArithmeticOverflow { .. })
+            });
+        }
+    }
+
+    #[test]
+    fn typeck_checks_library_calls_against_their_signatures() {
+        let src = "add math;
+add math.{sqrt};
+set x: int = math.sqrt(4.0);
+math.sqrt(\"a\");
+sqrt(1);
+set y: float = sqrt(2.0);";
+        let errors = check_types(src);
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [
+                    IError::MismatchedType { .. },
+                    IError::FunctionParamMismatch { .. },
+                    IError::FunctionParamMismatch { .. },
+                ]
+            ),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn native_functions_are_type_checked_and_their_values_validated() {
+        let mut lib = NativeLibrary::new();
+        lib.add_function(
+            "half",
+            vec![ParamTy::Ty(TyKind::Int)],
+            TyKind::Int,
+            |args| match args[0].kind {
+                ValueKind::Int(x) => Ok(ValueKind::Int(x / 2)),
+                _ => unreachable!("The argument is checked to be an integer"),
+            },
+        );
+        lib.add_function("broken", vec![], TyKind::Int, |_| Ok(ValueKind::Bool(true)));
+        let mut interpreter = Interpreter::new();
+        interpreter.add_library("host", lib);
+
+        let value = interpreter.eval_str("add host;\nhost.half(8)").unwrap();
+        assert!(matches!(value.kind, ValueKind::Int(4)));
+        for src in ["host.half(true);", "set x: bool = host.half(1);"] {
+            assert!(interpreter.eval_str(src).is_err(), "{}", src);
+        }
+
+        let diagnostics = interpreter.eval_str("host.broken();").unwrap_err();
+        assert!(
+            diagnostics.to_string().contains("instead of `int`"),
+            "{}",
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn top_level_code_of_a_module_runs_once() {
+        let counter = "add host;
+set mut i: int = 0;
+during i < 3 { i += 1; }
+set LOADS: int = host.load();
+set COUNT: int = i;";
+        let dir = write_files(
+            "modules",
+            &[
+                ("counter.boxx", counter),
+                ("uses_counter.boxx", "add counter;"),
+            ],
+        );
+
+        let loads = Rc::new(Cell::new(0));
+        let mut lib = NativeLibrary::new();
+        let host_loads = Rc::clone(&loads);
+        lib.add_function("load", vec![], TyKind::Int, move |_| {
+            host_loads.set(host_loads.get() + 1);
+            Ok(ValueKind::Int(host_loads.get()))
+        });
+        let mut interpreter = Interpreter::new();
+        interpreter.add_library("host", lib);
+        interpreter.add_lib_path(&dir);
+
+        let value = interpreter.eval_str(
+            "add counter;
+add uses_counter;
+fun f() -> int { add counter; yeet counter.LOADS; }
+f() * 10 + counter.COUNT",
+        );
+        let variable = interpreter.eval_str("counter.i");
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(value.unwrap().kind, ValueKind::Int(13)));
+        let codes = report_codes(&variable.unwrap_err());
+        assert_eq!(codes, ["E0267"]);
+        assert_eq!(loads.get(), 1);
+    }
+
+    #[test]
+    fn calls_to_library_functions_point_at_the_function() {
+        let dir = write_files(
+            "lib-calls",
+            &[("lib2.boxx", "fun outer(x: int) -> int { yeet 10 / x; }")],
+        );
+        let src = "add lib2;\nset y: int = lib2.outer(0);";
+        let contents = Arc::new(src.to_string());
+        let mut session =
+            Session::new_collecting(Arc::new(SourceFile::new("<test>", Arc::clone(&contents))));
+        let ast = parser::parse(&contents, &mut session).unwrap();
+        let new_env = || {
+            let mut env = Environment::new();
+            env.resolver = ModuleResolver::new(Some(dir.clone()), vec![]);
+            env.error_handler = Some(session.error_handler.clone());
+            env
+        };
+
+        let mut env = new_env();
+        let tree_walk = ast
+            .stmts
+            .iter()
+            .try_for_each(|stmt| interpret_stmt(&mut env, stmt, false, false).map(|_| ()));
+        let vm = vm::Vm::new(&vm::compile(&ast), new_env(), false).run();
+        fs::remove_dir_all(&dir).unwrap();
+
+        for result in [tree_walk, vm] {
+            let trace = match result.as_ref().map_err(Vec::as_slice) {
+                Err([IError::Traced { trace, .. }]) => trace,
+                _ => panic!("{:?}", result),
+            };
+            let [frame] = trace.as_slice() else {
+                panic!("{:?}", trace)
+            };
+            let span = frame.call_span;
+            assert_eq!(frame.func_name, "outer");
+            assert_eq!(span.file, FileId::MAIN);
+            assert_eq!(
+                &src[span.offset as usize..span.end() as usize],
+                "lib2.outer"
+            );
+        }
+    }
+
+    #[test]
+    fn hidden_library_functions_are_named_with_the_full_path() {
+        let dir = write_files(
+            "hidden-path",
+            &[("utils/strings.boxx", "priv fun secret() -> int { yeet 1; }")],
+        );
+        for src in [
+            "add utils.strings;\nstrings.secret();",
+            "add utils.strings as s;\ns.secret();",
+            "add utils.strings.{secret};",
+        ] {
+            let contents = Arc::new(src.to_string());
+            let mut session =
+                Session::new_collecting(Arc::new(SourceFile::new("<test>", Arc::clone(&contents))));
+            let ast = parser::parse(&contents, &mut session).unwrap();
+            let new_env = || {
+                let mut env = Environment::new();
+                env.resolver = ModuleResolver::new(Some(dir.clone()), vec![]);
+                env
+            };
+
+            let mut env = new_env();
+            let tree_walk = ast
+                .stmts
+                .iter()
+                .try_for_each(|stmt| interpret_stmt(&mut env, stmt, false, false).map(|_| ()));
+            let vm = vm::Vm::new(&vm::compile(&ast), new_env(), false).run();
+            for result in [tree_walk, vm] {
+                assert!(
+                    matches!(
+                        result.as_ref().map_err(Vec::as_slice),
+                        Err([IError::PrivateFunctionInLibrary { lib_name, .. }])
+                            if lib_name == "utils.strings"
+                    ),
+                    "{}: {:?}",
+                    src,
+                    result
+                );
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recursion_that_never_ends_exceeds_the_call_depth_of_the_vm() {
+        let src = "fun count(n: int) -> int {\n    yeet count(n + 1);\n}\nset x: int = count(0);";
+        let contents = Arc::new(src.to_string());
+        let mut session =
+            Session::new_collecting(Arc::new(SourceFile::new("<test>", Arc::clone(&contents))));
+        let ast = parser::parse(&contents, &mut session).unwrap();
+        let result = vm::Vm::new(&vm::compile(&ast), Environment::new(), false).run();
+
+        let errors = result.unwrap_err();
+        let [IError::Traced { error, trace }] = errors.as_slice() else {
+            panic!("{:?}", errors)
+        };
+        assert!(
+            matches!(**error, IError::CallDepthExceeded { .. }),
+            "{:?}",
+            error
+        );
+        assert_eq!(trace.len(), 10_000);
+        assert!(trace.iter().all(|frame| frame.func_name == "count"));
+    }
+
+    #[test]
+    fn library_functions_are_compiled_for_the_vm() {
+        let shapes = "add math;
+set SIDES: int = 4;
+set perimeter: fun(int) -> int = fun(side: int) -> int { yeet side * SIDES; };
+priv fun square(x: int) -> int { yeet x * x; }
+fun area(side: int) -> int { yeet square(side); }
+fun root(x: float) -> float { yeet math.sqrt(x); }";
+        let dir = write_files("compiled", &[("shapes.boxx", shapes)]);
+
+        let contents = Arc::new(shapes.to_string());
+        let mut session = Session::new_collecting(Arc::new(SourceFile::new(
+            "shapes.boxx",
+            Arc::clone(&contents),
+        )));
+        let ast = parser::parse(&contents, &mut session).unwrap();
+        let lib = vm::load_library(&ast, Environment::new(), Default::default(), false).unwrap();
+        assert!(matches!(
+            lib.get_user_function("area"),
+            Some(ValueKind::Function(func)) if matches!(func.body, FuncBody::Bytecode(_))
+        ));
+
+        let checked = Rc::new(Cell::new(0));
+        let mut host = NativeLibrary::new();
+        let host_checked = Rc::clone(&checked);
+        host.add_function(
+            "check",
+            vec![ParamTy::Ty(TyKind::Int)],
+            TyKind::Unit,
+            move |args| match args[0].kind {
+                ValueKind::Int(x) => {
+                    host_checked.set(x);
+                    Ok(ValueKind::Unit)
+                }
+                _ => unreachable!("The argument is checked to be an integer"),
+            },
+        );
+        let src = "add host;
+add shapes;
+set p: fun(int) -> int = shapes.perimeter;
+host.check(shapes.area(3) + p(2) + shapes.root(16.0) as int);";
+        let contents = Arc::new(src.to_string());
+        let mut session =
+            Session::new_collecting(Arc::new(SourceFile::new("<test>", Arc::clone(&contents))));
+        let ast = parser::parse(&contents, &mut session).unwrap();
+        let mut env = Environment::new();
+        env.resolver = ModuleResolver::new(Some(dir.clone()), vec![]);
+        env.native_libs.insert("host".to_string(), host);
+        let result = vm::Vm::new(&vm::compile(&ast), env, false).run();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(checked.get(), 21);
+    }
+
+    #[test]
+    fn eval_file_looks_up_libraries_next_to_the_file_only_while_it_runs() {
+        let dir = write_files(
+            "program-dir",
+            &[
+                ("helper.boxx", "fun one() -> int { yeet 1; }"),
+                ("main.box", "add helper;\nhelper.one()"),
+            ],
+        );
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.eval_file(dir.join("main.box"));
+        let later = interpreter.eval_str("add helper as again;");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(value.unwrap().kind, ValueKind::Int(1)));
+        assert!(later.is_err());
+    }
+
+    #[test]
+    fn errors_in_libraries_point_into_their_files() {
+        let dir = write_files(
+            "traces",
+            &[
+                ("calc.boxx", "fun inv(x: int) -> int { yeet 10 / x; }"),
+                (
+                    "main.box",
+                    "add calc;\nfun run(x: int) -> int { yeet calc.inv(x); }\nset y: int = run(0);",
+                ),
+            ],
+        );
+        let diagnostics = Interpreter::new()
+            .eval_file(dir.join("main.box"))
+            .unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report_codes(&diagnostics), ["E0245"]);
+        let rendered = diagnostics.to_string();
+        for expected in [
+            "calc.boxx",
+            "10 / x",
+            "main.box",
+            "in the call to `inv`",
+            "in the call to `run`",
+        ] {
+            assert!(rendered.contains(expected), "{}: {}", expected, rendered);
+        }
+    }
+
+    #[test]
+    fn modules_are_loaded_once_per_run() {
+        let dir = write_files("cache", &[("helper.boxx", "fun one() -> int { yeet 1; }")]);
+        let mut lib = NativeLibrary::new();
+        let helper = dir.join("helper.boxx");
+        lib.add_function("change_helper", vec![], TyKind::Unit, move |_| {
+            fs::write(&helper, "fun one() -> int { yeet 2; }").unwrap();
+            Ok(ValueKind::Unit)
+        });
+        let mut interpreter = Interpreter::new();
+        interpreter.add_library("host", lib);
+        interpreter.add_lib_path(&dir);
+
+        // The file has changed when the function imports the module again.
+        let value = interpreter.eval_str(
+            "add host;
+add helper;
+host.change_helper();
+fun count(n: int) -> int {
+    add helper;
+    when n == 0 { yeet 0; }
+    yeet helper.one() + count(n - 1);
+}
+count(10)",
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(value.unwrap().kind, ValueKind::Int(10)));
+    }
+
+    #[test]
+    fn libraries_are_added_with_aliases_and_selected_functions() {
+        let dir = write_files(
+            "imports",
+            &[(
+                "geometry.boxx",
+                "fun area(w: int, h: int) -> int { yeet w * h; }\nfun perimeter(w: int, h: int) -> int { yeet 2 * (w + h); }",
+            )],
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter.add_lib_path(&dir);
+        let value = interpreter.eval_str(
+            "add geometry as g;
+add geometry.{area, perimeter};
+add math as m;
+area(2, 3) * 100 + perimeter(1, 2) * 10 + g.area(1, 1) + m.gcd(4, 6)",
+        );
+        let conflict = interpreter.eval_str("add math as g;");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(value.unwrap().kind, ValueKind::Int(663)));
+        assert_eq!(report_codes(&conflict.unwrap_err()), ["E0209"]);
+    }
+
+    #[test]
+    fn circular_imports_are_reported() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.boxx", "add b;\nfun f() {}"),
+                ("b.boxx", "add c;\nfun g() {}"),
+                ("c.boxx", "add a;\nfun h() {}"),
+            ],
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter.add_lib_path(&dir);
+        let diagnostics = interpreter.eval_str("add a;\na.f();").unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report_codes(&diagnostics), ["E0259"]);
+        let rendered = diagnostics.to_string();
+        for lib in ["a.boxx", "b.boxx", "c.boxx"] {
+            assert!(rendered.contains(lib), "{}: {}", lib, rendered);
+        }
+    }
+
+    #[test]
+    fn private_functions_are_only_called_by_their_module() {
+        let dir = write_files(
+            "private",
+            &[(
+                "shapes.boxx",
+                "priv fun square(x: int) -> int { yeet x * x; }\nfun area(side: int) -> int { yeet square(side); }",
+            )],
+        );
+        let mut interpreter = Interpreter::new();
+        interpreter.add_lib_path(&dir);
+        let value = interpreter.eval_str("add shapes;\nshapes.area(3)");
+        let private = interpreter.eval_str("shapes.square(3);");
+        let selected = interpreter.eval_str("add shapes.{square};");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(value.unwrap().kind, ValueKind::Int(9)));
+        assert_eq!(report_codes(&private.unwrap_err()), ["E0260"]);
+        assert_eq!(report_codes(&selected.unwrap_err()), ["E0260"]);
+    }
+}
@@ -0,0 +1,152 @@
+//! The methods of arrays, e.g. `xs.push(1)`. They are called on an array stored in a variable,
+//! and change it in place.
+
+use std::fmt;
+
+use crate::span_encoding::Span;
+
+use super::{
+    errors::IError,
+    eval::{Value, ValueKind},
+    ty::TyKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArrayMethod {
+    /// `push(x)` adds `x` at the end.
+    Push,
+    /// `pop()` removes the last element and returns it.
+    Pop,
+    /// `insert(i, x)` adds `x` at the index `i`, shifting the elements after it.
+    Insert,
+    /// `remove(i)` removes the element at the index `i` and returns it.
+    Remove,
+    /// `clear()` removes every element.
+    Clear,
+    /// `extend(other)` adds the elements of the array `other` at the end.
+    Extend,
+}
+
+impl ArrayMethod {
+    pub const ALL: [ArrayMethod; 6] = [
+        ArrayMethod::Push,
+        ArrayMethod::Pop,
+        ArrayMethod::Insert,
+        ArrayMethod::Remove,
+        ArrayMethod::Clear,
+        ArrayMethod::Extend,
+    ];
+
+    pub fn from_name(name: &str) -> Option<ArrayMethod> {
+        ArrayMethod::ALL
+            .into_iter()
+            .find(|method| method.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrayMethod::Push => "push",
+            ArrayMethod::Pop => "pop",
+            ArrayMethod::Insert => "insert",
+            ArrayMethod::Remove => "remove",
+            ArrayMethod::Clear => "clear",
+            ArrayMethod::Extend => "extend",
+        }
+    }
+
+    /// The types of the parameters of the method, on an array whose elements have type `el_ty`.
+    pub fn params(&self, el_ty: &TyKind) -> Vec<TyKind> {
+        match self {
+            ArrayMethod::Push => vec![el_ty.clone()],
+            ArrayMethod::Pop | ArrayMethod::Clear => vec![],
+            ArrayMethod::Insert => vec![TyKind::Int, el_ty.clone()],
+            ArrayMethod::Remove => vec![TyKind::Int],
+            ArrayMethod::Extend => vec![TyKind::Array(Box::new(el_ty.clone()), -1)],
+        }
+    }
+
+    /// The type of the value returned by the method, on an array whose elements have type
+    /// `el_ty`.
+    pub fn output(&self, el_ty: &TyKind) -> TyKind {
+        match self {
+            ArrayMethod::Pop | ArrayMethod::Remove => el_ty.clone(),
+            _ => TyKind::Unit,
+        }
+    }
+
+    /// Calls the method on the elements of an array, with arguments of the types of its
+    /// parameters. An element added to an empty array of unknown element type gives the array
+    /// its type.
+    pub fn call(
+        &self,
+        elements: &mut Vec<Value>,
+        el_ty: &mut TyKind,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<ValueKind, Vec<IError>> {
+        let mut args = args.into_iter();
+        let mut next_arg = || args.next().expect("the arguments are checked");
+
+        match self {
+            ArrayMethod::Push => {
+                let value = typed_element(next_arg(), el_ty);
+                elements.push(value);
+            }
+            ArrayMethod::Pop => match elements.pop() {
+                Some(value) => return Ok(value.kind),
+                None => return Err(vec![IError::PopFromEmptyArray { span }]),
+            },
+            ArrayMethod::Insert => {
+                let index = next_arg();
+                let index = checked_index(&index, elements.len() + 1, elements.len())?;
+                let value = typed_element(next_arg(), el_ty);
+                elements.insert(index, value);
+            }
+            ArrayMethod::Remove => {
+                let index = next_arg();
+                let index = checked_index(&index, elements.len(), elements.len())?;
+                return Ok(elements.remove(index).kind);
+            }
+            ArrayMethod::Clear => elements.clear(),
+            ArrayMethod::Extend => {
+                let other = match next_arg().kind {
+                    ValueKind::Array(other, other_el_ty) => {
+                        el_ty.infer_from(&other_el_ty);
+                        other
+                    }
+                    _ => unreachable!("The argument is checked to be an array"),
+                };
+                elements.extend(other.into_iter().map(|value| typed_element(value, el_ty)));
+            }
+        }
+
+        Ok(ValueKind::Unit)
+    }
+}
+
+impl fmt::Display for ArrayMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Returns the element to add to an array of `el_ty`, giving their type to the array and to
+/// the empty arrays in the element if it is not known yet.
+fn typed_element(mut value: Value, el_ty: &mut TyKind) -> Value {
+    el_ty.infer_from(&value.to_ty_kind());
+    value.kind.infer_ty(el_ty);
+    value
+}
+
+/// Returns the index if it is below `bound`, where `len` is the length of the array.
+fn checked_index(index: &Value, bound: usize, len: usize) -> Result<usize, Vec<IError>> {
+    match index.kind {
+        ValueKind::Int(i) if i >= 0 && (i as usize) < bound => Ok(i as usize),
+        ValueKind::Int(i) => Err(vec![IError::IndexOutOfBounds {
+            len: len as i64,
+            index: i,
+            span: index.span,
+        }]),
+        _ => unreachable!("The index is checked to be an integer"),
+    }
+}
@@ -3,30 +3,66 @@ pub mod variable;
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
 };
 
 use miette::NamedSource;
-use variable::Variable;
+use variable::{Reference, Variable};
 
-use crate::{ast, parse::parser, session::Session, span_encoding::Span};
+use crate::{
+    ast, error_handler::ErrorHandler, parse::parser, session::Session, span_encoding::Span,
+};
 
 use super::{
     errors::IError,
     eval::ValueKind,
     ident::Ident,
-    interpret_ty,
-    libs::{math::MathLib, std::StdLib, CallerAttrs, Library},
-    Func, FuncParam, FuncSig, Ty, Value,
+    libs::{
+        function_not_found_error, math::MathLib, std::StdLib, Library, NativeFunction,
+        NativeLibrary,
+    },
+    resolver::ModuleResolver,
+    stmt::interpret_stmt,
+    vm, EnumDef, StructDef, Ty, TypeDef, Value,
 };
 
 pub type Wrapper<T> = Rc<RefCell<T>>;
 
+/// The libraries written in Pandora loaded so far, by the canonical path of their file. It is
+/// shared by all the environments of a run, so that each file is read and parsed once.
+#[derive(Default)]
+pub struct ModuleCache {
+    libraries: HashMap<PathBuf, Rc<ExternalLibrary>>,
+    /// The libraries being loaded, outermost first. A library adding one of them is a cycle.
+    loading: Vec<LoadingLibrary>,
+}
+
+struct LoadingLibrary {
+    path: PathBuf,
+    /// The file as it is shown in the reports.
+    file: String,
+    lib_name: String,
+    /// The `add` statement loading the library.
+    span: Span,
+}
+
 pub struct Environment {
     pub scopes: Vec<Scope>,
     pub in_function: bool,
     pub default_libs: HashMap<String, Box<dyn Library>>,
+    /// Finds the files of the libraries written in Pandora.
+    pub resolver: ModuleResolver,
+    pub module_cache: Wrapper<ModuleCache>,
+    /// The libraries added by the program embedding the interpreter, imported like `math`.
+    pub native_libs: HashMap<String, NativeLibrary>,
+    /// Where the errors of the program are reported. The libraries it adds are registered in its
+    /// source map, so that their errors are shown in their files.
+    pub error_handler: Option<ErrorHandler>,
+    /// Whether the libraries written in Pandora are compiled and run by the VM, rather than by
+    /// the tree walker.
+    pub compiles_libraries: bool,
 }
 
 impl Environment {
@@ -38,31 +74,40 @@ impl Environment {
             scopes: vec![Scope::new()],
             in_function: false,
             default_libs,
+            resolver: ModuleResolver::default(),
+            module_cache: Wrapper::default(),
+            native_libs: HashMap::new(),
+            error_handler: None,
+            compiles_libraries: false,
         }
     }
 
-    pub fn new_with_parent(parent: &Environment, is_verbose: bool) -> Self {
+    pub fn new_with_parent(parent: &Environment) -> Self {
         let mut env = Environment::new();
+        env.resolver = parent.resolver.clone();
+        env.module_cache = Rc::clone(&parent.module_cache);
+        env.native_libs = parent.native_libs.clone();
+        env.error_handler = parent.error_handler.clone();
+        env.compiles_libraries = parent.compiles_libraries;
 
         let mut functions = vec![];
-        let mut has_lib = HashSet::new();
-        let mut libs = vec![];
+        let mut types = vec![];
         for scope in parent.scopes.iter() {
             functions.extend(scope.functions.clone());
-            for (name, (span, _)) in scope.libraries.iter() {
-                if !has_lib.contains(name) {
-                    has_lib.insert(name.to_string());
-                    libs.push((name.to_string(), span.clone()));
-                }
-            }
+            types.extend(scope.types.clone());
+            // The libraries are shared rather than loaded again. Inner ones shadow outer ones
+            // with the same name.
+            let env_scope = &mut env.scopes[0];
+            env_scope.libraries.extend(scope.libraries.clone());
+            env_scope
+                .imported_functions
+                .extend(scope.imported_functions.clone());
         }
 
-        for (name, span) in libs {
-            env.import_library(&name, span, is_verbose).unwrap();
-        }
-        for (name, (span, value)) in functions {
-            env.insert_function(name, value, span, is_verbose).unwrap();
-        }
+        // Inner functions and types shadow outer ones with the same name.
+        let env_scope = env.scopes.last_mut().unwrap();
+        env_scope.functions.extend(functions);
+        env_scope.types.extend(types);
 
         env
     }
@@ -91,13 +136,45 @@ impl Environment {
             .find_map(|scope| scope.lookup_function(name))
     }
 
-    pub fn lookup_library(&self, name: &str) -> Option<&(Span, Box<dyn Library>)> {
+    /// Lookup the nearest type with the given name.
+    pub fn lookup_type(&self, name: &str) -> Option<(Span, TypeDef)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.lookup_type(name))
+    }
+
+    /// Lookup the nearest type with the given name, if it is a struct.
+    pub fn lookup_struct(&self, name: &str) -> Option<(Span, StructDef)> {
+        match self.lookup_type(name) {
+            Some((span, TypeDef::Struct(def))) => Some((span, def)),
+            _ => None,
+        }
+    }
+
+    /// Lookup the nearest type with the given name, if it is an enum.
+    pub fn lookup_enum(&self, name: &str) -> Option<(Span, EnumDef)> {
+        match self.lookup_type(name) {
+            Some((span, TypeDef::Enum(def))) => Some((span, def)),
+            _ => None,
+        }
+    }
+
+    pub fn lookup_library(&self, name: &str) -> Option<&(Span, Rc<dyn Library>, String)> {
         self.scopes
             .iter()
             .rev()
             .find_map(|scope| scope.lookup_library(name))
     }
 
+    /// Lookup the nearest function imported by name, e.g. with `add geometry.{area};`.
+    pub fn lookup_imported_function(&self, name: &str) -> Option<&(Span, Rc<dyn Library>)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.imported_functions.get(name))
+    }
+
     pub fn lookup_default_library(&self, name: &str) -> Option<&Box<dyn Library>> {
         self.default_libs.get(name)
     }
@@ -116,6 +193,23 @@ impl Environment {
             val: value,
             ty,
             first_assigned_span,
+            reference: None,
+        };
+
+        let var = Rc::new(RefCell::new(var));
+        self.scopes.last_mut().unwrap().variables.push(var);
+    }
+
+    /// Inserts a `mut` parameter bound to the place passed to it, through which it is read and
+    /// assigned.
+    pub fn insert_reference(&mut self, ident: Ident, ty: Ty, reference: Reference) {
+        let var = Variable {
+            first_assigned_span: Some(ident.span),
+            ident,
+            is_mut: true,
+            val: None,
+            ty,
+            reference: Some(reference),
         };
 
         let var = Rc::new(RefCell::new(var));
@@ -129,9 +223,15 @@ impl Environment {
         span: Span, // declaration span
         _is_verbose: bool,
     ) -> Result<(), Vec<IError>> {
-        // There must be no function with the same name in the current scope.
-        if self.scopes.last().unwrap().functions.contains_key(&name) {
-            let first_decl_span = self.scopes.last().unwrap().functions[&name].0;
+        // There must be no function with the same name in the current scope, whether it is
+        // declared or imported by name.
+        let scope = self.scopes.last().unwrap();
+        let first_decl_span = scope
+            .functions
+            .get(&name)
+            .map(|(span, _)| *span)
+            .or_else(|| scope.imported_functions.get(&name).map(|(span, _)| *span));
+        if let Some(first_decl_span) = first_decl_span {
             return Err(vec![IError::FunctionAlreadyDeclaredInScope {
                 func_name: name,
                 first_decl_span,
@@ -147,44 +247,167 @@ impl Environment {
         Ok(())
     }
 
-    pub fn import_library(
+    pub fn insert_type(
         &mut self,
-        name: &str,
-        span: Span,
-        is_verbose: bool,
+        name: String,
+        def: TypeDef,
+        span: Span, // declaration span
     ) -> Result<(), Vec<IError>> {
-        if self.scopes.last().unwrap().libraries.contains_key(name) {
-            let first_import_span = self.scopes.last().unwrap().libraries[name].0;
-            return Err(vec![IError::MultipleLibrariesInScope {
-                lib_name: name.to_string(),
-                first_lib_span: first_import_span,
-                second_lib_span: span,
+        // There must be no type with the same name in the current scope.
+        if let Some((first_decl_span, _)) = self.scopes.last().unwrap().types.get(&name) {
+            return Err(vec![IError::TypeAlreadyDeclaredInScope {
+                type_name: name,
+                first_decl_span: *first_decl_span,
+                second_decl_span: span,
             }]);
         }
 
-        let lib: Box<dyn Library> = if self.can_resolve_external_file(name) {
-            self.load_external_library(name, span, is_verbose)?
-        } else {
-            self.load_embedded_library(name, span, is_verbose)?
-        };
-
         self.scopes
             .last_mut()
             .unwrap()
-            .libraries
-            .insert(name.to_string(), (span, lib));
+            .types
+            .insert(name, (span, def));
+        Ok(())
+    }
+
+    /// Replaces the definition of a type declared in the current scope.
+    pub fn update_type(&mut self, name: &str, def: TypeDef) {
+        let (_, old_def) = self
+            .scopes
+            .last_mut()
+            .unwrap()
+            .types
+            .get_mut(name)
+            .expect("type should be declared before it is updated");
+        *old_def = def;
+    }
+
+    /// Imports the library with the given path, e.g. `utils.strings`, under its alias or the
+    /// last segment of its path, or imports the functions selected from it. A library file found
+    /// by the resolver takes precedence over an embedded library.
+    pub fn import_library(
+        &mut self,
+        import: &ast::Import,
+        is_verbose: bool,
+    ) -> Result<(), Vec<IError>> {
+        let span = import.span;
+        self.check_import_conflicts(import)?;
+
+        let segments = import.segments();
+        let lib_name = segments.join(".");
+        let lib: Rc<dyn Library> = match self.resolver.resolve(&segments) {
+            Some(lib_path) => self.load_cached_library(&lib_path, &lib_name, span, is_verbose)?,
+            None if segments.len() > 1 => {
+                return Err(vec![IError::ExternalLibraryNotFound { lib_name, span }])
+            }
+            None => self.load_embedded_library(&lib_name, span, is_verbose)?,
+        };
+
+        let scope = self.scopes.last_mut().unwrap();
+        match &import.kind {
+            ast::ImportKind::Library | ast::ImportKind::Alias(_) => {
+                let name = import.name();
+                scope
+                    .libraries
+                    .insert(name.name.to_string(), (name.span, lib, lib_name));
+            }
+            ast::ImportKind::Select(names) => {
+                let missing: Vec<IError> = names
+                    .iter()
+                    .filter(|name| {
+                        let name = name.name.as_str();
+                        lib.get_function(name).is_none() && lib.get_user_function(name).is_none()
+                    })
+                    .map(|name| {
+                        function_not_found_error(
+                            lib.as_ref(),
+                            &lib_name,
+                            name.name.as_str(),
+                            name.span,
+                        )
+                    })
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(missing);
+                }
+
+                for name in names {
+                    scope
+                        .imported_functions
+                        .insert(name.name.to_string(), (name.span, Rc::clone(&lib)));
+                }
+            }
+        }
         Ok(())
     }
 
-    fn load_embedded_library(
+    /// A library can be added once under the same name in a scope, and so can a function
+    /// selected from a library, which must not have the name of a function declared there.
+    fn check_import_conflicts(&self, import: &ast::Import) -> Result<(), Vec<IError>> {
+        let scope = self.scopes.last().unwrap();
+        let mut errors = vec![];
+        match &import.kind {
+            ast::ImportKind::Library | ast::ImportKind::Alias(_) => {
+                let name = import.name();
+                if let Some((first_lib_span, ..)) = scope.libraries.get(name.name.as_str()) {
+                    errors.push(IError::MultipleLibrariesInScope {
+                        lib_name: name.name.to_string(),
+                        first_lib_span: *first_lib_span,
+                        second_lib_span: name.span,
+                    });
+                }
+            }
+            ast::ImportKind::Select(names) => {
+                for (i, name) in names.iter().enumerate() {
+                    let first_span = scope
+                        .imported_functions
+                        .get(name.name.as_str())
+                        .map(|(span, _)| *span)
+                        .or_else(|| {
+                            names[..i]
+                                .iter()
+                                .find(|other| other.name == name.name)
+                                .map(|other| other.span)
+                        });
+                    if let Some(first_lib_span) = first_span {
+                        errors.push(IError::MultipleLibrariesInScope {
+                            lib_name: name.name.to_string(),
+                            first_lib_span,
+                            second_lib_span: name.span,
+                        });
+                    } else if let Some((first_decl_span, _)) =
+                        scope.functions.get(name.name.as_str())
+                    {
+                        errors.push(IError::FunctionAlreadyDeclaredInScope {
+                            func_name: name.name.to_string(),
+                            first_decl_span: *first_decl_span,
+                            second_decl_span: name.span,
+                        });
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn load_embedded_library(
         &mut self,
         name: &str,
         span: Span,
         _is_verbose: bool,
-    ) -> Result<Box<dyn Library>, Vec<IError>> {
+    ) -> Result<Rc<dyn Library>, Vec<IError>> {
+        if let Some(lib) = self.native_libs.get(name) {
+            return Ok(Rc::new(lib.clone()));
+        }
+
         Ok(match name {
-            "std" => Box::new(StdLib::new()),
-            "math" => Box::new(MathLib::new()),
+            "std" => Rc::new(StdLib::new()),
+            "math" => Rc::new(MathLib::new()),
             _ => {
                 return Err(vec![IError::LibraryNotFound {
                     library: name.to_string(),
@@ -194,72 +417,85 @@ impl Environment {
         })
     }
 
-    fn can_resolve_external_file(&self, name: &str) -> bool {
-        let args: Vec<String> = std::env::args().collect();
-        if args.len() < 2 {
-            return false;
-        }
-
-        let source_path = std::path::Path::new(&args[1]);
-        let source_dir = source_path.parent();
-        if source_dir.is_none() {
-            return false;
+    /// Loads a library file, or returns the library loaded before from the same file, even if
+    /// it was found with another path. A library that is still being loaded cannot be added
+    /// again, e.g. by a library it adds.
+    fn load_cached_library(
+        &mut self,
+        lib_path: &Path,
+        lib_name: &str,
+        span: Span,
+        is_verbose: bool,
+    ) -> Result<Rc<dyn Library>, Vec<IError>> {
+        let key = lib_path
+            .canonicalize()
+            .unwrap_or_else(|_| lib_path.to_path_buf());
+        let mut cache = self.module_cache.borrow_mut();
+        if let Some(lib) = cache.libraries.get(&key) {
+            if is_verbose {
+                println!(
+                    "\x1b[90m[DEBUG] Using the cached library of {}\x1b[0m",
+                    key.display()
+                );
+            }
+            return Ok(Rc::clone(lib) as Rc<dyn Library>);
         }
 
-        let source_dir = source_dir.unwrap();
-        let lib_filename = if crate::is_genz_mode() {
-            format!("{}.unbxx", name)
-        } else {
-            format!("{}.boxx", name)
-        };
-        let lib_path = source_dir.join(&lib_filename);
-
-        if !lib_path.exists() {
-            return false;
+        if let Some(start) = cache.loading.iter().position(|lib| lib.path == key) {
+            let chain = cache
+                .loading
+                .iter()
+                .map(|lib| (lib.lib_name.clone(), lib.span))
+                .chain([(lib_name.to_string(), span)])
+                .collect();
+            let cycle = cache.loading[start..]
+                .iter()
+                .map(|lib| (lib.file.clone(), lib.lib_name.clone()))
+                .collect();
+            return Err(vec![IError::CircularImport {
+                lib_name: lib_name.to_string(),
+                chain,
+                cycle,
+            }]);
         }
 
-        true
+        cache.loading.push(LoadingLibrary {
+            path: key.clone(),
+            file: lib_path.display().to_string(),
+            lib_name: lib_name.to_string(),
+            span,
+        });
+        drop(cache);
+        let lib = self.load_external_library(lib_path, span, is_verbose);
+        let mut cache = self.module_cache.borrow_mut();
+        cache.loading.pop();
+
+        let lib = Rc::new(lib?);
+        cache.libraries.insert(key, Rc::clone(&lib));
+        Ok(lib)
     }
 
     fn load_external_library(
         &mut self,
-        name: &str,
+        lib_path: &Path,
         span: Span,
         is_verbose: bool,
-    ) -> Result<Box<dyn Library>, Vec<IError>> {
-        let args: Vec<String> = std::env::args().collect();
-        if args.len() < 2 {
-            return Err(vec![IError::NoSourceFileSpecified]);
-        }
-
-        let source_path = std::path::Path::new(&args[1]);
-        let source_dir = source_path
-            .parent()
-            .ok_or_else(|| vec![IError::DirectoryNotFound])?;
-
-        let is_genz = crate::is_genz_mode();
-        let lib_filename = if is_genz {
-            format!("{}.unbxx", name)
-        } else {
-            format!("{}.boxx", name)
-        };
-
-        let lib_path = source_dir.join(&lib_filename);
-        if !lib_path.exists() {
-            return Err(vec![IError::ExternalLibraryNotFound {
-                lib_name: name.to_string(),
-                span,
-            }]);
-        }
-
+    ) -> Result<ExternalLibrary, Vec<IError>> {
+        let lib_filename = lib_path.display().to_string();
         let contents = Arc::new(std::fs::read_to_string(&lib_path).map_err(|_| {
             vec![IError::ReadLibraryFileFailed {
                 path: lib_path.display().to_string(),
             }]
         })?);
 
-        let file = Arc::new(NamedSource::new(name, Arc::clone(&contents)));
-        let mut session = Session::new(file);
+        let file = Arc::new(NamedSource::new(lib_filename, Arc::clone(&contents)));
+        let mut session = match &self.error_handler {
+            Some(error_handler) => {
+                let file = error_handler.source_map.add_file(file);
+                Session::with_error_handler(error_handler.clone(), file)
+            }
+            None => Session::new(file),
+        };
 
         let ast = parser::parse(&contents, &mut session);
         if ast.is_none() {
@@ -269,145 +505,140 @@ impl Environment {
             }]);
         }
 
-        let mut lib = Box::new(ExternalLibrary::new());
-
+        // A library declares functions and constants, and may add other libraries. Its
+        // top-level code runs once, in an environment of its own, where its functions run too.
         let ast = ast.unwrap();
-        for stmt in ast.stmts {
-            match stmt.kind {
-                ast::StmtKind::FuncDecl(fun) => {
-                    let ast::Fun { sig, body } = *fun;
-                    let ast::FunSig {
-                        name,
-                        inputs,
-                        output,
-                        span,
-                    } = sig;
-
-                    let ident = Ident {
-                        name: name.name.to_string(),
-                        span: name.span,
-                    };
-
-                    let output = if output.is_some() {
-                        Some(interpret_ty(
-                            &mut Environment::new(),
-                            &output.unwrap(),
-                            false,
-                            is_verbose,
-                        )?)
-                    } else {
-                        None
-                    };
-
-                    let inputs = inputs
-                        .iter()
-                        .map(|param| {
-                            let ast::FunParam {
-                                ty,
-                                ident,
-                                is_mut,
-                                span,
-                            } = param;
-
-                            let ty = interpret_ty(&mut Environment::new(), &ty, false, is_verbose)?;
-                            let ident = Ident {
-                                name: ident.name.as_str().to_string(),
-                                span: ident.span,
-                            };
-
-                            Ok(FuncParam {
-                                ty,
-                                ident,
-                                is_mut: *is_mut,
-                                span: *span,
-                            })
-                        })
-                        .collect::<Result<Vec<FuncParam>, Vec<IError>>>()?;
-
-                    let sig = FuncSig {
-                        ident,
-                        inputs,
-                        output,
-                        span,
-                    };
-
-                    let eval_function = ValueKind::Function(Func { sig, body });
-
-                    let name = name.name.to_string();
-                    lib.add_function(name, eval_function, is_verbose)?;
+        let mut env = Environment::new();
+        env.resolver = self.resolver.clone();
+        env.module_cache = Rc::clone(&self.module_cache);
+        env.native_libs = self.native_libs.clone();
+        env.error_handler = self.error_handler.clone();
+        env.compiles_libraries = self.compiles_libraries;
+
+        let private_functions = ast
+            .stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                ast::StmtKind::FuncDecl(fun) if fun.is_private => {
+                    Some(fun.sig.name.name.to_string())
                 }
-                _ => return Err(vec![IError::NonFunctionDeclaredInExternalLibrary { span }]),
-            }
+                _ => None,
+            })
+            .collect();
+        if self.compiles_libraries {
+            return vm::load_library(&ast, env, private_functions, is_verbose);
         }
 
-        Ok(lib)
+        for stmt in &ast.stmts {
+            interpret_stmt(&mut env, stmt, false, is_verbose)?;
+        }
+        Ok(ExternalLibrary::new(env, private_functions))
     }
 }
 
+/// A library written in Pandora, in a `.boxx` file.
 pub struct ExternalLibrary {
-    functions: HashMap<
-        String,
-        Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>,
-    >,
+    /// The functions the library exports, i.e. those not declared with `priv`.
+    user_functions: HashMap<String, ValueKind>,
+    /// The names of the `priv` functions, which only the library itself can call.
+    private_functions: HashSet<String>,
+    /// The values of the immutable `set` bindings of the library.
+    constants: HashMap<String, Value>,
+    /// The names of the `set mut` bindings of the library, which are not exported.
+    variables: HashSet<String>,
+    /// The environment the library was run in by the tree walker, with its functions and the
+    /// libraries it adds. The functions compiled by the VM keep their environment themselves.
+    env: Option<Environment>,
 }
 
 impl Library for ExternalLibrary {
-    fn get_function(
-        &self,
-        name: &str,
-    ) -> Option<&Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>>
-    {
-        self.functions.get(name)
+    fn get_function(&self, _name: &str) -> Option<&NativeFunction> {
+        None
+    }
+
+    fn function_names(&self) -> Vec<String> {
+        self.user_functions.keys().cloned().collect()
+    }
+
+    fn get_user_function(&self, name: &str) -> Option<&ValueKind> {
+        self.user_functions.get(name)
+    }
+
+    fn get_constant(&self, name: &str) -> Option<&Value> {
+        self.constants.get(name)
+    }
+
+    fn is_private(&self, name: &str) -> bool {
+        self.private_functions.contains(name)
+    }
+
+    fn is_variable(&self, name: &str) -> bool {
+        self.variables.contains(name)
+    }
+
+    fn env(&self) -> Option<&Environment> {
+        self.env.as_ref()
     }
 }
 
 impl ExternalLibrary {
-    pub fn new() -> Self {
-        ExternalLibrary {
-            functions: HashMap::new(),
+    /// Creates the library of the functions and the constants declared in the environment of
+    /// its file. The private functions and the mutable variables stay in the environment, but
+    /// are not exported.
+    pub fn new(env: Environment, private_functions: HashSet<String>) -> Self {
+        let scope = &env.scopes[0];
+        let functions = scope
+            .functions
+            .iter()
+            .map(|(name, (_, function))| (name.to_string(), function.clone()))
+            .collect();
+        let mut constants = HashMap::new();
+        let mut variables = HashSet::new();
+        for var in &scope.variables {
+            let var = var.borrow();
+            let name = var.ident.name.to_string();
+            // The mutable variables are only used by the top-level code of the library.
+            if var.is_mut {
+                variables.insert(name);
+            } else if let Some(value) = &var.val {
+                constants.insert(name, value.clone());
+            }
         }
+
+        let mut lib = ExternalLibrary::compiled(functions, private_functions, constants, variables);
+        lib.env = Some(env);
+        lib
     }
 
-    pub fn add_function(
-        &mut self,
-        name: String,
-        eval: ValueKind,
-        is_verbose: bool,
-    ) -> Result<(), Vec<IError>> {
-        let func_name = name.to_string();
-        let function = Box::new(
-            move |cattrs: CallerAttrs,
-                  args: Vec<(Value, bool)>|
-                  -> Result<ValueKind, Vec<IError>> {
-                let mut env = Environment::new();
-
-                Value::evaluate_function(
-                    &mut env,
-                    cattrs.prefix_span,
-                    eval.clone(),
-                    args,
-                    is_verbose,
-                )
-                // FIX: we actually want to use errors, but the error handler is not emitting
-                // errors correctly when dealing with multiple files
-                .map_err(|_errs| {
-                    vec![IError::PredefinedError {
-                        span: cattrs.span,
-                        message: "Error occurred while evaluating function".to_string(),
-                    }]
-                })
-            },
-        );
-
-        self.functions.insert(func_name, function);
-        Ok(())
+    /// Creates the library of a file run by the VM, from the functions declared at its top
+    /// level, which were compiled with the environment they run in, and its `set` bindings.
+    pub fn compiled(
+        functions: HashMap<String, ValueKind>,
+        private_functions: HashSet<String>,
+        constants: HashMap<String, Value>,
+        variables: HashSet<String>,
+    ) -> Self {
+        let user_functions = functions
+            .into_iter()
+            .filter(|(name, _)| !private_functions.contains(name.as_str()))
+            .collect();
+        ExternalLibrary {
+            user_functions,
+            private_functions,
+            constants,
+            variables,
+            env: None,
+        }
     }
 }
 
 pub struct Scope {
     pub variables: Vec<Wrapper<Variable>>,
-    pub libraries: HashMap<String, (Span, Box<dyn Library>)>, // (import span, library)
-    pub functions: HashMap<String, (Span, ValueKind)>,        // (declaration span, function)
+    pub libraries: HashMap<String, (Span, Rc<dyn Library>, String)>, // (import span, library, path)
+    /// The functions imported by name, with the library they come from.
+    pub imported_functions: HashMap<String, (Span, Rc<dyn Library>)>, // (import span, library)
+    pub functions: HashMap<String, (Span, ValueKind)>,               // (declaration span, function)
+    pub types: HashMap<String, (Span, TypeDef)>,                     // (declaration span, type)
 }
 
 impl Scope {
@@ -415,7 +646,9 @@ impl Scope {
         Scope {
             variables: Vec::new(),
             libraries: HashMap::new(),
+            imported_functions: HashMap::new(),
             functions: HashMap::new(),
+            types: HashMap::new(),
         }
     }
 
@@ -432,7 +665,11 @@ impl Scope {
         self.functions.get(name).cloned()
     }
 
-    pub fn lookup_library(&self, name: &str) -> Option<&(Span, Box<dyn Library>)> {
+    pub fn lookup_library(&self, name: &str) -> Option<&(Span, Rc<dyn Library>, String)> {
         self.libraries.get(name)
     }
+
+    pub fn lookup_type(&self, name: &str) -> Option<(Span, TypeDef)> {
+        self.types.get(name).cloned()
+    }
 }
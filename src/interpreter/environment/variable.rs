@@ -1,8 +1,10 @@
 use crate::{
-    interpreter::{ident::Ident, Ty, Value},
+    interpreter::{ident::Ident, Projection, Ty, Value},
     span_encoding::Span,
 };
 
+use super::Wrapper;
+
 #[derive(Debug, Clone)]
 pub struct Variable {
     pub ident: Ident,
@@ -10,6 +12,18 @@ pub struct Variable {
     pub val: Option<Value>,
     pub ty: Ty,
     pub first_assigned_span: Option<Span>,
+    /// The place a `mut` parameter refers to, where its value is read and written instead of
+    /// `val`.
+    pub reference: Option<Reference>,
+}
+
+/// A variable of the caller passed to a `mut` parameter, and the projections to the part of it
+/// that was passed, e.g. `[0]` and `.x` for `move(points[0].x)`. The variable is never a
+/// reference itself, as a parameter passed on is resolved to the place it refers to.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub var: Wrapper<Variable>,
+    pub projections: Vec<(Projection, Span)>,
 }
 
 impl Variable {
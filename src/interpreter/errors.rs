@@ -1,7 +1,60 @@
 use crate::{span_encoding::Span, ErrorHandler};
 
+/// A function call that was running when an error was raised.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub func_name: String,
+    pub call_span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum IError {
+    PassImmutableAsMutable {
+        /// The name of the parameter, or `None` if the function is called through a value of a
+        /// function type, which does not name its parameters.
+        param_name: Option<String>,
+        mut_kw: String,
+        var_name: Option<String>,
+        arg_span: Span,
+        /// The parameter, or the declaration of the value the function is called through.
+        param_span: Span,
+        help_span: Option<Span>,
+    },
+    NonExhaustivePatterns {
+        witness: String,
+        ty: String,
+        span: Span,
+    },
+    NoVariantInEnum {
+        variant: String,
+        enum_name: String,
+        span: Span,
+    },
+    VariantDeclaredMoreThanOnce {
+        variant: String,
+        first_span: Span,
+        second_span: Span,
+    },
+    TypeAlreadyDeclaredInScope {
+        type_name: String,
+        first_decl_span: Span,
+        second_decl_span: Span,
+    },
+    FieldSpecifiedMoreThanOnce {
+        field: String,
+        first_span: Span,
+        second_span: Span,
+    },
+    MissingStructFields {
+        struct_name: String,
+        fields: Vec<String>,
+        span: Span,
+    },
+    NoFieldOnType {
+        field: String,
+        ty: String,
+        span: Span,
+    },
     NegArraySize {
         size: String,
         span: Span,
@@ -22,6 +75,13 @@ pub enum IError {
         span: Span,
         max: i64,
     },
+    ArithmeticOverflow {
+        op: String,
+        /// The left operand, or `None` for a unary operator.
+        lhs: Option<String>,
+        rhs: String,
+        op_span: Span,
+    },
     ArrayHasMultipleTypes {
         first_el_ty: String,
         first_mismatch_ty: String,
@@ -77,12 +137,25 @@ pub enum IError {
         lib_name: String,
         span: Span,
     },
+    PrivateFunctionInLibrary {
+        func_name: String,
+        lib_name: String,
+        span: Span,
+    },
 
-    InvalidLibraryName {
+    MutableVariableInLibrary {
+        var_name: String,
+        lib_name: String,
         span: Span,
     },
 
-    NonFunctionDeclaredInExternalLibrary {
+    CallDepthExceeded {
+        func_name: String,
+        limit: usize,
+        span: Span,
+    },
+
+    InvalidLibraryName {
         span: Span,
     },
 
@@ -100,6 +173,14 @@ pub enum IError {
         span: Span,
     },
 
+    CircularImport {
+        lib_name: String,
+        /// The `add` statements from the program to the one closing the cycle.
+        chain: Vec<(String, Span)>, // (library name, import span)
+        /// The files of the libraries in the cycle, in the order they add each other.
+        cycle: Vec<(String, String)>, // (file, library name)
+    },
+
     DirectoryNotFound,
 
     NoSourceFileSpecified,
@@ -133,7 +214,7 @@ pub enum IError {
     },
 
     FunctionParamMismatch {
-        func_decl_span: Span,
+        func_decl_span: Option<Span>,
         args: usize,
         mismatch_params: Vec<(Span, String, String)>,
         missing_param_tys: Vec<String>, // this and unexpected_params should never be non empty at the
@@ -171,6 +252,43 @@ pub enum IError {
         span: Span,
     },
 
+    NoMethodOnType {
+        method: String,
+        ty: String,
+        /// The methods the type has, if it has any.
+        help: Option<String>,
+        span: Span,
+    },
+
+    ArrayLengthIsFixed {
+        method: String,
+        ty: String,
+        /// The type to declare the array with instead, without a length.
+        growable_ty: String,
+        span: Span,
+    },
+
+    PopFromEmptyArray {
+        span: Span,
+    },
+
+    PassFixedArrayAsGrowable {
+        /// The name of the parameter, or `None` if the function is called through a value of a
+        /// function type.
+        param_name: Option<String>,
+        /// The type of the argument, which fixes the length of an array.
+        ty: String,
+        /// The type of the parameter, which lets that length change.
+        param_ty: String,
+        arg_span: Span,
+        param_span: Span,
+    },
+
+    RangeStepNotPositive {
+        step: i64,
+        span: Span,
+    },
+
     NegRepeatCount {
         count: i64,
         span: Span,
@@ -262,11 +380,35 @@ pub enum IError {
     ComparisonOperatorsCannotBeChained {
         chain_op_span: Vec<Span>,
     },
+
+    /// An error raised inside function calls, with the calls from the innermost one.
+    Traced {
+        error: Box<IError>,
+        trace: Vec<CallFrame>,
+    },
 }
 
 impl IError {
+    /// Records that the error was raised inside the given call.
+    pub fn in_call(self, frame: CallFrame) -> IError {
+        match self {
+            IError::Traced { error, mut trace } => {
+                trace.push(frame);
+                IError::Traced { error, trace }
+            }
+            error => IError::Traced {
+                error: Box::new(error),
+                trace: vec![frame],
+            },
+        }
+    }
+
     pub fn to_report(self, error_handler: &ErrorHandler) -> miette::Report {
         match self {
+            IError::Traced { error, trace } => {
+                let report = error.to_report(error_handler);
+                error_handler.build_traced_error(report, trace).into()
+            }
             IError::CannotAdd {
                 lhs_ty,
                 rhs_ty,
@@ -366,6 +508,39 @@ impl IError {
             IError::IndexOutOfBounds { len, index, span } => error_handler
                 .build_index_out_of_bounds_error(len, index, span)
                 .into(),
+            IError::NoMethodOnType {
+                method,
+                ty,
+                help,
+                span,
+            } => error_handler
+                .build_no_method_on_type_error(method, ty, help, span)
+                .into(),
+            IError::ArrayLengthIsFixed {
+                method,
+                ty,
+                growable_ty,
+                span,
+            } => error_handler
+                .build_array_length_is_fixed_error(method, ty, growable_ty, span)
+                .into(),
+            IError::PopFromEmptyArray { span } => {
+                error_handler.build_pop_from_empty_array_error(span).into()
+            }
+            IError::PassFixedArrayAsGrowable {
+                param_name,
+                ty,
+                param_ty,
+                arg_span,
+                param_span,
+            } => error_handler
+                .build_pass_fixed_array_as_growable_error(
+                    param_name, ty, param_ty, arg_span, param_span,
+                )
+                .into(),
+            IError::RangeStepNotPositive { step, span } => error_handler
+                .build_range_step_not_positive_error(step, span)
+                .into(),
             IError::InvalidLhsAssign {
                 assign_span,
                 lhs_span,
@@ -379,6 +554,27 @@ impl IError {
             } => error_handler
                 .build_function_in_library_not_found_error(func_name, lib_name, span)
                 .into(),
+            IError::PrivateFunctionInLibrary {
+                func_name,
+                lib_name,
+                span,
+            } => error_handler
+                .build_private_function_in_library_error(func_name, lib_name, span)
+                .into(),
+            IError::MutableVariableInLibrary {
+                var_name,
+                lib_name,
+                span,
+            } => error_handler
+                .build_mutable_variable_in_library_error(var_name, lib_name, span)
+                .into(),
+            IError::CallDepthExceeded {
+                func_name,
+                limit,
+                span,
+            } => error_handler
+                .build_call_depth_exceeded_error(func_name, limit, span)
+                .into(),
             IError::DirectoryNotFound => error_handler.build_directory_not_found_error().into(),
             IError::IndexingWrongType { ty, span } => error_handler
                 .build_indexing_wrong_type_error(ty, span)
@@ -420,6 +616,13 @@ impl IError {
             IError::ExternalLibraryNotFound { lib_name, span } => error_handler
                 .build_external_library_not_found_error(lib_name, span)
                 .into(),
+            IError::CircularImport {
+                lib_name,
+                chain,
+                cycle,
+            } => error_handler
+                .build_circular_import_error(lib_name, chain, cycle)
+                .into(),
             IError::MutateImmutableVariable {
                 mut_kw,
                 var_name,
@@ -457,9 +660,6 @@ impl IError {
             } => error_handler
                 .build_mismatched_function_return_type(expected, found, expected_span, found_span)
                 .into(),
-            IError::NonFunctionDeclaredInExternalLibrary { span } => error_handler
-                .build_non_function_declared_in_external_library_error(span)
-                .into(),
             IError::MultipleLibrariesInScope {
                 lib_name,
                 first_lib_span,
@@ -532,6 +732,14 @@ impl IError {
             IError::LitOutOfRange { span, max } => {
                 error_handler.build_lit_out_of_range_error(span, max).into()
             }
+            IError::ArithmeticOverflow {
+                op,
+                lhs,
+                rhs,
+                op_span,
+            } => error_handler
+                .build_arithmetic_overflow_error(op, lhs, rhs, op_span)
+                .into(),
             IError::DividedByZero { divident, span } => error_handler
                 .build_divided_by_zero_error(divident, span)
                 .into(),
@@ -544,6 +752,63 @@ impl IError {
             IError::NegArraySize { size, span } => {
                 error_handler.build_neg_array_size_error(span, size).into()
             }
+            IError::NoFieldOnType { field, ty, span } => error_handler
+                .build_no_field_on_type_error(field, ty, span)
+                .into(),
+            IError::MissingStructFields {
+                struct_name,
+                fields,
+                span,
+            } => error_handler
+                .build_missing_struct_fields_error(struct_name, fields, span)
+                .into(),
+            IError::FieldSpecifiedMoreThanOnce {
+                field,
+                first_span,
+                second_span,
+            } => error_handler
+                .build_field_specified_more_than_once_error(field, first_span, second_span)
+                .into(),
+            IError::TypeAlreadyDeclaredInScope {
+                type_name,
+                first_decl_span,
+                second_decl_span,
+            } => error_handler
+                .build_type_already_declared_in_scope_error(
+                    type_name,
+                    first_decl_span,
+                    second_decl_span,
+                )
+                .into(),
+            IError::PassImmutableAsMutable {
+                param_name,
+                mut_kw,
+                var_name,
+                arg_span,
+                param_span,
+                help_span,
+            } => error_handler
+                .build_pass_immutable_as_mutable_error(
+                    param_name, mut_kw, var_name, arg_span, param_span, help_span,
+                )
+                .into(),
+            IError::NonExhaustivePatterns { witness, ty, span } => error_handler
+                .build_non_exhaustive_patterns_error(witness, ty, span)
+                .into(),
+            IError::NoVariantInEnum {
+                variant,
+                enum_name,
+                span,
+            } => error_handler
+                .build_no_variant_in_enum_error(variant, enum_name, span)
+                .into(),
+            IError::VariantDeclaredMoreThanOnce {
+                variant,
+                first_span,
+                second_span,
+            } => error_handler
+                .build_variant_declared_more_than_once_error(variant, first_span, second_span)
+                .into(),
         }
     }
 }
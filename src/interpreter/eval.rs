@@ -1,6 +1,24 @@
-use crate::{ast::Stmt, kw::Keyword, span_encoding::Span};
+use core::fmt;
+use std::rc::Rc;
 
-use super::{environment::Environment, errors::IError, ident::Ident, stmt, ty::TyKind, Ty};
+use crate::{
+    ast::Stmt,
+    kw::Keyword,
+    span_encoding::{Span, DUMMY_SP},
+};
+
+use super::{
+    environment::{
+        variable::{Reference, Variable},
+        Environment, Wrapper,
+    },
+    errors::{CallFrame, IError},
+    ident::Ident,
+    range::Range,
+    stmt,
+    ty::TyKind,
+    vm, Ty,
+};
 
 #[derive(Debug, Clone)]
 pub enum EvalResult {
@@ -21,18 +39,31 @@ pub struct Value {
 }
 
 impl Value {
+    /// Calls a function with the evaluated arguments. An argument passed by reference comes with
+    /// the place it refers to, which its `mut` parameter is bound to, so that the function reads
+    /// and assigns that place directly.
     pub fn evaluate_function(
         env: &Environment,
         prefix_span: Span,
         function: ValueKind,
-        evaluated_args: Vec<(Value, bool)>,
+        evaluated_args: Vec<(Value, Option<Reference>)>,
         is_verbose: bool,
     ) -> Result<ValueKind, Vec<IError>> {
-        // TODO: handle mutable arguments
-        let evaluated_args: Vec<Value> = evaluated_args.into_iter().map(|(val, _)| val).collect();
+        let (evaluated_args, mut references): (Vec<Value>, Vec<Option<Reference>>) =
+            evaluated_args.into_iter().unzip();
         match function {
             ValueKind::Function(func) => {
-                let Func { sig, body } = func;
+                let Func { sig, body } = *func;
+                let (body, captured) = match body {
+                    FuncBody::Tree(body, captured) => (body, captured),
+                    FuncBody::Bytecode(_) => {
+                        return Err(vec![IError::PredefinedError {
+                            span: prefix_span,
+                            message: "A compiled function cannot be called from a library"
+                                .to_string(),
+                        }])
+                    }
+                };
                 let FuncSig {
                     ident,
                     inputs,
@@ -41,7 +72,7 @@ impl Value {
                 } = sig;
 
                 let Ident {
-                    name: _func_name,
+                    name: func_name,
                     span: func_name_span,
                 } = ident;
 
@@ -53,8 +84,15 @@ impl Value {
                 // For return type hint if needed
                 let after_sig_span = Span::after(span);
 
-                let mut func_env = Environment::new_with_parent(env, is_verbose);
+                let mut func_env = Environment::new_with_parent(env);
                 func_env.in_function = true;
+                // Parameters are inserted after the captured variables, so they shadow them.
+                func_env
+                    .scopes
+                    .last_mut()
+                    .unwrap()
+                    .variables
+                    .extend(captured);
                 let mut errors: Vec<IError> = Vec::new();
 
                 let params = inputs
@@ -70,15 +108,16 @@ impl Value {
                     })
                     .collect::<Vec<_>>();
 
-                // FIX: not handle case where function argument is mutable
                 let mut mismatch_params: Vec<(Span, String, String)> = vec![];
-                for ((param_ident, param_ty, need_mut), arg) in
-                    params.iter().zip(evaluated_args.clone())
+                for (i, ((param_ident, param_ty, need_mut), arg)) in
+                    params.iter().zip(evaluated_args.clone()).enumerate()
                 {
                     let Value { ref kind, span } = arg;
                     let arg_ty = kind.to_ty_kind();
                     if param_ty.kind != arg_ty {
                         mismatch_params.push((span, param_ty.to_string(), arg_ty.to_string()));
+                    } else if let (true, Some(reference)) = (*need_mut, references[i].take()) {
+                        func_env.insert_reference(param_ident.clone(), param_ty.clone(), reference);
                     } else {
                         func_env.insert_variable(
                             param_ident.clone(),
@@ -104,9 +143,12 @@ impl Value {
                     }
                 }
 
-                if !mismatch_params.is_empty() || !unexpected_param_tys.is_empty() {
+                if !mismatch_params.is_empty()
+                    || !missing_param_tys.is_empty()
+                    || !unexpected_param_tys.is_empty()
+                {
                     errors.push(IError::FunctionParamMismatch {
-                        func_decl_span: func_name_span.clone(),
+                        func_decl_span: Some(func_name_span),
                         args: evaluated_args.len(),
                         mismatch_params,
                         missing_param_tys,
@@ -119,7 +161,20 @@ impl Value {
                     return Err(errors);
                 }
 
-                let result = stmt::interpret_stmt(&mut func_env, &body, false, is_verbose)?;
+                // The errors raised from here are raised inside the call.
+                let frame = CallFrame {
+                    func_name,
+                    call_span: prefix_span,
+                };
+                let in_call = |errors: Vec<IError>| {
+                    errors
+                        .into_iter()
+                        .map(|error| error.in_call(frame.clone()))
+                        .collect::<Vec<_>>()
+                };
+
+                let result = stmt::interpret_stmt(&mut func_env, &body, false, is_verbose)
+                    .map_err(in_call)?;
                 match result {
                     EvalResult::StmtResult(control_flow) => match control_flow {
                         Some(ControlFlow::Return(val)) => {
@@ -165,6 +220,7 @@ impl Value {
                         _ => unreachable!("function body should not return continue or break"),
                     },
                 }
+                .map_err(in_call)
             }
             _ => unreachable!("This should be a function"),
         }
@@ -181,18 +237,56 @@ impl Value {
         })
     }
 
-    pub fn into_iter(self) -> Result<Vec<Value>, String> {
+    /// Returns the elements of an array, a string or a range, in the order a `for` loop visits
+    /// them.
+    pub fn into_elements(self) -> Result<ValueIter, String> {
         let span = self.span;
         match self.kind {
-            ValueKind::Array(values) => Ok(values),
-            ValueKind::Str(s) => Ok(s
-                .chars()
-                .map(|c| Value {
-                    kind: ValueKind::Char(c),
-                    span,
+            ValueKind::Array(values, _) => Ok(ValueIter::Values(values.into_iter())),
+            ValueKind::Str(s) => Ok(ValueIter::Values(
+                s.chars()
+                    .map(|c| Value {
+                        kind: ValueKind::Char(c),
+                        span,
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+            ValueKind::Range(range) => Ok(ValueIter::Range {
+                range,
+                index: 0,
+                span,
+            }),
+            _ => Err(format!("expected array, string or range, found {:?}", self)),
+        }
+    }
+}
+
+/// The elements of an array, a string or a range, in order. Those of a range are computed as
+/// they are needed.
+pub enum ValueIter {
+    Values(std::vec::IntoIter<Value>),
+    Range {
+        range: Range,
+        index: u64,
+        span: Span,
+    },
+}
+
+impl Iterator for ValueIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            ValueIter::Values(values) => values.next(),
+            ValueIter::Range { range, index, span } => {
+                let value = range.get(*index)?;
+                *index += 1;
+                Some(Value {
+                    kind: ValueKind::Int(value),
+                    span: *span,
                 })
-                .collect()),
-            _ => Err(format!("expected array or string, found {:?}", self)),
+            }
         }
     }
 }
@@ -203,16 +297,71 @@ pub enum ValueKind {
     Float(f64),
     Str(String),
     Bool(bool),
-    Function(Func),
+    Function(Box<Func>),
     Char(char),
-    Array(Vec<Value>),
+    /// An array, with the type of its elements, which is known even if it is empty.
+    Array(Vec<Value>, Box<TyKind>),
+    Tuple(Vec<Value>),
+    /// A struct value, with its type name and fields in declaration order.
+    Struct(String, Vec<(String, Value)>),
+    /// An enum value, with its type name, variant name and payload.
+    Enum(String, String, Vec<Value>),
+    Range(Range),
     Unit,
 }
 
 #[derive(Debug, Clone)]
 pub struct Func {
     pub sig: FuncSig,
-    pub body: Box<Stmt>,
+    pub body: FuncBody,
+}
+
+#[derive(Clone)]
+pub enum FuncBody {
+    /// A body run by the tree walker, with the variables a closure captured from the scope it
+    /// was created in. They are shared with that scope, so an assignment on either side is seen
+    /// by the other one.
+    Tree(Box<Stmt>, Vec<Wrapper<Variable>>),
+    /// A body compiled to bytecode, run by the VM.
+    Bytecode(Rc<vm::Closure>),
+}
+
+impl fmt::Debug for FuncBody {
+    // A closure may capture the variable it is stored in, so only the names of the captured
+    // variables are printed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuncBody::Tree(body, captured) => {
+                let captured: Vec<String> = captured
+                    .iter()
+                    .map(|var| var.borrow().ident.name.to_string())
+                    .collect();
+                f.debug_struct("Tree")
+                    .field("body", body)
+                    .field("captured", &captured)
+                    .finish()
+            }
+            FuncBody::Bytecode(closure) => f
+                .debug_tuple("Bytecode")
+                .field(&closure.proto.name)
+                .finish(),
+        }
+    }
+}
+
+impl FuncSig {
+    pub fn to_ty_kind(&self) -> TyKind {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|param| (param.ty.kind.clone(), param.is_mut))
+            .collect();
+        let output = match &self.output {
+            Some(ty) => ty.kind.clone(),
+            None => TyKind::Unit,
+        };
+        TyKind::Function(inputs, Box::new(output))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -224,6 +373,35 @@ pub struct FuncSig {
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub ident: Ident,
+    pub fields: Vec<(Ident, Ty)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub ident: Ident,
+    pub variants: Vec<(Ident, Vec<Ty>)>,
+}
+
+impl EnumDef {
+    pub fn variant(&self, name: &str) -> Option<(usize, &Vec<Ty>)> {
+        self.variants
+            .iter()
+            .enumerate()
+            .find(|(_, (ident, _))| ident.name == name)
+            .map(|(index, (_, fields))| (index, fields))
+    }
+}
+
+/// A user-declared type.
+#[derive(Debug, Clone)]
+pub enum TypeDef {
+    Struct(StructDef),
+    Enum(EnumDef),
+}
+
 #[derive(Debug, Clone)]
 pub struct FuncParam {
     pub ty: Ty,
@@ -232,6 +410,56 @@ pub struct FuncParam {
     pub span: Span,
 }
 
+/// Formats a value the way it is written in the source code, e.g. for the REPL to echo it.
+/// Wraps a value created outside of a program, e.g. an argument passed by the host to
+/// `Interpreter::call_function`. It has no place in the source code, so its span is empty.
+impl From<ValueKind> for Value {
+    fn from(kind: ValueKind) -> Self {
+        Value {
+            kind,
+            span: DUMMY_SP,
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn join(values: &[Value]) -> String {
+            values
+                .iter()
+                .map(|value| value.kind.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        match self {
+            ValueKind::Int(val) => write!(f, "{}", val),
+            ValueKind::Float(val) => write!(f, "{:?}", val),
+            ValueKind::Str(val) => write!(f, "{:?}", val),
+            ValueKind::Bool(val) => write!(f, "{}", val),
+            ValueKind::Char(val) => write!(f, "{:?}", val),
+            ValueKind::Function(func) => write!(f, "<{}>", func.sig.to_ty_kind()),
+            ValueKind::Array(values, _) => write!(f, "[{}]", join(values)),
+            ValueKind::Range(range) => write!(f, "{}", range),
+            ValueKind::Tuple(values) => write!(f, "({})", join(values)),
+            ValueKind::Struct(name, fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, value.kind))
+                    .collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            ValueKind::Enum(name, variant, payload) if payload.is_empty() => {
+                write!(f, "{}::{}", name, variant)
+            }
+            ValueKind::Enum(name, variant, payload) => {
+                write!(f, "{}::{}({})", name, variant, join(payload))
+            }
+            ValueKind::Unit => write!(f, "()"),
+        }
+    }
+}
+
 impl ValueKind {
     pub fn try_cast_to(&self, ty: &TyKind) -> Result<ValueKind, (String, String)> {
         match self {
@@ -276,9 +504,13 @@ impl ValueKind {
                 TyKind::Str => Ok(ValueKind::Str(val.to_string())),
                 _ => Err((TyKind::Char.to_string(), ty.to_string())),
             },
-            ValueKind::Function(_) => Err((TyKind::Function.to_string(), ty.to_string())),
+            ValueKind::Function(func) => Err((func.sig.to_ty_kind().to_string(), ty.to_string())),
             ValueKind::Unit => Err((TyKind::Unit.to_string(), ty.to_string())),
-            ValueKind::Array(_) => Err(("array".to_string(), ty.to_string())),
+            ValueKind::Array(..) => Err(("array".to_string(), ty.to_string())),
+            ValueKind::Tuple(_)
+            | ValueKind::Struct(..)
+            | ValueKind::Enum(..)
+            | ValueKind::Range(_) => Err((self.to_ty_kind().to_string(), ty.to_string())),
         }
     }
 
@@ -289,13 +521,34 @@ impl ValueKind {
             ValueKind::Str(_) => TyKind::Str,
             ValueKind::Char(_) => TyKind::Char,
             ValueKind::Bool(_) => TyKind::Bool,
-            ValueKind::Function(_) => TyKind::Function,
-            ValueKind::Array(values) => {
-                let ty_kind = values.first().unwrap().to_ty_kind();
-                let len = values.len() as i64;
-                TyKind::Array(Box::new(ty_kind), len)
+            ValueKind::Function(func) => func.sig.to_ty_kind(),
+            ValueKind::Array(values, el_ty) => TyKind::Array(el_ty.clone(), values.len() as i64),
+            ValueKind::Tuple(values) => {
+                TyKind::Tuple(values.iter().map(|value| value.to_ty_kind()).collect())
             }
+            ValueKind::Struct(name, _) => TyKind::Struct(name.to_string()),
+            ValueKind::Enum(name, ..) => TyKind::Enum(name.to_string()),
+            ValueKind::Range(_) => TyKind::Range,
             ValueKind::Unit => TyKind::Unit,
         }
     }
+
+    /// Gives the empty arrays in the value the element type of the corresponding arrays in
+    /// `ty`, e.g. when the value is assigned to a variable declared with `ty`.
+    pub fn infer_ty(&mut self, ty: &TyKind) {
+        match (self, ty) {
+            (ValueKind::Array(values, el_ty), TyKind::Array(ty, _)) => {
+                el_ty.infer_from(ty);
+                for value in values {
+                    value.kind.infer_ty(ty);
+                }
+            }
+            (ValueKind::Tuple(values), TyKind::Tuple(tys)) => {
+                for (value, ty) in values.iter_mut().zip(tys) {
+                    value.kind.infer_ty(ty);
+                }
+            }
+            _ => {}
+        }
+    }
 }
@@ -0,0 +1,297 @@
+//! Exhaustiveness checking for `match` statements.
+//!
+//! Every pattern is first lowered to a [`DPat`], which only keeps the constructors
+//! that matter for exhaustiveness, and checked against the type of the scrutinee on
+//! the way. The lowered patterns are then searched for a value that none of them
+//! matches, following the usefulness algorithm described in "Warnings for pattern
+//! matching" (Luc Maranget, 2007). When such a value exists, it is reported as the
+//! witness of a `NonExhaustivePatterns` error, e.g. `Shape::Rect(_, _)`.
+
+use crate::{
+    ast::{Pat, PatKind},
+    kw::{self, Keyword},
+    span_encoding::Span,
+};
+
+use super::{environment::Environment, interpret_expr, ty::TyKind, IError, ValueKind};
+
+/// A constructor of a value, as far as exhaustiveness is concerned.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    /// The enum variant at the given index of the declaration.
+    Variant(usize),
+    /// The only constructor of a tuple (and of `()`).
+    Tuple,
+    Bool(bool),
+    /// A literal of a type with infinitely many values, e.g. `1` or `"foo"`.
+    Lit(String),
+}
+
+/// A pattern lowered for exhaustiveness checking.
+#[derive(Debug, Clone)]
+enum DPat {
+    /// A pattern that matches everything, i.e. `_` or a binding.
+    Wild,
+    Ctor(Ctor, Vec<DPat>),
+}
+
+/// Checks that the patterns of a `match` statement cover every value of the type
+/// of the scrutinee.
+pub fn check_match(
+    env: &mut Environment,
+    ty: &TyKind,
+    pats: &[&Pat],
+    scrutinee_span: Span,
+    is_verbose: bool,
+) -> Result<(), Vec<IError>> {
+    let mut rows = vec![];
+    for pat in pats {
+        rows.push(vec![lower_pat(env, pat, ty, is_verbose)?]);
+    }
+
+    match find_witness(env, &rows, std::slice::from_ref(ty)) {
+        Some(mut witness) => Err(vec![IError::NonExhaustivePatterns {
+            witness: witness.remove(0),
+            ty: ty.to_string(),
+            span: scrutinee_span,
+        }]),
+        None => Ok(()),
+    }
+}
+
+fn lower_pat(
+    env: &mut Environment,
+    pat: &Pat,
+    ty: &TyKind,
+    is_verbose: bool,
+) -> Result<DPat, Vec<IError>> {
+    match &pat.kind {
+        PatKind::Wild | PatKind::Ident(_) => Ok(DPat::Wild),
+        PatKind::Lit(expr) => {
+            let value = interpret_expr(env, expr, false, is_verbose)?;
+            if value.to_ty_kind() != *ty {
+                return Err(vec![IError::MismatchedType {
+                    expected: ty.to_string(),
+                    found: value.to_ty_kind().to_string(),
+                    span: pat.span,
+                }]);
+            }
+
+            let ctor = match value.kind {
+                ValueKind::Bool(val) => Ctor::Bool(val),
+                ValueKind::Int(val) => Ctor::Lit(val.to_string()),
+                ValueKind::Float(val) => Ctor::Lit(format!("{:?}", val)),
+                ValueKind::Char(val) => Ctor::Lit(format!("{:?}", val)),
+                ValueKind::Str(val) => Ctor::Lit(format!("{:?}", val)),
+                _ => unreachable!("a literal pattern is always a literal"),
+            };
+            Ok(DPat::Ctor(ctor, vec![]))
+        }
+        PatKind::Tuple(pats) => {
+            let tys = match ty {
+                TyKind::Tuple(tys) if tys.len() == pats.len() => tys.clone(),
+                TyKind::Unit if pats.is_empty() => vec![],
+                _ => {
+                    return Err(vec![IError::MismatchedType {
+                        expected: ty.to_string(),
+                        found: tuple_shape(pats.len()),
+                        span: pat.span,
+                    }])
+                }
+            };
+
+            let mut fields = vec![];
+            for (pat, ty) in pats.iter().zip(tys.iter()) {
+                fields.push(lower_pat(env, pat, ty, is_verbose)?);
+            }
+            Ok(DPat::Ctor(Ctor::Tuple, fields))
+        }
+        PatKind::Variant(enum_name, variant, pats) => {
+            let name = enum_name.name.as_str();
+            let def = match env.lookup_enum(name) {
+                Some((_, def)) => def,
+                None => {
+                    return Err(vec![IError::CannotFindTypeInScope {
+                        type_name: name.to_string(),
+                        span: enum_name.span,
+                    }])
+                }
+            };
+
+            if *ty != TyKind::Enum(name.to_string()) {
+                return Err(vec![IError::MismatchedType {
+                    expected: ty.to_string(),
+                    found: name.to_string(),
+                    span: pat.span,
+                }]);
+            }
+
+            let (index, field_tys) = match def.variant(variant.name.as_str()) {
+                Some(variant) => variant,
+                None => {
+                    return Err(vec![IError::NoVariantInEnum {
+                        variant: variant.name.to_string(),
+                        enum_name: name.to_string(),
+                        span: variant.span,
+                    }])
+                }
+            };
+
+            if field_tys.len() != pats.len() {
+                let expected = TyKind::Tuple(field_tys.iter().map(|ty| ty.kind.clone()).collect());
+                return Err(vec![IError::MismatchedType {
+                    expected: expected.to_string(),
+                    found: tuple_shape(pats.len()),
+                    span: pat.span,
+                }]);
+            }
+
+            let mut fields = vec![];
+            for (pat, ty) in pats.iter().zip(field_tys.iter()) {
+                fields.push(lower_pat(env, pat, &ty.kind, is_verbose)?);
+            }
+            Ok(DPat::Ctor(Ctor::Variant(index), fields))
+        }
+    }
+}
+
+/// Searches for a list of values, one for each type in `tys`, that is matched by
+/// none of the rows. Returns the printed values if there is one.
+fn find_witness(env: &Environment, rows: &[Vec<DPat>], tys: &[TyKind]) -> Option<Vec<String>> {
+    let (ty, rest_tys) = match tys.split_first() {
+        Some(split) => split,
+        // With no columns left, the rows match everything, unless there are none.
+        None if rows.is_empty() => return Some(vec![]),
+        None => return None,
+    };
+
+    let head_ctors: Vec<&Ctor> = rows
+        .iter()
+        .filter_map(|row| match &row[0] {
+            DPat::Ctor(ctor, _) => Some(ctor),
+            DPat::Wild => None,
+        })
+        .collect();
+    let all_ctors = all_ctors(env, ty);
+
+    match &all_ctors {
+        // Every constructor of the type appears in the first column, so each of them has
+        // to be checked on its own.
+        Some(all_ctors) if all_ctors.iter().all(|(ctor, _)| head_ctors.contains(&ctor)) => {
+            for (ctor, field_tys) in all_ctors {
+                let rows = specialize(rows, ctor, field_tys.len());
+                let tys: Vec<TyKind> = field_tys.iter().chain(rest_tys).cloned().collect();
+                if let Some(mut witness) = find_witness(env, &rows, &tys) {
+                    let rest = witness.split_off(field_tys.len());
+                    let mut result = vec![print_ctor(env, ty, ctor, witness)];
+                    result.extend(rest);
+                    return Some(result);
+                }
+            }
+            None
+        }
+        // Some constructors are missing, so only the rows starting with a wildcard can
+        // match them.
+        _ => {
+            let rows: Vec<Vec<DPat>> = rows
+                .iter()
+                .filter(|row| matches!(row[0], DPat::Wild))
+                .map(|row| row[1..].to_vec())
+                .collect();
+            let rest = find_witness(env, &rows, rest_tys)?;
+
+            let head = match all_ctors {
+                Some(all_ctors) if !head_ctors.is_empty() => {
+                    let (ctor, field_tys) = all_ctors
+                        .into_iter()
+                        .find(|(ctor, _)| !head_ctors.contains(&ctor))
+                        .unwrap();
+                    let fields = vec!["_".to_string(); field_tys.len()];
+                    print_ctor(env, ty, &ctor, fields)
+                }
+                _ => "_".to_string(),
+            };
+
+            let mut result = vec![head];
+            result.extend(rest);
+            Some(result)
+        }
+    }
+}
+
+/// Keeps the rows that match the constructor, with its fields in place of the first column.
+fn specialize(rows: &[Vec<DPat>], ctor: &Ctor, arity: usize) -> Vec<Vec<DPat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut fields = match &row[0] {
+                DPat::Ctor(head, fields) if head == ctor => fields.clone(),
+                DPat::Ctor(..) => return None,
+                DPat::Wild => vec![DPat::Wild; arity],
+            };
+            fields.extend_from_slice(&row[1..]);
+            Some(fields)
+        })
+        .collect()
+}
+
+/// Returns every constructor of the type with the types of its fields, or `None` if
+/// the type has infinitely many values.
+fn all_ctors(env: &Environment, ty: &TyKind) -> Option<Vec<(Ctor, Vec<TyKind>)>> {
+    match ty {
+        TyKind::Bool => Some(vec![
+            (Ctor::Bool(true), vec![]),
+            (Ctor::Bool(false), vec![]),
+        ]),
+        TyKind::Unit => Some(vec![(Ctor::Tuple, vec![])]),
+        TyKind::Tuple(tys) => Some(vec![(Ctor::Tuple, tys.clone())]),
+        TyKind::Enum(name) => {
+            let (_, def) = env.lookup_enum(name)?;
+            let ctors = def
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(index, (_, fields))| {
+                    let tys = fields.iter().map(|ty| ty.kind.clone()).collect();
+                    (Ctor::Variant(index), tys)
+                })
+                .collect();
+            Some(ctors)
+        }
+        _ => None,
+    }
+}
+
+fn print_ctor(env: &Environment, ty: &TyKind, ctor: &Ctor, fields: Vec<String>) -> String {
+    match ctor {
+        Ctor::Variant(index) => {
+            let name = match ty {
+                TyKind::Enum(name) => name,
+                _ => unreachable!("a variant always belongs to an enum"),
+            };
+            let (_, def) = env.lookup_enum(name).unwrap();
+            let variant = &def.variants[*index].0.name;
+            if fields.is_empty() {
+                format!("{}::{}", name, variant)
+            } else {
+                format!("{}::{}({})", name, variant, fields.join(", "))
+            }
+        }
+        Ctor::Tuple => print_tuple(fields),
+        Ctor::Bool(true) => kw::to_string(Keyword::True),
+        Ctor::Bool(false) => kw::to_string(Keyword::False),
+        Ctor::Lit(lit) => lit.to_string(),
+    }
+}
+
+/// Prints a tuple pattern of the given length, e.g. `(_, _)`.
+fn tuple_shape(len: usize) -> String {
+    print_tuple(vec!["_".to_string(); len])
+}
+
+fn print_tuple(fields: Vec<String>) -> String {
+    if fields.len() == 1 {
+        format!("({},)", fields[0])
+    } else {
+        format!("({})", fields.join(", "))
+    }
+}
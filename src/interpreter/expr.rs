@@ -1,15 +1,25 @@
-use std::num::IntErrorKind;
+use std::{num::IntErrorKind, rc::Rc};
 
 use crate::{
-    ast::{self, BinOp, BinOpKind, Expr, ExprKind, Lit, LitKind},
+    ast::{self, BinOp, BinOpKind, Expr, ExprField, ExprKind, Lit, LitKind, RangeLimits},
     kw::{self, Keyword},
     lexer,
     span_encoding::Span,
 };
 
 use super::{
-    environment::Environment, eval::ValueKind, interpret_ty, libs::CallerAttrs, ty::TyKind, IError,
-    Value,
+    array::ArrayMethod,
+    environment::{
+        variable::{Reference, Variable},
+        Environment, Wrapper,
+    },
+    eval::ValueKind,
+    ident::Ident,
+    interpret_fun_params, interpret_ty,
+    libs::{check_native_args, function_not_found_error, CallerAttrs, Library, ParamTy},
+    range::{Range, RangeMethod},
+    ty::TyKind,
+    Func, FuncBody, FuncParam, FuncSig, IError, Ty, Value,
 };
 
 pub fn interpret_expr(
@@ -35,7 +45,9 @@ pub fn interpret_expr(
         ExprKind::Assign(lhs, rhs, assign_span) => {
             interpret_expr_assign(env, lhs, rhs, *assign_span, in_loop, expr_span, is_verbose)?
         }
-        ExprKind::Unary(unop, expr) => interpret_expr_unary(env, unop, expr, in_loop, is_verbose)?,
+        ExprKind::Unary(unop, expr) => {
+            interpret_expr_unary(env, expr_span, unop, expr, in_loop, is_verbose)?
+        }
         ExprKind::AssignOp(binop, lhs, rhs) => {
             interpret_expr_assign_op(env, lhs, binop, rhs, in_loop, expr_span, is_verbose)?
         }
@@ -49,6 +61,20 @@ pub fn interpret_expr(
         ExprKind::Repeat(element, count) => {
             interpret_expr_repeat(env, element, count, in_loop, is_verbose)?
         }
+        ExprKind::Range(start, end, limits) => {
+            interpret_expr_range(env, start, end, *limits, in_loop, is_verbose)?
+        }
+        ExprKind::Tuple(elements) => interpret_expr_tuple(env, elements, in_loop, is_verbose)?,
+        ExprKind::TupleField(tuple, index, index_span) => {
+            interpret_expr_tuple_field(env, tuple, *index, *index_span, in_loop, is_verbose)?
+        }
+        ExprKind::Struct(name, fields) => {
+            interpret_expr_struct(env, name, fields, in_loop, is_verbose)?
+        }
+        ExprKind::Variant(enum_name, variant, args) => interpret_expr_variant(
+            env, expr_span, enum_name, variant, args, in_loop, is_verbose,
+        )?,
+        ExprKind::Closure(closure) => interpret_expr_closure(env, closure, in_loop, is_verbose)?,
     };
 
     Ok(Value {
@@ -66,8 +92,12 @@ fn interpret_expr_repeat(
 ) -> Result<ValueKind, Vec<IError>> {
     let e = interpret_expr(env, element, in_loop, is_verbose)?;
     let c = interpret_expr(env, count, in_loop, is_verbose)?;
+    eval_repeat(e, c)
+}
 
-    match c.kind {
+/// Repeats the element as many times as the count says, e.g. `[0; 3]`.
+pub fn eval_repeat(element: Value, count: Value) -> Result<ValueKind, Vec<IError>> {
+    match count.kind {
         ValueKind::Int(c) => {
             if c < 0 {
                 return Err(vec![IError::NegRepeatCount {
@@ -75,16 +105,49 @@ fn interpret_expr_repeat(
                     span: count.span,
                 }]);
             }
-            Ok(ValueKind::Array(vec![e; c as usize]))
+            let el_ty = Box::new(element.to_ty_kind());
+            Ok(ValueKind::Array(vec![element; c as usize], el_ty))
         }
         _ => Err(vec![IError::MismatchedType {
             expected: TyKind::Int.to_string(),
-            found: c.to_ty_kind().to_string(),
+            found: count.to_ty_kind().to_string(),
             span: count.span,
         }]),
     }
 }
 
+fn interpret_expr_range(
+    env: &mut Environment,
+    start: &Box<Expr>,
+    end: &Box<Expr>,
+    limits: RangeLimits,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let start = interpret_expr(env, start, in_loop, is_verbose)?;
+    let end = interpret_expr(env, end, in_loop, is_verbose)?;
+    eval_range(start, end, limits)
+}
+
+/// Creates the range between two integers, e.g. `0..10`.
+pub fn eval_range(start: Value, end: Value, limits: RangeLimits) -> Result<ValueKind, Vec<IError>> {
+    let bound = |value: Value| match value.kind {
+        ValueKind::Int(bound) => Ok(bound),
+        _ => Err(vec![IError::MismatchedType {
+            expected: TyKind::Int.to_string(),
+            found: value.to_ty_kind().to_string(),
+            span: value.span,
+        }]),
+    };
+    let start = bound(start)?;
+    let end = bound(end)?;
+    Ok(ValueKind::Range(Range::new(
+        start,
+        end,
+        limits == RangeLimits::Closed,
+    )))
+}
+
 fn interpret_expr_index(
     env: &mut Environment,
     array: &Box<Expr>,
@@ -94,9 +157,13 @@ fn interpret_expr_index(
 ) -> Result<ValueKind, Vec<IError>> {
     let a = interpret_expr(env, array, in_loop, is_verbose)?;
     let i = interpret_expr(env, index, in_loop, is_verbose)?;
+    eval_index(a, i)
+}
 
-    match a.kind {
-        ValueKind::Array(elements) => match i.kind {
+pub fn eval_index(array: Value, index: Value) -> Result<ValueKind, Vec<IError>> {
+    let array_ty = array.to_ty_kind();
+    match array.kind {
+        ValueKind::Array(mut elements, _) => match index.kind {
             ValueKind::Int(i) => {
                 if i < 0 || i as usize >= elements.len() {
                     return Err(vec![IError::IndexOutOfBounds {
@@ -105,16 +172,16 @@ fn interpret_expr_index(
                         span: index.span,
                     }]);
                 }
-                Ok(elements[i as usize].clone().kind)
+                Ok(elements.swap_remove(i as usize).kind)
             }
             _ => Err(vec![IError::MismatchedType {
                 expected: TyKind::Int.to_string(),
-                found: i.to_ty_kind().to_string(),
+                found: index.to_ty_kind().to_string(),
                 span: index.span,
             }]),
         },
         _ => Err(vec![IError::IndexingWrongType {
-            ty: a.to_ty_kind().to_string(),
+            ty: array_ty.to_string(),
             span: array.span,
         }]),
     }
@@ -127,10 +194,17 @@ fn interpret_expr_array(
     is_verbose: bool,
 ) -> Result<ValueKind, Vec<IError>> {
     let mut result = Vec::new();
-    let mut first_el: Option<(Span, TyKind)> = None;
     for element in elements {
-        let el_span = element.span;
-        let el = interpret_expr(env, element, in_loop, is_verbose)?;
+        result.push(interpret_expr(env, element, in_loop, is_verbose)?);
+    }
+    eval_array(result)
+}
+
+/// Builds an array from its elements, which must all have the type of the first one. The type
+/// of the elements of an empty array is inferred when it is given a type.
+pub fn eval_array(elements: Vec<Value>) -> Result<ValueKind, Vec<IError>> {
+    let mut first_el: Option<(Span, TyKind)> = None;
+    for el in &elements {
         let el_ty = el.to_ty_kind();
         match first_el {
             Some((first_el_span, ref first_el_ty)) => {
@@ -139,18 +213,258 @@ fn interpret_expr_array(
                         first_el_ty: first_el_ty.to_string(),
                         first_el_span,
                         first_mismatch_ty: el_ty.to_string(),
-                        first_mismatch_span: el_span,
+                        first_mismatch_span: el.span,
                     }]);
                 }
             }
             None => {
-                first_el = Some((el_span, el.to_ty_kind()));
+                first_el = Some((el.span, el_ty));
             }
         }
-        result.push(el);
     }
 
-    Ok(ValueKind::Array(result))
+    let el_ty = first_el.map_or(TyKind::Infer, |(_, el_ty)| el_ty);
+    Ok(ValueKind::Array(elements, Box::new(el_ty)))
+}
+
+fn interpret_expr_tuple(
+    env: &mut Environment,
+    elements: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    // The empty tuple is the unit value
+    if elements.is_empty() {
+        return Ok(ValueKind::Unit);
+    }
+
+    let mut result = Vec::with_capacity(elements.len());
+    for element in elements {
+        result.push(interpret_expr(env, element, in_loop, is_verbose)?);
+    }
+
+    Ok(ValueKind::Tuple(result))
+}
+
+fn interpret_expr_tuple_field(
+    env: &mut Environment,
+    tuple: &Box<Expr>,
+    index: usize,
+    index_span: Span,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let t = interpret_expr(env, tuple, in_loop, is_verbose)?;
+    eval_tuple_field(t, index, index_span)
+}
+
+pub fn eval_tuple_field(
+    t: Value,
+    index: usize,
+    index_span: Span,
+) -> Result<ValueKind, Vec<IError>> {
+    match t.kind {
+        ValueKind::Tuple(mut elements) if index < elements.len() => {
+            Ok(elements.swap_remove(index).kind)
+        }
+        _ => Err(vec![IError::NoFieldOnType {
+            field: index.to_string(),
+            ty: t.to_ty_kind().to_string(),
+            span: index_span,
+        }]),
+    }
+}
+
+fn interpret_expr_struct(
+    env: &mut Environment,
+    name: &ast::Ident,
+    fields: &Vec<ExprField>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let struct_name = name.name.as_str();
+    let def = match env.lookup_struct(struct_name) {
+        Some((_, def)) => def,
+        None => {
+            return Err(vec![IError::CannotFindTypeInScope {
+                type_name: struct_name.to_string(),
+                span: name.span,
+            }])
+        }
+    };
+
+    // The values are stored in the order of the declaration, with the span of the field name.
+    let mut values: Vec<Option<(Span, Value)>> = vec![None; def.fields.len()];
+    for field in fields {
+        let field_name = field.ident.name.as_str();
+        let index = def
+            .fields
+            .iter()
+            .position(|(ident, _)| ident.name == field_name);
+        let index = match index {
+            Some(index) => index,
+            None => {
+                return Err(vec![IError::NoFieldOnType {
+                    field: field_name.to_string(),
+                    ty: struct_name.to_string(),
+                    span: field.ident.span,
+                }])
+            }
+        };
+
+        if let Some((first_span, _)) = values[index] {
+            return Err(vec![IError::FieldSpecifiedMoreThanOnce {
+                field: field_name.to_string(),
+                first_span,
+                second_span: field.ident.span,
+            }]);
+        }
+
+        let value = interpret_expr(env, &field.expr, in_loop, is_verbose)?;
+        let field_ty = &def.fields[index].1;
+        if value.to_ty_kind() != field_ty.kind {
+            return Err(vec![IError::MismatchedType {
+                expected: field_ty.to_string(),
+                found: value.to_ty_kind().to_string(),
+                span: field.expr.span,
+            }]);
+        }
+
+        values[index] = Some((field.ident.span, value));
+    }
+
+    let missing_fields: Vec<String> = def
+        .fields
+        .iter()
+        .zip(values.iter())
+        .filter(|(_, value)| value.is_none())
+        .map(|((ident, _), _)| ident.name.to_string())
+        .collect();
+    if !missing_fields.is_empty() {
+        return Err(vec![IError::MissingStructFields {
+            struct_name: struct_name.to_string(),
+            fields: missing_fields,
+            span: name.span,
+        }]);
+    }
+
+    let fields = def
+        .fields
+        .into_iter()
+        .zip(values)
+        .map(|((ident, _), value)| (ident.name, value.unwrap().1))
+        .collect();
+    Ok(ValueKind::Struct(struct_name.to_string(), fields))
+}
+
+fn interpret_expr_closure(
+    env: &mut Environment,
+    closure: &ast::Closure,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let ast::Closure {
+        inputs,
+        output,
+        body,
+        span,
+    } = closure;
+
+    let inputs = interpret_fun_params(env, inputs, in_loop, is_verbose)?;
+    let output = match output {
+        Some(ty) => Some(interpret_ty(env, ty, in_loop, is_verbose)?),
+        None => None,
+    };
+
+    let ident = Ident {
+        name: "<closure>".to_string(),
+        span: *span,
+    };
+    let sig = FuncSig {
+        ident,
+        inputs,
+        output,
+        span: *span,
+    };
+
+    // Every variable visible here is captured, in declaration order so that inner
+    // variables still shadow outer ones.
+    let captured = env
+        .scopes
+        .iter()
+        .flat_map(|scope| scope.variables.iter().cloned())
+        .collect();
+
+    Ok(ValueKind::Function(Box::new(Func {
+        sig,
+        body: FuncBody::Tree(body.clone(), captured),
+    })))
+}
+
+fn interpret_expr_variant(
+    env: &mut Environment,
+    expr_span: Span,
+    enum_name: &ast::Ident,
+    variant: &ast::Ident,
+    args: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let name = enum_name.name.as_str();
+    let def = match env.lookup_enum(name) {
+        Some((_, def)) => def,
+        None => {
+            return Err(vec![IError::CannotFindTypeInScope {
+                type_name: name.to_string(),
+                span: enum_name.span,
+            }])
+        }
+    };
+
+    let variant_name = variant.name.as_str();
+    let field_tys = match def.variant(variant_name) {
+        Some((_, field_tys)) => field_tys.clone(),
+        None => {
+            return Err(vec![IError::NoVariantInEnum {
+                variant: variant_name.to_string(),
+                enum_name: name.to_string(),
+                span: variant.span,
+            }])
+        }
+    };
+
+    let mut values = vec![];
+    for arg in args {
+        values.push(interpret_expr(env, arg, in_loop, is_verbose)?);
+    }
+    eval_variant(name, variant_name, &field_tys, values, expr_span)
+}
+
+/// Builds an enum value from its payload, which must have the types of the variant fields.
+pub fn eval_variant(
+    name: &str,
+    variant_name: &str,
+    field_tys: &[Ty],
+    values: Vec<Value>,
+    expr_span: Span,
+) -> Result<ValueKind, Vec<IError>> {
+    // The payload is checked as a whole, so that a wrong number of arguments reads as
+    // `expected (float, float), found (float,)`.
+    let expected = TyKind::Tuple(field_tys.iter().map(|ty| ty.kind.clone()).collect());
+    let found = TyKind::Tuple(values.iter().map(|value| value.to_ty_kind()).collect());
+    if expected != found {
+        return Err(vec![IError::MismatchedType {
+            expected: expected.to_string(),
+            found: found.to_string(),
+            span: expr_span,
+        }]);
+    }
+
+    Ok(ValueKind::Enum(
+        name.to_string(),
+        variant_name.to_string(),
+        values,
+    ))
 }
 
 pub fn interpret_expr_lib_access(
@@ -160,10 +474,66 @@ pub fn interpret_expr_lib_access(
     in_loop: bool,
     is_verbose: bool,
 ) -> Result<ValueKind, Vec<IError>> {
+    // A name that is not a variable is the name of a library, e.g. `config` in `config.SIZE`.
+    if let ExprKind::Identifier(lib_ident) = &lib.kind {
+        if env.lookup_variable(lib_ident.name.as_str()).is_none() {
+            return eval_lib_constant(env, lib_ident, ident);
+        }
+    }
+
     let lib = interpret_expr(env, lib, in_loop, is_verbose)?;
+    eval_field(env, lib, ident)
+}
+
+/// Reads a constant of the library with the given name, e.g. `config.SIZE`.
+pub fn eval_lib_constant(
+    env: &Environment,
+    lib: &ast::Ident,
+    ident: &ast::Ident,
+) -> Result<ValueKind, Vec<IError>> {
+    if env.lookup_library(lib.name.as_str()).is_none() {
+        return Err(vec![IError::CannotFindVariableInScope {
+            var_name: lib.name.to_string(),
+            span: lib.span,
+        }]);
+    }
+
+    let lib = Value {
+        kind: ValueKind::Str(lib.name.to_string()),
+        span: lib.span,
+    };
+    eval_field(env, lib, ident)
+}
+
+/// Accesses a field of a struct or a constant of a library, or checks that a library has the
+/// function with the name.
+pub fn eval_field(
+    env: &Environment,
+    lib: Value,
+    ident: &ast::Ident,
+) -> Result<ValueKind, Vec<IError>> {
+    let lib_ty = lib.to_ty_kind();
     let lib_name = match lib.kind {
         ValueKind::Str(val) => val,
-        _ => return Err(vec![IError::InvalidLibraryName { span: lib.span }]),
+        // Field access on a struct.
+        ValueKind::Struct(_, fields) => {
+            let field_name = ident.name.as_str();
+            return match fields.into_iter().find(|(name, _)| name == field_name) {
+                Some((_, value)) => Ok(value.kind),
+                None => Err(vec![IError::NoFieldOnType {
+                    field: field_name.to_string(),
+                    ty: lib_ty.to_string(),
+                    span: ident.span,
+                }]),
+            };
+        }
+        _ => {
+            return Err(vec![IError::NoFieldOnType {
+                field: ident.name.to_string(),
+                ty: lib_ty.to_string(),
+                span: ident.span,
+            }])
+        }
     };
 
     let result = env.lookup_library(&lib_name);
@@ -174,19 +544,22 @@ pub fn interpret_expr_lib_access(
         }]);
     }
 
-    let lib = &result.unwrap().1;
+    let (_, lib, lib_path) = result.unwrap();
     let ast::Ident {
         name: func_name,
         span: func_span,
     } = ident;
     let func_name = func_name.as_str();
-    let func = lib.get_function(func_name);
-    if func.is_none() {
-        return Err(vec![IError::FunctionInLibraryNotFound {
-            func_name: func_name.to_string(),
-            lib_name,
-            span: func_span.clone(),
-        }]);
+    if let Some(value) = lib.get_constant(func_name) {
+        return Ok(value.kind.clone());
+    }
+    if lib.get_function(func_name).is_none() && lib.get_user_function(func_name).is_none() {
+        return Err(vec![function_not_found_error(
+            lib.as_ref(),
+            lib_path,
+            func_name,
+            *func_span,
+        )]);
     }
 
     Ok(ValueKind::Unit)
@@ -194,20 +567,34 @@ pub fn interpret_expr_lib_access(
 
 pub fn interpret_expr_unary(
     env: &mut Environment,
+    expr_span: Span,
     op: &ast::UnOp,
     expr: &Box<Expr>,
     in_loop: bool,
     is_verbose: bool,
 ) -> Result<ValueKind, Vec<IError>> {
     let e = interpret_expr(env, expr, in_loop, is_verbose)?;
+    eval_unary(op, e, expr_span)
+}
+
+/// Applies the unary operator to the evaluated operand of the expression.
+pub fn eval_unary(op: &ast::UnOp, e: Value, expr_span: Span) -> Result<ValueKind, Vec<IError>> {
     match op {
         ast::UnOp::Ne => match e.kind {
-            ValueKind::Int(val) => Ok(ValueKind::Int(-val)),
+            ValueKind::Int(val) => match val.checked_neg() {
+                Some(val) => Ok(ValueKind::Int(val)),
+                None => Err(vec![IError::ArithmeticOverflow {
+                    op: op.to_string(),
+                    lhs: None,
+                    rhs: val.to_string(),
+                    op_span: expr_span,
+                }]),
+            },
             ValueKind::Float(val) => Ok(ValueKind::Float(-val)),
             _ => Err(vec![IError::CannotApplyUnaryOp {
                 op: op.to_string(),
                 ty: e.to_ty_kind().to_string(),
-                span: expr.span,
+                span: e.span,
             }]),
         },
         ast::UnOp::Not => match e.kind {
@@ -215,7 +602,7 @@ pub fn interpret_expr_unary(
             _ => Err(vec![IError::CannotApplyUnaryOp {
                 op: op.to_string(),
                 ty: e.to_ty_kind().to_string(),
-                span: expr.span,
+                span: e.span,
             }]),
         },
     }
@@ -230,12 +617,16 @@ fn interpret_expr_cast(
 ) -> Result<ValueKind, Vec<IError>> {
     let e = interpret_expr(env, expr, in_loop, is_verbose)?;
     let ty = interpret_ty(env, ty, in_loop, is_verbose)?;
-    match e.try_cast_to(&ty.kind) {
+    eval_cast(e, &ty.kind)
+}
+
+pub fn eval_cast(e: Value, ty: &TyKind) -> Result<ValueKind, Vec<IError>> {
+    match e.try_cast_to(ty) {
         Ok(val) => Ok(val.kind),
         Err((from, to)) => Err(vec![IError::CannotCast {
             from,
             to,
-            span: expr.span,
+            span: e.span,
         }]),
     }
 }
@@ -289,20 +680,19 @@ fn interpret_expr_lib_funcall(
     is_verbose: bool,
 ) -> Result<ValueKind, Vec<IError>> {
     let (lib, func) = match &lib_fun.kind {
-        ExprKind::LibAccess(prefix, func) => match &prefix.kind {
-            ExprKind::Identifier(lib) => (lib, func),
-            _ => return Err(vec![IError::InvalidLibraryPath { span: prefix.span }]),
-        },
-        _ => unreachable!("Library function call prefix must be a library access"),
-    };
+        ExprKind::LibAccess(prefix, func) => {
+            if is_method_call(prefix, |name| env.lookup_variable(name).is_some()) {
+                return interpret_method_call(
+                    env, expr_span, prefix, func, args, in_loop, is_verbose,
+                );
+            }
 
-    // Fix: not handle mutable arguments
-    let evaluated_args = {
-        let mut args_vec = Vec::new();
-        for arg in args {
-            args_vec.push((interpret_expr(env, arg, in_loop, is_verbose)?, true));
+            match &prefix.kind {
+                ExprKind::Identifier(lib) => (lib, func),
+                _ => return Err(vec![IError::InvalidLibraryPath { span: prefix.span }]),
+            }
         }
-        args_vec
+        _ => unreachable!("Library function call prefix must be a library access"),
     };
 
     let ast::Ident {
@@ -314,28 +704,161 @@ fn interpret_expr_lib_funcall(
         span: func_span,
     } = func;
     let lib_name = lib_name.as_str();
+
     let func_name = func_name.as_str();
 
-    if let Some((_span, lib)) = env.lookup_library(lib_name) {
-        if let Some(func) = lib.get_function(func_name) {
-            let cattrs = CallerAttrs {
-                span: expr_span,
-                prefix_span: *lib_span,
-            };
-            return func(cattrs, evaluated_args);
-        } else {
-            return Err(vec![IError::FunctionInLibraryNotFound {
-                func_name: func_name.to_string(),
-                lib_name: lib_name.to_string(),
-                span: func_span.clone(),
-            }]);
-        }
-    } else {
-        return Err(vec![IError::LibraryNotFound {
+    let lib = env
+        .lookup_library(lib_name)
+        .map(|(_, lib, lib_path)| (Rc::clone(lib), lib_path.clone()));
+    let error = match &lib {
+        Some((lib, _)) if lib.get_function(func_name).is_some() => None,
+        Some((lib, _)) if lib.get_user_function(func_name).is_some() => None,
+        Some((lib, lib_path)) => Some(function_not_found_error(
+            lib.as_ref(),
+            lib_path,
+            func_name,
+            *func_span,
+        )),
+        None => Some(IError::LibraryNotFound {
             library: lib_name.to_string(),
-            span: lib_span.clone(),
+            span: *lib_span,
+        }),
+    };
+    if let Some(error) = error {
+        // The arguments are still evaluated first, so that their errors are reported before.
+        for arg in args {
+            interpret_expr(env, arg, in_loop, is_verbose)?;
+        }
+        return Err(vec![error]);
+    }
+
+    let (lib, _) = lib.unwrap();
+    let callee = LibraryCallee {
+        lib: lib.as_ref(),
+        func_name,
+        prefix_span: lib_span.to(*func_span),
+    };
+    interpret_library_call(env, expr_span, callee, args, in_loop, is_verbose)
+}
+
+/// Calls a method on a value stored in a variable, or in a part of it, e.g. `xs.push(1)` or
+/// `grid[0].pop()`, or on a temporary value, e.g. `(0..10).step(2)`.
+fn interpret_method_call(
+    env: &mut Environment,
+    expr_span: Span,
+    receiver: &Box<Expr>,
+    method: &ast::Ident,
+    args: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    if !is_place(receiver) {
+        let mut target = interpret_expr(env, receiver, in_loop, is_verbose)?;
+        let mut evaluated_args = Vec::new();
+        for arg in args {
+            evaluated_args.push(interpret_expr(env, arg, in_loop, is_verbose)?);
+        }
+        return eval_method_call(&mut target, None, method, evaluated_args, expr_span);
+    }
+
+    let (root, projections) = interpret_place(env, receiver, expr_span, in_loop, is_verbose)?;
+    let mut evaluated_args = Vec::new();
+    for arg in args {
+        evaluated_args.push(interpret_expr(env, arg, in_loop, is_verbose)?);
+    }
+
+    let var = match env.lookup_variable(root.name.as_str()) {
+        Some(var) => var,
+        None => {
+            return Err(vec![IError::CannotFindVariableInScope {
+                var_name: root.name.to_string(),
+                span: root.span,
+            }])
+        }
+    };
+    let (var, projections) = resolve_reference(var, projections);
+    let mut var_bind = var.borrow_mut();
+    if var_bind.val.is_none() {
+        return Err(vec![IError::VariableIsNotInitialized {
+            var_name: var_bind.ident.name.clone(),
+            declared_span: var_bind.ident.span,
+            used_span: root.span,
         }]);
     }
+
+    let is_mut = var_bind.is_mut || !needs_mut(&projections);
+    let first_assign_span = var_bind.first_assigned_span;
+    let help_span = var_bind.ident.span;
+    let declared_ty = project_ty(&var_bind.ty.kind, &projections);
+    let target = project(var_bind.val.as_mut().unwrap(), projections)?;
+    if !is_mut && method_mutates(&target.kind) {
+        return Err(vec![IError::MutateImmutableVariable {
+            mut_kw: Keyword::Mut.as_ref().to_string(),
+            var_name: root.name.to_string(),
+            first_assign_span: first_assign_span.expect("Variable must be assigned before"),
+            second_assign_span: expr_span,
+            help_span,
+        }]);
+    }
+    eval_method_call(target, declared_ty, method, evaluated_args, expr_span)
+}
+
+/// A function of a library being called, either as `lib.f()` or as `f()` after it was selected
+/// with `add lib.{f}`.
+struct LibraryCallee<'a> {
+    lib: &'a dyn Library,
+    func_name: &'a str,
+    /// The path of the function where it is called, e.g. `lib.f`.
+    prefix_span: Span,
+}
+
+/// Calls a function of a library, which must have a function with the given name.
+fn interpret_library_call(
+    env: &mut Environment,
+    expr_span: Span,
+    callee: LibraryCallee,
+    args: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let LibraryCallee {
+        lib,
+        func_name,
+        prefix_span,
+    } = callee;
+    if let Some(function) = lib.get_user_function(func_name).cloned() {
+        let params = match &function {
+            ValueKind::Function(func) => func.sig.inputs.clone(),
+            _ => unreachable!("This should be a function"),
+        };
+        let evaluated_args = interpret_call_args(env, &params, args, in_loop, is_verbose)?;
+        // The function runs in the environment of its library rather than of the caller.
+        let lib_env = lib.env().unwrap_or(env);
+        return Value::evaluate_function(
+            lib_env,
+            prefix_span,
+            function,
+            evaluated_args,
+            is_verbose,
+        );
+    }
+
+    let evaluated_args = {
+        let mut args_vec = Vec::new();
+        for arg in args {
+            args_vec.push(interpret_expr(env, arg, in_loop, is_verbose)?);
+        }
+        args_vec
+    };
+
+    let func = lib
+        .get_function(func_name)
+        .expect("the library should have the function");
+    let cattrs = CallerAttrs {
+        span: expr_span,
+        prefix_span,
+    };
+    func.call(cattrs, evaluated_args)
 }
 
 fn interpret_expr_binary(
@@ -377,14 +900,84 @@ fn interpret_expr_binary(
     }
 
     let lhs = interpret_expr(env, lhs, in_loop, is_verbose)?;
+    if is_short_circuit(&binop.node, &lhs.kind) {
+        return Ok(lhs.kind);
+    }
     let rhs = interpret_expr(env, rhs, in_loop, is_verbose)?;
+    eval_binary(binop, lhs, rhs, expr_span)
+}
 
+/// Returns the result of an operation on integers, or the overflow error of the operator if
+/// there is none, e.g. for `i64::MAX + 1` or a shift by 64 bits or more.
+fn checked_int(
+    binop: &BinOp,
+    lhs: i64,
+    rhs: i64,
+    result: Option<i64>,
+) -> Result<ValueKind, Vec<IError>> {
+    match result {
+        Some(result) => Ok(ValueKind::Int(result)),
+        None => Err(vec![IError::ArithmeticOverflow {
+            op: binop.to_string(),
+            lhs: Some(lhs.to_string()),
+            rhs: rhs.to_string(),
+            op_span: binop.span,
+        }]),
+    }
+}
+
+/// Whether two values are equal, or `None` if `==` is not implemented for them. Arrays are
+/// equal if they have the same length and their elements are equal.
+fn values_eq(lhs: &ValueKind, rhs: &ValueKind) -> Option<bool> {
+    match (lhs, rhs) {
+        (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Some(lhs == rhs),
+        (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Some(lhs == rhs),
+        (ValueKind::Str(lhs), ValueKind::Str(rhs)) => Some(lhs == rhs),
+        (ValueKind::Bool(lhs), ValueKind::Bool(rhs)) => Some(lhs == rhs),
+        (ValueKind::Char(lhs), ValueKind::Char(rhs)) => Some(lhs == rhs),
+        (ValueKind::Range(lhs), ValueKind::Range(rhs)) => Some(lhs == rhs),
+        (ValueKind::Array(lhs, lhs_el_ty), ValueKind::Array(rhs, rhs_el_ty)) => {
+            if lhs_el_ty != rhs_el_ty {
+                return None;
+            }
+            if lhs.len() != rhs.len() {
+                return Some(false);
+            }
+            for (lhs, rhs) in lhs.iter().zip(rhs) {
+                if !values_eq(&lhs.kind, &rhs.kind)? {
+                    return Some(false);
+                }
+            }
+            Some(true)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the left operand of `&&` or `||` decides the result, in which case the right one is
+/// not evaluated.
+pub fn is_short_circuit(op: &BinOpKind, lhs: &ValueKind) -> bool {
+    matches!(
+        (op, lhs),
+        (BinOpKind::And, ValueKind::Bool(false)) | (BinOpKind::Or, ValueKind::Bool(true))
+    )
+}
+
+/// Applies the binary operator to the evaluated operands of the expression.
+pub fn eval_binary(
+    binop: &BinOp,
+    lhs: Value,
+    rhs: Value,
+    expr_span: Span,
+) -> Result<ValueKind, Vec<IError>> {
     let lhs_ty = lhs.to_ty_kind();
     let rhs_ty = rhs.to_ty_kind();
 
     match &binop.node {
         BinOpKind::Add => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs + rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                checked_int(binop, lhs, rhs, lhs.checked_add(rhs))
+            }
             (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Float(lhs + rhs)),
             (ValueKind::Str(lhs), ValueKind::Str(rhs)) => {
                 Ok(ValueKind::Str(format!("{}{}", lhs, rhs)))
@@ -398,7 +991,9 @@ fn interpret_expr_binary(
             }
         },
         BinOpKind::Sub => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs - rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                checked_int(binop, lhs, rhs, lhs.checked_sub(rhs))
+            }
             (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Float(lhs - rhs)),
             _ => {
                 return Err(vec![IError::CannotSubtract {
@@ -409,7 +1004,9 @@ fn interpret_expr_binary(
             }
         },
         BinOpKind::Mul => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs * rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                checked_int(binop, lhs, rhs, lhs.checked_mul(rhs))
+            }
             (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Float(lhs * rhs)),
             _ => {
                 return Err(vec![IError::CannotMultiply {
@@ -428,7 +1025,9 @@ fn interpret_expr_binary(
                 divident: lhs.to_string(),
                 span: expr_span,
             }]),
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs / rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                checked_int(binop, lhs, rhs, lhs.checked_div(rhs))
+            }
             (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Float(lhs / rhs)),
             _ => {
                 return Err(vec![IError::CannotDivide {
@@ -447,7 +1046,9 @@ fn interpret_expr_binary(
                 divident: lhs.to_string(),
                 span: expr_span,
             }]),
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs % rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                checked_int(binop, lhs, rhs, lhs.checked_rem(rhs))
+            }
             (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Float(lhs % rhs)),
             _ => {
                 return Err(vec![IError::CannotModulo {
@@ -457,26 +1058,11 @@ fn interpret_expr_binary(
                 }])
             }
         },
-        BinOpKind::Eq => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Bool(lhs == rhs)),
-            (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Bool(lhs == rhs)),
-            (ValueKind::Str(lhs), ValueKind::Str(rhs)) => Ok(ValueKind::Bool(lhs == rhs)),
-            (ValueKind::Bool(lhs), ValueKind::Bool(rhs)) => Ok(ValueKind::Bool(lhs == rhs)),
-            (ValueKind::Char(lhs), ValueKind::Char(rhs)) => Ok(ValueKind::Bool(lhs == rhs)),
-            _ => Err(vec![IError::CannotCompare {
-                lhs_ty: lhs_ty.to_string(),
-                rhs_ty: rhs_ty.to_string(),
-                op: binop.to_string(),
-                op_span: binop.span,
-            }]),
-        },
-        BinOpKind::Ne => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Bool(lhs != rhs)),
-            (ValueKind::Float(lhs), ValueKind::Float(rhs)) => Ok(ValueKind::Bool(lhs != rhs)),
-            (ValueKind::Str(lhs), ValueKind::Str(rhs)) => Ok(ValueKind::Bool(lhs != rhs)),
-            (ValueKind::Bool(lhs), ValueKind::Bool(rhs)) => Ok(ValueKind::Bool(lhs != rhs)),
-            (ValueKind::Char(lhs), ValueKind::Char(rhs)) => Ok(ValueKind::Bool(lhs != rhs)),
-            _ => Err(vec![IError::CannotCompare {
+        BinOpKind::Eq | BinOpKind::Ne => match values_eq(&lhs.kind, &rhs.kind) {
+            Some(is_eq) => Ok(ValueKind::Bool(
+                is_eq == matches!(binop.node, BinOpKind::Eq),
+            )),
+            None => Err(vec![IError::CannotCompare {
                 lhs_ty: lhs_ty.to_string(),
                 rhs_ty: rhs_ty.to_string(),
                 op: binop.to_string(),
@@ -577,7 +1163,10 @@ fn interpret_expr_binary(
             }]),
         },
         BinOpKind::Shl => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs << rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                let result = u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs));
+                checked_int(binop, lhs, rhs, result)
+            }
             _ => Err(vec![IError::NoImplForOp {
                 lhs_ty: lhs_ty.to_string(),
                 rhs_ty: rhs_ty.to_string(),
@@ -586,7 +1175,10 @@ fn interpret_expr_binary(
             }]),
         },
         BinOpKind::Shr => match (lhs.kind, rhs.kind) {
-            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => Ok(ValueKind::Int(lhs >> rhs)),
+            (ValueKind::Int(lhs), ValueKind::Int(rhs)) => {
+                let result = u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs));
+                checked_int(binop, lhs, rhs, result)
+            }
             _ => Err(vec![IError::NoImplForOp {
                 lhs_ty: lhs_ty.to_string(),
                 rhs_ty: rhs_ty.to_string(),
@@ -603,6 +1195,11 @@ fn interpret_expr_ident(
 ) -> Result<ValueKind, Vec<IError>> {
     let result = env.lookup_variable(ident.name.as_str());
     if result.is_none() {
+        // A named function can be used as a value too.
+        if let Some((_, function)) = env.lookup_function(ident.name.as_str()) {
+            return Ok(function);
+        }
+
         return Err(vec![IError::CannotFindVariableInScope {
             var_name: ident.name.to_string(),
             span: ident.span,
@@ -610,7 +1207,7 @@ fn interpret_expr_ident(
     }
 
     let variable = result.unwrap();
-    let value = variable.borrow().val.clone();
+    let value = variable_value(Rc::clone(&variable))?;
     if value.is_none() {
         return Err(vec![IError::VariableIsNotInitialized {
             var_name: variable.borrow().ident.name.clone(),
@@ -632,22 +1229,56 @@ fn interpret_expr_funcall(
 ) -> Result<ValueKind, Vec<IError>> {
     let ident = match &prefix.kind {
         ExprKind::Identifier(ident) => ident,
-        _ => return Err(vec![IError::InvalidFunctionCall { span: prefix.span }]),
+        _ => {
+            // The callee is an expression that evaluates to a function, e.g. `make_adder(1)(2)`.
+            let function = interpret_expr(env, prefix, in_loop, is_verbose)?;
+            if !matches!(function.kind, ValueKind::Function(_)) {
+                return Err(vec![IError::InvalidFunctionCall { span: prefix.span }]);
+            }
+
+            return interpret_call(env, prefix.span, function.kind, args, in_loop, is_verbose);
+        }
     };
 
-    let result = env.lookup_function(ident.name.as_str());
+    // A variable holding a function shadows a named function with the same name.
+    let result = match env.lookup_variable(ident.name.as_str()) {
+        Some(variable) => match variable_value(variable)? {
+            Some(Value {
+                kind: ValueKind::Function(function),
+                span,
+            }) => Some((span, ValueKind::Function(function))),
+            _ => None,
+        },
+        None => None,
+    }
+    .or_else(|| env.lookup_function(ident.name.as_str()));
 
-    // Fix: not handle mutable arguments
+    if let Some((_, function)) = result {
+        return interpret_call(env, prefix.span, function, args, in_loop, is_verbose);
+    }
+
+    // A function imported by name shadows the one of the standard library with the same name.
+    if let Some((_, lib)) = env.lookup_imported_function(ident.name.as_str()) {
+        let lib = Rc::clone(lib);
+        let callee = LibraryCallee {
+            lib: lib.as_ref(),
+            func_name: ident.name.as_str(),
+            prefix_span: prefix.span,
+        };
+        return interpret_library_call(env, expr_span, callee, args, in_loop, is_verbose);
+    }
+
+    // We will try to find the function in the standard library, whose functions take every
+    // argument by value.
     let evaluated_args = {
         let mut args_vec = Vec::new();
         for arg in args {
-            args_vec.push((interpret_expr(env, arg, in_loop, is_verbose)?, true));
+            args_vec.push(interpret_expr(env, arg, in_loop, is_verbose)?);
         }
         args_vec
     };
 
-    if result.is_none() {
-        // We will try to find the function in the standard library
+    {
         let std_func_name = ident.name.as_str();
         let std_func_span = ident.span;
 
@@ -657,28 +1288,168 @@ fn interpret_expr_funcall(
                     span: expr_span,
                     prefix_span: prefix.span,
                 };
-                return func(cattrs, evaluated_args);
+                func.call(cattrs, evaluated_args)
             } else {
-                return Err(vec![IError::FunctionNotInScope {
+                Err(vec![IError::FunctionNotInScope {
                     function: std_func_name.to_string(),
                     span: std_func_span,
-                }]);
+                }])
             }
         } else {
             unreachable!("Standard library must be loaded");
         }
     }
+}
 
-    Value::evaluate_function(
-        env,
-        prefix.span,
-        result.unwrap().1,
-        evaluated_args,
-        is_verbose,
-    )
+/// Calls a function written in Pandora.
+fn interpret_call(
+    env: &mut Environment,
+    prefix_span: Span,
+    function: ValueKind,
+    args: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let params = match &function {
+        ValueKind::Function(func) => func.sig.inputs.clone(),
+        _ => unreachable!("This should be a function"),
+    };
+
+    let evaluated_args = interpret_call_args(env, &params, args, in_loop, is_verbose)?;
+    Value::evaluate_function(env, prefix_span, function, evaluated_args, is_verbose)
 }
 
-fn interpret_expr_literal(value: &Lit, span: Span) -> Result<ValueKind, Vec<IError>> {
+/// Evaluates the arguments of a call to a function with the given parameters. An argument passed
+/// to a `mut` parameter must be a place (e.g. `arr` or `p.x`) in a mutable variable, which the
+/// parameter refers to during the call.
+fn interpret_call_args(
+    env: &mut Environment,
+    params: &Vec<FuncParam>,
+    args: &Vec<Box<Expr>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<Vec<(Value, Option<Reference>)>, Vec<IError>> {
+    let mut evaluated_args = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let param = match params.get(i) {
+            Some(param) if param.is_mut => param,
+            _ => {
+                evaluated_args.push((interpret_expr(env, arg, in_loop, is_verbose)?, None));
+                continue;
+            }
+        };
+
+        if !is_place(arg) {
+            return Err(vec![IError::PassImmutableAsMutable {
+                param_name: Some(param.ident.name.to_string()),
+                mut_kw: Keyword::Mut.as_ref().to_string(),
+                var_name: None,
+                arg_span: arg.span,
+                param_span: param.span,
+                help_span: None,
+            }]);
+        }
+
+        let (root, projections) = interpret_place(env, arg, arg.span, in_loop, is_verbose)?;
+        let var = match env.lookup_variable(root.name.as_str()) {
+            Some(var) => var,
+            None => {
+                return Err(vec![IError::CannotFindVariableInScope {
+                    var_name: root.name.to_string(),
+                    span: root.span,
+                }])
+            }
+        };
+        // A `mut` parameter passed on refers to the same place as the parameter.
+        let (var, projections) = resolve_reference(var, projections);
+
+        let value = {
+            let mut var_bind = var.borrow_mut();
+            if var_bind.val.is_none() {
+                return Err(vec![IError::VariableIsNotInitialized {
+                    var_name: var_bind.ident.name.clone(),
+                    declared_span: var_bind.ident.span,
+                    used_span: root.span,
+                }]);
+            }
+
+            if !var_bind.is_mut {
+                return Err(vec![IError::PassImmutableAsMutable {
+                    param_name: Some(param.ident.name.to_string()),
+                    mut_kw: Keyword::Mut.as_ref().to_string(),
+                    var_name: Some(root.name.to_string()),
+                    arg_span: arg.span,
+                    param_span: param.span,
+                    help_span: Some(var_bind.ident.span),
+                }]);
+            }
+
+            if let Some(ty) = project_ty(&var_bind.ty.kind, &projections) {
+                if ty.fixes_growable_len(&param.ty.kind) {
+                    return Err(vec![pass_fixed_array_as_growable_error(
+                        param, &ty, arg.span,
+                    )]);
+                }
+            }
+
+            let target = project(var_bind.val.as_mut().unwrap(), projections.clone())?;
+            target.kind.clone()
+        };
+
+        evaluated_args.push((
+            Value {
+                kind: value,
+                span: arg.span,
+            },
+            Some(Reference { var, projections }),
+        ));
+    }
+
+    Ok(evaluated_args)
+}
+
+/// Follows a `mut` parameter to the variable it refers to. The projections to the part of that
+/// variable passed to the parameter come before the given ones.
+fn resolve_reference(
+    var: Wrapper<Variable>,
+    projections: Vec<(Projection, Span)>,
+) -> (Wrapper<Variable>, Vec<(Projection, Span)>) {
+    let reference = var.borrow().reference.clone();
+    match reference {
+        Some(Reference {
+            var,
+            projections: mut prefix,
+        }) => {
+            prefix.extend(projections);
+            (var, prefix)
+        }
+        None => (var, projections),
+    }
+}
+
+/// Returns the value of a variable, or of the place a `mut` parameter refers to, or `None` if the
+/// variable is not initialized.
+fn variable_value(var: Wrapper<Variable>) -> Result<Option<Value>, Vec<IError>> {
+    let (var, projections) = resolve_reference(var, vec![]);
+    let mut var_bind = var.borrow_mut();
+    match var_bind.val.as_mut() {
+        Some(val) => Ok(Some(project(val, projections)?.clone())),
+        None => Ok(None),
+    }
+}
+
+/// Returns `true` if the expression refers to a part of a variable, e.g. `foo.bar[2].0`.
+pub fn is_place(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Identifier(_) => true,
+        ExprKind::Index(base, ..)
+        | ExprKind::LibAccess(base, _)
+        | ExprKind::TupleField(base, ..) => is_place(base),
+        _ => false,
+    }
+}
+
+pub fn interpret_expr_literal(value: &Lit, span: Span) -> Result<ValueKind, Vec<IError>> {
     let Lit { kind, symbol } = value;
     let val = symbol.as_str();
     match kind {
@@ -767,68 +1538,6 @@ fn parse_int_number(input: &str, span: Span) -> Result<i64, Vec<IError>> {
     }
 }
 
-fn update_array_index(
-    env: &mut Environment,
-    arr: &Box<Expr>,
-    value: Vec<Value>,
-    is_verbose: bool,
-) -> Result<(), Vec<IError>> {
-    // This will store all the index access of the array (in reverse).
-    let mut indices: Vec<i64> = Vec::new();
-    let mut e = arr;
-    let var = loop {
-        match &e.kind {
-            ExprKind::Index(array, index, _) => {
-                let index = interpret_expr(env, index, false, is_verbose).unwrap();
-                match index.kind {
-                    ValueKind::Int(index) => indices.push(index),
-                    _ => unreachable!(),
-                }
-                e = array;
-            }
-            ExprKind::Identifier(array_name) => {
-                let var = env.lookup_variable(array_name.name.as_str());
-                if var.is_none() {
-                    return Err(vec![IError::CannotFindVariableInScope {
-                        var_name: array_name.name.to_string(),
-                        span: array_name.span,
-                    }]);
-                }
-                break var.unwrap();
-            }
-            _ => unreachable!(),
-        }
-    };
-
-    // Now we have the variable, we can update the value by traversing the indices in reverse.
-    let mut var_bind = var.borrow_mut();
-    let mut elements: &mut Vec<Value> = match var_bind.val.as_mut().unwrap().kind {
-        ValueKind::Array(ref mut elements) => elements,
-        _ => unreachable!(),
-    };
-
-    for index in indices.iter().rev() {
-        let index = *index as usize;
-
-        if index >= elements.len() {
-            return Err(vec![IError::IndexOutOfBounds {
-                len: elements.len() as i64,
-                index: index as i64,
-                span: arr.span,
-            }]);
-        }
-
-        elements = match &mut elements[index].kind {
-            ValueKind::Array(elements) => elements,
-            _ => unreachable!("This is not the last index so it must be an array"),
-        };
-    }
-
-    // Now we have the last array's dimension, we can update the value.
-    *elements = value;
-    Ok(())
-}
-
 fn interpret_expr_assign_ident_with_known_value(
     env: &mut Environment,
     ident: &ast::Ident,
@@ -866,55 +1575,366 @@ fn interpret_expr_assign_ident_with_known_value(
         }]);
     }
 
-    var.borrow_mut().val = Some(Value {
+    let value = Value {
         kind: rhs,
         span: rhs_span,
-    });
+    };
+    let reference = var.borrow().reference.clone();
+    match reference {
+        // A `mut` parameter assigns the place it refers to.
+        Some(Reference { var, projections }) => {
+            let mut var_bind = var.borrow_mut();
+            *project(var_bind.val.as_mut().unwrap(), projections)? = value;
+        }
+        None => var.borrow_mut().val = Some(value),
+    }
 
     var.borrow_mut().first_assigned_span = Some(expr_span);
     Ok(ValueKind::Unit)
 }
 
-fn interpret_expr_assign_array_index_with_known_value(
+/// A step from a variable to the part of its value that is being assigned.
+#[derive(Debug, Clone)]
+pub enum Projection {
+    /// `[index]`, with the span of the index.
+    Index(i64, Span),
+    /// `.field`, with the span of the field.
+    Field(String, Span),
+    /// `.0`, with the span of the field index.
+    TupleField(usize, Span),
+}
+
+/// The variable an assignable expression starts from, and the projections to apply to it, each
+/// with the span of the projected expression.
+type Place<'a> = (&'a ast::Ident, Vec<(Projection, Span)>);
+
+/// Resolves an assignable expression (e.g. `foo.bar[2].0`) into the variable it starts from and
+/// the projections to apply to it, in order.
+fn interpret_place<'a>(
     env: &mut Environment,
-    array: &Box<Expr>,
-    index: &Box<Expr>,
-    rhs: ValueKind,
+    lhs: &'a Box<Expr>,
+    assign_span: Span,
     in_loop: bool,
     is_verbose: bool,
-) -> Result<ValueKind, Vec<IError>> {
-    let arr = interpret_expr(env, array, in_loop, is_verbose)?;
-
-    match arr.kind {
-        ValueKind::Array(mut elements) => {
-            let index = interpret_expr(env, index, in_loop, is_verbose)?;
-
-            match index.kind {
-                ValueKind::Int(i) => {
-                    if i < 0 || i as usize >= elements.len() {
-                        return Err(vec![IError::IndexOutOfBounds {
-                            len: elements.len() as i64,
-                            index: i,
+) -> Result<Place<'a>, Vec<IError>> {
+    // This will store all the projections (in reverse) with the span of the projected expression.
+    let mut projections: Vec<(Projection, Span)> = Vec::new();
+    let mut e = lhs;
+    let root = loop {
+        match &e.kind {
+            ExprKind::Index(base, index, _) => {
+                let i = interpret_expr(env, index, in_loop, is_verbose)?;
+                match i.kind {
+                    ValueKind::Int(i) => {
+                        projections.push((Projection::Index(i, index.span), base.span))
+                    }
+                    _ => {
+                        return Err(vec![IError::MismatchedType {
+                            expected: TyKind::Int.to_string(),
+                            found: i.to_ty_kind().to_string(),
                             span: index.span,
-                        }]);
+                        }])
                     }
-                    elements[i as usize].kind = rhs;
+                }
+                e = base;
+            }
+            ExprKind::LibAccess(base, field) => {
+                let projection = Projection::Field(field.name.to_string(), field.span);
+                projections.push((projection, base.span));
+                e = base;
+            }
+            ExprKind::TupleField(base, index, index_span) => {
+                projections.push((Projection::TupleField(*index, *index_span), base.span));
+                e = base;
+            }
+            ExprKind::Identifier(ident) => break ident,
+            _ => {
+                return Err(vec![IError::InvalidLhsAssign {
+                    assign_span,
+                    lhs_span: lhs.span,
+                }]);
+            }
+        }
+    };
+
+    projections.reverse();
+    Ok((root, projections))
+}
 
-                    update_array_index(env, array, elements, is_verbose)?;
-                    Ok(ValueKind::Unit)
+/// Applies the projections to the value, returning the part of it they lead to.
+pub fn project(
+    mut target: &mut Value,
+    projections: Vec<(Projection, Span)>,
+) -> Result<&mut Value, Vec<IError>> {
+    for (projection, base_span) in projections {
+        let base_ty = target.to_ty_kind().to_string();
+        target = match (projection, &mut target.kind) {
+            (Projection::Index(index, span), ValueKind::Array(elements, _)) => {
+                if index < 0 || index as usize >= elements.len() {
+                    return Err(vec![IError::IndexOutOfBounds {
+                        len: elements.len() as i64,
+                        index,
+                        span,
+                    }]);
+                }
+                &mut elements[index as usize]
+            }
+            (Projection::Index(..), _) => {
+                return Err(vec![IError::IndexingWrongType {
+                    ty: base_ty,
+                    span: base_span,
+                }]);
+            }
+            (Projection::Field(name, span), ValueKind::Struct(_, fields)) => {
+                match fields.iter_mut().find(|(field, _)| *field == name) {
+                    Some((_, value)) => value,
+                    None => {
+                        return Err(vec![IError::NoFieldOnType {
+                            field: name,
+                            ty: base_ty,
+                            span,
+                        }]);
+                    }
                 }
-                _ => Err(vec![IError::MismatchedType {
-                    expected: TyKind::Int.to_string(),
-                    found: index.to_ty_kind().to_string(),
-                    span: index.span,
-                }]),
             }
+            (Projection::TupleField(index, _), ValueKind::Tuple(elements))
+                if index < elements.len() =>
+            {
+                &mut elements[index]
+            }
+            (Projection::Field(field, span), _) => {
+                return Err(vec![IError::NoFieldOnType {
+                    field,
+                    ty: base_ty,
+                    span,
+                }]);
+            }
+            (Projection::TupleField(index, span), _) => {
+                return Err(vec![IError::NoFieldOnType {
+                    field: index.to_string(),
+                    ty: base_ty,
+                    span,
+                }]);
+            }
+        };
+    }
+
+    Ok(target)
+}
+
+/// Returns the type declared for the part of a variable the projections lead to, given the type
+/// of the variable, or `None` if it is declared by a struct.
+pub fn project_ty(mut ty: &TyKind, projections: &[(Projection, Span)]) -> Option<TyKind> {
+    for (projection, _) in projections {
+        ty = match (projection, ty) {
+            (Projection::Index(..), TyKind::Array(el_ty, _)) => el_ty,
+            (Projection::TupleField(index, _), TyKind::Tuple(tys)) => tys.get(*index)?,
+            _ => return None,
+        };
+    }
+
+    Some(ty.clone())
+}
+
+/// Whether changing the part of a variable the projections lead to needs the variable to be
+/// mutable. Elements of an array can be modified through an immutable variable, but the variable
+/// itself and its fields can't.
+pub fn needs_mut(projections: &[(Projection, Span)]) -> bool {
+    projections.is_empty()
+        || projections
+            .iter()
+            .any(|(projection, _)| !matches!(projection, Projection::Index(..)))
+}
+
+/// Returns the variable at the root of a place, e.g. `grid` in `grid[0].x`, or `None` if the
+/// expression is not a place. It is the variable a method is called on if the prefix of a call
+/// like `grid[0].push(1)` is a place.
+pub fn place_root(receiver: &Expr) -> Option<&ast::Ident> {
+    match &receiver.kind {
+        ExprKind::Identifier(ident) => Some(ident),
+        ExprKind::Index(base, ..)
+        | ExprKind::LibAccess(base, _)
+        | ExprKind::TupleField(base, ..) => place_root(base),
+        _ => None,
+    }
+}
+
+/// Whether a call like `foo.bar()` calls a method on the value of `foo` rather than a function
+/// of the library `foo`. It is a call to a library if the prefix is a place whose root is not a
+/// variable, e.g. `math` in `math.sqrt(2.0)`.
+pub fn is_method_call(receiver: &Expr, is_variable: impl Fn(&str) -> bool) -> bool {
+    match place_root(receiver) {
+        Some(root) => is_variable(root.name.as_str()),
+        None => true,
+    }
+}
+
+/// Whether calling a method on the value may change it, in which case a variable holding it must
+/// be mutable. Only the methods of arrays do.
+pub fn method_mutates(target: &ValueKind) -> bool {
+    matches!(target, ValueKind::Array(..))
+}
+
+/// Returns the error of an argument of type `ty` that fixes the length of an array whose length
+/// can change through the `mut` parameter it is passed to.
+pub fn pass_fixed_array_as_growable_error(
+    param: &FuncParam,
+    ty: &TyKind,
+    arg_span: Span,
+) -> IError {
+    IError::PassFixedArrayAsGrowable {
+        param_name: Some(param.ident.name.to_string()),
+        ty: ty.to_string(),
+        param_ty: param.ty.to_string(),
+        arg_span,
+        param_span: param.span,
+    }
+}
+
+/// Returns the error of a method that a type does not have, with the methods it has if any.
+pub fn no_method_on_type_error(method: &ast::Ident, ty: &TyKind) -> IError {
+    fn names<T: ToString>(methods: &[T]) -> String {
+        let names: Vec<String> = methods
+            .iter()
+            .map(|method| format!("`{}`", method.to_string()))
+            .collect();
+        match names.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+            None => String::new(),
         }
-        _ => Err(vec![IError::IndexingWrongType {
-            ty: arr.to_ty_kind().to_string(),
-            span: array.span,
-        }]),
     }
+
+    let help = match ty {
+        TyKind::Array(..) => Some(format!(
+            "the methods of arrays are {}",
+            names(&ArrayMethod::ALL)
+        )),
+        TyKind::Range => Some(format!(
+            "the methods of ranges are {}",
+            names(&RangeMethod::ALL)
+        )),
+        _ => None,
+    };
+    IError::NoMethodOnType {
+        method: method.name.to_string(),
+        ty: ty.to_string(),
+        help,
+        span: method.span,
+    }
+}
+
+/// Calls a method on `target`, which must be an array or a range. `declared_ty` is the type an
+/// array is declared with, if it is known: its length can only change if the type does not fix
+/// it.
+pub fn eval_method_call(
+    target: &mut Value,
+    declared_ty: Option<TyKind>,
+    method: &ast::Ident,
+    args: Vec<Value>,
+    span: Span,
+) -> Result<ValueKind, Vec<IError>> {
+    let method_name = method.name.as_str();
+    if let (ValueKind::Range(range), Some(range_method)) =
+        (&target.kind, RangeMethod::from_name(method_name))
+    {
+        let params: Vec<ParamTy> = range_method
+            .params()
+            .into_iter()
+            .map(ParamTy::from)
+            .collect();
+        check_native_args(&params, &args, method.span)?;
+        return range_method.call(range, args, span);
+    }
+
+    let array_method = match (&target.kind, ArrayMethod::from_name(method_name)) {
+        (ValueKind::Array(..), Some(array_method)) => array_method,
+        _ => return Err(vec![no_method_on_type_error(method, &target.to_ty_kind())]),
+    };
+
+    if let Some(ty @ TyKind::Array(el_ty, len)) = &declared_ty {
+        if *len != -1 {
+            return Err(vec![IError::ArrayLengthIsFixed {
+                method: array_method.to_string(),
+                ty: ty.to_string(),
+                growable_ty: TyKind::Array(el_ty.clone(), -1).to_string(),
+                span: method.span,
+            }]);
+        }
+    }
+
+    let (elements, el_ty) = match &mut target.kind {
+        ValueKind::Array(elements, el_ty) => (elements, el_ty.as_mut()),
+        _ => unreachable!("The target is checked to be an array"),
+    };
+    let params: Vec<ParamTy> = array_method
+        .params(el_ty)
+        .into_iter()
+        .map(ParamTy::from)
+        .collect();
+    check_native_args(&params, &args, method.span)?;
+    array_method.call(elements, el_ty, args, span)
+}
+
+fn interpret_expr_assign_place_with_known_value(
+    env: &mut Environment,
+    lhs: &Box<Expr>,
+    rhs: ValueKind,
+    in_loop: bool,
+    assign_span: Span,
+    expr_span: Span,
+    is_verbose: bool,
+) -> Result<ValueKind, Vec<IError>> {
+    let (root, projections) = interpret_place(env, lhs, assign_span, in_loop, is_verbose)?;
+
+    let var = env.lookup_variable(root.name.as_str());
+    if var.is_none() {
+        return Err(vec![IError::CannotFindVariableInScope {
+            var_name: root.name.to_string(),
+            span: root.span,
+        }]);
+    }
+
+    let (var, projections) = resolve_reference(var.unwrap(), projections);
+    let mut var_bind = var.borrow_mut();
+    let decl_span = var_bind.ident.span;
+    if var_bind.val.is_none() {
+        return Err(vec![IError::VariableIsNotInitialized {
+            var_name: var_bind.ident.name.clone(),
+            declared_span: decl_span,
+            used_span: root.span,
+        }]);
+    }
+
+    // Elements of an array can be modified through an immutable variable, but fields can't.
+    let mutates_field = projections
+        .iter()
+        .any(|(projection, _)| !matches!(projection, Projection::Index(..)));
+    if mutates_field && !var_bind.is_mut {
+        return Err(vec![IError::MutateImmutableVariable {
+            mut_kw: Keyword::Mut.as_ref().to_string(),
+            var_name: root.name.to_string(),
+            first_assign_span: var_bind
+                .first_assigned_span
+                .expect("Variable must be assigned before"),
+            second_assign_span: expr_span,
+            help_span: decl_span,
+        }]);
+    }
+
+    // Now we have the variable, we can find the target by applying the projections.
+    let target = project(var_bind.val.as_mut().unwrap(), projections)?;
+
+    if target.to_ty_kind() != rhs.to_ty_kind() {
+        return Err(vec![IError::MismatchedType {
+            expected: target.to_ty_kind().to_string(),
+            found: rhs.to_ty_kind().to_string(),
+            span: expr_span,
+        }]);
+    }
+
+    target.kind = rhs;
+    Ok(ValueKind::Unit)
 }
 
 fn interpret_expr_assign_with_known_value(
@@ -931,14 +1951,14 @@ fn interpret_expr_assign_with_known_value(
         ExprKind::Identifier(ident) => interpret_expr_assign_ident_with_known_value(
             env, ident, kind, span, expr_span, is_verbose,
         ),
-        ExprKind::Index(arr, idx, _) => interpret_expr_assign_array_index_with_known_value(
-            env, arr, idx, kind, in_loop, is_verbose,
+        _ => interpret_expr_assign_place_with_known_value(
+            env,
+            lhs,
+            kind,
+            in_loop,
+            assign_span,
+            expr_span,
+            is_verbose,
         ),
-        _ => {
-            return Err(vec![IError::InvalidLhsAssign {
-                assign_span: assign_span,
-                lhs_span: lhs.span,
-            }]);
-        }
     }
 }
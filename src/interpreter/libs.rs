@@ -1,21 +1,243 @@
+use ::std::{collections::HashMap, fmt, rc::Rc};
+
 use crate::{
-    interpreter::eval::{Value, ValueKind},
+    interpreter::{
+        environment::Environment,
+        eval::{Value, ValueKind},
+    },
     span_encoding::Span,
 };
 
-use super::errors::IError;
+use super::{errors::IError, ty::TyKind};
 
 pub mod math;
 pub mod std;
 
 pub trait Library {
-    fn get_function(
-        &self,
-        name: &str,
-    ) -> Option<&Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>>;
+    fn get_function(&self, name: &str) -> Option<&NativeFunction>;
+
+    /// Returns the function with the given name if it is written in Pandora, so that the caller
+    /// can pass arguments to its `mut` parameters by reference.
+    fn get_user_function(&self, _name: &str) -> Option<&ValueKind> {
+        None
+    }
+
+    /// Returns the names of the functions of the library, e.g. for completion in an editor.
+    fn function_names(&self) -> Vec<String>;
+
+    /// Returns the constant with the given name, read as `lib.NAME`.
+    fn get_constant(&self, _name: &str) -> Option<&Value> {
+        None
+    }
+
+    /// Whether the library has a function with the given name that only the library itself can
+    /// call.
+    fn is_private(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Whether the library has a mutable variable with the given name, which only its top-level
+    /// code can use.
+    fn is_variable(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Returns the environment the functions written in Pandora run in, so that they can call
+    /// the other functions of their library.
+    fn env(&self) -> Option<&Environment> {
+        None
+    }
+}
+
+/// The error of a function missing from a library, or hidden in it.
+pub fn function_not_found_error(
+    lib: &dyn Library,
+    lib_name: &str,
+    func_name: &str,
+    span: Span,
+) -> IError {
+    if lib.is_variable(func_name) {
+        IError::MutableVariableInLibrary {
+            var_name: func_name.to_string(),
+            lib_name: lib_name.to_string(),
+            span,
+        }
+    } else if lib.is_private(func_name) {
+        IError::PrivateFunctionInLibrary {
+            func_name: func_name.to_string(),
+            lib_name: lib_name.to_string(),
+            span,
+        }
+    } else {
+        IError::FunctionInLibraryNotFound {
+            func_name: func_name.to_string(),
+            lib_name: lib_name.to_string(),
+            span,
+        }
+    }
 }
 
 pub struct CallerAttrs {
     pub span: Span,
     pub prefix_span: Span,
 }
+
+/// The type of a parameter of a native function.
+#[derive(Debug, Clone)]
+pub enum ParamTy {
+    Ty(TyKind),
+    /// An array of any element type and length, e.g. for `arrlen`.
+    AnyArray,
+}
+
+impl ParamTy {
+    pub fn accepts(&self, ty: &TyKind) -> bool {
+        match self {
+            ParamTy::Ty(param_ty) => param_ty == ty,
+            ParamTy::AnyArray => matches!(ty, TyKind::Array(..)),
+        }
+    }
+}
+
+impl From<TyKind> for ParamTy {
+    fn from(ty: TyKind) -> Self {
+        ParamTy::Ty(ty)
+    }
+}
+
+impl fmt::Display for ParamTy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamTy::Ty(ty) => write!(f, "{}", ty),
+            ParamTy::AnyArray => write!(f, "[_]"),
+        }
+    }
+}
+
+pub type NativeBody = dyn Fn(CallerAttrs, Vec<Value>) -> Result<ValueKind, Vec<IError>>;
+
+/// A function of a library written in Rust. Its arguments are checked against the types of
+/// its parameters before its body is called, so the body can assume them, and the value it
+/// returns is checked against its return type, which the type checker relies on.
+pub struct NativeFunction {
+    pub params: Vec<ParamTy>,
+    pub output: TyKind,
+    body: Box<NativeBody>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        params: Vec<ParamTy>,
+        output: TyKind,
+        body: impl Fn(CallerAttrs, Vec<Value>) -> Result<ValueKind, Vec<IError>> + 'static,
+    ) -> Self {
+        NativeFunction {
+            params,
+            output,
+            body: Box::new(body),
+        }
+    }
+
+    pub fn call(&self, cattrs: CallerAttrs, args: Vec<Value>) -> Result<ValueKind, Vec<IError>> {
+        check_native_args(&self.params, &args, cattrs.prefix_span)?;
+        let span = cattrs.span;
+        let value = (self.body)(cattrs, args)?;
+
+        let ty = value.to_ty_kind();
+        if ty != self.output {
+            return Err(vec![IError::PredefinedError {
+                span,
+                message: format!(
+                    "the function returned a value of type `{}` instead of `{}`",
+                    ty, self.output
+                ),
+            }]);
+        }
+        Ok(value)
+    }
+}
+
+/// Checks the arguments of a call to a function written in Rust, or to a method of an array,
+/// against the types of its parameters.
+pub fn check_native_args(
+    params: &[ParamTy],
+    args: &[Value],
+    prefix_span: Span,
+) -> Result<(), Vec<IError>> {
+    let mismatch_params: Vec<(Span, String, String)> = params
+        .iter()
+        .zip(args)
+        .filter(|(param, arg)| !param.accepts(&arg.to_ty_kind()))
+        .map(|(param, arg)| (arg.span, param.to_string(), arg.to_ty_kind().to_string()))
+        .collect();
+    let missing_param_tys: Vec<String> = params
+        .iter()
+        .skip(args.len())
+        .map(|param| param.to_string())
+        .collect();
+    let unexpected_param_tys: Vec<(Span, String)> = args
+        .iter()
+        .skip(params.len())
+        .map(|arg| (arg.span, arg.to_ty_kind().to_string()))
+        .collect();
+
+    if !mismatch_params.is_empty()
+        || !missing_param_tys.is_empty()
+        || !unexpected_param_tys.is_empty()
+    {
+        return Err(vec![IError::FunctionParamMismatch {
+            func_decl_span: None,
+            args: args.len(),
+            mismatch_params,
+            missing_param_tys,
+            unexpected_param_tys,
+            prefix_span,
+        }]);
+    }
+
+    Ok(())
+}
+
+/// A library whose functions are written in Rust by the program embedding the interpreter,
+/// and imported with `add <name>;` like `math`.
+#[derive(Clone, Default)]
+pub struct NativeLibrary {
+    functions: HashMap<String, Rc<NativeFunction>>,
+}
+
+impl Library for NativeLibrary {
+    fn get_function(&self, name: &str) -> Option<&NativeFunction> {
+        self.functions.get(name).map(|function| function.as_ref())
+    }
+
+    fn function_names(&self) -> Vec<String> {
+        self.functions.keys().cloned().collect()
+    }
+}
+
+impl NativeLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function taking arguments of the given types and returning a value of type
+    /// `output`. If the function fails, its message is reported at the call.
+    pub fn add_function(
+        &mut self,
+        name: &str,
+        params: Vec<ParamTy>,
+        output: TyKind,
+        function: impl Fn(Vec<Value>) -> Result<ValueKind, String> + 'static,
+    ) -> &mut Self {
+        let function = NativeFunction::new(params, output, move |cattrs, args| {
+            function(args).map_err(|message| {
+                vec![IError::PredefinedError {
+                    span: cattrs.span,
+                    message,
+                }]
+            })
+        });
+        self.functions.insert(name.to_string(), Rc::new(function));
+        self
+    }
+}
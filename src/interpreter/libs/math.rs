@@ -1,27 +1,21 @@
 use std::collections::HashMap;
 
-use crate::interpreter::{
-    errors::IError,
-    eval::{Value, ValueKind},
-};
+use crate::interpreter::{errors::IError, eval::ValueKind, ty::TyKind};
 
-use super::{CallerAttrs, Library};
+use super::{Library, NativeFunction};
 
 pub struct MathLib {
-    pub functions: HashMap<
-        String,
-        Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>,
-    >,
+    pub functions: HashMap<String, NativeFunction>,
 }
 
 impl Library for MathLib {
-    fn get_function(
-        &self,
-        name: &str,
-    ) -> Option<&Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>>
-    {
+    fn get_function(&self, name: &str) -> Option<&NativeFunction> {
         self.functions.get(name)
     }
+
+    fn function_names(&self) -> Vec<String> {
+        self.functions.keys().cloned().collect()
+    }
 }
 
 impl MathLib {
@@ -53,285 +47,145 @@ impl MathLib {
     }
 
     fn register_ln_function(&mut self) {
-        // ln() function
-        self.functions.insert(
-            "ln".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "ln() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.ln())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "ln() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // ln(float) -> float function
+        self.functions
+            .insert("ln".to_string(), float_function(|f| f.ln()));
     }
 
     fn register_log_function(&mut self) {
-        // log() function
+        // log(float, float) -> float function
         self.functions.insert(
             "log".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 2 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "log() takes exactly 2 argument".to_string(),
-                    }]);
-                }
-
-                match (&args[0].0.kind, &args[1].0.kind) {
-                    (ValueKind::Float(i), ValueKind::Float(j)) => {
-                        Ok(ValueKind::Float(i.log(j.clone())))
-                    }
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "log() takes two floats".to_string(),
-                    }]),
-                }
-            }),
+            NativeFunction::new(
+                vec![TyKind::Float.into(), TyKind::Float.into()],
+                TyKind::Float,
+                |_, args| match (&args[0].kind, &args[1].kind) {
+                    (ValueKind::Float(i), ValueKind::Float(j)) => Ok(ValueKind::Float(i.log(*j))),
+                    _ => unreachable!("The arguments are checked to be floats"),
+                },
+            ),
         );
     }
 
     fn register_tan_function(&mut self) {
-        // tan() function
-        self.functions.insert(
-            "tan".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "tan() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.to_radians().tan())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "tan() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // tan(float) -> float function
+        self.functions
+            .insert("tan".to_string(), float_function(|f| f.to_radians().tan()));
     }
 
     fn register_cos_function(&mut self) {
-        // cos() function
-        self.functions.insert(
-            "cos".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "cos() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.to_radians().cos())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "cos() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // cos(float) -> float function
+        self.functions
+            .insert("cos".to_string(), float_function(|f| f.to_radians().cos()));
     }
 
     fn register_sin_function(&mut self) {
-        // sin() function
-        self.functions.insert(
-            "sin".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "sin() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.to_radians().sin())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "sin() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // sin(float) -> float function
+        self.functions
+            .insert("sin".to_string(), float_function(|f| f.to_radians().sin()));
     }
 
     fn register_floor_function(&mut self) {
-        // floor() function
-        self.functions.insert(
-            "floor".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "floor() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Int(f.floor() as i64)),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "floor() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // floor(float) -> int function
+        self.functions
+            .insert("floor".to_string(), float_to_int_function(|f| f.floor()));
     }
 
     fn register_ceil_function(&mut self) {
-        // ceil() function
-        self.functions.insert(
-            "ceil".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "ceil() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Int(f.ceil() as i64)),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "ceil() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // ceil(float) -> int function
+        self.functions
+            .insert("ceil".to_string(), float_to_int_function(|f| f.ceil()));
     }
 
     fn register_gcd_function(&mut self) {
-        // gcd() function
+        // gcd(int, int) -> int function
+        // The absolute value of `i64::MIN` does not fit in an `i64`, so the GCD is computed on
+        // unsigned integers. It only overflows when it is that value, e.g. `gcd(i64::MIN, 0)`.
+        fn calculate_gcd(a: i64, b: i64) -> Option<i64> {
+            let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+            while b != 0 {
+                let temp = b;
+                b = a % b;
+                a = temp;
+            }
+            i64::try_from(a).ok()
+        }
+
         self.functions.insert(
             "gcd".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 2 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "gcd() takes exactly 2 arguments".to_string(),
-                    }]);
-                }
-
-                fn calculate_gcd(mut a: i64, mut b: i64) -> i64 {
-                    a = a.abs();
-                    b = b.abs();
-                    while b != 0 {
-                        let temp = b;
-                        b = a % b;
-                        a = temp;
-                    }
-                    a
-                }
-
-                match (&args[0].0.kind, &args[1].0.kind) {
-                    (ValueKind::Int(i), ValueKind::Int(j)) => {
-                        Ok(ValueKind::Int(calculate_gcd(*i, *j)))
-                    }
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "gcd() takes two integers".to_string(),
-                    }]),
-                }
-            }),
+            NativeFunction::new(
+                vec![TyKind::Int.into(), TyKind::Int.into()],
+                TyKind::Int,
+                |cattrs, args| match (&args[0].kind, &args[1].kind) {
+                    (ValueKind::Int(i), ValueKind::Int(j)) => match calculate_gcd(*i, *j) {
+                        Some(gcd) => Ok(ValueKind::Int(gcd)),
+                        None => Err(vec![IError::ArithmeticOverflow {
+                            op: "gcd".to_string(),
+                            lhs: None,
+                            rhs: format!("({}, {})", i, j),
+                            op_span: cattrs.span,
+                        }]),
+                    },
+                    _ => unreachable!("The arguments are checked to be integers"),
+                },
+            ),
         );
     }
 
     fn register_abs_function(&mut self) {
-        // abs() function
-        self.functions.insert(
-            "abs".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "abs() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.abs())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "abs() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // abs(float) -> float function
+        self.functions
+            .insert("abs".to_string(), float_function(|f| f.abs()));
     }
 
     fn register_round_function(&mut self) {
-        // round() function
-        self.functions.insert(
-            "round".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "round() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Int(f.round() as i64)),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "round() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // round(float) -> int function
+        self.functions
+            .insert("round".to_string(), float_to_int_function(|f| f.round()));
     }
 
     fn register_sqrt_function(&mut self) {
-        // sqrt() function
-        self.functions.insert(
-            "sqrt".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "sqrt() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Float(f) => Ok(ValueKind::Float(f.sqrt())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "sqrt() takes a float".to_string(),
-                    }]),
-                }
-            }),
-        );
+        // sqrt(float) -> float function
+        self.functions
+            .insert("sqrt".to_string(), float_function(|f| f.sqrt()));
     }
 
     fn register_pow_function(&mut self) {
-        // pow() function
+        // pow(float, float) -> float function
         self.functions.insert(
             "pow".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 2 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "pow() takes exactly 2 arguments".to_string(),
-                    }]);
-                }
-                match (&args[0].0.kind, &args[1].0.kind) {
-                    (ValueKind::Float(i), ValueKind::Float(j)) => {
-                        Ok(ValueKind::Float((i.powf(*j)) as f64))
-                    }
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "pow() takes two floats".to_string(),
-                    }]),
-                }
-            }),
+            NativeFunction::new(
+                vec![TyKind::Float.into(), TyKind::Float.into()],
+                TyKind::Float,
+                |_, args| match (&args[0].kind, &args[1].kind) {
+                    (ValueKind::Float(i), ValueKind::Float(j)) => Ok(ValueKind::Float(i.powf(*j))),
+                    _ => unreachable!("The arguments are checked to be floats"),
+                },
+            ),
         );
     }
 }
+
+/// A function taking a float and returning a float.
+fn float_function(f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::new(
+        vec![TyKind::Float.into()],
+        TyKind::Float,
+        move |_, args| match &args[0].kind {
+            ValueKind::Float(x) => Ok(ValueKind::Float(f(*x))),
+            _ => unreachable!("The argument is checked to be a float"),
+        },
+    )
+}
+
+/// A function taking a float and returning an int, e.g. a rounding function.
+fn float_to_int_function(f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::new(
+        vec![TyKind::Float.into()],
+        TyKind::Int,
+        move |_, args| match &args[0].kind {
+            ValueKind::Float(x) => Ok(ValueKind::Int(f(*x) as i64)),
+            _ => unreachable!("The argument is checked to be a float"),
+        },
+    )
+}
@@ -1,28 +1,22 @@
 use std::collections::HashMap;
 
-use crate::interpreter::{
-    errors::IError,
-    eval::{Value, ValueKind},
-};
+use crate::interpreter::{errors::IError, eval::ValueKind, ty::TyKind};
 
-use super::{CallerAttrs, Library};
+use super::{Library, NativeFunction, ParamTy};
 use ::std::io::{self, Write};
 
 pub struct StdLib {
-    pub functions: HashMap<
-        String,
-        Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>,
-    >,
+    pub functions: HashMap<String, NativeFunction>,
 }
 
 impl Library for StdLib {
-    fn get_function(
-        &self,
-        name: &str,
-    ) -> Option<&Box<dyn Fn(CallerAttrs, Vec<(Value, bool)>) -> Result<ValueKind, Vec<IError>>>>
-    {
+    fn get_function(&self, name: &str) -> Option<&NativeFunction> {
         self.functions.get(name)
     }
+
+    fn function_names(&self) -> Vec<String> {
+        self.functions.keys().cloned().collect()
+    }
 }
 
 impl StdLib {
@@ -50,26 +44,12 @@ impl StdLib {
     }
 
     fn register_delay_function(&mut self) {
-        // delay() function
+        // delay(int) function
         self.functions.insert(
             "delay".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "delay() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Int(ms) => {
-                        std::thread::sleep(std::time::Duration::from_millis(*ms as u64));
-                    }
-                    _ => {
-                        return Err(vec![IError::PredefinedError {
-                            span: cattrs.prefix_span,
-                            message: "delay() takes an integer".to_string(),
-                        }]);
-                    }
+            NativeFunction::new(vec![TyKind::Int.into()], TyKind::Unit, |_, args| {
+                if let ValueKind::Int(ms) = &args[0].kind {
+                    std::thread::sleep(std::time::Duration::from_millis(*ms as u64));
                 }
                 Ok(ValueKind::Unit)
             }),
@@ -77,90 +57,62 @@ impl StdLib {
     }
 
     fn register_str_len_function(&mut self) {
-        // strlen() function for strings
+        // strlen(str) function for strings
         self.functions.insert(
             "strlen".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "strlen() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
+            NativeFunction::new(
+                vec![TyKind::Str.into()],
+                TyKind::Int,
+                |_, args| match &args[0].kind {
                     ValueKind::Str(s) => Ok(ValueKind::Int(s.len() as i64)),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "strlen() takes a string".to_string(),
-                    }]),
-                }
-            }),
+                    _ => unreachable!("The argument is checked to be a string"),
+                },
+            ),
         );
     }
 
     fn register_array_len_function(&mut self) {
-        // arrlen() function for arrays
+        // arrlen([_]) function for arrays
         self.functions.insert(
             "arrlen".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "arrlen() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Array(arr) => Ok(ValueKind::Int(arr.len() as i64)),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "arrlen() takes an array".to_string(),
-                    }]),
-                }
-            }),
+            NativeFunction::new(
+                vec![ParamTy::AnyArray],
+                TyKind::Int,
+                |_, args| match &args[0].kind {
+                    ValueKind::Array(arr, _) => Ok(ValueKind::Int(arr.len() as i64)),
+                    _ => unreachable!("The argument is checked to be an array"),
+                },
+            ),
         );
     }
 
     fn register_lower_function(&mut self) {
-        // lower() function
+        // lower(str) function
         self.functions.insert(
             "lower".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "lower() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
+            NativeFunction::new(
+                vec![TyKind::Str.into()],
+                TyKind::Str,
+                |_, args| match &args[0].kind {
                     ValueKind::Str(s) => Ok(ValueKind::Str(s.to_lowercase())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "lower() takes a string".to_string(),
-                    }]),
-                }
-            }),
+                    _ => unreachable!("The argument is checked to be a string"),
+                },
+            ),
         );
     }
 
     fn register_upper_function(&mut self) {
-        // upper() function
+        // upper(str) function
         self.functions.insert(
             "upper".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "upper() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
+            NativeFunction::new(
+                vec![TyKind::Str.into()],
+                TyKind::Str,
+                |_, args| match &args[0].kind {
                     ValueKind::Str(s) => Ok(ValueKind::Str(s.to_uppercase())),
-                    _ => Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "upper() takes a string".to_string(),
-                    }]),
-                }
-            }),
+                    _ => unreachable!("The argument is checked to be a string"),
+                },
+            ),
         );
     }
 
@@ -168,13 +120,7 @@ impl StdLib {
         // input() function
         self.functions.insert(
             "input".to_string(),
-            Box::new(|cattrs, args| {
-                if !args.is_empty() {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "input() takes no arguments".to_string(),
-                    }]);
-                }
+            NativeFunction::new(vec![], TyKind::Str, |_, _| {
                 let mut input = String::new();
                 std::io::stdin()
                     .read_line(&mut input)
@@ -185,26 +131,12 @@ impl StdLib {
     }
 
     fn register_println_function(&mut self) {
-        // println() function
+        // println(str) function
         self.functions.insert(
             "println".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "println() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Str(s) => {
-                        println!("{}", s);
-                    }
-                    _ => {
-                        return Err(vec![IError::PredefinedError {
-                            span: cattrs.prefix_span,
-                            message: "println() takes a string".to_string(),
-                        }]);
-                    }
+            NativeFunction::new(vec![TyKind::Str.into()], TyKind::Unit, |_, args| {
+                if let ValueKind::Str(s) = &args[0].kind {
+                    println!("{}", s);
                 }
                 Ok(ValueKind::Unit)
             }),
@@ -212,26 +144,12 @@ impl StdLib {
     }
 
     fn register_print_function(&mut self) {
-        // print() function
+        // print(str) function
         self.functions.insert(
             "print".to_string(),
-            Box::new(|cattrs, args| {
-                if args.len() != 1 {
-                    return Err(vec![IError::PredefinedError {
-                        span: cattrs.prefix_span,
-                        message: "print() takes exactly 1 argument".to_string(),
-                    }]);
-                }
-                match &args[0].0.kind {
-                    ValueKind::Str(s) => {
-                        print!("{}", s);
-                    }
-                    _ => {
-                        return Err(vec![IError::PredefinedError {
-                            span: cattrs.prefix_span,
-                            message: "print() takes a string".to_string(),
-                        }]);
-                    }
+            NativeFunction::new(vec![TyKind::Str.into()], TyKind::Unit, |cattrs, args| {
+                if let ValueKind::Str(s) = &args[0].kind {
+                    print!("{}", s);
                 }
                 io::stdout().flush().map_err(|e| {
                     vec![IError::PredefinedError {
@@ -0,0 +1,189 @@
+//! Ranges of integers, e.g. `0..10`, `0..=10` or `(0..10).step(2).rev()`. A range only holds
+//! its bounds and its step, and its elements are computed one at a time when it is iterated.
+
+use std::fmt;
+
+use crate::span_encoding::Span;
+
+use super::{
+    errors::IError,
+    eval::{Value, ValueKind},
+    ty::TyKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    /// The first element, if the range is not empty.
+    pub start: i64,
+    pub end: i64,
+    /// The difference between two elements in a row. It is negative for a reversed range.
+    pub step: i64,
+    /// Whether the end is an element, as in `0..=10`.
+    pub is_inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, is_inclusive: bool) -> Self {
+        Range {
+            start,
+            end,
+            step: 1,
+            is_inclusive,
+        }
+    }
+
+    /// Returns the number of elements in the range.
+    pub fn len(&self) -> u64 {
+        let (start, end, step) = (self.start as i128, self.end as i128, self.step as i128);
+        // The distance from the first element to the end, in the direction of the step.
+        let distance = if step > 0 { end - start } else { start - end };
+        let distance = if self.is_inclusive {
+            distance + 1
+        } else {
+            distance
+        };
+
+        if distance <= 0 {
+            0
+        } else {
+            // Rounded up, the last element may be closer to the end than a step.
+            ((distance + step.abs() - 1) / step.abs()) as u64
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at `index`, or `None` if the range is not that long.
+    pub fn get(&self, index: u64) -> Option<i64> {
+        if index >= self.len() {
+            return None;
+        }
+        // It is between the first and the last element, so it fits in an `i64`.
+        Some((self.start as i128 + index as i128 * self.step as i128) as i64)
+    }
+
+    fn last(&self) -> Option<i64> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+}
+
+impl fmt::Display for Range {
+    /// Formats the range the way it can be written, e.g. `(0..=8).step(2).rev()` for the range
+    /// from `8` down to `0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.step > 0 {
+            let limits = if self.is_inclusive { "..=" } else { ".." };
+            if self.step == 1 {
+                return write!(f, "{}{}{}", self.start, limits, self.end);
+            }
+            return write!(
+                f,
+                "({}{}{}).step({})",
+                self.start, limits, self.end, self.step
+            );
+        }
+
+        // A reversed range is never empty, it is the reverse of the range from its last element
+        // up to its first one.
+        let forward = Range {
+            start: self.last().unwrap_or(self.end),
+            end: self.start,
+            step: -self.step,
+            is_inclusive: true,
+        };
+        if forward.step == 1 {
+            write!(f, "({}).rev()", forward)
+        } else {
+            write!(f, "{}.rev()", forward)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeMethod {
+    /// `step(n)` keeps every `n`th element, starting with the first one.
+    Step,
+    /// `rev()` yields the elements in the reverse order.
+    Rev,
+}
+
+impl RangeMethod {
+    pub const ALL: [RangeMethod; 2] = [RangeMethod::Step, RangeMethod::Rev];
+
+    pub fn from_name(name: &str) -> Option<RangeMethod> {
+        RangeMethod::ALL
+            .into_iter()
+            .find(|method| method.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RangeMethod::Step => "step",
+            RangeMethod::Rev => "rev",
+        }
+    }
+
+    /// The types of the parameters of the method.
+    pub fn params(&self) -> Vec<TyKind> {
+        match self {
+            RangeMethod::Step => vec![TyKind::Int],
+            RangeMethod::Rev => vec![],
+        }
+    }
+
+    /// Calls the method on a range, with arguments of the types of its parameters. The range
+    /// is not changed, a new one is returned.
+    pub fn call(
+        &self,
+        range: &Range,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<ValueKind, Vec<IError>> {
+        let range = match self {
+            RangeMethod::Step => {
+                let (step, step_span) = match args.first() {
+                    Some(Value {
+                        kind: ValueKind::Int(step),
+                        span,
+                    }) => (*step, *span),
+                    _ => unreachable!("The argument is checked to be an integer"),
+                };
+                if step <= 0 {
+                    return Err(vec![IError::RangeStepNotPositive {
+                        step,
+                        span: step_span,
+                    }]);
+                }
+
+                let step = range.step.checked_mul(step).ok_or_else(|| {
+                    vec![IError::ArithmeticOverflow {
+                        op: "*".to_string(),
+                        lhs: Some(range.step.to_string()),
+                        rhs: step.to_string(),
+                        op_span: span,
+                    }]
+                })?;
+                Range { step, ..*range }
+            }
+            RangeMethod::Rev => match range.last() {
+                Some(last) => Range {
+                    start: last,
+                    end: range.start,
+                    step: -range.step,
+                    is_inclusive: true,
+                },
+                None => *range,
+            },
+        };
+
+        Ok(ValueKind::Range(range))
+    }
+}
+
+impl fmt::Display for RangeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable listing directories where libraries are looked up, separated like
+/// the directories of `PATH`.
+pub const PANDORA_PATH: &str = "PANDORA_PATH";
+
+/// Finds the files of the libraries added by a program. The library `utils.strings` is the file
+/// `utils/strings.boxx`, looked up in the directory of the program first, then in the
+/// directories given with `--lib-path`, then in those of `PANDORA_PATH`.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleResolver {
+    program_dir: Option<PathBuf>,
+    lib_paths: Vec<PathBuf>,
+    /// The directories of `PANDORA_PATH`.
+    env_paths: Vec<PathBuf>,
+}
+
+impl ModuleResolver {
+    /// Creates a resolver for a program in `program_dir`, with the directories of `PANDORA_PATH`
+    /// searched after `lib_paths`.
+    pub fn new(program_dir: Option<PathBuf>, lib_paths: Vec<PathBuf>) -> Self {
+        let env_paths = match env::var_os(PANDORA_PATH) {
+            Some(paths) => env::split_paths(&paths)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect(),
+            None => vec![],
+        };
+
+        ModuleResolver {
+            program_dir,
+            lib_paths,
+            env_paths,
+        }
+    }
+
+    /// Sets the directory of the program, which is searched before any other.
+    pub fn set_program_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.program_dir = Some(dir.into());
+    }
+
+    /// Adds a directory searched after the ones given before, but before those of
+    /// `PANDORA_PATH`.
+    pub fn add_lib_path(&mut self, dir: impl Into<PathBuf>) {
+        self.lib_paths.push(dir.into());
+    }
+
+    /// Returns the directories searched for libraries, in order.
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.program_dir
+            .iter()
+            .chain(self.lib_paths.iter())
+            .chain(self.env_paths.iter())
+            .map(PathBuf::as_path)
+    }
+
+    /// Returns the file of the library with the given path, e.g. `["utils", "strings"]`, or
+    /// `None` if there is none in the searched directories.
+    pub fn resolve(&self, path: &[&str]) -> Option<PathBuf> {
+        let extension = if crate::is_genz_mode() {
+            "unbxx"
+        } else {
+            "boxx"
+        };
+        let relative: PathBuf = path.iter().collect::<PathBuf>().with_extension(extension);
+
+        self.dirs()
+            .map(|dir| dir.join(&relative))
+            .find(|file| file.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn dotted_paths_are_looked_up_in_order() {
+        let dir = env::temp_dir().join(format!("pandora-resolver-{}", std::process::id()));
+        for file in ["app/util.boxx", "lib/util.boxx", "lib/geo/calc.boxx"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let mut resolver = ModuleResolver::default();
+        resolver.add_lib_path(dir.join("lib"));
+        let before_program_dir = resolver.resolve(&["util"]);
+        resolver.set_program_dir(dir.join("app"));
+        let util = resolver.resolve(&["util"]);
+        let calc = resolver.resolve(&["geo", "calc"]);
+        let missing = resolver.resolve(&["geo", "util"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before_program_dir, Some(dir.join("lib/util.boxx")));
+        assert_eq!(util, Some(dir.join("app/util.boxx")));
+        assert_eq!(calc, Some(dir.join("lib/geo/calc.boxx")));
+        assert_eq!(missing, None);
+    }
+}
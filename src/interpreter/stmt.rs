@@ -1,5 +1,5 @@
 use crate::{
-    ast::{self, Expr, Fun, FunSig, Local, LocalKind, Stmt, StmtKind},
+    ast::{self, Arm, Expr, Fun, FunSig, Local, LocalKind, Pat, PatKind, Stmt, StmtKind},
     kw::{self, Keyword},
     span_encoding::Span,
 };
@@ -8,10 +8,11 @@ use super::{
     environment::Environment,
     errors::IError,
     eval::{ControlFlow, EvalResult},
+    exhaustiveness,
     ident::Ident,
     interpret_expr, interpret_ty,
     ty::TyKind,
-    Func, FuncParam, FuncSig, IResult, Ty, Value, ValueKind,
+    EnumDef, Func, FuncBody, FuncParam, FuncSig, IResult, StructDef, Ty, TypeDef, Value, ValueKind,
 };
 
 pub fn interpret_stmt(
@@ -22,7 +23,7 @@ pub fn interpret_stmt(
 ) -> IResult {
     let span = stmt.span.clone();
     match &stmt.kind {
-        StmtKind::Import(ident) => interpret_stmt_import(env, ident, is_verbose),
+        StmtKind::Import(import) => interpret_stmt_import(env, import, is_verbose),
         StmtKind::Expr(expr) => interpret_stmt_expr(env, expr, in_loop, is_verbose),
         StmtKind::Var(local) => interpret_stmt_var_decl(env, local, in_loop, is_verbose),
         StmtKind::If(cond, then_block, else_block) => {
@@ -33,6 +34,11 @@ pub fn interpret_stmt(
         StmtKind::Continue => interpret_stmt_continue(span, in_loop, is_verbose),
         StmtKind::Block(stmts) => interpret_stmt_block(env, stmts, in_loop, is_verbose),
         StmtKind::FuncDecl(fun) => interpret_stmt_func_decl(env, fun, in_loop, is_verbose),
+        StmtKind::StructDecl(def) => interpret_stmt_struct_decl(env, def, in_loop, is_verbose),
+        StmtKind::EnumDecl(def) => interpret_stmt_enum_decl(env, def, in_loop, is_verbose),
+        StmtKind::Match(scrutinee, arms) => {
+            interpret_stmt_match(env, scrutinee, arms, in_loop, is_verbose)
+        }
         StmtKind::Return(expr) => interpret_stmt_return(env, expr, span, is_verbose),
         StmtKind::For(ident, iterator, block) => {
             interpret_stmt_for(env, ident, iterator, block, in_loop, is_verbose)
@@ -68,7 +74,7 @@ pub fn interpret_stmt_for(
 
     let value = interpret_expr(env, expr, in_loop, is_verbose)?;
     let value_ty = value.to_ty_kind();
-    let values = value.clone().into_iter().map_err(|_| {
+    let values = value.clone().into_elements().map_err(|_| {
         [IError::ExpectedIterator {
             ty: value_ty.to_string(),
             span: value.span,
@@ -169,7 +175,7 @@ pub fn interpret_stmt_func_decl(
             fun
         );
     }
-    let Fun { sig, body } = fun.as_ref();
+    let Fun { sig, body, .. } = fun.as_ref();
     let FunSig {
         name,
         inputs,
@@ -177,22 +183,7 @@ pub fn interpret_stmt_func_decl(
         span: sig_span,
     } = sig;
 
-    let mut params: Vec<FuncParam> = vec![];
-    for input in inputs {
-        let ty = interpret_ty(env, &input.ty, in_loop, is_verbose)?;
-        let ident = Ident {
-            name: input.ident.name.to_string(),
-            span: input.ident.span.clone(),
-        };
-        let is_mut = input.is_mut;
-        let span = input.span;
-        params.push(FuncParam {
-            ident,
-            ty,
-            is_mut,
-            span,
-        });
-    }
+    let params = interpret_fun_params(env, inputs, in_loop, is_verbose)?;
 
     let output = match output {
         Some(ty) => Some(interpret_ty(env, ty, in_loop, is_verbose)?),
@@ -212,9 +203,9 @@ pub fn interpret_stmt_func_decl(
 
     let function = Func {
         sig: func_sig,
-        body: body.clone(),
+        body: FuncBody::Tree(body.clone(), vec![]),
     };
-    let func_val = ValueKind::Function(function);
+    let func_val = ValueKind::Function(Box::new(function));
 
     env.insert_function(
         name.name.as_str().to_string(),
@@ -225,6 +216,234 @@ pub fn interpret_stmt_func_decl(
     Ok(EvalResult::StmtResult(None))
 }
 
+pub fn interpret_fun_params(
+    env: &mut Environment,
+    inputs: &Vec<ast::FunParam>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<Vec<FuncParam>, Vec<IError>> {
+    let mut params: Vec<FuncParam> = vec![];
+    for input in inputs {
+        let ty = interpret_ty(env, &input.ty, in_loop, is_verbose)?;
+        let ident = Ident {
+            name: input.ident.name.to_string(),
+            span: input.ident.span,
+        };
+        let is_mut = input.is_mut;
+        let span = input.span;
+        params.push(FuncParam {
+            ident,
+            ty,
+            is_mut,
+            span,
+        });
+    }
+    Ok(params)
+}
+
+pub fn interpret_stmt_struct_decl(
+    env: &mut Environment,
+    def: &ast::StructDef,
+    in_loop: bool,
+    is_verbose: bool,
+) -> IResult {
+    if is_verbose {
+        println!(
+            "\x1b[90m[DEBUG] Interpreting struct declaration with definition: {:?}\x1b[0m",
+            def
+        );
+    }
+    let ast::StructDef { name, fields } = def;
+
+    let mut resolved_fields: Vec<(Ident, Ty)> = vec![];
+    for field in fields {
+        let field_name = field.ident.name.as_str();
+        if let Some((first, _)) = resolved_fields
+            .iter()
+            .find(|(ident, _)| ident.name == field_name)
+        {
+            return Err(vec![IError::FieldSpecifiedMoreThanOnce {
+                field: field_name.to_string(),
+                first_span: first.span,
+                second_span: field.ident.span,
+            }]);
+        }
+
+        let ty = interpret_ty(env, &field.ty, in_loop, is_verbose)?;
+        let ident = Ident {
+            name: field_name.to_string(),
+            span: field.ident.span,
+        };
+        resolved_fields.push((ident, ty));
+    }
+
+    let ident = Ident {
+        name: name.name.as_str().to_string(),
+        span: name.span,
+    };
+    let def = StructDef {
+        ident,
+        fields: resolved_fields,
+    };
+
+    env.insert_type(name.name.to_string(), TypeDef::Struct(def), name.span)?;
+    Ok(EvalResult::StmtResult(None))
+}
+
+pub fn interpret_stmt_enum_decl(
+    env: &mut Environment,
+    def: &ast::EnumDef,
+    in_loop: bool,
+    is_verbose: bool,
+) -> IResult {
+    if is_verbose {
+        println!(
+            "\x1b[90m[DEBUG] Interpreting enum declaration with definition: {:?}\x1b[0m",
+            def
+        );
+    }
+    let ast::EnumDef { name, variants } = def;
+
+    // The enum is declared before its variants are resolved, so that a variant can hold
+    // a value of the enum itself.
+    let ident = Ident {
+        name: name.name.as_str().to_string(),
+        span: name.span,
+    };
+    let mut def = EnumDef {
+        ident,
+        variants: vec![],
+    };
+    env.insert_type(name.name.to_string(), TypeDef::Enum(def.clone()), name.span)?;
+
+    for variant in variants {
+        let variant_name = variant.ident.name.as_str();
+        if let Some((first, _)) = def
+            .variants
+            .iter()
+            .find(|(ident, _)| ident.name == variant_name)
+        {
+            return Err(vec![IError::VariantDeclaredMoreThanOnce {
+                variant: variant_name.to_string(),
+                first_span: first.span,
+                second_span: variant.ident.span,
+            }]);
+        }
+
+        let mut fields = vec![];
+        for ty in &variant.fields {
+            fields.push(interpret_ty(env, ty, in_loop, is_verbose)?);
+        }
+        let ident = Ident {
+            name: variant_name.to_string(),
+            span: variant.ident.span,
+        };
+        def.variants.push((ident, fields));
+    }
+
+    env.update_type(name.name.as_str(), TypeDef::Enum(def));
+    Ok(EvalResult::StmtResult(None))
+}
+
+pub fn interpret_stmt_match(
+    env: &mut Environment,
+    scrutinee: &Box<Expr>,
+    arms: &Vec<Arm>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> IResult {
+    if is_verbose {
+        println!(
+            "\x1b[90m[DEBUG] Interpreting match statement with scrutinee: {:?} --- arms: {:?}\x1b[0m",
+            scrutinee, arms
+        );
+    }
+
+    let value = interpret_expr(env, scrutinee, in_loop, is_verbose)?;
+    let pats: Vec<&Pat> = arms.iter().map(|arm| &arm.pat).collect();
+    exhaustiveness::check_match(env, &value.to_ty_kind(), &pats, scrutinee.span, is_verbose)?;
+
+    for arm in arms {
+        let bindings = match match_pat(env, &arm.pat, &value, is_verbose)? {
+            Some(bindings) => bindings,
+            None => continue,
+        };
+
+        env.push_scope();
+        for (ident, value) in bindings {
+            let ty = Ty {
+                kind: value.to_ty_kind(),
+                span: ident.span,
+            };
+            let first_assigned_span = Some(ident.span);
+            env.insert_variable(ident, Some(value), false, ty, first_assigned_span);
+        }
+        let result = interpret_stmt(env, &arm.body, in_loop, is_verbose);
+        env.pop_scope();
+        return result;
+    }
+
+    unreachable!("the patterns of a match statement are exhaustive")
+}
+
+/// Matches the value against the pattern, returning the bindings of the pattern if it matches.
+fn match_pat(
+    env: &mut Environment,
+    pat: &Pat,
+    value: &Value,
+    is_verbose: bool,
+) -> Result<Option<Vec<(Ident, Value)>>, Vec<IError>> {
+    match (&pat.kind, &value.kind) {
+        (PatKind::Wild, _) => Ok(Some(vec![])),
+        (PatKind::Ident(ident), _) => {
+            let ident = Ident {
+                name: ident.name.to_string(),
+                span: ident.span,
+            };
+            Ok(Some(vec![(ident, value.clone())]))
+        }
+        (PatKind::Lit(expr), _) => {
+            let lit = interpret_expr(env, expr, false, is_verbose)?;
+            let is_match = match (&lit.kind, &value.kind) {
+                (ValueKind::Int(lit), ValueKind::Int(val)) => lit == val,
+                (ValueKind::Float(lit), ValueKind::Float(val)) => lit == val,
+                (ValueKind::Str(lit), ValueKind::Str(val)) => lit == val,
+                (ValueKind::Char(lit), ValueKind::Char(val)) => lit == val,
+                (ValueKind::Bool(lit), ValueKind::Bool(val)) => lit == val,
+                _ => false,
+            };
+            Ok(if is_match { Some(vec![]) } else { None })
+        }
+        (PatKind::Tuple(pats), ValueKind::Tuple(values)) => {
+            match_pats(env, pats, values, is_verbose)
+        }
+        (PatKind::Tuple(pats), ValueKind::Unit) if pats.is_empty() => Ok(Some(vec![])),
+        (PatKind::Variant(_, variant, pats), ValueKind::Enum(_, value_variant, values)) => {
+            if variant.name.as_str() != value_variant {
+                return Ok(None);
+            }
+            match_pats(env, pats, values, is_verbose)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn match_pats(
+    env: &mut Environment,
+    pats: &Vec<Pat>,
+    values: &Vec<Value>,
+    is_verbose: bool,
+) -> Result<Option<Vec<(Ident, Value)>>, Vec<IError>> {
+    let mut bindings = vec![];
+    for (pat, value) in pats.iter().zip(values) {
+        match match_pat(env, pat, value, is_verbose)? {
+            Some(pat_bindings) => bindings.extend(pat_bindings),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(bindings))
+}
+
 pub fn interpret_stmt_break(span: Span, in_loop: bool, is_verbose: bool) -> IResult {
     if is_verbose {
         println!("\x1b[90m[DEBUG] Interpreting break statement\x1b[0m");
@@ -456,26 +675,39 @@ pub fn interpret_stmt_var_decl(
     }
     let Local {
         is_mut,
-        ident,
+        pat,
         ty,
         kind,
         span: _,
     } = local;
 
+    let ident = match &pat.kind {
+        PatKind::Ident(ident) => ident,
+        PatKind::Wild | PatKind::Tuple(_) | PatKind::Lit(_) | PatKind::Variant(..) => {
+            return interpret_stmt_var_decl_destructure(
+                env, *is_mut, pat, ty, kind, in_loop, is_verbose,
+            )
+        }
+    };
+    let ty = ty
+        .as_ref()
+        .expect("parser requires a type for a non-tuple pattern");
+
     let decl_ty = interpret_ty(env, ty, in_loop, is_verbose)?;
 
     // If the variable is array, it must have a length if it is not declared with an initializer
     let (value, var_ty_kind, first_assigned_span) = match kind {
         LocalKind::Init(expr) => {
-            let value = interpret_expr(env, expr, false, is_verbose)?;
-            let value_ty = value.to_ty_kind();
-            if value_ty != decl_ty.kind {
+            let mut value = interpret_expr(env, expr, false, is_verbose)?;
+            if value.to_ty_kind() != decl_ty.kind {
                 return Err(vec![IError::MismatchedType {
                     expected: decl_ty.to_string(),
-                    found: value_ty.to_string(),
+                    found: value.to_ty_kind().to_string(),
                     span: value.span,
                 }]);
             }
+            value.kind.infer_ty(&decl_ty.kind);
+            let value_ty = value.to_ty_kind();
 
             // Handle array length mismatch
             let decl_ty_span = decl_ty.span.clone();
@@ -490,9 +722,10 @@ pub fn interpret_stmt_var_decl(
                         }]);
                     }
 
+                    // An array declared without a length can change its length.
                     (
                         Some(value),
-                        TyKind::Array(val_ty, val_len),
+                        TyKind::Array(val_ty, var_len),
                         Some(ident.span.clone()),
                     )
                 }
@@ -526,17 +759,96 @@ pub fn interpret_stmt_var_decl(
     Ok(EvalResult::StmtResult(None))
 }
 
+fn interpret_stmt_var_decl_destructure(
+    env: &mut Environment,
+    is_mut: bool,
+    pat: &Pat,
+    ty: &Option<ast::Ty>,
+    kind: &LocalKind,
+    in_loop: bool,
+    is_verbose: bool,
+) -> IResult {
+    let init = match kind {
+        LocalKind::Init(expr) => expr,
+        LocalKind::Decl => unreachable!("parser requires an initializer for a non-ident pattern"),
+    };
+
+    let mut value = interpret_expr(env, init, in_loop, is_verbose)?;
+    if let Some(ty) = ty {
+        let decl_ty = interpret_ty(env, ty, in_loop, is_verbose)?;
+        let value_ty = value.to_ty_kind();
+        if value_ty != decl_ty.kind {
+            return Err(vec![IError::MismatchedType {
+                expected: decl_ty.to_string(),
+                found: value_ty.to_string(),
+                span: value.span,
+            }]);
+        }
+        value.kind.infer_ty(&decl_ty.kind);
+    }
+
+    bind_pat(env, is_mut, pat, value)?;
+    Ok(EvalResult::StmtResult(None))
+}
+
+/// Binds every identifier in the pattern to the corresponding part of the value.
+fn bind_pat(
+    env: &mut Environment,
+    is_mut: bool,
+    pat: &Pat,
+    value: Value,
+) -> Result<(), Vec<IError>> {
+    match &pat.kind {
+        PatKind::Wild => Ok(()),
+        PatKind::Ident(ident) => {
+            let ty = Ty {
+                kind: value.to_ty_kind(),
+                span: pat.span,
+            };
+            let ident = Ident {
+                name: ident.name.to_string(),
+                span: ident.span,
+            };
+            let first_assigned_span = Some(ident.span);
+            env.insert_variable(ident, Some(value), is_mut, ty, first_assigned_span);
+            Ok(())
+        }
+        PatKind::Tuple(pats) => {
+            let value_ty = value.to_ty_kind();
+            let elements = match value.kind {
+                ValueKind::Tuple(elements) if elements.len() == pats.len() => elements,
+                ValueKind::Unit if pats.is_empty() => vec![],
+                _ => {
+                    return Err(vec![IError::MismatchedType {
+                        expected: format!("a tuple with {} elements", pats.len()),
+                        found: value_ty.to_string(),
+                        span: value.span,
+                    }])
+                }
+            };
+
+            for (pat, element) in pats.iter().zip(elements) {
+                bind_pat(env, is_mut, pat, element)?;
+            }
+            Ok(())
+        }
+        PatKind::Lit(_) | PatKind::Variant(..) => {
+            unreachable!("parser only allows irrefutable patterns in a variable declaration")
+        }
+    }
+}
+
 pub fn interpret_stmt_import(
     env: &mut Environment,
-    ident: &ast::Ident,
+    import: &ast::Import,
     is_verbose: bool,
 ) -> IResult {
     if is_verbose {
         println!(
-            "\x1b[90m[DEBUG] Interpreting import statement with identifier: {:?}\x1b[0m",
-            ident
+            "\x1b[90m[DEBUG] Interpreting import statement with path: {:?}\x1b[0m",
+            import.segments()
         );
     }
-    env.import_library(ident.name.as_str(), ident.span, is_verbose)?;
+    env.import_library(import, is_verbose)?;
     Ok(EvalResult::StmtResult(None))
 }
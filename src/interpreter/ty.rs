@@ -1,9 +1,13 @@
 use core::fmt;
 use std::fmt::{Display, Formatter};
 
-use crate::{ast, span_encoding::Span};
+use crate::{
+    ast,
+    kw::{self, Keyword},
+    span_encoding::Span,
+};
 
-use super::{environment::Environment, interpret_expr, IError, ValueKind};
+use super::{environment::Environment, interpret_expr, IError, TypeDef, ValueKind};
 
 pub fn interpret_ty(
     env: &mut Environment,
@@ -12,8 +16,12 @@ pub fn interpret_ty(
     is_verbose: bool,
 ) -> Result<Ty, Vec<IError>> {
     let kind = match &ty.kind {
-        ast::TyKind::Named(ident) => interpret_ty_ident(ident)?,
+        ast::TyKind::Named(ident) => interpret_ty_ident(env, ident)?,
         ast::TyKind::Array(ty, len) => interpret_ty_array(env, ty, len, in_loop, is_verbose)?,
+        ast::TyKind::Tuple(tys) => interpret_ty_tuple(env, tys, in_loop, is_verbose)?,
+        ast::TyKind::Fun(inputs, output) => {
+            interpret_ty_fun(env, inputs, output, in_loop, is_verbose)?
+        }
     };
 
     Ok(Ty {
@@ -22,6 +30,44 @@ pub fn interpret_ty(
     })
 }
 
+fn interpret_ty_tuple(
+    env: &mut Environment,
+    tys: &Vec<ast::Ty>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<TyKind, Vec<IError>> {
+    if tys.is_empty() {
+        return Ok(TyKind::Unit);
+    }
+
+    let mut kinds = Vec::with_capacity(tys.len());
+    for ty in tys {
+        kinds.push(interpret_ty(env, ty, in_loop, is_verbose)?.kind);
+    }
+
+    Ok(TyKind::Tuple(kinds))
+}
+
+fn interpret_ty_fun(
+    env: &mut Environment,
+    inputs: &Vec<(ast::Ty, bool)>,
+    output: &Option<Box<ast::Ty>>,
+    in_loop: bool,
+    is_verbose: bool,
+) -> Result<TyKind, Vec<IError>> {
+    let mut kinds = Vec::with_capacity(inputs.len());
+    for (ty, is_mut) in inputs {
+        kinds.push((interpret_ty(env, ty, in_loop, is_verbose)?.kind, *is_mut));
+    }
+
+    let output = match output {
+        Some(ty) => interpret_ty(env, ty, in_loop, is_verbose)?.kind,
+        None => TyKind::Unit,
+    };
+
+    Ok(TyKind::Function(kinds, Box::new(output)))
+}
+
 fn interpret_ty_array(
     env: &mut Environment,
     ty: &ast::Ty,
@@ -57,17 +103,22 @@ fn interpret_ty_array(
     }
 }
 
-fn interpret_ty_ident(ident: &ast::Ident) -> Result<TyKind, Vec<IError>> {
+fn interpret_ty_ident(env: &Environment, ident: &ast::Ident) -> Result<TyKind, Vec<IError>> {
     match ident.name.as_str() {
         "int" => Ok(TyKind::Int),
         "float" => Ok(TyKind::Float),
         "str" => Ok(TyKind::Str),
         "bool" => Ok(TyKind::Bool),
         "char" => Ok(TyKind::Char),
-        _ => Err(vec![IError::CannotFindTypeInScope {
-            type_name: ident.name.as_str().to_string(),
-            span: ident.span,
-        }]),
+        "range" => Ok(TyKind::Range),
+        name => match env.lookup_type(name) {
+            Some((_, TypeDef::Struct(_))) => Ok(TyKind::Struct(name.to_string())),
+            Some((_, TypeDef::Enum(_))) => Ok(TyKind::Enum(name.to_string())),
+            None => Err(vec![IError::CannotFindTypeInScope {
+                type_name: ident.name.as_str().to_string(),
+                span: ident.span,
+            }]),
+        },
     }
 }
 
@@ -89,14 +140,27 @@ pub enum TyKind {
     Float,
     Str,
     Bool,
-    Function,
+    /// A function with its parameter types, each with whether it is `mut`, and its return type.
+    Function(Vec<(TyKind, bool)>, Box<TyKind>),
     Unit,
     Char,
     Array(Box<TyKind>, i64),
+    Tuple(Vec<TyKind>),
+    Struct(String),
+    Enum(String),
+    /// A range of integers, e.g. `0..10`.
+    Range,
+    /// The element type of an empty array literal, `[]`, which is not known until the array is
+    /// given a type, e.g. by a declaration. It is compatible with every type.
+    Infer,
 }
 
 impl PartialEq for TyKind {
     fn eq(&self, other: &Self) -> bool {
+        if matches!(self, TyKind::Infer) || matches!(other, TyKind::Infer) {
+            return true;
+        }
+
         match self {
             TyKind::Int => match other {
                 TyKind::Int => true,
@@ -114,8 +178,10 @@ impl PartialEq for TyKind {
                 TyKind::Bool => true,
                 _ => false,
             },
-            TyKind::Function => match other {
-                TyKind::Function => true,
+            TyKind::Function(inputs, output) => match other {
+                TyKind::Function(other_inputs, other_output) => {
+                    inputs == other_inputs && output == other_output
+                }
                 _ => false,
             },
             TyKind::Unit => match other {
@@ -133,6 +199,20 @@ impl PartialEq for TyKind {
                 }
                 _ => false,
             },
+            TyKind::Tuple(tys) => match other {
+                TyKind::Tuple(other_tys) => tys == other_tys,
+                _ => false,
+            },
+            TyKind::Struct(name) => match other {
+                TyKind::Struct(other_name) => name == other_name,
+                _ => false,
+            },
+            TyKind::Enum(name) => match other {
+                TyKind::Enum(other_name) => name == other_name,
+                _ => false,
+            },
+            TyKind::Range => matches!(other, TyKind::Range),
+            TyKind::Infer => true,
         }
     }
 }
@@ -144,13 +224,68 @@ impl Display for TyKind {
 }
 
 impl TyKind {
+    /// Replaces the parts of the type that are not known yet with the corresponding parts of
+    /// `ty`, e.g. `[_; 0]` becomes `[int; 0]` with `[int]`. The length of an array is dropped
+    /// when `ty` does not give one, e.g. `[int; 2]` becomes `[int]` with `[int]`, so that the
+    /// length of an element of an array declared with `[[int]]` can change.
+    pub fn infer_from(&mut self, ty: &TyKind) {
+        if matches!(self, TyKind::Infer) {
+            *self = ty.clone();
+            return;
+        }
+
+        match (self, ty) {
+            (TyKind::Array(el_ty, len), TyKind::Array(ty, ty_len)) => {
+                el_ty.infer_from(ty);
+                if *ty_len == -1 {
+                    *len = -1;
+                }
+            }
+            (TyKind::Tuple(tys), TyKind::Tuple(other_tys)) => {
+                for (ty, other_ty) in tys.iter_mut().zip(other_tys) {
+                    ty.infer_from(other_ty);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether an argument of this type fixes the length of an array whose length can
+    /// change through a `mut` parameter of type `param`, e.g. `[int; 2]` for `[int]`. Such an
+    /// argument would get another length through the parameter, which refers to it.
+    pub fn fixes_growable_len(&self, param: &TyKind) -> bool {
+        match (self, param) {
+            (TyKind::Array(el_ty, len), TyKind::Array(param_el_ty, param_len)) => {
+                (*len != -1 && *param_len == -1) || el_ty.fixes_growable_len(param_el_ty)
+            }
+            (TyKind::Tuple(tys), TyKind::Tuple(param_tys)) => tys
+                .iter()
+                .zip(param_tys)
+                .any(|(ty, param_ty)| ty.fixes_growable_len(param_ty)),
+            _ => false,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             TyKind::Int => "int".to_string(),
             TyKind::Float => "float".to_string(),
             TyKind::Str => "str".to_string(),
             TyKind::Bool => "bool".to_string(),
-            TyKind::Function => "function".to_string(),
+            TyKind::Function(inputs, output) => {
+                let inputs: Vec<String> = inputs
+                    .iter()
+                    .map(|(ty, is_mut)| match is_mut {
+                        true => format!("{} {}", kw::to_string(Keyword::Mut), ty),
+                        false => ty.to_string(),
+                    })
+                    .collect();
+                let fun = kw::to_string(Keyword::Fun);
+                match output.as_ref() {
+                    TyKind::Unit => format!("{}({})", fun, inputs.join(", ")),
+                    output => format!("{}({}) -> {}", fun, inputs.join(", "), output),
+                }
+            }
             TyKind::Unit => "()".to_string(),
             TyKind::Char => "char".to_string(),
             TyKind::Array(ty, len) => {
@@ -160,6 +295,17 @@ impl TyKind {
                     format!("[{}; {}]", ty.to_string(), len)
                 }
             }
+            TyKind::Tuple(tys) => {
+                if tys.len() == 1 {
+                    format!("({},)", tys[0].to_string())
+                } else {
+                    let tys: Vec<String> = tys.iter().map(|ty| ty.to_string()).collect();
+                    format!("({})", tys.join(", "))
+                }
+            }
+            TyKind::Struct(name) | TyKind::Enum(name) => name.to_string(),
+            TyKind::Range => "range".to_string(),
+            TyKind::Infer => "_".to_string(),
         }
     }
 }
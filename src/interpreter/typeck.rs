@@ -0,0 +1,1738 @@
+//! Static type checking.
+//!
+//! The program is checked as a whole before it runs, so that a type error is reported even
+//! if the line it is on is never executed. The checker follows the scoping rules of the
+//! interpreter: the body of a named function cannot see the variables around its
+//! declaration, and it is only checked at the end of the block the function is declared in,
+//! since it may use every function and type declared there.
+//!
+//! Some types cannot be known before running the program, e.g. the type of the value
+//! returned by a function of a library written in Pandora, which the checker does not load.
+//! Such a type is left unknown (`None`), and an unknown type is compatible with every other
+//! type, so these cases are left to the interpreter. The functions of the libraries written
+//! in Rust are checked against their signatures.
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+use crate::{
+    ast::{
+        self, Arm, Ast, BinOp, BinOpKind, Closure, EnumDef, Expr, ExprField, ExprKind, Fun,
+        FunParam, LitKind, Local, LocalKind, Pat, PatKind, Stmt, StmtKind, StructDef, UnOp,
+    },
+    kw::Keyword,
+    span_encoding::Span,
+    visitor::{self, Visitor},
+};
+
+use super::{
+    array::ArrayMethod,
+    environment::Environment,
+    exhaustiveness,
+    ident::Ident,
+    interpret_expr_literal, is_method_call, is_place,
+    libs::{Library, NativeFunction, ParamTy},
+    no_method_on_type_error, place_root,
+    range::RangeMethod,
+    resolver::ModuleResolver,
+    IError, Ty, TyKind, TypeDef, ValueKind,
+};
+
+/// Checks the types of the whole program, returning every type error found. The libraries it
+/// adds are looked up with `resolver`, like the interpreter does.
+pub fn check(ast: &Ast, resolver: ModuleResolver) -> Vec<IError> {
+    let mut checker = TypeChecker::new();
+    checker.env.resolver = resolver;
+    checker.check_stmts(&ast.stmts);
+    checker.errors
+}
+
+/// The declarations made by the inputs of the REPL so far, against which the next input is
+/// checked.
+pub struct Context {
+    env: Environment,
+    scope: Scope,
+    /// The names of the declared types, whose definitions are held by the environment.
+    types: HashSet<String>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context {
+            env: Environment::new(),
+            scope: Scope::new(false),
+            types: HashSet::new(),
+        }
+    }
+
+    /// Looks up the libraries added by the next inputs like the interpreter running them does.
+    pub fn use_libraries_of(&mut self, env: &Environment) {
+        self.env.resolver = env.resolver.clone();
+        self.env.native_libs = env.native_libs.clone();
+    }
+}
+
+/// Checks the types of an input of the REPL, returning every type error found. The
+/// declarations of the input are kept in the context only if there is none.
+pub fn check_input(ast: &Ast, ctx: &mut Context) -> Vec<IError> {
+    let mut checker = TypeChecker::resume(ctx);
+    checker.check_stmts(&ast.stmts);
+    checker.suspend(ctx)
+}
+
+/// Checks the type of an expression typed in the REPL, without declaring anything.
+pub fn check_input_expr(expr: &Expr, ctx: &mut Context) -> Result<Option<TyKind>, Vec<IError>> {
+    let mut checker = TypeChecker::resume(ctx);
+    // Closures may declare variables in the current scope while being checked.
+    checker.push_scope(false);
+    let ty = checker.check_expr(expr);
+    checker.pop_scope();
+    let errors = checker.suspend(ctx);
+    if errors.is_empty() {
+        Ok(ty)
+    } else {
+        Err(errors)
+    }
+}
+
+#[derive(Clone)]
+struct Scope {
+    /// The variables, with their type, the span of their name and whether they are mutable.
+    variables: Vec<(String, Option<TyKind>, Span, bool)>,
+    /// The named functions, with the span of their name, their type and their parameters.
+    functions: HashMap<String, (Span, Option<TyKind>, Vec<FunParam>)>,
+    /// The libraries added by name, and the functions imported from libraries by name. A
+    /// library is only known if it is written in Rust.
+    libraries: HashMap<String, Option<Rc<dyn Library>>>,
+    imported_functions: HashMap<String, Option<Rc<dyn Library>>>,
+    /// Whether this is the scope of the parameters of a named function, which hides the
+    /// variables of the scopes around it.
+    is_fun_boundary: bool,
+}
+
+impl Scope {
+    fn new(is_fun_boundary: bool) -> Self {
+        Scope {
+            variables: vec![],
+            functions: HashMap::new(),
+            libraries: HashMap::new(),
+            imported_functions: HashMap::new(),
+            is_fun_boundary,
+        }
+    }
+}
+
+/// The function whose body is being checked.
+struct FunCtx {
+    output: Option<TyKind>,
+    /// The span of the return type, if there is one.
+    output_span: Option<Span>,
+    sig_span: Span,
+}
+
+/// A named function, with the types of its parameters and its return type.
+type DeferredFun<'ast> = (&'ast Fun, Vec<Option<TyKind>>, Option<TyKind>);
+
+struct TypeChecker<'ast> {
+    /// Holds the user-declared types, so that they are resolved the same way as in the
+    /// interpreter.
+    env: Environment,
+    scopes: Vec<Scope>,
+    /// For each block being checked, the named functions declared in it.
+    deferred: Vec<Vec<DeferredFun<'ast>>>,
+    funs: Vec<FunCtx>,
+    /// The type of the last visited expression.
+    ty: Option<TyKind>,
+    errors: Vec<IError>,
+}
+
+impl<'ast> TypeChecker<'ast> {
+    fn new() -> Self {
+        TypeChecker {
+            env: Environment::new(),
+            scopes: vec![Scope::new(false)],
+            deferred: vec![],
+            funs: vec![],
+            ty: None,
+            errors: vec![],
+        }
+    }
+
+    /// Creates a checker that continues from the declarations of the REPL context.
+    fn resume(ctx: &mut Context) -> Self {
+        let mut checker = TypeChecker::new();
+        checker.env = mem::replace(&mut ctx.env, Environment::new());
+        checker.scopes = vec![ctx.scope.clone()];
+        checker
+    }
+
+    /// Gives the environment back to the REPL context, keeping the declarations made only if
+    /// there was no error. Returns the errors found.
+    fn suspend(mut self, ctx: &mut Context) -> Vec<IError> {
+        let scope = self.scopes.pop().unwrap();
+        let types = &mut self.env.scopes.first_mut().unwrap().types;
+        if self.errors.is_empty() {
+            ctx.scope = scope;
+            ctx.types = types.keys().cloned().collect();
+        } else {
+            // A type cannot be declared twice in the same scope, so the types of the erroneous
+            // input are the ones unknown to the context.
+            types.retain(|name, _| ctx.types.contains(name));
+        }
+        ctx.env = self.env;
+        self.errors
+    }
+
+    fn push_scope(&mut self, is_fun_boundary: bool) {
+        self.scopes.push(Scope::new(is_fun_boundary));
+        self.env.push_scope();
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+        self.env.pop_scope();
+    }
+
+    /// Lookup the nearest variable with the given name, returning its type and the span of
+    /// its declaration.
+    fn lookup_variable(&self, name: &str) -> Option<(Option<TyKind>, Span)> {
+        let (_, ty, span, _) = self.find_variable(name)?;
+        Some((ty.clone(), *span))
+    }
+
+    fn find_variable(&self, name: &str) -> Option<&(String, Option<TyKind>, Span, bool)> {
+        for scope in self.scopes.iter().rev() {
+            let var = scope
+                .variables
+                .iter()
+                .rev()
+                .find(|(var_name, _, _, _)| var_name == name);
+            if var.is_some() {
+                return var;
+            }
+
+            if scope.is_fun_boundary {
+                break;
+            }
+        }
+        None
+    }
+
+    fn lookup_function(&self, name: &str) -> Option<(Span, Option<TyKind>, Vec<FunParam>)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.functions.get(name).cloned())
+    }
+
+    /// Lookup the nearest library added with the given name. It is `Some(None)` if the library
+    /// is not known to the checker.
+    fn lookup_library(&self, name: &str) -> Option<Option<Rc<dyn Library>>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.libraries.get(name).cloned())
+    }
+
+    /// Lookup the nearest function imported by name, returning the library it comes from like
+    /// [`TypeChecker::lookup_library`].
+    fn lookup_imported_function(&self, name: &str) -> Option<Option<Rc<dyn Library>>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.imported_functions.get(name).cloned())
+    }
+
+    /// Returns the library an import adds if it is written in Rust, i.e. if no library file is
+    /// found for it and it is embedded or given by the program running the interpreter.
+    fn native_library(&mut self, import: &ast::Import) -> Option<Rc<dyn Library>> {
+        let segments = import.segments();
+        if segments.len() > 1 || self.env.resolver.resolve(&segments).is_some() {
+            return None;
+        }
+        self.env
+            .load_embedded_library(segments[0], import.span, false)
+            .ok()
+    }
+
+    fn insert_variable(&mut self, ident: &ast::Ident, ty: Option<TyKind>, is_mut: bool) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope
+            .variables
+            .push((ident.name.to_string(), ty, ident.span, is_mut));
+    }
+
+    /// Checks the statements of a block, then the bodies of the functions declared in it.
+    fn check_stmts(&mut self, stmts: &'ast [Box<Stmt>]) {
+        self.deferred.push(vec![]);
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+
+        for (fun, inputs, output) in self.deferred.pop().unwrap() {
+            self.push_scope(true);
+            for (param, ty) in fun.sig.inputs.iter().zip(inputs) {
+                self.insert_variable(&param.ident, ty, param.is_mut);
+            }
+            self.funs.push(FunCtx {
+                output,
+                output_span: fun.sig.output.as_ref().map(|ty| ty.span),
+                sig_span: fun.sig.span,
+            });
+            self.visit_stmt(&fun.body);
+            let ctx = self.funs.pop().unwrap();
+            self.check_body_returns(&ctx, fun.sig.name.span, &fun.body);
+            self.pop_scope();
+        }
+    }
+
+    fn check_expr(&mut self, expr: &'ast Expr) -> Option<TyKind> {
+        self.visit_expr(expr);
+        self.ty.take()
+    }
+
+    fn check_cond(&mut self, cond: &'ast Expr) {
+        let ty = self.check_expr(cond);
+        if let Some(ty) = ty {
+            if ty != TyKind::Bool {
+                self.errors.push(IError::MismatchedType {
+                    expected: TyKind::Bool.to_string(),
+                    found: ty.to_string(),
+                    span: cond.span,
+                });
+            }
+        }
+    }
+
+    fn check_return(&mut self, expr: Option<&'ast Expr>, span: Span) {
+        let (found, found_span) = match expr {
+            Some(expr) => (self.check_expr(expr), expr.span),
+            None => (Some(TyKind::Unit), Span::after(span)),
+        };
+
+        let FunCtx {
+            output,
+            output_span,
+            sig_span,
+        } = match self.funs.last() {
+            Some(ctx) => ctx,
+            None => return,
+        };
+        let (expected, found) = match (output, found) {
+            (Some(expected), Some(found)) if *expected != found => (expected, found),
+            _ => return,
+        };
+
+        let error = match output_span {
+            Some(output_span) => IError::MismatchedFunctionReturnType {
+                expected: expected.to_string(),
+                found: found.to_string(),
+                expected_span: *output_span,
+                found_span,
+            },
+            None => IError::MissingFunctionReturnType {
+                found: found.to_string(),
+                ret_help_span: Span::after(*sig_span),
+                found_span,
+            },
+        };
+        self.errors.push(error);
+    }
+
+    /// Checks that the body of a function whose return type is not `()` cannot end without
+    /// returning a value. `decl_span` is the name of the function, or the signature of a closure.
+    fn check_body_returns(&mut self, ctx: &FunCtx, decl_span: Span, body: &Stmt) {
+        let (Some(output), Some(output_span)) = (&ctx.output, ctx.output_span) else {
+            return;
+        };
+        if *output != TyKind::Unit && !always_returns(body) {
+            self.errors.push(IError::MissingReturnStatement {
+                ret_kw: Keyword::Yeet.as_ref().to_string(),
+                expected: output.to_string(),
+                expected_span: output_span,
+                func_decl_span: decl_span,
+            });
+        }
+    }
+
+    /// Resolves a type written in the source code.
+    fn resolve_ty(&mut self, ty: &'ast ast::Ty) -> Option<TyKind> {
+        match &ty.kind {
+            ast::TyKind::Named(ident) => match ident.name.as_str() {
+                "int" => Some(TyKind::Int),
+                "float" => Some(TyKind::Float),
+                "str" => Some(TyKind::Str),
+                "bool" => Some(TyKind::Bool),
+                "char" => Some(TyKind::Char),
+                "range" => Some(TyKind::Range),
+                name => match self.env.lookup_type(name) {
+                    Some((_, TypeDef::Struct(_))) => Some(TyKind::Struct(name.to_string())),
+                    Some((_, TypeDef::Enum(_))) => Some(TyKind::Enum(name.to_string())),
+                    None => {
+                        self.errors.push(IError::CannotFindTypeInScope {
+                            type_name: name.to_string(),
+                            span: ident.span,
+                        });
+                        None
+                    }
+                },
+            },
+            ast::TyKind::Array(elem, len) => {
+                let elem = self.resolve_ty(elem);
+                let len = match len {
+                    Some(len) => {
+                        let len_ty = self.check_expr(len);
+                        self.expect_int(len_ty, len.span);
+                        match self.int_lit(len) {
+                            Some(size) if size < 0 => {
+                                self.errors.push(IError::NegArraySize {
+                                    size: size.to_string(),
+                                    span: len.span,
+                                });
+                                -1
+                            }
+                            Some(size) => size,
+                            None => -1,
+                        }
+                    }
+                    None => -1,
+                };
+                Some(TyKind::Array(Box::new(elem?), len))
+            }
+            ast::TyKind::Tuple(tys) => {
+                if tys.is_empty() {
+                    return Some(TyKind::Unit);
+                }
+                let tys: Vec<Option<TyKind>> = tys.iter().map(|ty| self.resolve_ty(ty)).collect();
+                Some(TyKind::Tuple(tys.into_iter().collect::<Option<_>>()?))
+            }
+            ast::TyKind::Fun(inputs, output) => {
+                let inputs: Vec<(Option<TyKind>, bool)> = inputs
+                    .iter()
+                    .map(|(ty, is_mut)| (self.resolve_ty(ty), *is_mut))
+                    .collect();
+                let output = match output {
+                    Some(output) => self.resolve_ty(output),
+                    None => Some(TyKind::Unit),
+                };
+                fun_ty(inputs, output)
+            }
+        }
+    }
+
+    fn resolve_fun_sig(
+        &mut self,
+        inputs: &'ast [FunParam],
+        output: &'ast Option<ast::Ty>,
+    ) -> (Vec<Option<TyKind>>, Option<TyKind>) {
+        let inputs = inputs
+            .iter()
+            .map(|param| self.resolve_ty(&param.ty))
+            .collect();
+        let output = match output {
+            Some(ty) => self.resolve_ty(ty),
+            None => Some(TyKind::Unit),
+        };
+        (inputs, output)
+    }
+
+    /// Returns the value of an integer literal, e.g. the length in `[int; 3]` or `[int; -1]`.
+    fn int_lit(&mut self, expr: &'ast Expr) -> Option<i64> {
+        match &expr.kind {
+            ExprKind::Unary(UnOp::Ne, lit) if matches!(lit.kind, ExprKind::Literal(_)) => {
+                self.int_lit(lit).map(|val| -val)
+            }
+            ExprKind::Literal(lit) if matches!(lit.kind, LitKind::Int) => {
+                match interpret_expr_literal(lit, expr.span) {
+                    Ok(ValueKind::Int(val)) => Some(val),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_int(&mut self, ty: Option<TyKind>, span: Span) {
+        if let Some(ty) = ty {
+            if ty != TyKind::Int {
+                self.errors.push(IError::MismatchedType {
+                    expected: TyKind::Int.to_string(),
+                    found: ty.to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    /// Binds every identifier in the pattern to the corresponding part of the type. The shape
+    /// of a tuple pattern is only checked against the type when the span of the value is given.
+    fn bind_pat(
+        &mut self,
+        pat: &'ast Pat,
+        ty: Option<TyKind>,
+        value_span: Option<Span>,
+        is_mut: bool,
+    ) {
+        match &pat.kind {
+            PatKind::Wild | PatKind::Lit(_) => {}
+            PatKind::Ident(ident) => self.insert_variable(ident, ty, is_mut),
+            PatKind::Tuple(pats) => {
+                let tys = match ty {
+                    Some(TyKind::Tuple(tys)) if tys.len() == pats.len() => {
+                        tys.into_iter().map(Some).collect()
+                    }
+                    Some(TyKind::Unit) if pats.is_empty() => vec![],
+                    Some(ty) => {
+                        if let Some(span) = value_span {
+                            self.errors.push(IError::MismatchedType {
+                                expected: format!("a tuple with {} elements", pats.len()),
+                                found: ty.to_string(),
+                                span,
+                            });
+                        }
+                        vec![None; pats.len()]
+                    }
+                    None => vec![None; pats.len()],
+                };
+
+                for (pat, ty) in pats.iter().zip(tys) {
+                    self.bind_pat(pat, ty, value_span, is_mut);
+                }
+            }
+            PatKind::Variant(enum_name, variant, pats) => {
+                let tys = match self.env.lookup_enum(enum_name.name.as_str()) {
+                    Some((_, def)) => match def.variant(variant.name.as_str()) {
+                        Some((_, tys)) if tys.len() == pats.len() => {
+                            tys.iter().map(|ty| Some(ty.kind.clone())).collect()
+                        }
+                        _ => vec![None; pats.len()],
+                    },
+                    None => vec![None; pats.len()],
+                };
+
+                for (pat, ty) in pats.iter().zip(tys) {
+                    self.bind_pat(pat, ty, value_span, is_mut);
+                }
+            }
+        }
+    }
+
+    fn check_expr_binary(
+        &mut self,
+        op: &BinOp,
+        lhs: &'ast Expr,
+        rhs: &'ast Expr,
+    ) -> Option<TyKind> {
+        // Like in the interpreter, the operands of a chained comparison are not checked.
+        if is_comparison(&op.node) {
+            if let ExprKind::Binary(prev_op, ..) = &lhs.kind {
+                if is_comparison(&prev_op.node) {
+                    self.errors
+                        .push(IError::ComparisonOperatorsCannotBeChained {
+                            chain_op_span: vec![prev_op.span, op.span],
+                        });
+                    return None;
+                }
+            }
+        }
+
+        let lhs_ty = self.check_expr(lhs);
+        let rhs_ty = self.check_expr(rhs);
+        self.check_binary_op(op, lhs_ty, rhs_ty)
+    }
+
+    /// Returns the type of the result of a binary operation on operands of the given types.
+    fn check_binary_op(
+        &mut self,
+        op: &BinOp,
+        lhs_ty: Option<TyKind>,
+        rhs_ty: Option<TyKind>,
+    ) -> Option<TyKind> {
+        let (lhs_ty, rhs_ty) = match (lhs_ty, rhs_ty) {
+            (Some(lhs_ty), Some(rhs_ty)) => (lhs_ty, rhs_ty),
+            // Both operands have the same type if the operation is valid.
+            (Some(ty), None) | (None, Some(ty)) => return binary_op_ty(&op.node, &ty),
+            (None, None) => {
+                return match op.node {
+                    BinOpKind::Add
+                    | BinOpKind::Sub
+                    | BinOpKind::Mul
+                    | BinOpKind::Div
+                    | BinOpKind::Mod => None,
+                    BinOpKind::BitAnd
+                    | BinOpKind::BitOr
+                    | BinOpKind::BitXor
+                    | BinOpKind::Shl
+                    | BinOpKind::Shr => Some(TyKind::Int),
+                    _ => Some(TyKind::Bool),
+                }
+            }
+        };
+
+        if lhs_ty == rhs_ty {
+            if let Some(ty) = binary_op_ty(&op.node, &lhs_ty) {
+                return Some(ty);
+            }
+        }
+        // Arrays of different lengths can be compared, and are not equal.
+        if let (TyKind::Array(lhs_el_ty, _), TyKind::Array(rhs_el_ty, _)) = (&lhs_ty, &rhs_ty) {
+            if matches!(op.node, BinOpKind::Eq | BinOpKind::Ne)
+                && lhs_el_ty == rhs_el_ty
+                && is_equatable(lhs_el_ty)
+            {
+                return Some(TyKind::Bool);
+            }
+        }
+
+        let lhs_ty = lhs_ty.to_string();
+        let rhs_ty = rhs_ty.to_string();
+        let op_span = op.span;
+        let error = match op.node {
+            BinOpKind::Add => IError::CannotAdd {
+                lhs_ty,
+                rhs_ty,
+                op_span,
+            },
+            BinOpKind::Sub => IError::CannotSubtract {
+                lhs_ty,
+                rhs_ty,
+                op_span,
+            },
+            BinOpKind::Mul => IError::CannotMultiply {
+                lhs_ty,
+                rhs_ty,
+                op_span,
+            },
+            BinOpKind::Div => IError::CannotDivide {
+                lhs_ty,
+                rhs_ty,
+                op_span,
+            },
+            BinOpKind::Mod => IError::CannotModulo {
+                lhs_ty,
+                rhs_ty,
+                op_span,
+            },
+            ref node if is_comparison(node) => IError::CannotCompare {
+                lhs_ty,
+                rhs_ty,
+                op: op.to_string(),
+                op_span,
+            },
+            _ => IError::NoImplForOp {
+                lhs_ty,
+                rhs_ty,
+                op: op.to_string(),
+                op_span,
+            },
+        };
+        self.errors.push(error);
+        None
+    }
+
+    fn check_expr_unary(&mut self, op: &UnOp, expr: &'ast Expr) -> Option<TyKind> {
+        let ty = self.check_expr(expr)?;
+        match (op, &ty) {
+            (UnOp::Ne, TyKind::Int | TyKind::Float) | (UnOp::Not, TyKind::Bool) => Some(ty),
+            _ => {
+                self.errors.push(IError::CannotApplyUnaryOp {
+                    op: op.to_string(),
+                    ty: ty.to_string(),
+                    span: expr.span,
+                });
+                None
+            }
+        }
+    }
+
+    fn check_expr_cast(&mut self, expr: &'ast Expr, ty: &'ast ast::Ty) -> Option<TyKind> {
+        let from = self.check_expr(expr);
+        let to = self.resolve_ty(ty)?;
+        if let Some(from) = from {
+            let can_cast = match from {
+                TyKind::Int | TyKind::Float => {
+                    matches!(to, TyKind::Int | TyKind::Float | TyKind::Str)
+                }
+                TyKind::Str => {
+                    matches!(to, TyKind::Str | TyKind::Int | TyKind::Float | TyKind::Bool)
+                }
+                TyKind::Bool => matches!(to, TyKind::Bool | TyKind::Int | TyKind::Str),
+                TyKind::Char => matches!(to, TyKind::Char | TyKind::Int | TyKind::Str),
+                _ => false,
+            };
+            if !can_cast {
+                let from = match from {
+                    TyKind::Array(..) => "array".to_string(),
+                    from => from.to_string(),
+                };
+                self.errors.push(IError::CannotCast {
+                    from,
+                    to: to.to_string(),
+                    span: expr.span,
+                });
+            }
+        }
+        Some(to)
+    }
+
+    /// Returns the type of a variable, or of a named function used as a value. The variables
+    /// around a named function are hidden from its body, but the functions are not.
+    fn check_expr_ident(&mut self, ident: &'ast ast::Ident) -> Option<TyKind> {
+        let name = ident.name.as_str();
+        if let Some((ty, _)) = self.lookup_variable(name) {
+            return ty;
+        }
+
+        match self.lookup_function(name) {
+            Some((_, ty, _)) => ty,
+            None => {
+                self.errors.push(cannot_find_variable_error(ident));
+                None
+            }
+        }
+    }
+
+    /// Returns the type of the place being assigned to, if it is known.
+    fn check_place(&mut self, place: &'ast Expr) -> Option<TyKind> {
+        if !is_place(place) {
+            return None;
+        }
+        match &place.kind {
+            ExprKind::Identifier(ident) => match self.lookup_variable(ident.name.as_str()) {
+                Some((ty, _)) => ty,
+                None => {
+                    self.errors.push(cannot_find_variable_error(ident));
+                    None
+                }
+            },
+            _ => self.check_expr(place),
+        }
+    }
+
+    fn check_assign(&mut self, place_ty: Option<TyKind>, value_ty: Option<TyKind>, span: Span) {
+        if let (Some(expected), Some(found)) = (place_ty, value_ty) {
+            if expected != found {
+                self.errors.push(IError::MismatchedType {
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    fn check_expr_call(&mut self, callee: &'ast Expr, args: &'ast [Box<Expr>]) -> Option<TyKind> {
+        // Only the parameters of a named function can be `mut`.
+        let mut fun_params = vec![];
+        let function = match &callee.kind {
+            // A variable holding a function shadows a named function with the same name, which
+            // shadows a function imported by name. Any other name is looked up in the standard
+            // library.
+            ExprKind::Identifier(ident) => match self.lookup_variable(ident.name.as_str()) {
+                Some((Some(ty @ TyKind::Function(..)), span)) => Some((span, ty)),
+                Some((None, _)) => None,
+                _ => match self.lookup_function(ident.name.as_str()) {
+                    Some((span, Some(ty), params)) => {
+                        fun_params = params;
+                        Some((span, ty))
+                    }
+                    Some((_, None, _)) => None,
+                    None => {
+                        let name = ident.name.as_str();
+                        let sig = match self.lookup_imported_function(name) {
+                            Some(lib) => lib.and_then(|lib| native_sig(lib.as_ref(), name)),
+                            None => {
+                                let sig = self
+                                    .env
+                                    .lookup_default_library("std")
+                                    .and_then(|lib| native_sig(lib.as_ref(), name));
+                                if sig.is_none() {
+                                    self.errors.push(IError::FunctionNotInScope {
+                                        function: name.to_string(),
+                                        span: ident.span,
+                                    });
+                                }
+                                sig
+                            }
+                        };
+                        return self.check_native_call(sig, args, callee.span);
+                    }
+                },
+            },
+            _ => match self.check_expr(callee) {
+                Some(ty @ TyKind::Function(..)) => Some((callee.span, ty)),
+                Some(_) => {
+                    self.errors
+                        .push(IError::InvalidFunctionCall { span: callee.span });
+                    None
+                }
+                None => None,
+            },
+        };
+
+        let arg_tys: Vec<Option<TyKind>> = args.iter().map(|arg| self.check_expr(arg)).collect();
+        let (func_decl_span, params, output) = match function {
+            Some((span, TyKind::Function(params, output))) => (span, params, output),
+            _ => return None,
+        };
+
+        self.check_mut_args(&fun_params, func_decl_span, &params, args, &arg_tys);
+        let params = params
+            .into_iter()
+            .map(|(ty, _)| ParamTy::from(ty))
+            .collect();
+        self.check_args(Some(func_decl_span), params, args, &arg_tys, callee.span);
+        Some(*output)
+    }
+
+    /// Checks a call to a function of a library against its signature, if the function is
+    /// written in Rust. Otherwise the type of the value it returns is unknown.
+    fn check_native_call(
+        &mut self,
+        sig: Option<(Vec<ParamTy>, TyKind)>,
+        args: &'ast [Box<Expr>],
+        prefix_span: Span,
+    ) -> Option<TyKind> {
+        let arg_tys: Vec<Option<TyKind>> = args.iter().map(|arg| self.check_expr(arg)).collect();
+        let (params, output) = sig?;
+        self.check_args(None, params, args, &arg_tys, prefix_span);
+        Some(output)
+    }
+
+    /// Checks the arguments of the `mut` parameters of a call. Such an argument must be a place
+    /// in a mutable variable, and must not fix the length of an array that the parameter lets
+    /// change, which would change the length of the argument. The parameters are known by name
+    /// only for a named function, otherwise the errors point at the declaration of the function.
+    fn check_mut_args(
+        &mut self,
+        fun_params: &[FunParam],
+        func_decl_span: Span,
+        params: &[(TyKind, bool)],
+        args: &'ast [Box<Expr>],
+        arg_tys: &[Option<TyKind>],
+    ) {
+        for (i, (((param, is_mut), arg), arg_ty)) in
+            params.iter().zip(args).zip(arg_tys).enumerate()
+        {
+            if !is_mut {
+                continue;
+            }
+            let (param_name, param_span) = match fun_params.get(i) {
+                Some(fun_param) => (Some(fun_param.ident.name.to_string()), fun_param.span),
+                None => (None, func_decl_span),
+            };
+
+            // A place whose root is not a variable is a constant of a library.
+            let var = match place_root(arg) {
+                Some(root) => match self.find_variable(root.name.as_str()) {
+                    Some((name, _, span, is_mut)) => Some((name.to_string(), *span, *is_mut)),
+                    None if self.lookup_library(root.name.as_str()).is_some() => None,
+                    None => continue,
+                },
+                None => None,
+            };
+            match var {
+                Some((_, _, true)) => {}
+                var => {
+                    let (var_name, help_span) = match var {
+                        Some((name, span, _)) => (Some(name), Some(span)),
+                        None => (None, None),
+                    };
+                    self.errors.push(IError::PassImmutableAsMutable {
+                        param_name,
+                        mut_kw: Keyword::Mut.as_ref().to_string(),
+                        var_name,
+                        arg_span: arg.span,
+                        param_span,
+                        help_span,
+                    });
+                    continue;
+                }
+            }
+
+            if let Some(arg_ty) = arg_ty {
+                if arg_ty.fixes_growable_len(param) {
+                    self.errors.push(IError::PassFixedArrayAsGrowable {
+                        param_name,
+                        ty: arg_ty.to_string(),
+                        param_ty: param.to_string(),
+                        arg_span: arg.span,
+                        param_span,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Checks the arguments of a call against the types of the parameters of the function.
+    fn check_args(
+        &mut self,
+        func_decl_span: Option<Span>,
+        params: Vec<ParamTy>,
+        args: &'ast [Box<Expr>],
+        arg_tys: &[Option<TyKind>],
+        prefix_span: Span,
+    ) {
+        let mut mismatch_params = vec![];
+        for ((param, arg), arg_ty) in params.iter().zip(args).zip(arg_tys) {
+            if let Some(arg_ty) = arg_ty {
+                if !param.accepts(arg_ty) {
+                    mismatch_params.push((arg.span, param.to_string(), arg_ty.to_string()));
+                }
+            }
+        }
+
+        let missing_param_tys: Vec<String> = params
+            .iter()
+            .skip(args.len())
+            .map(|ty| ty.to_string())
+            .collect();
+
+        let unexpected_param_tys: Vec<(Span, String)> = args
+            .iter()
+            .zip(arg_tys)
+            .skip(params.len())
+            .map(|(arg, ty)| {
+                let ty = match ty {
+                    Some(ty) => ty.to_string(),
+                    None => "_".to_string(),
+                };
+                (arg.span, ty)
+            })
+            .collect();
+
+        if !mismatch_params.is_empty()
+            || !missing_param_tys.is_empty()
+            || !unexpected_param_tys.is_empty()
+        {
+            self.errors.push(IError::FunctionParamMismatch {
+                func_decl_span,
+                args: args.len(),
+                mismatch_params,
+                missing_param_tys,
+                unexpected_param_tys,
+                prefix_span,
+            });
+        }
+    }
+
+    /// Checks a call to a method of an array (e.g. `xs.push(1)`) or of a range (e.g.
+    /// `(0..10).step(2)`). Every method of an array changes its length, which the type of a
+    /// variable holding it must not fix.
+    fn check_expr_method_call(
+        &mut self,
+        receiver: &'ast Expr,
+        method: &'ast ast::Ident,
+        args: &'ast [Box<Expr>],
+    ) -> Option<TyKind> {
+        let receiver_ty = self.check_expr(receiver);
+        let arg_tys: Vec<Option<TyKind>> = args.iter().map(|arg| self.check_expr(arg)).collect();
+        let receiver_ty = receiver_ty?;
+        let method_name = method.name.as_str();
+        if let (TyKind::Range, Some(range_method)) =
+            (&receiver_ty, RangeMethod::from_name(method_name))
+        {
+            let params = range_method.params().into_iter().map(ParamTy::from);
+            self.check_args(None, params.collect(), args, &arg_tys, method.span);
+            return Some(TyKind::Range);
+        }
+
+        let (array_method, el_ty, len) = match (&receiver_ty, ArrayMethod::from_name(method_name)) {
+            (TyKind::Array(el_ty, len), Some(array_method)) => (array_method, el_ty, *len),
+            _ => {
+                self.errors
+                    .push(no_method_on_type_error(method, &receiver_ty));
+                return None;
+            }
+        };
+
+        // The length of a temporary array, e.g. `[1, 2].push(3)`, is not fixed by any type.
+        if len != -1 && is_place(receiver) {
+            self.errors.push(IError::ArrayLengthIsFixed {
+                method: array_method.to_string(),
+                ty: receiver_ty.to_string(),
+                growable_ty: TyKind::Array(el_ty.clone(), -1).to_string(),
+                span: method.span,
+            });
+        }
+
+        let params = array_method.params(el_ty).into_iter().map(ParamTy::from);
+        self.check_args(None, params.collect(), args, &arg_tys, method.span);
+        Some(array_method.output(el_ty))
+    }
+
+    /// Checks a field access (e.g. `p.x`). When the base is not a variable, it is the name of
+    /// a library.
+    fn check_expr_field(&mut self, base: &'ast Expr, field: &'ast ast::Ident) -> Option<TyKind> {
+        if let ExprKind::Identifier(ident) = &base.kind {
+            let name = ident.name.as_str();
+            if self.lookup_variable(name).is_none() {
+                if self.lookup_library(name).is_none() {
+                    self.errors.push(cannot_find_variable_error(ident));
+                }
+                return None;
+            }
+        }
+
+        let ty = self.check_expr(base)?;
+        let field_name = field.name.as_str();
+        let field_ty = match &ty {
+            TyKind::Struct(name) => {
+                let (_, def) = self.env.lookup_struct(name)?;
+                def.fields
+                    .into_iter()
+                    .find(|(ident, _)| ident.name == field_name)
+                    .map(|(_, ty)| ty.kind)
+            }
+            // A string holds the name of a library.
+            TyKind::Str => return None,
+            _ => None,
+        };
+
+        if field_ty.is_none() {
+            self.errors.push(IError::NoFieldOnType {
+                field: field_name.to_string(),
+                ty: ty.to_string(),
+                span: field.span,
+            });
+        }
+        field_ty
+    }
+
+    fn check_expr_array(&mut self, elements: &'ast [Box<Expr>]) -> Option<TyKind> {
+        let mut first_el: Option<(Span, TyKind)> = None;
+        let mut has_mismatch = false;
+        for element in elements {
+            let el_ty = match self.check_expr(element) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            match &first_el {
+                Some((first_el_span, first_el_ty)) => {
+                    if *first_el_ty != el_ty && !has_mismatch {
+                        has_mismatch = true;
+                        self.errors.push(IError::ArrayHasMultipleTypes {
+                            first_el_ty: first_el_ty.to_string(),
+                            first_el_span: *first_el_span,
+                            first_mismatch_ty: el_ty.to_string(),
+                            first_mismatch_span: element.span,
+                        });
+                    }
+                }
+                None => first_el = Some((element.span, el_ty)),
+            }
+        }
+
+        if has_mismatch {
+            return None;
+        }
+        let el_ty = match first_el {
+            Some((_, el_ty)) => el_ty,
+            // The type of the elements of `[]` is given by the type it is declared with.
+            None if elements.is_empty() => TyKind::Infer,
+            None => return None,
+        };
+        Some(TyKind::Array(Box::new(el_ty), elements.len() as i64))
+    }
+
+    fn check_expr_index(&mut self, array: &'ast Expr, index: &'ast Expr) -> Option<TyKind> {
+        let array_ty = self.check_expr(array);
+        let index_ty = self.check_expr(index);
+
+        let el_ty = match array_ty {
+            Some(TyKind::Array(el_ty, _)) => known(*el_ty),
+            Some(ty) => {
+                self.errors.push(IError::IndexingWrongType {
+                    ty: ty.to_string(),
+                    span: array.span,
+                });
+                None
+            }
+            None => None,
+        };
+        self.expect_int(index_ty, index.span);
+        el_ty
+    }
+
+    fn check_expr_repeat(&mut self, element: &'ast Expr, count: &'ast Expr) -> Option<TyKind> {
+        let el_ty = self.check_expr(element);
+        let count_ty = self.check_expr(count);
+        self.expect_int(count_ty, count.span);
+
+        let len = match self.int_lit(count) {
+            Some(len) if len < 0 => {
+                self.errors.push(IError::NegRepeatCount {
+                    count: len,
+                    span: count.span,
+                });
+                -1
+            }
+            Some(len) => len,
+            None => -1,
+        };
+        Some(TyKind::Array(Box::new(el_ty?), len))
+    }
+
+    fn check_expr_range(&mut self, start: &'ast Expr, end: &'ast Expr) -> Option<TyKind> {
+        let start_ty = self.check_expr(start);
+        self.expect_int(start_ty, start.span);
+        let end_ty = self.check_expr(end);
+        self.expect_int(end_ty, end.span);
+        Some(TyKind::Range)
+    }
+
+    fn check_expr_tuple(&mut self, elements: &'ast [Box<Expr>]) -> Option<TyKind> {
+        if elements.is_empty() {
+            return Some(TyKind::Unit);
+        }
+
+        let tys: Vec<Option<TyKind>> = elements.iter().map(|el| self.check_expr(el)).collect();
+        Some(TyKind::Tuple(tys.into_iter().collect::<Option<_>>()?))
+    }
+
+    fn check_expr_tuple_field(
+        &mut self,
+        tuple: &'ast Expr,
+        index: usize,
+        index_span: Span,
+    ) -> Option<TyKind> {
+        let ty = self.check_expr(tuple)?;
+        match &ty {
+            TyKind::Tuple(tys) if index < tys.len() => Some(tys[index].clone()),
+            _ => {
+                self.errors.push(IError::NoFieldOnType {
+                    field: index.to_string(),
+                    ty: ty.to_string(),
+                    span: index_span,
+                });
+                None
+            }
+        }
+    }
+
+    fn check_expr_struct(
+        &mut self,
+        name: &'ast ast::Ident,
+        fields: &'ast Vec<ExprField>,
+    ) -> Option<TyKind> {
+        let struct_name = name.name.as_str();
+        let def = match self.env.lookup_struct(struct_name) {
+            Some((_, def)) => def,
+            None => {
+                self.errors.push(IError::CannotFindTypeInScope {
+                    type_name: struct_name.to_string(),
+                    span: name.span,
+                });
+                for field in fields {
+                    self.check_expr(&field.expr);
+                }
+                return None;
+            }
+        };
+
+        let mut specified: Vec<Option<Span>> = vec![None; def.fields.len()];
+        for field in fields {
+            let value_ty = self.check_expr(&field.expr);
+            let field_name = field.ident.name.as_str();
+            let index = match def
+                .fields
+                .iter()
+                .position(|(ident, _)| ident.name == field_name)
+            {
+                Some(index) => index,
+                None => {
+                    self.errors.push(IError::NoFieldOnType {
+                        field: field_name.to_string(),
+                        ty: struct_name.to_string(),
+                        span: field.ident.span,
+                    });
+                    continue;
+                }
+            };
+
+            if let Some(first_span) = specified[index] {
+                self.errors.push(IError::FieldSpecifiedMoreThanOnce {
+                    field: field_name.to_string(),
+                    first_span,
+                    second_span: field.ident.span,
+                });
+                continue;
+            }
+            specified[index] = Some(field.ident.span);
+
+            let field_ty = &def.fields[index].1;
+            if let Some(value_ty) = value_ty {
+                if value_ty != field_ty.kind {
+                    self.errors.push(IError::MismatchedType {
+                        expected: field_ty.to_string(),
+                        found: value_ty.to_string(),
+                        span: field.expr.span,
+                    });
+                }
+            }
+        }
+
+        let missing_fields: Vec<String> = def
+            .fields
+            .iter()
+            .zip(specified)
+            .filter(|(_, span)| span.is_none())
+            .map(|((ident, _), _)| ident.name.to_string())
+            .collect();
+        if !missing_fields.is_empty() {
+            self.errors.push(IError::MissingStructFields {
+                struct_name: struct_name.to_string(),
+                fields: missing_fields,
+                span: name.span,
+            });
+        }
+
+        Some(TyKind::Struct(struct_name.to_string()))
+    }
+
+    fn check_expr_variant(
+        &mut self,
+        expr_span: Span,
+        enum_name: &'ast ast::Ident,
+        variant: &'ast ast::Ident,
+        args: &'ast [Box<Expr>],
+    ) -> Option<TyKind> {
+        let arg_tys: Vec<Option<TyKind>> = args.iter().map(|arg| self.check_expr(arg)).collect();
+
+        let name = enum_name.name.as_str();
+        let def = match self.env.lookup_enum(name) {
+            Some((_, def)) => def,
+            None => {
+                self.errors.push(IError::CannotFindTypeInScope {
+                    type_name: name.to_string(),
+                    span: enum_name.span,
+                });
+                return None;
+            }
+        };
+
+        let field_tys = match def.variant(variant.name.as_str()) {
+            Some((_, field_tys)) => field_tys,
+            None => {
+                self.errors.push(IError::NoVariantInEnum {
+                    variant: variant.name.to_string(),
+                    enum_name: name.to_string(),
+                    span: variant.span,
+                });
+                return Some(TyKind::Enum(name.to_string()));
+            }
+        };
+
+        // Like in the interpreter, the payload is checked as a whole.
+        if let Some(arg_tys) = arg_tys.into_iter().collect::<Option<Vec<_>>>() {
+            let expected = TyKind::Tuple(field_tys.iter().map(|ty| ty.kind.clone()).collect());
+            let found = TyKind::Tuple(arg_tys);
+            if expected != found {
+                self.errors.push(IError::MismatchedType {
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                    span: expr_span,
+                });
+            }
+        }
+
+        Some(TyKind::Enum(name.to_string()))
+    }
+
+    fn check_expr_closure(&mut self, closure: &'ast Closure) -> Option<TyKind> {
+        let Closure {
+            inputs,
+            output,
+            body,
+            span,
+        } = closure;
+
+        let (input_tys, output_ty) = self.resolve_fun_sig(inputs, output);
+
+        // The body can use the variables around the closure, since they are captured.
+        self.push_scope(false);
+        for (param, ty) in inputs.iter().zip(&input_tys) {
+            self.insert_variable(&param.ident, ty.clone(), param.is_mut);
+        }
+        self.funs.push(FunCtx {
+            output: output_ty.clone(),
+            output_span: output.as_ref().map(|ty| ty.span),
+            sig_span: *span,
+        });
+        self.visit_stmt(body);
+        let ctx = self.funs.pop().unwrap();
+        self.check_body_returns(&ctx, *span, body);
+        self.pop_scope();
+
+        let is_mut = inputs.iter().map(|param| param.is_mut);
+        fun_ty(input_tys.into_iter().zip(is_mut).collect(), output_ty)
+    }
+}
+
+impl<'ast> Visitor<'ast> for TypeChecker<'ast> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.ty = match &expr.kind {
+            ExprKind::Binary(op, lhs, rhs) => self.check_expr_binary(op, lhs, rhs),
+            ExprKind::Unary(op, expr) => self.check_expr_unary(op, expr),
+            ExprKind::Literal(lit) => match lit.kind {
+                LitKind::Int => Some(TyKind::Int),
+                LitKind::Float => Some(TyKind::Float),
+                LitKind::Str | LitKind::RawStr(_) => Some(TyKind::Str),
+                LitKind::Bool => Some(TyKind::Bool),
+                LitKind::Char => Some(TyKind::Char),
+                LitKind::Err => None,
+            },
+            ExprKind::Assign(lhs, rhs, _) => {
+                let value_ty = self.check_expr(rhs);
+                let place_ty = self.check_place(lhs);
+                self.check_assign(place_ty, value_ty, expr.span);
+                Some(TyKind::Unit)
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                let place_ty = self.check_place(lhs);
+                let rhs_ty = self.check_expr(rhs);
+                let value_ty = self.check_binary_op(op, place_ty.clone(), rhs_ty);
+                self.check_assign(place_ty, value_ty, expr.span);
+                Some(TyKind::Unit)
+            }
+            ExprKind::Identifier(ident) => self.check_expr_ident(ident),
+            ExprKind::Cast(expr, ty) => self.check_expr_cast(expr, ty),
+            ExprKind::FunCall(callee, args) => self.check_expr_call(callee, args),
+            ExprKind::LibAccess(base, field) => self.check_expr_field(base, field),
+            ExprKind::LibFunCall(callee, args) => match &callee.kind {
+                ExprKind::LibAccess(receiver, method)
+                    if is_method_call(receiver, |name| self.lookup_variable(name).is_some()) =>
+                {
+                    self.check_expr_method_call(receiver, method, args)
+                }
+                ExprKind::LibAccess(receiver, func) => {
+                    let sig = match &receiver.kind {
+                        ExprKind::Identifier(lib) => match self.lookup_library(lib.name.as_str()) {
+                            Some(lib) => {
+                                lib.and_then(|lib| native_sig(lib.as_ref(), func.name.as_str()))
+                            }
+                            // Even if the call is never run, the library must have been added.
+                            None => {
+                                self.errors.push(IError::LibraryNotFound {
+                                    library: lib.name.to_string(),
+                                    span: lib.span,
+                                });
+                                None
+                            }
+                        },
+                        _ => None,
+                    };
+                    self.check_native_call(sig, args, callee.span)
+                }
+                _ => {
+                    for arg in args {
+                        self.check_expr(arg);
+                    }
+                    None
+                }
+            },
+            ExprKind::Array(elements) => self.check_expr_array(elements),
+            ExprKind::Index(array, index, _) => self.check_expr_index(array, index),
+            ExprKind::Repeat(element, count) => self.check_expr_repeat(element, count),
+            ExprKind::Range(start, end, _) => self.check_expr_range(start, end),
+            ExprKind::Tuple(elements) => self.check_expr_tuple(elements),
+            ExprKind::TupleField(tuple, index, index_span) => {
+                self.check_expr_tuple_field(tuple, *index, *index_span)
+            }
+            ExprKind::Struct(name, fields) => self.check_expr_struct(name, fields),
+            ExprKind::Variant(enum_name, variant, args) => {
+                self.check_expr_variant(expr.span, enum_name, variant, args)
+            }
+            ExprKind::Closure(closure) => self.check_expr_closure(closure),
+        };
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match &stmt.kind {
+            // The span of the statement is needed when nothing is returned.
+            StmtKind::Return(expr) => self.check_return(expr.as_deref(), stmt.span),
+            _ => visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_stmt_import(&mut self, import: &'ast ast::Import) {
+        let lib = self.native_library(import);
+        let scope = self.scopes.last_mut().unwrap();
+        match &import.kind {
+            ast::ImportKind::Library | ast::ImportKind::Alias(_) => {
+                let name = import.name().name.to_string();
+                scope.libraries.insert(name, lib);
+            }
+            ast::ImportKind::Select(names) => {
+                for name in names {
+                    scope
+                        .imported_functions
+                        .insert(name.name.to_string(), lib.clone());
+                }
+            }
+        }
+    }
+
+    fn visit_stmt_block(&mut self, stmts: &'ast Vec<Box<Stmt>>) {
+        self.push_scope(false);
+        self.check_stmts(stmts);
+        self.pop_scope();
+    }
+
+    fn visit_stmt_func_decl(&mut self, fun: &'ast Fun) {
+        let (inputs, output) = self.resolve_fun_sig(&fun.sig.inputs, &fun.sig.output);
+        let is_mut = fun.sig.inputs.iter().map(|param| param.is_mut);
+        let ty = fun_ty(inputs.iter().cloned().zip(is_mut).collect(), output.clone());
+
+        let name = &fun.sig.name;
+        let scope = self.scopes.last_mut().unwrap();
+        scope.functions.insert(
+            name.name.to_string(),
+            (name.span, ty, fun.sig.inputs.clone()),
+        );
+
+        // The body is checked once every function and type of the block is declared.
+        self.deferred
+            .last_mut()
+            .unwrap()
+            .push((fun, inputs, output));
+    }
+
+    fn visit_stmt_if(
+        &mut self,
+        condition: &'ast Expr,
+        block: &'ast Stmt,
+        optional_else: Option<&'ast Stmt>,
+    ) {
+        self.check_cond(condition);
+        self.visit_stmt(block);
+        if let Some(else_block) = optional_else {
+            self.visit_stmt(else_block);
+        }
+    }
+
+    fn visit_stmt_while(&mut self, condition: &'ast Expr, block: &'ast Stmt) {
+        self.check_cond(condition);
+        self.visit_stmt(block);
+    }
+
+    fn visit_stmt_for(&mut self, ident: &'ast ast::Ident, expr: &'ast Expr, block: &'ast Stmt) {
+        let el_ty = match self.check_expr(expr) {
+            Some(TyKind::Array(el_ty, _)) => known(*el_ty),
+            Some(TyKind::Str) => Some(TyKind::Char),
+            Some(TyKind::Range) => Some(TyKind::Int),
+            Some(ty) => {
+                self.errors.push(IError::ExpectedIterator {
+                    ty: ty.to_string(),
+                    span: expr.span,
+                });
+                None
+            }
+            None => None,
+        };
+
+        self.push_scope(false);
+        self.insert_variable(ident, el_ty, false);
+        self.visit_stmt(block);
+        self.pop_scope();
+    }
+
+    fn visit_stmt_var(&mut self, local: &'ast Local) {
+        let Local {
+            is_mut,
+            pat,
+            ty,
+            kind,
+            span: _,
+        } = local;
+
+        let decl_ty = ty.as_ref().map(|ty| (self.resolve_ty(ty), ty));
+        let value = match kind {
+            LocalKind::Init(expr) => Some((self.check_expr(expr), expr.span)),
+            LocalKind::Decl => None,
+        };
+
+        if let (Some((Some(decl_ty), _)), Some((Some(value_ty), value_span))) = (&decl_ty, &value) {
+            if value_ty != decl_ty {
+                self.errors.push(IError::MismatchedType {
+                    expected: decl_ty.to_string(),
+                    found: value_ty.to_string(),
+                    span: *value_span,
+                });
+            }
+        }
+
+        let ident = match &pat.kind {
+            PatKind::Ident(ident) => ident,
+            _ => {
+                let (value_ty, value_span) = value.expect("parser requires an initializer");
+                let ty = value_ty.or_else(|| decl_ty.and_then(|(ty, _)| ty));
+                self.bind_pat(pat, ty, Some(value_span), *is_mut);
+                return;
+            }
+        };
+
+        let (decl_ty, ty) = decl_ty.expect("parser requires a type for a non-tuple pattern");
+        let var_ty = match (decl_ty, value) {
+            // An array declared without a length can change its length, and `[]` takes the type
+            // of its elements from the declaration. An array whose length is not a literal has
+            // the length of its value.
+            (Some(decl_ty @ TyKind::Array(_, -1)), Some((Some(mut value_ty), _))) => {
+                let value_len = match value_ty {
+                    TyKind::Array(_, len) => len,
+                    _ => -1,
+                };
+                value_ty.infer_from(&decl_ty);
+                if let (TyKind::Array(_, len), ast::TyKind::Array(_, Some(_))) =
+                    (&mut value_ty, &ty.kind)
+                {
+                    *len = value_len;
+                }
+                Some(value_ty)
+            }
+            (Some(decl_ty), None) if matches!(ty.kind, ast::TyKind::Array(_, None)) => {
+                self.errors.push(IError::UnknownSizeArray {
+                    ty: decl_ty.to_string(),
+                    span: ty.span,
+                });
+                Some(decl_ty)
+            }
+            (decl_ty, _) => decl_ty,
+        };
+        self.insert_variable(ident, var_ty, *is_mut);
+    }
+
+    fn visit_stmt_struct_decl(&mut self, def: &'ast StructDef) {
+        let StructDef { name, fields } = def;
+
+        let mut resolved_fields: Vec<(Ident, Ty)> = vec![];
+        let mut is_resolved = true;
+        for field in fields {
+            let field_name = field.ident.name.as_str();
+            if let Some((first, _)) = resolved_fields
+                .iter()
+                .find(|(ident, _)| ident.name == field_name)
+            {
+                self.errors.push(IError::FieldSpecifiedMoreThanOnce {
+                    field: field_name.to_string(),
+                    first_span: first.span,
+                    second_span: field.ident.span,
+                });
+                continue;
+            }
+
+            match self.resolve_ty(&field.ty) {
+                Some(kind) => {
+                    let ident = Ident {
+                        name: field_name.to_string(),
+                        span: field.ident.span,
+                    };
+                    let ty = Ty {
+                        kind,
+                        span: field.ty.span,
+                    };
+                    resolved_fields.push((ident, ty));
+                }
+                None => is_resolved = false,
+            }
+        }
+
+        // A struct with a field of an unknown type is not declared, like in the interpreter.
+        if !is_resolved {
+            return;
+        }
+
+        let ident = Ident {
+            name: name.name.to_string(),
+            span: name.span,
+        };
+        let def = super::StructDef {
+            ident,
+            fields: resolved_fields,
+        };
+        if let Err(errors) =
+            self.env
+                .insert_type(name.name.to_string(), TypeDef::Struct(def), name.span)
+        {
+            self.errors.extend(errors);
+        }
+    }
+
+    fn visit_stmt_enum_decl(&mut self, def: &'ast EnumDef) {
+        let EnumDef { name, variants } = def;
+
+        // The enum is declared before its variants are resolved, so that a variant can hold
+        // a value of the enum itself.
+        let ident = Ident {
+            name: name.name.to_string(),
+            span: name.span,
+        };
+        let mut def = super::EnumDef {
+            ident,
+            variants: vec![],
+        };
+        if let Err(errors) =
+            self.env
+                .insert_type(name.name.to_string(), TypeDef::Enum(def.clone()), name.span)
+        {
+            self.errors.extend(errors);
+            return;
+        }
+
+        for variant in variants {
+            let variant_name = variant.ident.name.as_str();
+            if let Some((first, _)) = def
+                .variants
+                .iter()
+                .find(|(ident, _)| ident.name == variant_name)
+            {
+                self.errors.push(IError::VariantDeclaredMoreThanOnce {
+                    variant: variant_name.to_string(),
+                    first_span: first.span,
+                    second_span: variant.ident.span,
+                });
+                continue;
+            }
+
+            let fields: Vec<Option<Ty>> = variant
+                .fields
+                .iter()
+                .map(|ty| {
+                    self.resolve_ty(ty).map(|kind| Ty {
+                        kind,
+                        span: ty.span,
+                    })
+                })
+                .collect();
+            if let Some(fields) = fields.into_iter().collect() {
+                let ident = Ident {
+                    name: variant_name.to_string(),
+                    span: variant.ident.span,
+                };
+                def.variants.push((ident, fields));
+            }
+        }
+
+        self.env.update_type(name.name.as_str(), TypeDef::Enum(def));
+    }
+
+    fn visit_stmt_match(&mut self, scrutinee: &'ast Expr, arms: &'ast Vec<Arm>) {
+        let ty = self.check_expr(scrutinee);
+        if let Some(ty) = &ty {
+            let pats: Vec<&Pat> = arms.iter().map(|arm| &arm.pat).collect();
+            if let Err(errors) =
+                exhaustiveness::check_match(&mut self.env, ty, &pats, scrutinee.span, false)
+            {
+                self.errors.extend(errors);
+            }
+        }
+
+        for arm in arms {
+            self.push_scope(false);
+            self.bind_pat(&arm.pat, ty.clone(), None, false);
+            self.visit_stmt(&arm.body);
+            self.pop_scope();
+        }
+    }
+}
+
+fn is_comparison(op: &BinOpKind) -> bool {
+    matches!(
+        op,
+        BinOpKind::Eq
+            | BinOpKind::Ne
+            | BinOpKind::Lt
+            | BinOpKind::Le
+            | BinOpKind::Gt
+            | BinOpKind::Ge
+    )
+}
+
+/// Returns the type of the result of a binary operation on two operands of the given type,
+/// or `None` if the operation is not implemented for it.
+fn binary_op_ty(op: &BinOpKind, ty: &TyKind) -> Option<TyKind> {
+    let is_implemented = match op {
+        BinOpKind::Add => matches!(ty, TyKind::Int | TyKind::Float | TyKind::Str),
+        BinOpKind::Sub | BinOpKind::Mul | BinOpKind::Div | BinOpKind::Mod => {
+            matches!(ty, TyKind::Int | TyKind::Float)
+        }
+        BinOpKind::Eq | BinOpKind::Ne => is_equatable(ty),
+        BinOpKind::Lt | BinOpKind::Le | BinOpKind::Gt | BinOpKind::Ge => {
+            matches!(ty, TyKind::Int | TyKind::Float | TyKind::Str | TyKind::Char)
+        }
+        BinOpKind::And | BinOpKind::Or => matches!(ty, TyKind::Bool),
+        BinOpKind::BitAnd
+        | BinOpKind::BitOr
+        | BinOpKind::BitXor
+        | BinOpKind::Shl
+        | BinOpKind::Shr => matches!(ty, TyKind::Int),
+    };
+    if !is_implemented {
+        return None;
+    }
+
+    if is_comparison(op) {
+        Some(TyKind::Bool)
+    } else {
+        Some(ty.clone())
+    }
+}
+
+/// Whether `==` and `!=` are implemented for the type. Arrays are equal if their elements are.
+fn is_equatable(ty: &TyKind) -> bool {
+    match ty {
+        TyKind::Int | TyKind::Float | TyKind::Str | TyKind::Bool | TyKind::Char => true,
+        TyKind::Range => true,
+        TyKind::Array(el_ty, _) => is_equatable(el_ty),
+        TyKind::Infer => true,
+        _ => false,
+    }
+}
+
+/// Whether a statement returns from its function whichever way it runs: it is a `yeet`, a block
+/// with such a statement, a `when` whose branches, `alt` included, all return, or a `match`
+/// whose arms all return.
+fn always_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::Block(stmts) => stmts.iter().any(|stmt| always_returns(stmt)),
+        StmtKind::If(_, then, Some(alt)) => always_returns(then) && always_returns(alt),
+        StmtKind::Match(_, arms) => arms.iter().all(|arm| always_returns(&arm.body)),
+        _ => false,
+    }
+}
+
+fn cannot_find_variable_error(ident: &ast::Ident) -> IError {
+    IError::CannotFindVariableInScope {
+        var_name: ident.name.to_string(),
+        span: ident.span,
+    }
+}
+
+/// Returns a type unless it is the element type of `[]`, which is not known.
+fn known(ty: TyKind) -> Option<TyKind> {
+    match ty {
+        TyKind::Infer => None,
+        ty => Some(ty),
+    }
+}
+
+/// Returns the type of a function, if the types of its parameters and its return type are
+/// all known. Each parameter type comes with whether it is `mut`.
+fn fun_ty(inputs: Vec<(Option<TyKind>, bool)>, output: Option<TyKind>) -> Option<TyKind> {
+    let inputs = inputs
+        .into_iter()
+        .map(|(ty, is_mut)| Some((ty?, is_mut)))
+        .collect::<Option<Vec<_>>>()?;
+    Some(TyKind::Function(inputs, Box::new(output?)))
+}
+
+/// Returns the types of the parameters and the return type of a function of a library, if it
+/// is written in Rust.
+fn native_sig(lib: &dyn Library, name: &str) -> Option<(Vec<ParamTy>, TyKind)> {
+    let NativeFunction { params, output, .. } = lib.get_function(name)?;
+    Some((params.clone(), output.clone()))
+}
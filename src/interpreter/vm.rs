@@ -0,0 +1,1509 @@
+//! A stack-based virtual machine running the bytecode the program is compiled to. It reports the
+//! same errors as the tree walker, which is still available behind the `--tree-walk` flag so that
+//! the two can be compared.
+
+mod chunk;
+mod compiler;
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+use crate::{
+    ast::{self, Ast, BinOpKind},
+    kw::Keyword,
+    span_encoding::{Span, DUMMY_SP},
+};
+
+use super::{
+    environment::{Environment, ExternalLibrary},
+    errors::{CallFrame, IError},
+    eval_array, eval_binary, eval_cast, eval_field, eval_index, eval_lib_constant,
+    eval_method_call, eval_range, eval_repeat, eval_tuple_field, eval_unary, eval_variant,
+    exhaustiveness, is_short_circuit,
+    libs::{function_not_found_error, CallerAttrs},
+    method_mutates, needs_mut, pass_fixed_array_as_growable_error, project, project_ty,
+    ty::TyKind,
+    Func, FuncBody, FuncParam, FuncSig, Projection, Value, ValueKind,
+};
+
+pub use chunk::Program;
+use chunk::{Callee, LocalInfo, Op, Pattern, Place, PlaceProj, Proto, VarRef};
+pub use compiler::compile;
+
+/// The number of calls that can run at the same time, so that a recursion that never ends stops
+/// the program with an error instead of using all the memory.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// A function value created by the VM: the compiled function with the variables it captured.
+pub struct Closure {
+    pub proto: Rc<Proto>,
+    upvalues: Vec<UpvalueRef>,
+    /// The file the function was compiled from.
+    module: Rc<Module>,
+}
+
+/// A compiled file, the program or one of the libraries it adds, with the state shared by the
+/// functions declared in it.
+struct Module {
+    program: Program,
+    /// Whether each named function has been declared.
+    declared: RefCell<Vec<bool>>,
+    /// The libraries in scope.
+    env: RefCell<Environment>,
+}
+
+impl Module {
+    /// Returns the named function with the given id, if it has been declared.
+    fn fun(self: &Rc<Self>, id: usize) -> Option<Rc<Closure>> {
+        if !self.declared.borrow()[id] {
+            return None;
+        }
+        Some(Rc::new(Closure {
+            proto: Rc::clone(&self.program.funs[id]),
+            upvalues: vec![],
+            module: Rc::clone(self),
+        }))
+    }
+}
+
+type UpvalueRef = Rc<RefCell<Upvalue>>;
+
+/// A variable captured by a closure. It stays in the locals of the function that declared it
+/// until it goes out of scope, then it is moved into the upvalue.
+#[derive(Debug)]
+enum Upvalue {
+    Open(usize),
+    Closed(Var),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Var {
+    val: Option<Value>,
+    /// The span of the last assignment, to point at it when the variable is immutable.
+    assigned_span: Option<Span>,
+    /// The place a `mut` parameter refers to, where its value is read and written instead of
+    /// `val`.
+    alias: Option<Rc<RefTarget>>,
+}
+
+/// Where the value of a variable lives.
+#[derive(Debug, Clone)]
+enum Root {
+    Local(usize),
+    Upvalue(UpvalueRef),
+}
+
+/// A place passed by reference to a `mut` parameter. Its root is always an upvalue, so that the
+/// place outlives the scope of the caller if the parameter is captured by a closure, and it is
+/// never a `mut` parameter itself, as a parameter passed on is resolved to the place it refers
+/// to.
+#[derive(Debug)]
+struct RefTarget {
+    root: Root,
+    projections: Vec<(Projection, Span)>,
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// The index of the first local of the frame.
+    base: usize,
+    /// The height of the stack when the frame was entered.
+    stack_base: usize,
+    /// The number of library scopes of its module when the frame was entered.
+    lib_depth: usize,
+    /// The span of the called function in the call, for the stack trace of an error.
+    call_span: Span,
+}
+
+pub struct Vm {
+    /// The program being run.
+    module: Rc<Module>,
+    frames: Vec<Frame>,
+    stack: Vec<Value>,
+    locals: Vec<Var>,
+    /// The upvalues still pointing into the locals, sorted by the index of their local.
+    open_upvalues: Vec<UpvalueRef>,
+    /// The places evaluated for the call being prepared, passed by reference.
+    ref_args: Vec<RefTarget>,
+    is_verbose: bool,
+}
+
+impl Vm {
+    /// Creates a VM running the program, with the libraries it adds loaded in `env`. The
+    /// libraries written in Pandora are compiled too.
+    pub fn new(program: &Program, mut env: Environment, is_verbose: bool) -> Self {
+        env.compiles_libraries = true;
+        let module = Rc::new(Module {
+            program: program.clone(),
+            declared: RefCell::new(vec![false; program.funs.len()]),
+            env: RefCell::new(env),
+        });
+        Vm {
+            module,
+            frames: vec![],
+            stack: vec![],
+            locals: vec![],
+            open_upvalues: vec![],
+            ref_args: vec![],
+            is_verbose,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), Vec<IError>> {
+        let main = Rc::new(Closure {
+            proto: Rc::clone(&self.module.program.main),
+            upvalues: vec![],
+            module: Rc::clone(&self.module),
+        });
+        self.locals.resize(main.proto.locals.len(), Var::default());
+        self.frames.push(Frame {
+            closure: Rc::clone(&main),
+            ip: 0,
+            base: 0,
+            stack_base: 0,
+            lib_depth: self.module.env.borrow().scopes.len(),
+            call_span: DUMMY_SP,
+        });
+
+        self.execute().map_err(|errors| self.trace(errors))
+    }
+
+    /// Returns the variables declared at the top level of the program with their values, in
+    /// declaration order.
+    pub fn globals(&self) -> impl Iterator<Item = (&LocalInfo, Option<&Value>)> {
+        let program = &self.module.program;
+        program
+            .top_locals
+            .iter()
+            .map(|slot| (&program.main.locals[*slot], self.locals[*slot].val.as_ref()))
+            .filter(|(info, _)| !info.name.is_empty())
+    }
+
+    /// Records the calls running when the errors were raised, from the innermost one.
+    fn trace(&self, errors: Vec<IError>) -> Vec<IError> {
+        let frames: Vec<CallFrame> = self
+            .frames
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|frame| CallFrame {
+                func_name: frame.closure.proto.sig.as_ref().unwrap().ident.name.clone(),
+                call_span: frame.call_span,
+            })
+            .collect();
+        errors
+            .into_iter()
+            .map(|error| {
+                frames
+                    .iter()
+                    .fold(error, |error, frame| error.in_call(frame.clone()))
+            })
+            .collect()
+    }
+
+    fn execute(&mut self) -> Result<(), Vec<IError>> {
+        // The state of the current frame is kept here, and saved into the frame on a call.
+        let frame = self.frames.last().unwrap();
+        let mut closure = Rc::clone(&frame.closure);
+        let mut ip = frame.ip;
+        let mut base = frame.base;
+        loop {
+            let proto = &closure.proto;
+            let module = &closure.module;
+            let span = proto.spans[ip];
+            let op = &proto.code[ip];
+            ip += 1;
+            let mut is_frame_changed = false;
+
+            match op {
+                Op::Constant(index) => self.push(proto.constants[*index].clone(), span),
+                Op::Unit => self.push(ValueKind::Unit, span),
+                Op::Pop => {
+                    self.pop();
+                }
+
+                Op::GetLocal(slot) => {
+                    let kind = self.read(&Root::Local(base + slot), &proto.locals[*slot], span)?;
+                    self.push(kind, span);
+                }
+                Op::DefineLocal(slot) => {
+                    let value = self.pop();
+                    self.locals[base + slot] = Var {
+                        val: Some(value),
+                        assigned_span: Some(proto.locals[*slot].span),
+                        alias: None,
+                    };
+                }
+                Op::DeclareLocal(slot) => self.locals[base + slot] = Var::default(),
+                Op::SetLocal(slot) => {
+                    let value = self.pop();
+                    let root = Root::Local(base + slot);
+                    self.assign(&root, &proto.locals[*slot], value, span)?;
+                    self.push(ValueKind::Unit, span);
+                }
+                Op::GetUpvalue(index) => {
+                    let root = Root::Upvalue(Rc::clone(&closure.upvalues[*index]));
+                    let kind = self.read(&root, &proto.upvalues[*index].local, span)?;
+                    self.push(kind, span);
+                }
+                Op::SetUpvalue(index) => {
+                    let value = self.pop();
+                    let root = Root::Upvalue(Rc::clone(&closure.upvalues[*index]));
+                    self.assign(&root, &proto.upvalues[*index].local, value, span)?;
+                    self.push(ValueKind::Unit, span);
+                }
+                Op::CloseUpvalues(slot) => self.close_upvalues(base + slot),
+                Op::SetPlace(index) => {
+                    let place = &proto.places[*index];
+                    let projections = self.pop_projections(place)?;
+                    let value = self.pop();
+                    let (root, info) = self.place_root(&closure, place, base);
+                    self.assign_place(root, info, place, projections, value, span)?;
+                    self.push(ValueKind::Unit, span);
+                }
+                Op::PlaceArg { place, callee, arg } => {
+                    let place = &proto.places[*place];
+                    let param =
+                        self.callee_param(module, proto, *callee, *arg, place.index_count());
+                    let projections = self.pop_projections(place)?;
+                    let (root, info) = self.place_root(&closure, place, base);
+                    let declared_ty = info.ty.as_ref().map(|ty| project_ty(ty, &projections));
+                    // A `mut` parameter passed on refers to the same place as the parameter.
+                    let (root, projections) = self.resolve(root, projections);
+                    let kind = self.with_var(&root, |var| -> Result<ValueKind, Vec<IError>> {
+                        let val = match var.val.as_mut() {
+                            Some(val) => val,
+                            None => {
+                                return Err(vec![IError::VariableIsNotInitialized {
+                                    var_name: info.name.to_string(),
+                                    declared_span: info.span,
+                                    used_span: place.root_ident.span,
+                                }])
+                            }
+                        };
+                        if let Some(param) = &param {
+                            if !info.is_mut {
+                                return Err(vec![IError::PassImmutableAsMutable {
+                                    param_name: Some(param.ident.name.to_string()),
+                                    mut_kw: Keyword::Mut.as_ref().to_string(),
+                                    var_name: Some(place.root_ident.name.to_string()),
+                                    arg_span: span,
+                                    param_span: param.span,
+                                    help_span: Some(info.span),
+                                }]);
+                            }
+                            // A local without a declared type has the type of its value.
+                            let ty = declared_ty
+                                .unwrap_or_else(|| project_ty(&val.to_ty_kind(), &projections));
+                            if let Some(ty) = ty {
+                                if ty.fixes_growable_len(&param.ty.kind) {
+                                    return Err(vec![pass_fixed_array_as_growable_error(
+                                        param, &ty, span,
+                                    )]);
+                                }
+                            }
+                        }
+                        Ok(project(val, projections.clone())?.kind.clone())
+                    })?;
+                    if param.is_some() {
+                        let root = match root {
+                            Root::Local(at) => Root::Upvalue(self.capture_upvalue(at)),
+                            root => root,
+                        };
+                        self.ref_args.push(RefTarget { root, projections });
+                    }
+                    self.push(kind, span);
+                }
+
+                Op::CheckTy(index) => {
+                    let ty = &proto.tys[*index];
+                    let value = self.stack.last_mut().unwrap();
+                    let value_ty = value.to_ty_kind();
+                    if value_ty != ty.kind {
+                        return Err(vec![IError::MismatchedType {
+                            expected: ty.to_string(),
+                            found: value_ty.to_string(),
+                            span: value.span,
+                        }]);
+                    }
+                    value.kind.infer_ty(&ty.kind);
+                }
+                Op::CheckLen => {
+                    let len = self.pop();
+                    match len.kind {
+                        ValueKind::Int(size) if size < 0 => {
+                            return Err(vec![IError::NegArraySize {
+                                size: size.to_string(),
+                                span: len.span,
+                            }])
+                        }
+                        ValueKind::Int(_) => {}
+                        _ => {
+                            return Err(vec![IError::MismatchedType {
+                                expected: TyKind::Int.to_string(),
+                                found: len.to_ty_kind().to_string(),
+                                span: len.span,
+                            }])
+                        }
+                    }
+                }
+                Op::CheckCallable => {
+                    if !matches!(self.stack.last().unwrap().kind, ValueKind::Function(_)) {
+                        return Err(vec![IError::InvalidFunctionCall { span }]);
+                    }
+                }
+
+                Op::DeclareFun(id) => module.declared.borrow_mut()[*id] = true,
+                Op::GetFun(id) => match module.fun(*id) {
+                    Some(fun) => {
+                        let kind = function_value(fun);
+                        self.push(kind, span);
+                    }
+                    None => {
+                        return Err(vec![IError::CannotFindVariableInScope {
+                            var_name: module.program.funs[*id].name.to_string(),
+                            span,
+                        }])
+                    }
+                },
+                Op::MakeClosure(index) => {
+                    let proto = Rc::clone(&proto.protos[*index]);
+                    let upvalues = proto
+                        .upvalues
+                        .iter()
+                        .map(|upvalue| {
+                            if upvalue.is_local {
+                                self.capture_upvalue(base + upvalue.index)
+                            } else {
+                                Rc::clone(&closure.upvalues[upvalue.index])
+                            }
+                        })
+                        .collect();
+                    let kind = function_value(Rc::new(Closure {
+                        proto,
+                        upvalues,
+                        module: Rc::clone(module),
+                    }));
+                    self.push(kind, span);
+                }
+
+                Op::Binary(op) => {
+                    let len = self.stack.len();
+                    let (lhs, rhs) = (&self.stack[len - 2].kind, &self.stack[len - 1].kind);
+                    if let Some(kind) = int_binary(&op.node, lhs, rhs) {
+                        self.stack.truncate(len - 1);
+                        self.stack[len - 2] = Value { kind, span };
+                        continue;
+                    }
+
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let kind = eval_binary(op, lhs, rhs, span)?;
+                    self.push(kind, span);
+                }
+                Op::Unary(op) => {
+                    let operand = self.pop();
+                    let kind = eval_unary(op, operand, span)?;
+                    self.push(kind, span);
+                }
+                Op::Cast(index) => {
+                    let operand = self.pop();
+                    let kind = eval_cast(operand, &proto.tys[*index].kind)?;
+                    self.push(kind, span);
+                }
+                Op::MakeArray(len) => {
+                    let elements = self.pop_n(*len);
+                    let kind = eval_array(elements)?;
+                    self.push(kind, span);
+                }
+                Op::Repeat => {
+                    let count = self.pop();
+                    let element = self.pop();
+                    let kind = eval_repeat(element, count)?;
+                    self.push(kind, span);
+                }
+                Op::MakeRange(limits) => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let kind = eval_range(start, end, *limits)?;
+                    self.push(kind, span);
+                }
+                Op::Index => {
+                    let index = self.pop();
+                    let array = self.pop();
+                    let kind = eval_index(array, index)?;
+                    self.push(kind, span);
+                }
+                Op::MakeTuple(len) => {
+                    let elements = self.pop_n(*len);
+                    self.push(ValueKind::Tuple(elements), span);
+                }
+                Op::TupleField(index, index_span) => {
+                    let tuple = self.pop();
+                    let kind = eval_tuple_field(tuple, *index, *index_span)?;
+                    self.push(kind, span);
+                }
+                Op::Field(ident) => {
+                    let base = self.pop();
+                    let kind = eval_field(&module.env.borrow(), base, ident)?;
+                    self.push(kind, span);
+                }
+                Op::LibConstant(lib, ident) => {
+                    let kind = eval_lib_constant(&module.env.borrow(), lib, ident)?;
+                    self.push(kind, span);
+                }
+                Op::MakeStruct(site) => {
+                    let site = &proto.structs[*site];
+                    let values = self.pop_n(site.fields.len());
+                    let mut fields: Vec<Option<Value>> = vec![None; site.def.fields.len()];
+                    for (value, index) in values.into_iter().zip(&site.fields) {
+                        let field_ty = &site.def.fields[*index].1;
+                        let value_ty = value.to_ty_kind();
+                        if value_ty != field_ty.kind {
+                            return Err(vec![IError::MismatchedType {
+                                expected: field_ty.to_string(),
+                                found: value_ty.to_string(),
+                                span: value.span,
+                            }]);
+                        }
+                        fields[*index] = Some(value);
+                    }
+
+                    let fields = site
+                        .def
+                        .fields
+                        .iter()
+                        .zip(fields)
+                        .map(|((ident, _), value)| (ident.name.to_string(), value.unwrap()))
+                        .collect();
+                    let name = site.def.ident.name.to_string();
+                    self.push(ValueKind::Struct(name, fields), span);
+                }
+                Op::MakeVariant(site) => {
+                    let site = &proto.variants[*site];
+                    let values = self.pop_n(site.argc);
+                    let kind = eval_variant(
+                        &site.enum_name,
+                        &site.variant,
+                        &site.field_tys,
+                        values,
+                        span,
+                    )?;
+                    self.push(kind, span);
+                }
+
+                Op::Jump(to) => ip = *to,
+                Op::ShortCircuit(op, to) => {
+                    let lhs = self.stack.last_mut().unwrap();
+                    if is_short_circuit(op, &lhs.kind) {
+                        lhs.span = span;
+                        ip = *to;
+                    }
+                }
+                Op::JumpIfFalse(to) => {
+                    let cond = self.pop();
+                    match cond.kind {
+                        ValueKind::Bool(true) => {}
+                        ValueKind::Bool(false) => ip = *to,
+                        _ => {
+                            return Err(vec![IError::MismatchedType {
+                                expected: TyKind::Bool.to_string(),
+                                found: cond.to_ty_kind().to_string(),
+                                span: cond.span,
+                            }])
+                        }
+                    }
+                }
+                Op::IntoIter(slot) => {
+                    let value = self.pop();
+                    let value_ty = value.to_ty_kind();
+                    let value_span = value.span;
+                    // The elements of a range are computed from its position as it goes.
+                    let iter = match value.kind {
+                        ValueKind::Range(range) => ValueKind::Range(range),
+                        _ => {
+                            let values = value.into_elements().map_err(|_| {
+                                vec![IError::ExpectedIterator {
+                                    ty: value_ty.to_string(),
+                                    span: value_span,
+                                }]
+                            })?;
+                            let el_ty = match value_ty {
+                                TyKind::Array(el_ty, _) => el_ty,
+                                _ => Box::new(TyKind::Char),
+                            };
+                            ValueKind::Array(values.collect(), el_ty)
+                        }
+                    };
+                    self.locals[base + slot] = hidden_var(iter, value_span);
+                    self.locals[base + slot + 1] = hidden_var(ValueKind::Int(0), value_span);
+                }
+                Op::ForNext { iter, var, exit } => {
+                    let position = match &mut self.locals[base + iter + 1].val {
+                        Some(Value {
+                            kind: ValueKind::Int(position),
+                            ..
+                        }) => {
+                            *position += 1;
+                            *position as usize - 1
+                        }
+                        _ => unreachable!("the position of an iterator is an integer"),
+                    };
+                    let next = match &self.locals[base + iter].val {
+                        Some(Value {
+                            kind: ValueKind::Array(values, _),
+                            ..
+                        }) => values.get(position).cloned(),
+                        Some(Value {
+                            kind: ValueKind::Range(range),
+                            span,
+                        }) => range.get(position as u64).map(|element| Value {
+                            kind: ValueKind::Int(element),
+                            span: *span,
+                        }),
+                        _ => unreachable!("an iterator is an array or a range"),
+                    };
+                    match next {
+                        Some(value) => {
+                            self.locals[base + var] = Var {
+                                val: Some(value),
+                                assigned_span: Some(proto.locals[*var].span),
+                                alias: None,
+                            }
+                        }
+                        None => ip = *exit,
+                    }
+                }
+                Op::CheckMatch { site, slot } => {
+                    let site = &proto.matches[*site];
+                    if !site.checked.get() {
+                        let mut env = Environment::new();
+                        env.scopes[0].types = site.types.iter().cloned().collect::<HashMap<_, _>>();
+                        let value = self.locals[base + slot].val.as_ref().unwrap();
+                        let pats: Vec<_> = site.pats.iter().collect();
+                        exhaustiveness::check_match(
+                            &mut env,
+                            &value.to_ty_kind(),
+                            &pats,
+                            site.scrutinee_span,
+                            self.is_verbose,
+                        )?;
+                        site.checked.set(true);
+                    }
+                }
+                Op::MatchArm { slot, pat, next } => {
+                    let value = self.locals[base + slot].val.as_ref().unwrap();
+                    let mut bindings = vec![];
+                    if match_pattern(&proto.pats[*pat], value, &mut bindings) {
+                        for (slot, value) in bindings {
+                            self.define(proto, base, slot, value);
+                        }
+                    } else {
+                        ip = *next;
+                    }
+                }
+                Op::Bind(pat) => {
+                    let value = self.pop();
+                    let mut bindings = vec![];
+                    bind_pattern(&proto.pats[*pat], value, &mut bindings)?;
+                    for (slot, value) in bindings {
+                        self.define(proto, base, slot, value);
+                    }
+                }
+
+                Op::Call { argc, site } => {
+                    self.frames.last_mut().unwrap().ip = ip;
+                    is_frame_changed = true;
+                    let site = &proto.calls[*site];
+                    let args = self.pop_n(*argc);
+                    let func = match self.pop().kind {
+                        ValueKind::Function(func) => func,
+                        _ => unreachable!("the callee is checked before its arguments"),
+                    };
+                    self.call(&func, args, &site.places, site.prefix_span)?;
+                }
+                Op::CallFun { id, argc, site } => {
+                    self.frames.last_mut().unwrap().ip = ip;
+                    is_frame_changed = true;
+                    let site = &proto.calls[*site];
+                    let args = self.pop_n(*argc);
+                    match module.fun(*id) {
+                        Some(fun) => {
+                            let func = match function_value(fun) {
+                                ValueKind::Function(func) => func,
+                                _ => unreachable!(),
+                            };
+                            self.call(&func, args, &site.places, site.prefix_span)?;
+                        }
+                        // The function is not declared yet, so the call goes to an imported
+                        // function or the standard library like in the tree walker.
+                        None => self.call_named(module, args, site, span)?,
+                    }
+                }
+                Op::CallNamed { argc, site } => {
+                    self.frames.last_mut().unwrap().ip = ip;
+                    is_frame_changed = true;
+                    let site = &proto.calls[*site];
+                    let args = self.pop_n(*argc);
+                    self.call_named(module, args, site, span)?;
+                }
+                Op::CallLib { argc, site } => {
+                    self.frames.last_mut().unwrap().ip = ip;
+                    is_frame_changed = true;
+                    let site = &proto.lib_calls[*site];
+                    let args = self.pop_n(*argc);
+                    self.call_lib(module, args, site, span)?;
+                }
+                Op::CallMethod {
+                    place,
+                    method,
+                    argc,
+                } => {
+                    let args = self.pop_n(*argc);
+                    let kind = match place {
+                        Some(place) => {
+                            let place = &proto.places[*place];
+                            self.call_method(&closure, base, place, method, args, span)?
+                        }
+                        None => {
+                            let mut target = self.pop();
+                            eval_method_call(&mut target, None, method, args, span)?
+                        }
+                    };
+                    self.push(kind, span);
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    self.check_return(proto, &value)?;
+                    self.return_from_frame(value.kind)?;
+                    is_frame_changed = true;
+                }
+                Op::ImplicitReturn => {
+                    let sig = proto.sig.as_ref().unwrap();
+                    if let Some(output) = &sig.output {
+                        if output.kind != TyKind::Unit {
+                            return Err(vec![IError::MissingReturnStatement {
+                                ret_kw: Keyword::Yeet.as_ref().to_string(),
+                                expected: output.to_string(),
+                                expected_span: output.span,
+                                func_decl_span: sig.ident.span,
+                            }]);
+                        }
+                    }
+                    self.return_from_frame(ValueKind::Unit)?;
+                    is_frame_changed = true;
+                }
+
+                Op::Import(import) => {
+                    let mut env = module.env.borrow_mut();
+                    env.import_library(import, self.is_verbose)?;
+                }
+                Op::PushLibScope => module.env.borrow_mut().push_scope(),
+                Op::PopLibScope => module.env.borrow_mut().pop_scope(),
+                Op::Fail(index) => return Err(proto.errors[*index].clone()),
+                Op::Halt => return Ok(()),
+            }
+
+            if is_frame_changed {
+                let frame = self.frames.last().unwrap();
+                closure = Rc::clone(&frame.closure);
+                ip = frame.ip;
+                base = frame.base;
+            }
+        }
+    }
+
+    fn push(&mut self, kind: ValueKind, span: Span) {
+        self.stack.push(Value { kind, span });
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the stack should not be empty")
+    }
+
+    /// Pops the given number of values, in the order they were pushed.
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        let at = self.stack.len() - n;
+        self.stack.split_off(at)
+    }
+
+    fn define(&mut self, proto: &Proto, base: usize, slot: usize, value: Value) {
+        self.locals[base + slot] = Var {
+            val: Some(value),
+            assigned_span: Some(proto.locals[slot].span),
+            alias: None,
+        };
+    }
+
+    fn capture_upvalue(&mut self, at: usize) -> UpvalueRef {
+        let position = self
+            .open_upvalues
+            .partition_point(|upvalue| match &*upvalue.borrow() {
+                Upvalue::Open(index) => *index < at,
+                Upvalue::Closed(_) => unreachable!("closed upvalues are not open"),
+            });
+        if let Some(upvalue) = self.open_upvalues.get(position) {
+            if matches!(&*upvalue.borrow(), Upvalue::Open(index) if *index == at) {
+                return Rc::clone(upvalue);
+            }
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(at)));
+        self.open_upvalues.insert(position, Rc::clone(&upvalue));
+        upvalue
+    }
+
+    /// Moves the locals from the given index on into the upvalues that capture them.
+    fn close_upvalues(&mut self, from: usize) {
+        let position = self
+            .open_upvalues
+            .partition_point(|upvalue| match &*upvalue.borrow() {
+                Upvalue::Open(index) => *index < from,
+                Upvalue::Closed(_) => unreachable!("closed upvalues are not open"),
+            });
+        for upvalue in self.open_upvalues.split_off(position) {
+            let mut upvalue = upvalue.borrow_mut();
+            let at = match &*upvalue {
+                Upvalue::Open(at) => *at,
+                Upvalue::Closed(_) => unreachable!("closed upvalues are not open"),
+            };
+            *upvalue = Upvalue::Closed(mem::take(&mut self.locals[at]));
+        }
+    }
+
+    fn place_root<'a>(
+        &self,
+        closure: &'a Closure,
+        place: &Place,
+        base: usize,
+    ) -> (Root, &'a LocalInfo) {
+        match place.root {
+            VarRef::Local(slot) => (Root::Local(base + slot), &closure.proto.locals[slot]),
+            VarRef::Upvalue(index) => (
+                Root::Upvalue(Rc::clone(&closure.upvalues[index])),
+                &closure.proto.upvalues[index].local,
+            ),
+        }
+    }
+
+    fn with_var<R>(&mut self, root: &Root, f: impl FnOnce(&mut Var) -> R) -> R {
+        match root {
+            Root::Local(at) => f(&mut self.locals[*at]),
+            Root::Upvalue(upvalue) => {
+                let mut upvalue = upvalue.borrow_mut();
+                match &mut *upvalue {
+                    Upvalue::Open(at) => f(&mut self.locals[*at]),
+                    Upvalue::Closed(var) => f(var),
+                }
+            }
+        }
+    }
+
+    /// Follows a `mut` parameter to the place it refers to. The projections to the part passed
+    /// to the parameter come before the given ones.
+    fn resolve(
+        &mut self,
+        root: Root,
+        projections: Vec<(Projection, Span)>,
+    ) -> (Root, Vec<(Projection, Span)>) {
+        match self.with_var(&root, |var| var.alias.clone()) {
+            Some(alias) => {
+                let mut prefix = alias.projections.clone();
+                prefix.extend(projections);
+                (alias.root.clone(), prefix)
+            }
+            None => (root, projections),
+        }
+    }
+
+    /// Reads a variable, or the place it refers to if it is a `mut` parameter.
+    fn read(
+        &mut self,
+        root: &Root,
+        info: &LocalInfo,
+        span: Span,
+    ) -> Result<ValueKind, Vec<IError>> {
+        match self.with_var(root, |var| var.alias.clone()) {
+            Some(alias) => self.with_var(&alias.root, |var| {
+                let val = var.val.as_mut().unwrap();
+                Ok(project(val, alias.projections.clone())?.kind.clone())
+            }),
+            None => self.with_var(root, |var| read_var(var, info, span)),
+        }
+    }
+
+    /// Assigns a variable, or the place it refers to if it is a `mut` parameter.
+    fn assign(
+        &mut self,
+        root: &Root,
+        info: &LocalInfo,
+        value: Value,
+        span: Span,
+    ) -> Result<(), Vec<IError>> {
+        match self.with_var(root, |var| var.alias.clone()) {
+            Some(alias) => {
+                self.with_var(root, |var| check_assign(var, info, &value, span))?;
+                self.with_var(&alias.root, |var| {
+                    let val = var.val.as_mut().unwrap();
+                    project(val, alias.projections.clone())?.kind = value.kind;
+                    Ok(())
+                })
+            }
+            None => self.with_var(root, |var| assign_var(var, info, value, span)),
+        }
+    }
+
+    /// Pops the indices of the place, and resolves its projections.
+    fn pop_projections(&mut self, place: &Place) -> Result<Vec<(Projection, Span)>, Vec<IError>> {
+        let mut indices = self.pop_n(place.index_count());
+        let mut projections = Vec::with_capacity(place.projections.len());
+        for (projection, base_span) in &place.projections {
+            let projection = match projection {
+                PlaceProj::Index => {
+                    let index = indices.pop().unwrap();
+                    match index.kind {
+                        ValueKind::Int(i) => Projection::Index(i, index.span),
+                        _ => {
+                            return Err(vec![IError::MismatchedType {
+                                expected: TyKind::Int.to_string(),
+                                found: index.to_ty_kind().to_string(),
+                                span: index.span,
+                            }])
+                        }
+                    }
+                }
+                PlaceProj::Field(name, span) => Projection::Field(name.to_string(), *span),
+                PlaceProj::TupleField(index, span) => Projection::TupleField(*index, *span),
+            };
+            projections.push((projection, *base_span));
+        }
+        Ok(projections)
+    }
+
+    fn assign_place(
+        &mut self,
+        root: Root,
+        info: &LocalInfo,
+        place: &Place,
+        projections: Vec<(Projection, Span)>,
+        value: Value,
+        span: Span,
+    ) -> Result<(), Vec<IError>> {
+        let (root, projections) = self.resolve(root, projections);
+        self.with_var(&root, |var| {
+            let val = match var.val.as_mut() {
+                Some(val) => val,
+                None => {
+                    return Err(vec![IError::VariableIsNotInitialized {
+                        var_name: info.name.to_string(),
+                        declared_span: info.span,
+                        used_span: place.root_ident.span,
+                    }])
+                }
+            };
+
+            // Elements of an array can be modified through an immutable variable, but fields
+            // can't.
+            let mutates_field = projections
+                .iter()
+                .any(|(projection, _)| !matches!(projection, Projection::Index(..)));
+            if mutates_field && !info.is_mut {
+                return Err(vec![IError::MutateImmutableVariable {
+                    mut_kw: Keyword::Mut.as_ref().to_string(),
+                    var_name: info.name.to_string(),
+                    first_assign_span: var.assigned_span.expect("Variable must be assigned before"),
+                    second_assign_span: span,
+                    help_span: info.span,
+                }]);
+            }
+
+            let target = project(val, projections)?;
+            let (target_ty, value_ty) = (target.to_ty_kind(), value.to_ty_kind());
+            if target_ty != value_ty {
+                return Err(vec![IError::MismatchedType {
+                    expected: target_ty.to_string(),
+                    found: value_ty.to_string(),
+                    span,
+                }]);
+            }
+
+            target.kind = value.kind;
+            Ok(())
+        })
+    }
+
+    /// Pops the indices of the place, and calls the method on the array it refers to.
+    fn call_method(
+        &mut self,
+        closure: &Closure,
+        base: usize,
+        place: &Place,
+        method: &ast::Ident,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<ValueKind, Vec<IError>> {
+        let projections = self.pop_projections(place)?;
+        let (root, info) = self.place_root(closure, place, base);
+        // A `mut` parameter has the type it declares, rather than the type of the place it
+        // refers to.
+        let declared_ty = info.ty.as_ref().map(|ty| project_ty(ty, &projections));
+        let (root, projections) = self.resolve(root, projections);
+        self.with_var(&root, |var| {
+            let val = match var.val.as_mut() {
+                Some(val) => val,
+                None => {
+                    return Err(vec![IError::VariableIsNotInitialized {
+                        var_name: info.name.to_string(),
+                        declared_span: info.span,
+                        used_span: place.root_ident.span,
+                    }])
+                }
+            };
+
+            let is_mut = info.is_mut || !needs_mut(&projections);
+            // A local without a declared type has the type of its value.
+            let declared_ty =
+                declared_ty.unwrap_or_else(|| project_ty(&val.to_ty_kind(), &projections));
+            let target = project(val, projections)?;
+            if !is_mut && method_mutates(&target.kind) {
+                return Err(vec![IError::MutateImmutableVariable {
+                    mut_kw: Keyword::Mut.as_ref().to_string(),
+                    var_name: info.name.to_string(),
+                    first_assign_span: var.assigned_span.expect("Variable must be assigned before"),
+                    second_assign_span: span,
+                    help_span: info.span,
+                }]);
+            }
+            eval_method_call(target, declared_ty, method, args, span)
+        })
+    }
+
+    /// Returns the parameter an argument is passed to, if it is `mut`.
+    fn callee_param(
+        &self,
+        module: &Module,
+        proto: &Proto,
+        callee: Callee,
+        arg: usize,
+        index_count: usize,
+    ) -> Option<FuncParam> {
+        let sig = match callee {
+            Callee::Stack => {
+                // The function is below the arguments pushed before this one, and the indices
+                // of the place.
+                let at = self.stack.len() - index_count - arg - 1;
+                match &self.stack[at].kind {
+                    ValueKind::Function(func) => func.sig.clone(),
+                    _ => return None,
+                }
+            }
+            Callee::Fun(id) => {
+                let sig = module.program.funs[id].sig.clone()?;
+                match module.declared.borrow()[id] {
+                    true => sig,
+                    false => imported_user_sig(module, sig.ident.name.as_str())?,
+                }
+            }
+            Callee::Named(site) => imported_user_sig(module, proto.calls[site].name.as_str())?,
+            Callee::Lib(site) => {
+                let site = &proto.lib_calls[site];
+                let env = module.env.borrow();
+                let (_, lib, _) = env.lookup_library(site.lib.name.as_str())?;
+                match lib.get_user_function(site.func.name.as_str())? {
+                    ValueKind::Function(func) => func.sig.clone(),
+                    _ => return None,
+                }
+            }
+        };
+
+        sig.inputs.into_iter().nth(arg).filter(|param| param.is_mut)
+    }
+
+    /// Pops the places passed by reference to the `mut` parameters of a call. An argument that is
+    /// not a place cannot be passed to a `mut` parameter.
+    fn take_ref_args(
+        &mut self,
+        sig: &FuncSig,
+        args: &[Value],
+        places: &[bool],
+    ) -> Result<Vec<(usize, RefTarget)>, Vec<IError>> {
+        let mut slots = vec![];
+        for (i, (param, arg)) in sig.inputs.iter().zip(args).enumerate() {
+            if !param.is_mut {
+                continue;
+            }
+            if !places[i] {
+                return Err(vec![IError::PassImmutableAsMutable {
+                    param_name: Some(param.ident.name.to_string()),
+                    mut_kw: Keyword::Mut.as_ref().to_string(),
+                    var_name: None,
+                    arg_span: arg.span,
+                    param_span: param.span,
+                    help_span: None,
+                }]);
+            }
+            slots.push(i);
+        }
+
+        let at = self.ref_args.len() - slots.len();
+        let targets = self.ref_args.split_off(at);
+        Ok(slots.into_iter().zip(targets).collect())
+    }
+
+    fn call(
+        &mut self,
+        func: &Func,
+        args: Vec<Value>,
+        places: &[bool],
+        prefix_span: Span,
+    ) -> Result<(), Vec<IError>> {
+        let ref_params = self.take_ref_args(&func.sig, &args, places)?;
+        let closure = match &func.body {
+            FuncBody::Bytecode(closure) => Rc::clone(closure),
+            FuncBody::Tree(..) => {
+                return Err(vec![IError::PredefinedError {
+                    span: prefix_span,
+                    message: "A function of the tree walker cannot be called by the VM".to_string(),
+                }])
+            }
+        };
+
+        check_args(&func.sig, &args, prefix_span)?;
+        // The frame of the top-level code is not a call.
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err(vec![IError::CallDepthExceeded {
+                func_name: func.sig.ident.name.clone(),
+                limit: MAX_CALL_DEPTH,
+                span: prefix_span,
+            }]);
+        }
+
+        let proto = &closure.proto;
+        let base = self.locals.len();
+        self.locals
+            .resize(base + proto.locals.len(), Var::default());
+        for ((param, arg), local) in func
+            .sig
+            .inputs
+            .iter()
+            .zip(args)
+            .zip(&mut self.locals[base..])
+        {
+            *local = Var {
+                val: Some(arg),
+                assigned_span: Some(param.ident.span),
+                alias: None,
+            };
+        }
+        // A `mut` parameter refers to the place passed to it.
+        for (slot, target) in ref_params {
+            let local = &mut self.locals[base + slot];
+            local.val = None;
+            local.alias = Some(Rc::new(target));
+        }
+
+        let lib_depth = closure.module.env.borrow().scopes.len();
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base,
+            stack_base: self.stack.len(),
+            lib_depth,
+            call_span: prefix_span,
+        });
+        Ok(())
+    }
+
+    /// Calls a function that is not declared in the module: a function imported by name, or else
+    /// a function of the standard library.
+    fn call_named(
+        &mut self,
+        module: &Module,
+        args: Vec<Value>,
+        site: &chunk::CallSite,
+        span: Span,
+    ) -> Result<(), Vec<IError>> {
+        let chunk::CallSite {
+            name,
+            name_span,
+            prefix_span,
+            places,
+        } = site;
+        let (name_span, prefix_span) = (*name_span, *prefix_span);
+
+        let imported = module
+            .env
+            .borrow()
+            .lookup_imported_function(name)
+            .map(|(_, lib)| Rc::clone(lib));
+        if let Some(lib) = imported {
+            if let Some(ValueKind::Function(func)) = lib.get_user_function(name).cloned() {
+                return self.call(&func, args, places, prefix_span);
+            }
+
+            let func = lib
+                .get_function(name)
+                .expect("the library should have the imported function");
+            let cattrs = CallerAttrs { span, prefix_span };
+            let kind = func.call(cattrs, args)?;
+            self.push(kind, span);
+            return Ok(());
+        }
+
+        let env = module.env.borrow();
+        let lib = env
+            .lookup_default_library("std")
+            .expect("Standard library must be loaded");
+        let func = match lib.get_function(name) {
+            Some(func) => func,
+            None => {
+                return Err(vec![IError::FunctionNotInScope {
+                    function: name.to_string(),
+                    span: name_span,
+                }])
+            }
+        };
+
+        let cattrs = CallerAttrs { span, prefix_span };
+        let kind = func.call(cattrs, args)?;
+        drop(env);
+        self.push(kind, span);
+        Ok(())
+    }
+
+    fn call_lib(
+        &mut self,
+        module: &Module,
+        args: Vec<Value>,
+        site: &chunk::LibCallSite,
+        span: Span,
+    ) -> Result<(), Vec<IError>> {
+        let lib_name = site.lib.name.as_str();
+        let func_name = site.func.name.as_str();
+
+        let (lib, lib_path) = match module.env.borrow().lookup_library(lib_name) {
+            Some((_, lib, lib_path)) => (Rc::clone(lib), lib_path.clone()),
+            None => {
+                return Err(vec![IError::LibraryNotFound {
+                    library: lib_name.to_string(),
+                    span: site.lib.span,
+                }])
+            }
+        };
+        let prefix_span = site.lib.span.to(site.func.span);
+        // A function written in Pandora was compiled with its library, and runs in it.
+        if let Some(ValueKind::Function(func)) = lib.get_user_function(func_name).cloned() {
+            return self.call(&func, args, &site.places, prefix_span);
+        }
+        let func = match lib.get_function(func_name) {
+            Some(func) => func,
+            None => {
+                return Err(vec![function_not_found_error(
+                    lib.as_ref(),
+                    &lib_path,
+                    func_name,
+                    site.func.span,
+                )])
+            }
+        };
+
+        let cattrs = CallerAttrs { span, prefix_span };
+        let kind = func.call(cattrs, args)?;
+        self.push(kind, span);
+        Ok(())
+    }
+
+    fn check_return(&self, proto: &Proto, value: &Value) -> Result<(), Vec<IError>> {
+        let sig = proto.sig.as_ref().unwrap();
+        let value_ty = value.to_ty_kind();
+        match &sig.output {
+            Some(output) if value_ty != output.kind => {
+                Err(vec![IError::MismatchedFunctionReturnType {
+                    expected: output.to_string(),
+                    found: value_ty.to_string(),
+                    expected_span: output.span,
+                    found_span: value.span,
+                }])
+            }
+            None if value_ty != TyKind::Unit => Err(vec![IError::MissingFunctionReturnType {
+                found: value_ty.to_string(),
+                ret_help_span: Span::after(sig.span),
+                found_span: value.span,
+            }]),
+            _ => Ok(()),
+        }
+    }
+
+    /// Leaves the current function, and pushes the returned value for the caller.
+    fn return_from_frame(&mut self, kind: ValueKind) -> Result<(), Vec<IError>> {
+        let frame = self.frames.pop().unwrap();
+
+        self.close_upvalues(frame.base);
+        self.locals.truncate(frame.base);
+        self.stack.truncate(frame.stack_base);
+        let mut env = frame.closure.module.env.borrow_mut();
+        while env.scopes.len() > frame.lib_depth {
+            env.pop_scope();
+        }
+        drop(env);
+
+        // The value gets the span of the call expression.
+        let caller = self.frames.last().unwrap();
+        let span = caller.closure.proto.spans[caller.ip - 1];
+        self.push(kind, span);
+        Ok(())
+    }
+}
+
+/// Compiles a library and runs its top-level code. Its functions keep the environment of the
+/// library, where the libraries it adds are, and run on the VM calling them.
+pub fn load_library(
+    ast: &Ast,
+    env: Environment,
+    private_functions: HashSet<String>,
+    is_verbose: bool,
+) -> Result<ExternalLibrary, Vec<IError>> {
+    let program = compile(ast);
+    let mut vm = Vm::new(&program, env, is_verbose);
+    vm.run()?;
+
+    let functions = program
+        .top_funs
+        .iter()
+        .filter_map(|(name, id)| Some((name.to_string(), function_value(vm.module.fun(*id)?))))
+        .collect();
+    let mut constants = HashMap::new();
+    let mut variables = HashSet::new();
+    for (info, value) in vm.globals() {
+        // The mutable variables are only used by the top-level code of the library.
+        if info.is_mut {
+            variables.insert(info.name.to_string());
+        } else if let Some(value) = value {
+            constants.insert(info.name.to_string(), value.clone());
+        }
+    }
+    // The closures created by the top-level code outlive the locals they captured.
+    vm.close_upvalues(0);
+
+    Ok(ExternalLibrary::compiled(
+        functions,
+        private_functions,
+        constants,
+        variables,
+    ))
+}
+
+/// Returns the signature of the function imported by name in the module, if it is written in
+/// Pandora.
+fn imported_user_sig(module: &Module, name: &str) -> Option<FuncSig> {
+    let env = module.env.borrow();
+    let (_, lib) = env.lookup_imported_function(name)?;
+    match lib.get_user_function(name)? {
+        ValueKind::Function(func) => Some(func.sig.clone()),
+        _ => None,
+    }
+}
+
+fn function_value(closure: Rc<Closure>) -> ValueKind {
+    let sig = closure
+        .proto
+        .sig
+        .clone()
+        .expect("a compiled function has a signature");
+    ValueKind::Function(Box::new(Func {
+        sig,
+        body: FuncBody::Bytecode(closure),
+    }))
+}
+
+/// Applies an operator to two integers in place, as most arithmetic in a hot loop does. Anything
+/// else, including a division by zero or an overflow, goes through `eval_binary`.
+fn int_binary(op: &BinOpKind, lhs: &ValueKind, rhs: &ValueKind) -> Option<ValueKind> {
+    let (ValueKind::Int(lhs), ValueKind::Int(rhs)) = (lhs, rhs) else {
+        return None;
+    };
+    let kind = match op {
+        BinOpKind::Add => ValueKind::Int(lhs.checked_add(*rhs)?),
+        BinOpKind::Sub => ValueKind::Int(lhs.checked_sub(*rhs)?),
+        BinOpKind::Mul => ValueKind::Int(lhs.checked_mul(*rhs)?),
+        BinOpKind::Div => ValueKind::Int(lhs.checked_div(*rhs)?),
+        BinOpKind::Mod => ValueKind::Int(lhs.checked_rem(*rhs)?),
+        BinOpKind::Eq => ValueKind::Bool(lhs == rhs),
+        BinOpKind::Ne => ValueKind::Bool(lhs != rhs),
+        BinOpKind::Lt => ValueKind::Bool(lhs < rhs),
+        BinOpKind::Le => ValueKind::Bool(lhs <= rhs),
+        BinOpKind::Gt => ValueKind::Bool(lhs > rhs),
+        BinOpKind::Ge => ValueKind::Bool(lhs >= rhs),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn hidden_var(kind: ValueKind, span: Span) -> Var {
+    Var {
+        val: Some(Value { kind, span }),
+        assigned_span: Some(span),
+        alias: None,
+    }
+}
+
+fn read_var(var: &Var, info: &LocalInfo, span: Span) -> Result<ValueKind, Vec<IError>> {
+    match &var.val {
+        Some(value) => Ok(value.kind.clone()),
+        None => Err(vec![IError::VariableIsNotInitialized {
+            var_name: info.name.to_string(),
+            declared_span: info.span,
+            used_span: span,
+        }]),
+    }
+}
+
+fn assign_var(
+    var: &mut Var,
+    info: &LocalInfo,
+    value: Value,
+    span: Span,
+) -> Result<(), Vec<IError>> {
+    check_assign(var, info, &value, span)?;
+    var.val = Some(value);
+    var.assigned_span = Some(span);
+    Ok(())
+}
+
+/// Checks that a value can be assigned to a variable: the variable is mutable or not assigned
+/// yet, and the value has its type.
+fn check_assign(var: &Var, info: &LocalInfo, value: &Value, span: Span) -> Result<(), Vec<IError>> {
+    if !(info.is_mut || var.val.is_none()) {
+        return Err(vec![IError::MutateImmutableVariable {
+            mut_kw: Keyword::Mut.as_ref().to_string(),
+            var_name: info.name.to_string(),
+            first_assign_span: var.assigned_span.expect("Variable must be assigned before"),
+            second_assign_span: span,
+            help_span: info.span,
+        }]);
+    }
+
+    let expected = match (&info.ty, &var.val) {
+        (Some(ty), _) => ty.clone(),
+        (None, Some(current)) => current.to_ty_kind(),
+        (None, None) => unreachable!("a local without a declared type has a value"),
+    };
+    let value_ty = value.to_ty_kind();
+    if expected != value_ty {
+        return Err(vec![IError::MismatchedType {
+            expected: expected.to_string(),
+            found: value_ty.to_string(),
+            span,
+        }]);
+    }
+    Ok(())
+}
+
+/// Checks the arguments of a call against the parameters of the function.
+fn check_args(sig: &FuncSig, args: &[Value], prefix_span: Span) -> Result<(), Vec<IError>> {
+    let mut mismatch_params = vec![];
+    for (param, arg) in sig.inputs.iter().zip(args) {
+        let arg_ty = arg.to_ty_kind();
+        if param.ty.kind != arg_ty {
+            mismatch_params.push((arg.span, param.ty.to_string(), arg_ty.to_string()));
+        }
+    }
+
+    let missing_param_tys: Vec<String> = sig
+        .inputs
+        .iter()
+        .skip(args.len())
+        .map(|param| param.ty.to_string())
+        .collect();
+    let unexpected_param_tys: Vec<(Span, String)> = args
+        .iter()
+        .skip(sig.inputs.len())
+        .map(|arg| (arg.span, arg.to_ty_kind().to_string()))
+        .collect();
+
+    if !mismatch_params.is_empty() || !unexpected_param_tys.is_empty() {
+        return Err(vec![IError::FunctionParamMismatch {
+            func_decl_span: Some(sig.ident.span),
+            args: args.len(),
+            mismatch_params,
+            missing_param_tys,
+            unexpected_param_tys,
+            prefix_span,
+        }]);
+    }
+    Ok(())
+}
+
+/// Matches the value against a pattern, collecting the values bound by the pattern.
+fn match_pattern(pat: &Pattern, value: &Value, bindings: &mut Vec<(usize, Value)>) -> bool {
+    match (pat, &value.kind) {
+        (Pattern::Wild, _) => true,
+        (Pattern::Bind(slot), _) => {
+            bindings.push((*slot, value.clone()));
+            true
+        }
+        (Pattern::Lit(lit), kind) => match (lit, kind) {
+            (ValueKind::Int(lit), ValueKind::Int(val)) => lit == val,
+            (ValueKind::Float(lit), ValueKind::Float(val)) => lit == val,
+            (ValueKind::Str(lit), ValueKind::Str(val)) => lit == val,
+            (ValueKind::Char(lit), ValueKind::Char(val)) => lit == val,
+            (ValueKind::Bool(lit), ValueKind::Bool(val)) => lit == val,
+            _ => false,
+        },
+        (Pattern::Tuple(pats), ValueKind::Tuple(values)) => pats
+            .iter()
+            .zip(values)
+            .all(|(pat, value)| match_pattern(pat, value, bindings)),
+        (Pattern::Tuple(pats), ValueKind::Unit) => pats.is_empty(),
+        (Pattern::Variant(variant, pats), ValueKind::Enum(_, value_variant, values)) => {
+            variant == value_variant
+                && pats
+                    .iter()
+                    .zip(values)
+                    .all(|(pat, value)| match_pattern(pat, value, bindings))
+        }
+        _ => false,
+    }
+}
+
+/// Binds an irrefutable pattern to the value, e.g. in `set (a, b) = (1, 2);`.
+fn bind_pattern(
+    pat: &Pattern,
+    value: Value,
+    bindings: &mut Vec<(usize, Value)>,
+) -> Result<(), Vec<IError>> {
+    match pat {
+        Pattern::Wild => Ok(()),
+        Pattern::Bind(slot) => {
+            bindings.push((*slot, value));
+            Ok(())
+        }
+        Pattern::Tuple(pats) => {
+            let value_ty = value.to_ty_kind();
+            let elements = match value.kind {
+                ValueKind::Tuple(elements) if elements.len() == pats.len() => elements,
+                ValueKind::Unit if pats.is_empty() => vec![],
+                _ => {
+                    return Err(vec![IError::MismatchedType {
+                        expected: format!("a tuple with {} elements", pats.len()),
+                        found: value_ty.to_string(),
+                        span: value.span,
+                    }])
+                }
+            };
+
+            for (pat, element) in pats.iter().zip(elements) {
+                bind_pattern(pat, element, bindings)?;
+            }
+            Ok(())
+        }
+        Pattern::Lit(_) | Pattern::Variant(..) => {
+            unreachable!("parser only allows irrefutable patterns in a variable declaration")
+        }
+    }
+}
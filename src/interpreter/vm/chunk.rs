@@ -0,0 +1,341 @@
+//! The bytecode run by the VM, as produced by the compiler.
+
+use core::fmt;
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use crate::{
+    ast::{self, BinOp, UnOp},
+    span_encoding::Span,
+};
+
+use super::super::{errors::IError, ty::TyKind, FuncSig, StructDef, Ty, TypeDef, ValueKind};
+
+/// A compiled program: the top level, and every named function declared in it.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub main: Rc<Proto>,
+    /// The named functions, indexed by their id.
+    pub funs: Vec<Rc<Proto>>,
+    /// The ids of the named functions declared at the top level, which a library exports.
+    pub top_funs: HashMap<String, usize>,
+    /// The slots of the locals declared at the top level, in the order they are declared.
+    pub top_locals: Vec<usize>,
+}
+
+/// An instruction. Every instruction that pushes a value gives it the span of the expression it
+/// was compiled from, which is stored next to it in [`Proto::spans`].
+#[derive(Debug, Clone)]
+pub enum Op {
+    /// Pushes the constant at the given index.
+    Constant(usize),
+    /// Pushes `()`.
+    Unit,
+    /// Discards the value on top of the stack.
+    Pop,
+
+    /// Pushes the value of the local in the given slot.
+    GetLocal(usize),
+    /// Pops a value into the local in the given slot, which is being declared.
+    DefineLocal(usize),
+    /// Declares the local in the given slot without a value.
+    DeclareLocal(usize),
+    /// Pops a value and assigns it to the local in the given slot, then pushes `()`.
+    SetLocal(usize),
+    GetUpvalue(usize),
+    SetUpvalue(usize),
+    /// Closes the upvalues of the locals from the given slot on, as they go out of scope.
+    CloseUpvalues(usize),
+    /// Pops the indices of the place at the given index, then the value to assign to it, and
+    /// pushes `()`.
+    SetPlace(usize),
+    /// Pops the indices of the place at the given index, and pushes the value it refers to as the
+    /// argument at the given position of a call. The argument is passed by reference if its
+    /// parameter is `mut`.
+    PlaceArg {
+        place: usize,
+        callee: Callee,
+        arg: usize,
+    },
+
+    /// Checks that the value on top of the stack has the type at the given index.
+    CheckTy(usize),
+    /// Pops the length of an array type, which must be a non-negative integer.
+    CheckLen,
+    /// Checks that the value on top of the stack is a function, before its arguments are pushed.
+    CheckCallable,
+
+    /// Declares the named function with the given id, so that it can be called.
+    DeclareFun(usize),
+    /// Pushes the named function with the given id.
+    GetFun(usize),
+    /// Pushes a closure of the function at the given index in [`Proto::protos`].
+    MakeClosure(usize),
+
+    Binary(BinOp),
+    Unary(UnOp),
+    /// Casts the value on top of the stack to the type at the given index.
+    Cast(usize),
+    /// Pops the given number of elements into an array.
+    MakeArray(usize),
+    /// Pops a count and an element, and pushes an array repeating the element.
+    Repeat,
+    /// Pops an index and an array, and pushes the element at the index.
+    Index,
+    /// Pops the end and the start of a range, and pushes the range.
+    MakeRange(ast::RangeLimits),
+    /// Pops the given number of elements into a tuple.
+    MakeTuple(usize),
+    /// Replaces the tuple on top of the stack with its field, with the span of the field index.
+    TupleField(usize, Span),
+    /// Replaces the struct on top of the stack with its field.
+    Field(ast::Ident),
+    /// Pushes the constant of a library, e.g. `config.SIZE`.
+    LibConstant(ast::Ident, ast::Ident),
+    /// Pops the fields of the struct literal at the given index.
+    MakeStruct(usize),
+    /// Pops the payload of the enum variant at the given index.
+    MakeVariant(usize),
+
+    Jump(usize),
+    /// Pops a condition, and jumps if it is false.
+    JumpIfFalse(usize),
+    /// Jumps if the left operand of `&&` or `||` decides the result, leaving it as the result.
+    /// Otherwise the right operand is evaluated, and both are popped by the binary operation.
+    ShortCircuit(ast::BinOpKind, usize),
+    /// Pops an array, a string or a range into the iterator in the given slot (and the next one,
+    /// which holds the position of the iterator).
+    IntoIter(usize),
+    /// Moves the iterator to its next element, which is put into a local, or jumps to the exit.
+    ForNext {
+        iter: usize,
+        var: usize,
+        exit: usize,
+    },
+    /// Checks that the patterns of the match statement at the given index cover the type of the
+    /// scrutinee, which is in the given slot.
+    CheckMatch {
+        site: usize,
+        slot: usize,
+    },
+    /// Binds the pattern at the given index to the scrutinee in the given slot, or jumps to the
+    /// next arm if it does not match.
+    MatchArm {
+        slot: usize,
+        pat: usize,
+        next: usize,
+    },
+    /// Pops a value and binds it to the irrefutable pattern at the given index.
+    Bind(usize),
+
+    /// Pops the arguments and the function to call, described by the call site at the given
+    /// index.
+    Call {
+        argc: usize,
+        site: usize,
+    },
+    /// Pops the arguments of a call to the named function with the given id.
+    CallFun {
+        id: usize,
+        argc: usize,
+        site: usize,
+    },
+    /// Pops the arguments of a call to a function imported by name, or else of the standard
+    /// library.
+    CallNamed {
+        argc: usize,
+        site: usize,
+    },
+    /// Pops the arguments of a call to the library function at the given index.
+    CallLib {
+        argc: usize,
+        site: usize,
+    },
+    /// Pops the arguments of a call to a method, then the indices of the place at the given
+    /// index, and calls the method on the value the place refers to. Without a place, the method
+    /// is called on a temporary value, popped after the arguments.
+    CallMethod {
+        place: Option<usize>,
+        method: ast::Ident,
+        argc: usize,
+    },
+    /// Pops the value returned by the current function.
+    Return,
+    /// Returns from a function whose body ends without a return statement.
+    ImplicitReturn,
+
+    Import(Box<ast::Import>),
+    /// Opens a scope for the libraries imported in a block.
+    PushLibScope,
+    PopLibScope,
+    /// Stops with the errors at the given index.
+    Fail(usize),
+    Halt,
+}
+
+/// The function whose parameter decides whether an argument is passed by reference.
+#[derive(Debug, Clone, Copy)]
+pub enum Callee {
+    /// The function below the arguments on the stack.
+    Stack,
+    /// The named function with the given id.
+    Fun(usize),
+    /// The library function at the given index.
+    Lib(usize),
+    /// The function that is not declared in the program, called at the given index: a function
+    /// imported by name, or one of the standard library.
+    Named(usize),
+}
+
+/// Where a variable lives.
+#[derive(Debug, Clone, Copy)]
+pub enum VarRef {
+    Local(usize),
+    Upvalue(usize),
+}
+
+/// A compiled function, or the top level of the program.
+#[derive(Debug, Default)]
+pub struct Proto {
+    pub name: String,
+    /// The signature of the function, or `None` for the top level.
+    pub sig: Option<FuncSig>,
+    pub code: Vec<Op>,
+    /// The span of the expression or statement each instruction was compiled from.
+    pub spans: Vec<Span>,
+    pub constants: Vec<ValueKind>,
+    /// The locals, indexed by their slot. The parameters come first.
+    pub locals: Vec<LocalInfo>,
+    /// The variables a closure captures from the functions around it.
+    pub upvalues: Vec<UpvalueInfo>,
+    pub tys: Vec<Ty>,
+    pub places: Vec<Place>,
+    pub calls: Vec<CallSite>,
+    pub lib_calls: Vec<LibCallSite>,
+    pub structs: Vec<StructSite>,
+    pub variants: Vec<VariantSite>,
+    pub matches: Vec<MatchSite>,
+    pub pats: Vec<Pattern>,
+    pub errors: Vec<Vec<IError>>,
+    /// The closures created in the function.
+    pub protos: Vec<Rc<Proto>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalInfo {
+    pub name: String,
+    /// The span of the identifier the local is declared with.
+    pub span: Span,
+    pub is_mut: bool,
+    /// The type a value assigned to the local must have, or `None` if it is the type of the
+    /// value the local was declared with.
+    pub ty: Option<TyKind>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpvalueInfo {
+    /// Whether the captured variable is a local of the enclosing function, rather than one of
+    /// its upvalues.
+    pub is_local: bool,
+    pub index: usize,
+    pub local: LocalInfo,
+}
+
+/// A part of a variable, e.g. `foo.bar[2].0`, whose indices are evaluated on the stack.
+#[derive(Debug, Clone)]
+pub struct Place {
+    pub root: VarRef,
+    pub root_ident: ast::Ident,
+    /// The projections from the variable, each with the span of the projected expression.
+    pub projections: Vec<(PlaceProj, Span)>,
+}
+
+impl Place {
+    pub fn index_count(&self) -> usize {
+        self.projections
+            .iter()
+            .filter(|(proj, _)| matches!(proj, PlaceProj::Index))
+            .count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PlaceProj {
+    Index,
+    Field(String, Span),
+    TupleField(usize, Span),
+}
+
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    pub name: String,
+    pub name_span: Span,
+    pub prefix_span: Span,
+    /// For each argument, whether it is a place that can be passed by reference.
+    pub places: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LibCallSite {
+    pub lib: ast::Ident,
+    pub func: ast::Ident,
+    pub places: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructSite {
+    pub def: StructDef,
+    /// The index in the declaration of each field, in the order of the literal.
+    pub fields: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantSite {
+    pub enum_name: String,
+    pub variant: String,
+    pub field_tys: Vec<Ty>,
+    /// The number of values given to the variant, which may not match its fields.
+    pub argc: usize,
+}
+
+#[derive(Debug)]
+pub struct MatchSite {
+    pub pats: Vec<ast::Pat>,
+    /// The types visible at the match statement, to check the patterns against.
+    pub types: Vec<(String, (Span, TypeDef))>,
+    pub scrutinee_span: Span,
+    /// Whether the patterns were already found to be exhaustive.
+    pub checked: Cell<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wild,
+    /// Binds the value to the local in the given slot.
+    Bind(usize),
+    Lit(ValueKind),
+    Tuple(Vec<Pattern>),
+    Variant(String, Vec<Pattern>),
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.main)?;
+        for proto in &self.funs {
+            write!(f, "{}", proto)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Proto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== {} ==", self.name)?;
+        for (i, (op, span)) in self.code.iter().zip(&self.spans).enumerate() {
+            writeln!(f, "{:04} {:<12} {:?}", i, span.to_string(), op)?;
+        }
+        for proto in &self.protos {
+            write!(f, "{}", proto)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1500 @@
+//! Compiles the syntax tree into bytecode. Names are resolved here: a variable becomes the slot of
+//! a local or the index of an upvalue, and a named function becomes an id.
+//!
+//! An error that the tree walker only reports when it runs the faulty code (e.g. a variable that
+//! is not in scope) is compiled into a `Fail` instruction, so the VM reports it at the same point.
+
+use std::{collections::HashMap, ptr, rc::Rc};
+
+use crate::{
+    ast::{
+        self, Ast, BinOpKind, Expr, ExprField, ExprKind, Fun, FunParam, Local, LocalKind, Pat,
+        PatKind, Stmt, StmtKind,
+    },
+    kw::{self, Keyword},
+    span_encoding::{Span, DUMMY_SP},
+};
+
+use super::{
+    super::{
+        environment::Environment, errors::IError, ident::Ident, interpret_expr,
+        interpret_expr_literal, is_place, ty::TyKind, EnumDef, FuncParam, FuncSig, StructDef, Ty,
+        TypeDef, ValueKind,
+    },
+    chunk::{
+        CallSite, Callee, LibCallSite, LocalInfo, MatchSite, Op, Pattern, Place, PlaceProj,
+        Program, Proto, StructSite, UpvalueInfo, VarRef, VariantSite,
+    },
+};
+
+pub fn compile(ast: &Ast) -> Program {
+    let mut compiler = Compiler::new();
+    compiler.compile_stmts(&ast.stmts);
+    compiler.compile_deferred();
+    compiler.emit(Op::Halt, DUMMY_SP);
+
+    let top_funs = compiler.scopes[0]
+        .funs
+        .iter()
+        .map(|(name, (id, _))| (name.to_string(), *id))
+        .collect();
+    let main = compiler.states.pop().unwrap();
+    let top_locals = main.locals.iter().map(|local| local.slot).collect();
+    let funs = compiler
+        .funs
+        .into_iter()
+        .map(|fun| fun.expect("every named function is compiled at the end of its block"))
+        .collect();
+    Program {
+        main: Rc::new(main.proto),
+        funs,
+        top_funs,
+        top_locals,
+    }
+}
+
+struct LocalVar {
+    name: String,
+    slot: usize,
+    depth: usize,
+    /// Whether a closure captures the local, so that its upvalue must be closed when the local
+    /// goes out of scope.
+    is_captured: bool,
+}
+
+struct Loop {
+    /// The first slot declared in the loop, from which upvalues are closed when leaving it.
+    first_slot: usize,
+    /// The number of library scopes open around the loop.
+    lib_scopes: usize,
+    start: usize,
+    /// The jumps of the `br` statements, patched to the end of the loop.
+    breaks: Vec<usize>,
+}
+
+/// The state of the function being compiled.
+struct FunState {
+    proto: Proto,
+    locals: Vec<LocalVar>,
+    depth: usize,
+    /// A closure sees the variables of the functions around it, a named function does not.
+    is_closure: bool,
+    in_function: bool,
+    loops: Vec<Loop>,
+    lib_scopes: usize,
+}
+
+impl FunState {
+    fn new(name: &str, sig: Option<FuncSig>, is_closure: bool) -> Self {
+        FunState {
+            in_function: sig.is_some(),
+            proto: Proto {
+                name: name.to_string(),
+                sig,
+                ..Proto::default()
+            },
+            locals: vec![],
+            depth: 0,
+            is_closure,
+            loops: vec![],
+            lib_scopes: 0,
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|local| local.name == name)
+            .map(|local| local.slot)
+    }
+
+    /// Marks the local in the given slot as captured, so that it is closed at the end of its
+    /// scope.
+    fn capture(&mut self, slot: usize) {
+        if let Some(local) = self
+            .locals
+            .iter_mut()
+            .rev()
+            .find(|local| local.slot == slot)
+        {
+            local.is_captured = true;
+        }
+    }
+}
+
+/// The named functions of a block. They are all known from the start of the block, and their
+/// bodies are compiled at its end, once every type of the block is declared.
+#[derive(Default)]
+struct FunScope<'ast> {
+    funs: HashMap<String, (usize, Span)>,
+    decls: Vec<(usize, &'ast Fun)>,
+    deferred: Vec<(usize, &'ast Fun)>,
+}
+
+struct Compiler<'ast> {
+    states: Vec<FunState>,
+    scopes: Vec<FunScope<'ast>>,
+    /// The user types in scope.
+    types: Environment,
+    funs: Vec<Option<Rc<Proto>>>,
+    /// The signatures of the named functions whose body is not compiled yet.
+    fun_sigs: Vec<Option<FuncSig>>,
+}
+
+impl<'ast> Compiler<'ast> {
+    fn new() -> Self {
+        Compiler {
+            states: vec![FunState::new("<main>", None, false)],
+            scopes: vec![FunScope::default()],
+            types: Environment::new(),
+            funs: vec![],
+            fun_sigs: vec![],
+        }
+    }
+
+    fn state(&mut self) -> &mut FunState {
+        self.states.last_mut().unwrap()
+    }
+
+    fn proto(&mut self) -> &mut Proto {
+        &mut self.state().proto
+    }
+
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        let proto = self.proto();
+        proto.code.push(op);
+        proto.spans.push(span);
+        proto.code.len() - 1
+    }
+
+    fn fail(&mut self, errors: Vec<IError>, span: Span) {
+        let proto = self.proto();
+        proto.errors.push(errors);
+        let index = proto.errors.len() - 1;
+        self.emit(Op::Fail(index), span);
+    }
+
+    fn here(&mut self) -> usize {
+        self.proto().code.len()
+    }
+
+    /// Makes the jump at the given position go to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.proto().code[at] {
+            Op::Jump(to) | Op::JumpIfFalse(to) | Op::ShortCircuit(_, to) => *to = target,
+            Op::ForNext { exit, .. } => *exit = target,
+            Op::MatchArm { next, .. } => *next = target,
+            op => unreachable!("{:?} is not a jump", op),
+        }
+    }
+
+    fn add_local(&mut self, name: &str, span: Span, is_mut: bool, ty: Option<TyKind>) -> usize {
+        let state = self.state();
+        let slot = state.proto.locals.len();
+        state.proto.locals.push(LocalInfo {
+            name: name.to_string(),
+            span,
+            is_mut,
+            ty,
+        });
+        state.locals.push(LocalVar {
+            name: name.to_string(),
+            slot,
+            depth: state.depth,
+            is_captured: false,
+        });
+        slot
+    }
+
+    /// Adds a local that is not visible to the program, e.g. the iterator of a `for` loop.
+    fn add_hidden_local(&mut self, span: Span) -> usize {
+        self.add_local("", span, false, None)
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().depth += 1;
+        self.types.push_scope();
+        self.scopes.push(FunScope::default());
+    }
+
+    fn end_scope(&mut self, span: Span) {
+        self.compile_deferred();
+        self.scopes.pop();
+        self.types.pop_scope();
+
+        let state = self.state();
+        state.depth -= 1;
+        let mut first_captured = None;
+        while let Some(local) = state.locals.last() {
+            if local.depth <= state.depth {
+                break;
+            }
+            if local.is_captured {
+                first_captured = Some(local.slot);
+            }
+            state.locals.pop();
+        }
+        if let Some(slot) = first_captured {
+            self.emit(Op::CloseUpvalues(slot), span);
+        }
+    }
+
+    fn resolve_var(&mut self, name: &str) -> Option<VarRef> {
+        let level = self.states.len() - 1;
+        self.resolve_var_at(level, name)
+    }
+
+    fn resolve_var_at(&mut self, level: usize, name: &str) -> Option<VarRef> {
+        if let Some(slot) = self.states[level].resolve_local(name) {
+            return Some(VarRef::Local(slot));
+        }
+        if level == 0 || !self.states[level].is_closure {
+            return None;
+        }
+
+        let (is_local, index, local) = match self.resolve_var_at(level - 1, name)? {
+            VarRef::Local(slot) => {
+                let enclosing = &mut self.states[level - 1];
+                enclosing.capture(slot);
+                (true, slot, enclosing.proto.locals[slot].clone())
+            }
+            VarRef::Upvalue(index) => {
+                let local = self.states[level - 1].proto.upvalues[index].local.clone();
+                (false, index, local)
+            }
+        };
+
+        let upvalues = &mut self.states[level].proto.upvalues;
+        let existing = upvalues
+            .iter()
+            .position(|upvalue| upvalue.is_local == is_local && upvalue.index == index);
+        let index = existing.unwrap_or_else(|| {
+            upvalues.push(UpvalueInfo {
+                is_local,
+                index,
+                local,
+            });
+            upvalues.len() - 1
+        });
+        Some(VarRef::Upvalue(index))
+    }
+
+    fn lookup_fun(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.funs.get(name))
+            .map(|(id, _)| *id)
+    }
+
+    fn compile_stmts(&mut self, stmts: &'ast [Box<Stmt>]) {
+        for stmt in stmts {
+            if let StmtKind::FuncDecl(fun) = &stmt.kind {
+                let id = self.funs.len();
+                self.funs.push(None);
+                self.fun_sigs.push(None);
+
+                let scope = self.scopes.last_mut().unwrap();
+                scope.decls.push((id, fun));
+                let name = fun.sig.name.name.to_string();
+                scope.funs.entry(name).or_insert((id, fun.sig.name.span));
+            }
+        }
+
+        // The top level imports into the outermost scope of the libraries.
+        let has_import = self.state().depth > 0
+            && stmts
+                .iter()
+                .any(|stmt| matches!(stmt.kind, StmtKind::Import(_)));
+        if has_import {
+            self.emit(Op::PushLibScope, DUMMY_SP);
+            self.state().lib_scopes += 1;
+        }
+
+        for stmt in stmts {
+            self.compile_stmt(stmt);
+        }
+
+        if has_import {
+            self.emit(Op::PopLibScope, DUMMY_SP);
+            self.state().lib_scopes -= 1;
+        }
+    }
+
+    fn compile_block(&mut self, stmts: &'ast [Box<Stmt>], span: Span) {
+        self.begin_scope();
+        self.compile_stmts(stmts);
+        self.end_scope(span);
+    }
+
+    fn compile_deferred(&mut self) {
+        let deferred = std::mem::take(&mut self.scopes.last_mut().unwrap().deferred);
+        for (id, fun) in deferred {
+            self.compile_fun_body(id, fun);
+        }
+    }
+
+    fn compile_stmt(&mut self, stmt: &'ast Stmt) {
+        let span = stmt.span;
+        match &stmt.kind {
+            StmtKind::Import(import) => self.compile_import(import, span),
+            StmtKind::Expr(expr) => {
+                self.compile_expr(expr);
+                self.emit(Op::Pop, span);
+            }
+            StmtKind::Var(local) => self.compile_var(local),
+            StmtKind::If(cond, then_block, else_block) => {
+                self.compile_if(cond, then_block, else_block)
+            }
+            StmtKind::While(cond, block) => self.compile_while(cond, block),
+            StmtKind::Break => self.compile_break(span),
+            StmtKind::Continue => self.compile_continue(span),
+            StmtKind::Block(stmts) => self.compile_block(stmts, span),
+            StmtKind::FuncDecl(fun) => self.compile_fun_decl(fun, span),
+            StmtKind::StructDecl(def) => self.compile_struct_decl(def, span),
+            StmtKind::EnumDecl(def) => self.compile_enum_decl(def, span),
+            StmtKind::Match(scrutinee, arms) => self.compile_match(scrutinee, arms),
+            StmtKind::Return(expr) => self.compile_return(expr, span),
+            StmtKind::For(ident, expr, block) => self.compile_for(ident, expr, block),
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn compile_var(&mut self, local: &'ast Local) {
+        let Local {
+            is_mut,
+            pat,
+            ty,
+            kind,
+            span: _,
+        } = local;
+
+        let ident = match &pat.kind {
+            PatKind::Ident(ident) => ident,
+            _ => {
+                let init = match kind {
+                    LocalKind::Init(expr) => expr,
+                    LocalKind::Decl => {
+                        unreachable!("parser requires an initializer for a non-ident pattern")
+                    }
+                };
+                self.compile_expr(init);
+                if let Some(ty) = ty {
+                    let ty = self.compile_ty(ty);
+                    let index = self.add_ty(ty);
+                    self.emit(Op::CheckTy(index), init.span);
+                }
+                let pat = self.compile_pat(pat, *is_mut);
+                let pat = self.add_pat(pat);
+                self.emit(Op::Bind(pat), local.span);
+                return;
+            }
+        };
+        let ast_ty = ty
+            .as_ref()
+            .expect("parser requires a type for a non-tuple pattern");
+        let decl_ty = self.compile_ty(ast_ty);
+
+        match kind {
+            LocalKind::Init(expr) => {
+                self.compile_expr(expr);
+                let index = self.add_ty(decl_ty.clone());
+                self.emit(Op::CheckTy(index), expr.span);
+
+                // An array declared without a length can change its length, and one declared
+                // with a length has the length of its value, which is checked when the length is
+                // not a literal.
+                let is_fixed_array = matches!(ast_ty.kind, ast::TyKind::Array(_, Some(_)));
+                let ty = if is_fixed_array {
+                    None
+                } else {
+                    Some(decl_ty.kind)
+                };
+                let slot = self.add_local(ident.name.as_str(), ident.span, *is_mut, ty);
+                self.emit(Op::DefineLocal(slot), ident.span);
+            }
+            LocalKind::Decl => {
+                if matches!(ast_ty.kind, ast::TyKind::Array(_, None)) {
+                    let error = IError::UnknownSizeArray {
+                        ty: decl_ty.to_string(),
+                        span: ast_ty.span,
+                    };
+                    self.fail(vec![error], ast_ty.span);
+                }
+                let slot =
+                    self.add_local(ident.name.as_str(), ident.span, *is_mut, Some(decl_ty.kind));
+                self.emit(Op::DeclareLocal(slot), ident.span);
+            }
+        }
+    }
+
+    fn compile_if(
+        &mut self,
+        cond: &'ast Expr,
+        then_block: &'ast Stmt,
+        else_block: &'ast Option<Box<Stmt>>,
+    ) {
+        self.compile_expr(cond);
+
+        if !matches!(then_block.kind, StmtKind::Block(_)) {
+            let error = IError::ExpectedBlockAfterCondition {
+                condition_span: cond.span,
+                if_symbol: kw::to_string(Keyword::When),
+                stmt_span: then_block.span,
+            };
+            return self.fail(vec![error], then_block.span);
+        }
+        if let Some(else_block) = else_block {
+            if !matches!(else_block.kind, StmtKind::Block(_) | StmtKind::If(..)) {
+                let error = IError::ExpectedBlock {
+                    stmt_span: else_block.span,
+                };
+                return self.fail(vec![error], else_block.span);
+            }
+        }
+
+        let to_else = self.emit(Op::JumpIfFalse(usize::MAX), cond.span);
+        self.compile_stmt(then_block);
+        match else_block {
+            Some(else_block) => {
+                let to_end = self.emit(Op::Jump(usize::MAX), else_block.span);
+                self.patch(to_else);
+                self.compile_stmt(else_block);
+                self.patch(to_end);
+            }
+            None => self.patch(to_else),
+        }
+    }
+
+    fn begin_loop(&mut self, start: usize) {
+        let state = self.state();
+        let first_slot = state.proto.locals.len();
+        let lib_scopes = state.lib_scopes;
+        state.loops.push(Loop {
+            first_slot,
+            lib_scopes,
+            start,
+            breaks: vec![],
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let breaks = self.state().loops.pop().unwrap().breaks;
+        for at in breaks {
+            self.patch(at);
+        }
+    }
+
+    fn compile_while(&mut self, cond: &'ast Expr, block: &'ast Stmt) {
+        if !matches!(block.kind, StmtKind::Block(_)) {
+            let error = IError::ExpectedBlock {
+                stmt_span: block.span,
+            };
+            return self.fail(vec![error], block.span);
+        }
+
+        let start = self.here();
+        self.compile_expr(cond);
+        let to_end = self.emit(Op::JumpIfFalse(usize::MAX), cond.span);
+        self.begin_loop(start);
+        self.compile_stmt(block);
+        self.emit(Op::Jump(start), block.span);
+        self.patch(to_end);
+        self.end_loop();
+    }
+
+    fn compile_for(&mut self, ident: &'ast ast::Ident, expr: &'ast Expr, block: &'ast Stmt) {
+        let stmts = match &block.kind {
+            StmtKind::Block(stmts) => stmts,
+            _ => {
+                let error = IError::ExpectedBlock {
+                    stmt_span: block.span,
+                };
+                return self.fail(vec![error], block.span);
+            }
+        };
+
+        self.compile_expr(expr);
+        let iter = self.add_hidden_local(expr.span);
+        self.add_hidden_local(expr.span);
+        self.emit(Op::IntoIter(iter), expr.span);
+
+        // Every iteration has its own scope, with its own loop variable.
+        let start = self.here();
+        self.begin_loop(start);
+        self.begin_scope();
+        let var = self.add_local(ident.name.as_str(), ident.span, false, None);
+        let to_end = self.emit(
+            Op::ForNext {
+                iter,
+                var,
+                exit: usize::MAX,
+            },
+            ident.span,
+        );
+        self.compile_stmts(stmts);
+        self.end_scope(block.span);
+        self.emit(Op::Jump(start), block.span);
+        self.patch(to_end);
+        self.end_loop();
+    }
+
+    /// Leaves the scopes of the innermost loop, before jumping out of it or to its next
+    /// iteration.
+    fn leave_loop_scopes(&mut self, span: Span) -> bool {
+        let state = self.state();
+        let (first_slot, lib_scopes) = match state.loops.last() {
+            Some(loop_) => (loop_.first_slot, state.lib_scopes - loop_.lib_scopes),
+            None => return false,
+        };
+
+        self.emit(Op::CloseUpvalues(first_slot), span);
+        for _ in 0..lib_scopes {
+            self.emit(Op::PopLibScope, span);
+        }
+        true
+    }
+
+    fn compile_break(&mut self, span: Span) {
+        if !self.leave_loop_scopes(span) {
+            let error = IError::BreakOutsideLoop {
+                symbol: kw::to_string(Keyword::Br),
+                span,
+            };
+            return self.fail(vec![error], span);
+        }
+
+        let at = self.emit(Op::Jump(usize::MAX), span);
+        self.state().loops.last_mut().unwrap().breaks.push(at);
+    }
+
+    fn compile_continue(&mut self, span: Span) {
+        if !self.leave_loop_scopes(span) {
+            let error = IError::ContinueOutsideLoop {
+                symbol: kw::to_string(Keyword::Skip),
+                span,
+            };
+            return self.fail(vec![error], span);
+        }
+
+        let start = self.state().loops.last().unwrap().start;
+        self.emit(Op::Jump(start), span);
+    }
+
+    fn compile_return(&mut self, expr: &'ast Option<Box<Expr>>, span: Span) {
+        if !self.state().in_function {
+            let error = IError::ReturnOutsideFunction {
+                symbol: kw::to_string(Keyword::Yeet),
+                span,
+            };
+            return self.fail(vec![error], span);
+        }
+
+        match expr {
+            Some(expr) => self.compile_expr(expr),
+            None => {
+                self.emit(Op::Unit, Span::after(span));
+            }
+        }
+        self.emit(Op::Return, span);
+    }
+
+    /// A function selected from a library must not have the name of a function declared in the
+    /// same block, wherever the declaration is.
+    fn compile_import(&mut self, import: &ast::Import, span: Span) {
+        let funs = &self.scopes.last().unwrap().funs;
+        let errors: Vec<IError> = match &import.kind {
+            ast::ImportKind::Select(names) => names
+                .iter()
+                .filter_map(|name| {
+                    let (_, decl_span) = funs.get(name.name.as_str())?;
+                    let (first_decl_span, second_decl_span) = if decl_span.offset < name.span.offset
+                    {
+                        (*decl_span, name.span)
+                    } else {
+                        (name.span, *decl_span)
+                    };
+                    Some(IError::FunctionAlreadyDeclaredInScope {
+                        func_name: name.name.to_string(),
+                        first_decl_span,
+                        second_decl_span,
+                    })
+                })
+                .collect(),
+            ast::ImportKind::Library | ast::ImportKind::Alias(_) => vec![],
+        };
+
+        if !errors.is_empty() {
+            return self.fail(errors, span);
+        }
+        self.emit(Op::Import(Box::new(import.clone())), span);
+    }
+
+    fn compile_fun_decl(&mut self, fun: &'ast Fun, span: Span) {
+        let name = &fun.sig.name;
+        let sig = self.compile_fun_sig(
+            name.name.as_str(),
+            name.span,
+            &fun.sig.inputs,
+            &fun.sig.output,
+            fun.sig.span,
+        );
+
+        let scope = self.scopes.last_mut().unwrap();
+        let id = match scope.decls.iter().find(|(_, decl)| ptr::eq(*decl, fun)) {
+            Some((id, _)) => *id,
+            None => unreachable!("named functions are collected at the start of their block"),
+        };
+        let (first_id, first_decl_span) = scope.funs[name.name.as_str()];
+        scope.deferred.push((id, fun));
+        self.fun_sigs[id] = Some(sig);
+
+        if first_id != id {
+            let error = IError::FunctionAlreadyDeclaredInScope {
+                func_name: name.name.to_string(),
+                first_decl_span,
+                second_decl_span: name.span,
+            };
+            return self.fail(vec![error], span);
+        }
+        self.emit(Op::DeclareFun(id), span);
+    }
+
+    fn compile_fun_sig(
+        &mut self,
+        name: &str,
+        name_span: Span,
+        inputs: &'ast [FunParam],
+        output: &'ast Option<ast::Ty>,
+        span: Span,
+    ) -> FuncSig {
+        let mut params = vec![];
+        for input in inputs {
+            params.push(FuncParam {
+                ty: self.compile_ty(&input.ty),
+                ident: Ident {
+                    name: input.ident.name.to_string(),
+                    span: input.ident.span,
+                },
+                is_mut: input.is_mut,
+                span: input.span,
+            });
+        }
+        let output = output.as_ref().map(|ty| self.compile_ty(ty));
+
+        FuncSig {
+            ident: Ident {
+                name: name.to_string(),
+                span: name_span,
+            },
+            inputs: params,
+            output,
+            span,
+        }
+    }
+
+    /// Compiles a function body in its own state, with the parameters as its first locals.
+    fn compile_fun(&mut self, sig: FuncSig, body: &'ast Stmt, is_closure: bool) -> Proto {
+        let params: Vec<(String, Span, bool, TyKind)> = sig
+            .inputs
+            .iter()
+            .map(|param| {
+                let name = param.ident.name.to_string();
+                (name, param.ident.span, param.is_mut, param.ty.kind.clone())
+            })
+            .collect();
+
+        let name = sig.ident.name.to_string();
+        self.states
+            .push(FunState::new(&name, Some(sig), is_closure));
+        for (name, span, is_mut, ty) in params {
+            self.add_local(&name, span, is_mut, Some(ty));
+        }
+        self.compile_stmt(body);
+        self.emit(Op::ImplicitReturn, body.span);
+        self.states.pop().unwrap().proto
+    }
+
+    fn compile_fun_body(&mut self, id: usize, fun: &'ast Fun) {
+        let sig = self.fun_sigs[id]
+            .take()
+            .expect("the signature is compiled with the declaration");
+        let proto = self.compile_fun(sig, &fun.body, false);
+        self.funs[id] = Some(Rc::new(proto));
+    }
+
+    fn compile_struct_decl(&mut self, def: &'ast ast::StructDef, span: Span) {
+        let ast::StructDef { name, fields } = def;
+
+        let mut resolved_fields: Vec<(Ident, Ty)> = vec![];
+        for field in fields {
+            let field_name = field.ident.name.as_str();
+            if let Some((first, _)) = resolved_fields
+                .iter()
+                .find(|(ident, _)| ident.name == field_name)
+            {
+                let error = IError::FieldSpecifiedMoreThanOnce {
+                    field: field_name.to_string(),
+                    first_span: first.span,
+                    second_span: field.ident.span,
+                };
+                return self.fail(vec![error], span);
+            }
+
+            let ty = self.compile_ty(&field.ty);
+            let ident = Ident {
+                name: field_name.to_string(),
+                span: field.ident.span,
+            };
+            resolved_fields.push((ident, ty));
+        }
+
+        let def = StructDef {
+            ident: Ident {
+                name: name.name.to_string(),
+                span: name.span,
+            },
+            fields: resolved_fields,
+        };
+        if let Err(errors) =
+            self.types
+                .insert_type(name.name.to_string(), TypeDef::Struct(def), name.span)
+        {
+            self.fail(errors, span);
+        }
+    }
+
+    fn compile_enum_decl(&mut self, def: &'ast ast::EnumDef, span: Span) {
+        let ast::EnumDef { name, variants } = def;
+
+        // The enum is declared before its variants are resolved, so that a variant can hold
+        // a value of the enum itself.
+        let mut def = EnumDef {
+            ident: Ident {
+                name: name.name.to_string(),
+                span: name.span,
+            },
+            variants: vec![],
+        };
+        if let Err(errors) =
+            self.types
+                .insert_type(name.name.to_string(), TypeDef::Enum(def.clone()), name.span)
+        {
+            return self.fail(errors, span);
+        }
+
+        for variant in variants {
+            let variant_name = variant.ident.name.as_str();
+            if let Some((first, _)) = def
+                .variants
+                .iter()
+                .find(|(ident, _)| ident.name == variant_name)
+            {
+                let error = IError::VariantDeclaredMoreThanOnce {
+                    variant: variant_name.to_string(),
+                    first_span: first.span,
+                    second_span: variant.ident.span,
+                };
+                return self.fail(vec![error], span);
+            }
+
+            let fields = variant
+                .fields
+                .iter()
+                .map(|ty| self.compile_ty(ty))
+                .collect();
+            let ident = Ident {
+                name: variant_name.to_string(),
+                span: variant.ident.span,
+            };
+            def.variants.push((ident, fields));
+        }
+
+        self.types
+            .update_type(name.name.as_str(), TypeDef::Enum(def));
+    }
+
+    fn compile_match(&mut self, scrutinee: &'ast Expr, arms: &'ast Vec<ast::Arm>) {
+        self.compile_expr(scrutinee);
+        let slot = self.add_hidden_local(scrutinee.span);
+        self.emit(Op::DefineLocal(slot), scrutinee.span);
+
+        let types = self
+            .types
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.types.iter())
+            .map(|(name, def)| (name.to_string(), def.clone()))
+            .collect();
+        let site = MatchSite {
+            pats: arms.iter().map(|arm| arm.pat.clone()).collect(),
+            types,
+            scrutinee_span: scrutinee.span,
+            checked: Default::default(),
+        };
+        self.proto().matches.push(site);
+        let site = self.proto().matches.len() - 1;
+        self.emit(Op::CheckMatch { site, slot }, scrutinee.span);
+
+        let mut to_end = vec![];
+        for arm in arms {
+            self.begin_scope();
+            let pat = self.compile_pat(&arm.pat, false);
+            let pat = self.add_pat(pat);
+            let next = self.emit(
+                Op::MatchArm {
+                    slot,
+                    pat,
+                    next: usize::MAX,
+                },
+                arm.pat.span,
+            );
+            self.compile_stmt(&arm.body);
+            self.end_scope(arm.span);
+            to_end.push(self.emit(Op::Jump(usize::MAX), arm.span));
+            self.patch(next);
+        }
+        for at in to_end {
+            self.patch(at);
+        }
+    }
+
+    /// Compiles a pattern, declaring a local for each of its bindings.
+    fn compile_pat(&mut self, pat: &'ast Pat, is_mut: bool) -> Pattern {
+        match &pat.kind {
+            PatKind::Wild => Pattern::Wild,
+            PatKind::Ident(ident) => {
+                Pattern::Bind(self.add_local(ident.name.as_str(), ident.span, is_mut, None))
+            }
+            PatKind::Lit(expr) => {
+                match interpret_expr(&mut Environment::new(), expr, false, false) {
+                    Ok(value) => Pattern::Lit(value.kind),
+                    Err(errors) => {
+                        self.fail(errors, expr.span);
+                        Pattern::Wild
+                    }
+                }
+            }
+            PatKind::Tuple(pats) => Pattern::Tuple(
+                pats.iter()
+                    .map(|pat| self.compile_pat(pat, is_mut))
+                    .collect(),
+            ),
+            PatKind::Variant(_, variant, pats) => Pattern::Variant(
+                variant.name.to_string(),
+                pats.iter()
+                    .map(|pat| self.compile_pat(pat, is_mut))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn add_pat(&mut self, pat: Pattern) -> usize {
+        let proto = self.proto();
+        proto.pats.push(pat);
+        proto.pats.len() - 1
+    }
+
+    fn add_ty(&mut self, ty: Ty) -> usize {
+        let proto = self.proto();
+        proto.tys.push(ty);
+        proto.tys.len() - 1
+    }
+
+    fn add_constant(&mut self, value: ValueKind) -> usize {
+        let proto = self.proto();
+        proto.constants.push(value);
+        proto.constants.len() - 1
+    }
+
+    /// Resolves a type. The length of an array type that is not a literal is only known when the
+    /// program runs, so it is checked then, and the type accepts arrays of any length.
+    fn compile_ty(&mut self, ty: &'ast ast::Ty) -> Ty {
+        let kind = match &ty.kind {
+            ast::TyKind::Named(ident) => match ident.name.as_str() {
+                "int" => TyKind::Int,
+                "float" => TyKind::Float,
+                "str" => TyKind::Str,
+                "bool" => TyKind::Bool,
+                "char" => TyKind::Char,
+                "range" => TyKind::Range,
+                name => match self.types.lookup_type(name) {
+                    Some((_, TypeDef::Struct(_))) => TyKind::Struct(name.to_string()),
+                    Some((_, TypeDef::Enum(_))) => TyKind::Enum(name.to_string()),
+                    None => {
+                        let error = IError::CannotFindTypeInScope {
+                            type_name: name.to_string(),
+                            span: ident.span,
+                        };
+                        self.fail(vec![error], ident.span);
+                        TyKind::Unit
+                    }
+                },
+            },
+            ast::TyKind::Array(elem, len) => {
+                let elem = self.compile_ty(elem).kind;
+                let len = match len {
+                    Some(len) => match &len.kind {
+                        ExprKind::Literal(lit) => match interpret_expr_literal(lit, len.span) {
+                            Ok(ValueKind::Int(len)) => len,
+                            _ => self.compile_ty_len(len),
+                        },
+                        _ => self.compile_ty_len(len),
+                    },
+                    None => -1,
+                };
+                TyKind::Array(Box::new(elem), len)
+            }
+            ast::TyKind::Tuple(tys) => {
+                if tys.is_empty() {
+                    TyKind::Unit
+                } else {
+                    TyKind::Tuple(tys.iter().map(|ty| self.compile_ty(ty).kind).collect())
+                }
+            }
+            ast::TyKind::Fun(inputs, output) => {
+                let inputs = inputs
+                    .iter()
+                    .map(|(ty, is_mut)| (self.compile_ty(ty).kind, *is_mut))
+                    .collect();
+                let output = match output {
+                    Some(ty) => self.compile_ty(ty).kind,
+                    None => TyKind::Unit,
+                };
+                TyKind::Function(inputs, Box::new(output))
+            }
+        };
+
+        Ty {
+            kind,
+            span: ty.span,
+        }
+    }
+
+    fn compile_ty_len(&mut self, len: &'ast Expr) -> i64 {
+        self.compile_expr(len);
+        self.emit(Op::CheckLen, len.span);
+        -1
+    }
+
+    fn compile_expr(&mut self, expr: &'ast Expr) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Binary(op, lhs, rhs) => {
+                if let Some(error) = chained_comparison(op, lhs) {
+                    return self.fail(vec![error], span);
+                }
+                self.compile_expr(lhs);
+                let to_end = match op.node {
+                    BinOpKind::And | BinOpKind::Or => {
+                        Some(self.emit(Op::ShortCircuit(op.node.clone(), usize::MAX), span))
+                    }
+                    _ => None,
+                };
+                self.compile_expr(rhs);
+                self.emit(Op::Binary(op.clone()), span);
+                if let Some(to_end) = to_end {
+                    self.patch(to_end);
+                }
+            }
+            ExprKind::Unary(op, operand) => {
+                self.compile_expr(operand);
+                self.emit(Op::Unary(op.clone()), span);
+            }
+            ExprKind::Literal(lit) => match interpret_expr_literal(lit, span) {
+                Ok(value) => {
+                    let index = self.add_constant(value);
+                    self.emit(Op::Constant(index), span);
+                }
+                Err(errors) => self.fail(errors, span),
+            },
+            ExprKind::Assign(lhs, rhs, assign_span) => {
+                self.compile_expr(rhs);
+                self.compile_assign(lhs, *assign_span, span);
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                if let Some(error) = chained_comparison(op, lhs) {
+                    return self.fail(vec![error], span);
+                }
+                self.compile_expr(lhs);
+                self.compile_expr(rhs);
+                self.emit(Op::Binary(op.clone()), span);
+                self.compile_assign(lhs, op.span, span);
+            }
+            ExprKind::Identifier(ident) => self.compile_ident(ident, span),
+            ExprKind::Cast(operand, ty) => {
+                self.compile_expr(operand);
+                let ty = self.compile_ty(ty);
+                let index = self.add_ty(ty);
+                self.emit(Op::Cast(index), span);
+            }
+            ExprKind::FunCall(prefix, args) => self.compile_call(prefix, args, span),
+            ExprKind::LibFunCall(lib_fun, args) => self.compile_lib_call(lib_fun, args, span),
+            ExprKind::LibAccess(base, ident) => match &base.kind {
+                // A name that is not a variable is the name of a library.
+                ExprKind::Identifier(lib) if self.resolve_var(lib.name.as_str()).is_none() => {
+                    self.emit(Op::LibConstant(lib.clone(), ident.clone()), span);
+                }
+                _ => {
+                    self.compile_expr(base);
+                    self.emit(Op::Field(ident.clone()), span);
+                }
+            },
+            ExprKind::Array(elements) => {
+                for element in elements {
+                    self.compile_expr(element);
+                }
+                self.emit(Op::MakeArray(elements.len()), span);
+            }
+            ExprKind::Index(base, index, _) => {
+                self.compile_expr(base);
+                self.compile_expr(index);
+                self.emit(Op::Index, span);
+            }
+            ExprKind::Repeat(element, count) => {
+                self.compile_expr(element);
+                self.compile_expr(count);
+                self.emit(Op::Repeat, span);
+            }
+            ExprKind::Range(start, end, limits) => {
+                self.compile_expr(start);
+                self.compile_expr(end);
+                self.emit(Op::MakeRange(*limits), span);
+            }
+            ExprKind::Tuple(elements) => {
+                // The empty tuple is the unit value
+                if elements.is_empty() {
+                    self.emit(Op::Unit, span);
+                    return;
+                }
+                for element in elements {
+                    self.compile_expr(element);
+                }
+                self.emit(Op::MakeTuple(elements.len()), span);
+            }
+            ExprKind::TupleField(base, index, index_span) => {
+                self.compile_expr(base);
+                self.emit(Op::TupleField(*index, *index_span), span);
+            }
+            ExprKind::Struct(name, fields) => self.compile_struct(name, fields, span),
+            ExprKind::Variant(enum_name, variant, args) => {
+                self.compile_variant(enum_name, variant, args, span)
+            }
+            ExprKind::Closure(closure) => {
+                let ast::Closure {
+                    inputs,
+                    output,
+                    body,
+                    span: closure_span,
+                } = closure.as_ref();
+                let sig =
+                    self.compile_fun_sig("<closure>", *closure_span, inputs, output, *closure_span);
+                let proto = self.compile_fun(sig, body, true);
+                let protos = &mut self.proto().protos;
+                protos.push(Rc::new(proto));
+                let index = protos.len() - 1;
+                self.emit(Op::MakeClosure(index), span);
+            }
+        }
+    }
+
+    fn compile_ident(&mut self, ident: &'ast ast::Ident, span: Span) {
+        let name = ident.name.as_str();
+        if let Some(var) = self.resolve_var(name) {
+            self.emit(get_var(var), span);
+        } else if let Some(id) = self.lookup_fun(name) {
+            // A named function can be used as a value too.
+            self.emit(Op::GetFun(id), span);
+        } else {
+            let error = IError::CannotFindVariableInScope {
+                var_name: name.to_string(),
+                span: ident.span,
+            };
+            self.fail(vec![error], span);
+        }
+    }
+
+    fn compile_assign(&mut self, lhs: &'ast Expr, assign_span: Span, expr_span: Span) {
+        if let ExprKind::Identifier(ident) = &lhs.kind {
+            match self.resolve_var(ident.name.as_str()) {
+                Some(VarRef::Local(slot)) => {
+                    self.emit(Op::SetLocal(slot), expr_span);
+                }
+                Some(VarRef::Upvalue(index)) => {
+                    self.emit(Op::SetUpvalue(index), expr_span);
+                }
+                None => {
+                    let error = IError::CannotFindVariableInScope {
+                        var_name: ident.name.to_string(),
+                        span: ident.span,
+                    };
+                    self.fail(vec![error], expr_span);
+                }
+            }
+            return;
+        }
+
+        if !is_place(lhs) {
+            let error = IError::InvalidLhsAssign {
+                assign_span,
+                lhs_span: lhs.span,
+            };
+            return self.fail(vec![error], expr_span);
+        }
+        match self.compile_place(lhs) {
+            Ok(place) => {
+                self.emit(Op::SetPlace(place), expr_span);
+            }
+            Err(root) => {
+                let error = IError::CannotFindVariableInScope {
+                    var_name: root.name.to_string(),
+                    span: root.span,
+                };
+                self.fail(vec![error], expr_span);
+            }
+        }
+    }
+
+    /// Compiles the indices of a place, outermost first like the tree walker evaluates them.
+    /// Fails with the identifier the place starts from if it is not a variable.
+    fn compile_place(&mut self, expr: &'ast Expr) -> Result<usize, &'ast ast::Ident> {
+        let mut projections = vec![];
+        let mut indices = vec![];
+        let mut e = expr;
+        let root_ident = loop {
+            match &e.kind {
+                ExprKind::Index(base, index, _) => {
+                    projections.push((PlaceProj::Index, base.span));
+                    indices.push(index);
+                    e = base;
+                }
+                ExprKind::LibAccess(base, field) => {
+                    let projection = PlaceProj::Field(field.name.to_string(), field.span);
+                    projections.push((projection, base.span));
+                    e = base;
+                }
+                ExprKind::TupleField(base, index, index_span) => {
+                    projections.push((PlaceProj::TupleField(*index, *index_span), base.span));
+                    e = base;
+                }
+                ExprKind::Identifier(ident) => break ident,
+                _ => unreachable!("a place starts from an identifier"),
+            }
+        };
+
+        let root = self
+            .resolve_var(root_ident.name.as_str())
+            .ok_or(root_ident)?;
+        for index in indices {
+            self.compile_expr(index);
+        }
+        projections.reverse();
+
+        let proto = self.proto();
+        proto.places.push(Place {
+            root,
+            root_ident: root_ident.clone(),
+            projections,
+        });
+        Ok(proto.places.len() - 1)
+    }
+
+    /// Compiles the arguments of a call. An argument that is a place is pushed by `PlaceArg`, so
+    /// that it can be passed by reference to a `mut` parameter.
+    fn compile_args(&mut self, args: &'ast [Box<Expr>], callee: Option<Callee>) -> Vec<bool> {
+        let mut places = vec![];
+        for (i, arg) in args.iter().enumerate() {
+            if let (Some(callee), true) = (callee, is_place(arg)) {
+                if let Ok(place) = self.compile_place(arg) {
+                    // The place may be passed by reference, which captures its root.
+                    if let VarRef::Local(slot) = self.proto().places[place].root {
+                        self.state().capture(slot);
+                    }
+                    self.emit(
+                        Op::PlaceArg {
+                            place,
+                            callee,
+                            arg: i,
+                        },
+                        arg.span,
+                    );
+                    places.push(true);
+                    continue;
+                }
+            }
+            self.compile_expr(arg);
+            places.push(false);
+        }
+        places
+    }
+
+    fn add_call_site(
+        &mut self,
+        ident: Option<&ast::Ident>,
+        prefix_span: Span,
+        places: Vec<bool>,
+    ) -> usize {
+        let (name, name_span) = match ident {
+            Some(ident) => (ident.name.to_string(), ident.span),
+            None => (String::new(), prefix_span),
+        };
+        let proto = self.proto();
+        proto.calls.push(CallSite {
+            name,
+            name_span,
+            prefix_span,
+            places,
+        });
+        proto.calls.len() - 1
+    }
+
+    fn compile_call(&mut self, prefix: &'ast Expr, args: &'ast [Box<Expr>], span: Span) {
+        let argc = args.len();
+        let ident = match &prefix.kind {
+            ExprKind::Identifier(ident) => ident,
+            _ => {
+                // The callee is an expression that evaluates to a function, e.g. `make_adder(1)(2)`.
+                self.compile_expr(prefix);
+                self.emit(Op::CheckCallable, prefix.span);
+                let places = self.compile_args(args, Some(Callee::Stack));
+                let site = self.add_call_site(None, prefix.span, places);
+                self.emit(Op::Call { argc, site }, span);
+                return;
+            }
+        };
+
+        // A variable holding a function shadows a named function with the same name.
+        if let Some(var) = self.resolve_var(ident.name.as_str()) {
+            self.emit(get_var(var), prefix.span);
+            self.emit(Op::CheckCallable, prefix.span);
+            let places = self.compile_args(args, Some(Callee::Stack));
+            let site = self.add_call_site(Some(ident), prefix.span, places);
+            self.emit(Op::Call { argc, site }, span);
+        } else if let Some(id) = self.lookup_fun(ident.name.as_str()) {
+            let places = self.compile_args(args, Some(Callee::Fun(id)));
+            let site = self.add_call_site(Some(ident), prefix.span, places);
+            self.emit(Op::CallFun { id, argc, site }, span);
+        } else {
+            // A function imported by name may take arguments by reference, while the functions
+            // of the standard library take every argument by value.
+            let site = self.add_call_site(Some(ident), prefix.span, vec![]);
+            let places = self.compile_args(args, Some(Callee::Named(site)));
+            self.proto().calls[site].places = places;
+            self.emit(Op::CallNamed { argc, site }, span);
+        }
+    }
+
+    fn compile_lib_call(&mut self, lib_fun: &'ast Expr, args: &'ast [Box<Expr>], span: Span) {
+        let (lib, func) = match &lib_fun.kind {
+            ExprKind::LibAccess(prefix, func) => {
+                // A method is called on a variable, which shadows a library with the same name.
+                if is_place(prefix) {
+                    if let Ok(place) = self.compile_place(prefix) {
+                        return self.compile_method_call(Some(place), func, args, span);
+                    }
+                } else {
+                    self.compile_expr(prefix);
+                    return self.compile_method_call(None, func, args, span);
+                }
+
+                match &prefix.kind {
+                    ExprKind::Identifier(lib) => (lib, func),
+                    _ => {
+                        let error = IError::InvalidLibraryPath { span: prefix.span };
+                        return self.fail(vec![error], span);
+                    }
+                }
+            }
+            _ => unreachable!("Library function call prefix must be a library access"),
+        };
+
+        self.proto().lib_calls.push(LibCallSite {
+            lib: lib.clone(),
+            func: func.clone(),
+            places: vec![],
+        });
+        let site = self.proto().lib_calls.len() - 1;
+        let places = self.compile_args(args, Some(Callee::Lib(site)));
+        self.proto().lib_calls[site].places = places;
+        self.emit(
+            Op::CallLib {
+                argc: args.len(),
+                site,
+            },
+            span,
+        );
+    }
+
+    /// Compiles a call to a method, e.g. `xs.push(1)`, once the indices of the place of the value
+    /// it is called on are compiled, or the value itself if it is not in a place.
+    fn compile_method_call(
+        &mut self,
+        place: Option<usize>,
+        method: &'ast ast::Ident,
+        args: &'ast [Box<Expr>],
+        span: Span,
+    ) {
+        for arg in args {
+            self.compile_expr(arg);
+        }
+        self.emit(
+            Op::CallMethod {
+                place,
+                method: method.clone(),
+                argc: args.len(),
+            },
+            span,
+        );
+    }
+
+    fn compile_struct(&mut self, name: &'ast ast::Ident, fields: &'ast Vec<ExprField>, span: Span) {
+        let struct_name = name.name.as_str();
+        let def = match self.types.lookup_struct(struct_name) {
+            Some((_, def)) => def,
+            None => {
+                let error = IError::CannotFindTypeInScope {
+                    type_name: struct_name.to_string(),
+                    span: name.span,
+                };
+                return self.fail(vec![error], span);
+            }
+        };
+
+        let mut indices: Vec<usize> = vec![];
+        for field in fields {
+            let field_name = field.ident.name.as_str();
+            let index = match def
+                .fields
+                .iter()
+                .position(|(ident, _)| ident.name == field_name)
+            {
+                Some(index) => index,
+                None => {
+                    let error = IError::NoFieldOnType {
+                        field: field_name.to_string(),
+                        ty: struct_name.to_string(),
+                        span: field.ident.span,
+                    };
+                    return self.fail(vec![error], span);
+                }
+            };
+
+            if let Some(first) = fields[..indices.len()]
+                .iter()
+                .zip(&indices)
+                .find(|(_, i)| **i == index)
+            {
+                let error = IError::FieldSpecifiedMoreThanOnce {
+                    field: field_name.to_string(),
+                    first_span: first.0.ident.span,
+                    second_span: field.ident.span,
+                };
+                return self.fail(vec![error], span);
+            }
+
+            self.compile_expr(&field.expr);
+            indices.push(index);
+        }
+
+        let missing_fields: Vec<String> = def
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !indices.contains(i))
+            .map(|(_, (ident, _))| ident.name.to_string())
+            .collect();
+        if !missing_fields.is_empty() {
+            let error = IError::MissingStructFields {
+                struct_name: struct_name.to_string(),
+                fields: missing_fields,
+                span: name.span,
+            };
+            return self.fail(vec![error], span);
+        }
+
+        self.proto().structs.push(StructSite {
+            def,
+            fields: indices,
+        });
+        let site = self.proto().structs.len() - 1;
+        self.emit(Op::MakeStruct(site), span);
+    }
+
+    fn compile_variant(
+        &mut self,
+        enum_name: &'ast ast::Ident,
+        variant: &'ast ast::Ident,
+        args: &'ast [Box<Expr>],
+        span: Span,
+    ) {
+        let name = enum_name.name.as_str();
+        let def = match self.types.lookup_enum(name) {
+            Some((_, def)) => def,
+            None => {
+                let error = IError::CannotFindTypeInScope {
+                    type_name: name.to_string(),
+                    span: enum_name.span,
+                };
+                return self.fail(vec![error], span);
+            }
+        };
+
+        let variant_name = variant.name.as_str();
+        let field_tys = match def.variant(variant_name) {
+            Some((_, field_tys)) => field_tys.clone(),
+            None => {
+                let error = IError::NoVariantInEnum {
+                    variant: variant_name.to_string(),
+                    enum_name: name.to_string(),
+                    span: variant.span,
+                };
+                return self.fail(vec![error], span);
+            }
+        };
+
+        for arg in args {
+            self.compile_expr(arg);
+        }
+        self.proto().variants.push(VariantSite {
+            enum_name: name.to_string(),
+            variant: variant_name.to_string(),
+            field_tys,
+            argc: args.len(),
+        });
+        let site = self.proto().variants.len() - 1;
+        self.emit(Op::MakeVariant(site), span);
+    }
+}
+
+fn get_var(var: VarRef) -> Op {
+    match var {
+        VarRef::Local(slot) => Op::GetLocal(slot),
+        VarRef::Upvalue(index) => Op::GetUpvalue(index),
+    }
+}
+
+fn is_comparison(op: &BinOpKind) -> bool {
+    matches!(
+        op,
+        BinOpKind::Eq
+            | BinOpKind::Ne
+            | BinOpKind::Lt
+            | BinOpKind::Le
+            | BinOpKind::Gt
+            | BinOpKind::Ge
+    )
+}
+
+/// Returns the error for a comparison whose left operand is a comparison too, e.g. `a < b < c`.
+fn chained_comparison(op: &ast::BinOp, lhs: &Expr) -> Option<IError> {
+    match &lhs.kind {
+        ExprKind::Binary(lhs_op, ..) if is_comparison(&lhs_op.node) && is_comparison(&op.node) => {
+            Some(IError::ComparisonOperatorsCannotBeChained {
+                chain_op_span: vec![lhs_op.span, op.span],
+            })
+        }
+        _ => None,
+    }
+}
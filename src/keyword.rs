@@ -54,6 +54,10 @@ pub enum Keyword {
     As,
     Add,
     Yeet,
+    Struct,
+    Enum,
+    Match,
+    Priv,
 }
 
 impl FromStr for Keyword {
@@ -101,7 +105,8 @@ pub fn to_string(keyword: Keyword) -> String {
     keyword.as_ref().to_string()
 }
 
-fn get_kw_map_arr() -> [(Keyword, (&'static str, &'static str)); 15] {
+/// Returns every keyword, with its text in the normal and in the chaos mode.
+pub fn get_kw_map_arr() -> [(Keyword, (&'static str, &'static str)); 19] {
     [
         (Keyword::True, ("true", "yass")),
         (Keyword::False, ("false", "nope")),
@@ -118,5 +123,9 @@ fn get_kw_map_arr() -> [(Keyword, (&'static str, &'static str)); 15] {
         (Keyword::As, ("as", "flexin")),
         (Keyword::Add, ("add", "snatch")),
         (Keyword::Yeet, ("yeet", "bounce")),
+        (Keyword::Struct, ("struct", "squad")),
+        (Keyword::Enum, ("enum", "menu")),
+        (Keyword::Match, ("match", "spill")),
+        (Keyword::Priv, ("priv", "lowkey")),
     ]
 }
@@ -284,8 +284,8 @@ impl Cursor<'_> {
         // Only Decimal base here, and the part before `.` or `e|E` has been eaten.
         match self.first() {
             // After '.' cannot be id_start because we might add method for primary type in the
-            // future.
-            '.' if !is_id_start(self.second()) => {
+            // future, nor another '.' because `0..` starts a range.
+            '.' if self.second() != '.' && !is_id_start(self.second()) => {
                 self.eat();
 
                 // If there is something after '.', it has to be a number. Else we will stop
@@ -1317,6 +1317,51 @@ customer_id_is_1
         );
     }
 
+    #[test]
+    fn tokenize_range_bounds() {
+        let source = r#"
+0..
+0..3
+0.5
+"#;
+
+        let mut cursor = Cursor::new(&source);
+        let int = Token::new(
+            TokenKind::Literal(LiteralKind::Int {
+                base: Base::Decimal,
+                empty_int: false,
+            }),
+            1,
+        );
+        let dot = Token::new(TokenKind::Dot, 1);
+
+        //0..
+        assert_eq!(cursor.advance_token(), Token::new(TokenKind::Whitespace, 1));
+        assert_eq!(cursor.advance_token(), int);
+        assert_eq!(cursor.advance_token(), dot);
+        assert_eq!(cursor.advance_token(), dot);
+
+        //0..3
+        assert_eq!(cursor.advance_token(), Token::new(TokenKind::Whitespace, 1));
+        assert_eq!(cursor.advance_token(), int);
+        assert_eq!(cursor.advance_token(), dot);
+        assert_eq!(cursor.advance_token(), dot);
+        assert_eq!(cursor.advance_token(), int);
+
+        //0.5
+        assert_eq!(cursor.advance_token(), Token::new(TokenKind::Whitespace, 1));
+        assert_eq!(
+            cursor.advance_token(),
+            Token::new(
+                TokenKind::Literal(LiteralKind::Float {
+                    base: Base::Decimal,
+                    empty_exponent: false
+                }),
+                3,
+            )
+        );
+    }
+
     fn tokenize(source: &str) -> Vec<Token> {
         let mut tokens: Vec<Token> = Vec::new();
         let mut cursor = Cursor::new(source);
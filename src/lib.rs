@@ -0,0 +1,54 @@
+//! The Pandora language: its parser, type checker and interpreters.
+//!
+//! Programs can be run in-process with [`Interpreter`], which returns the value of a program,
+//! or its [`Diagnostics`], instead of printing them and exiting like the `unbox` binary.
+
+mod ast;
+#[doc(hidden)]
+pub mod error_docs;
+mod error_handler;
+#[doc(hidden)]
+pub mod format;
+#[doc(hidden)]
+pub mod interpreter;
+#[path = "keyword.rs"]
+mod kw;
+mod lexer;
+#[doc(hidden)]
+pub mod lsp;
+#[doc(hidden)]
+pub mod parse;
+#[doc(hidden)]
+pub mod repl;
+#[doc(hidden)]
+pub mod session;
+mod span_encoding;
+mod symbol;
+mod visitor;
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error_handler::*;
+
+pub use interpreter::{
+    eval::{Value, ValueKind},
+    Diagnostics, Interpreter, NativeLibrary, ParamTy, Range, TyKind,
+};
+
+// Global flag for Gen Z mode
+static GENZ_MODE: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+
+pub fn enable_genz_mode() {
+    GENZ_MODE.store(true, Ordering::SeqCst);
+}
+
+/// Switches between the normal and the chaos mode, e.g. for the language server, which can
+/// have files of both modes open.
+pub fn set_genz_mode(is_genz: bool) {
+    GENZ_MODE.store(is_genz, Ordering::SeqCst);
+}
+
+pub fn is_genz_mode() -> bool {
+    GENZ_MODE.load(Ordering::SeqCst)
+}
@@ -0,0 +1,448 @@
+//! The language server, started with `unbox lsp`. It speaks JSON-RPC over the standard input
+//! and output, as described by the Language Server Protocol.
+//!
+//! Documents are synchronized in full: every change sends the whole text, which is analyzed
+//! again to publish its diagnostics.
+
+mod analysis;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process,
+};
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    interpreter::{self, resolver::ModuleResolver},
+    kw,
+    span_encoding::Span,
+};
+use analysis::{Analysis, Decl, DeclKind};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// The kinds of completion items, as numbered by the protocol.
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_MODULE: u32 = 9;
+const COMPLETION_ENUM: u32 = 13;
+const COMPLETION_KEYWORD: u32 = 14;
+const COMPLETION_STRUCT: u32 = 22;
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+    /// The declarations of the last version of the document that could be parsed, so that
+    /// completion still works while a statement is being typed.
+    last_decls: Vec<Decl>,
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, Document>,
+    is_shutdown: bool,
+}
+
+/// Answers the messages of the client until it asks to exit.
+pub fn run() {
+    // A panic in the analysis must not end the session, and its message would be mixed with
+    // the messages sent to the client.
+    panic::set_hook(Box::new(|info| eprintln!("[unbox lsp] {}", info)));
+
+    let mut server = Server::default();
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Some(message) = read_message(&mut input) {
+        server.handle(message);
+    }
+
+    process::exit(if server.is_shutdown { 0 } else { 1 });
+}
+
+/// Reads a message, or returns `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("Content-Length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let Some(length) = length else {
+            eprintln!("[unbox lsp] Message without a Content-Length header");
+            continue;
+        };
+        let mut content = vec![0; length];
+        input.read_exact(&mut content).ok()?;
+        match serde_json::from_slice(&content) {
+            Ok(message) => return Some(message),
+            Err(e) => eprintln!("[unbox lsp] Invalid message: {}", e),
+        }
+    }
+}
+
+fn send(message: Value) {
+    let content = message.to_string();
+    let mut stdout = io::stdout().lock();
+    write!(
+        stdout,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )
+    .unwrap();
+    stdout.flush().unwrap();
+}
+
+fn respond(id: Value, result: Value) {
+    send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+}
+
+fn notify(method: &str, params: Value) {
+    send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+impl Server {
+    fn handle(&mut self, message: Value) {
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        match method.as_str() {
+            "initialize" => respond(id.unwrap_or_default(), capabilities()),
+            "shutdown" => {
+                self.is_shutdown = true;
+                respond(id.unwrap_or_default(), Value::Null);
+            }
+            "exit" => process::exit(if self.is_shutdown { 0 } else { 1 }),
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_string());
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                // With full synchronization, the last change is the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|changes| changes.last()) {
+                    let text = text["text"].as_str().unwrap_or_default();
+                    self.update(uri, text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+            }
+            "textDocument/hover" => {
+                let result = self.hover(params).unwrap_or_default();
+                respond(id.unwrap_or_default(), result);
+            }
+            "textDocument/definition" => {
+                let result = self.definition(params).unwrap_or_default();
+                respond(id.unwrap_or_default(), result);
+            }
+            "textDocument/completion" => {
+                let result = self.completion(params).unwrap_or_default();
+                respond(id.unwrap_or_default(), result);
+            }
+            _ => {
+                // Notifications that are not handled, like `initialized`, are ignored.
+                if let Some(id) = id {
+                    send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("Unknown method '{}'", method),
+                        },
+                    }));
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) {
+        crate::set_genz_mode(is_genz_uri(uri));
+        let name = uri.rsplit('/').next().unwrap_or(uri).to_string();
+        let resolver = document_resolver(uri).unwrap_or_default();
+        let analysis = panic::catch_unwind(AssertUnwindSafe(|| {
+            analysis::analyze(&name, &text, &resolver)
+        }))
+        .unwrap_or(Analysis {
+            diagnostics: vec![],
+            decls: None,
+            refs: vec![],
+        });
+
+        let diagnostics: Vec<Value> = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let mut value = json!({
+                    "range": to_range(&text, diagnostic.span),
+                    "severity": 1,
+                    "source": "unbox",
+                    "message": diagnostic.message,
+                });
+                if let Some(code) = &diagnostic.code {
+                    value["code"] = json!(code);
+                }
+                value
+            })
+            .collect();
+        notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        );
+
+        let last_decls = match (&analysis.decls, self.documents.remove(uri)) {
+            (Some(decls), _) => decls.clone(),
+            (None, Some(document)) => document.last_decls,
+            (None, None) => vec![],
+        };
+        self.documents.insert(
+            uri.to_string(),
+            Document {
+                text,
+                analysis,
+                last_decls,
+            },
+        );
+    }
+
+    /// Returns the document and the byte offset of the position of a request.
+    fn position<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let offset = to_offset(&document.text, line, character);
+        Some((uri, document, offset))
+    }
+
+    /// Returns the declaration of the identifier at the position of a request.
+    fn decl_at<'a>(&self, document: &'a Document, offset: usize) -> Option<&'a Decl> {
+        let decls = document.analysis.decls.as_ref()?;
+        let contains = |span: Span| {
+            let start = span.offset as usize;
+            start <= offset && offset <= start + span.length
+        };
+
+        if let Some(decl) = decls.iter().find(|decl| contains(decl.span)) {
+            return Some(decl);
+        }
+        let (_, index) = document
+            .analysis
+            .refs
+            .iter()
+            .find(|(span, _)| contains(*span))?;
+        decls.get(*index)
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (_, document, offset) = self.position(params)?;
+        let decl = self.decl_at(document, offset)?;
+        Some(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```pandora\n{}\n```", decl.detail),
+            },
+        }))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, document, offset) = self.position(params)?;
+        let decl = self.decl_at(document, offset)?;
+        Some(json!({
+            "uri": uri,
+            "range": to_range(&document.text, decl.span),
+        }))
+    }
+
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (uri, document, offset) = self.position(params)?;
+        crate::set_genz_mode(is_genz_uri(uri));
+
+        let before = &document.text[..offset];
+        let word_start = before
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |i| i + 1);
+
+        // After `lib.`, only the functions of the library are offered.
+        if before[..word_start].ends_with('.') {
+            let prefix = &before[..word_start - 1];
+            let lib_start = prefix
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(0, |i| i + 1);
+            let lib = &prefix[lib_start..];
+            let decls = match &document.analysis.decls {
+                Some(decls) => decls,
+                None => &document.last_decls,
+            };
+            // The library may be added with a path or an alias, e.g. `add utils.strings;` for
+            // `strings` or `add math as m;` for `m`.
+            let path = decls
+                .iter()
+                .rev()
+                .find(|decl| decl.kind == DeclKind::Library && decl.name == lib)
+                .and_then(|decl| decl.lib_path.as_deref())
+                .unwrap_or(lib);
+            return Some(json!(library_completions(uri, path)));
+        }
+
+        let mut items = vec![];
+        for (_, (normal, genz)) in kw::get_kw_map_arr() {
+            let label = if crate::is_genz_mode() { genz } else { normal };
+            items.push(json!({ "label": label, "kind": COMPLETION_KEYWORD }));
+        }
+        for name in interpreter::embedded_library_functions("std").unwrap_or_default() {
+            items.push(json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": "std" }));
+        }
+
+        let decls = match &document.analysis.decls {
+            Some(decls) => decls,
+            None => &document.last_decls,
+        };
+        let mut seen = vec![];
+        // Later declarations shadow the earlier ones.
+        for decl in decls.iter().rev() {
+            let (start, end) = decl.visible;
+            if offset < start || offset > end || seen.contains(&decl.name) {
+                continue;
+            }
+            seen.push(decl.name.clone());
+            let kind = match decl.kind {
+                DeclKind::Variable | DeclKind::Parameter => COMPLETION_VARIABLE,
+                DeclKind::Function => COMPLETION_FUNCTION,
+                DeclKind::Struct => COMPLETION_STRUCT,
+                DeclKind::Enum => COMPLETION_ENUM,
+                DeclKind::Library => COMPLETION_MODULE,
+            };
+            items.push(json!({ "label": decl.name, "kind": kind, "detail": decl.detail }));
+        }
+
+        Some(json!(items))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "completionProvider": { "triggerCharacters": ["."] },
+        },
+        "serverInfo": {
+            "name": "unbox",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// Returns a resolver that looks up modules from the directory of the document, or `None` if
+/// the document is not a file. The URI is percent-decoded, e.g. `%20` for a space.
+fn document_resolver(uri: &str) -> Option<ModuleResolver> {
+    let document_path = Url::parse(uri).ok()?.to_file_path().ok()?;
+    Some(ModuleResolver::new(
+        document_path.parent().map(Path::to_path_buf),
+        vec![],
+    ))
+}
+
+/// Returns the functions of the library with the given path, e.g. `utils.strings`, looked up
+/// like the program does from the directory of the document, or of an embedded library.
+fn library_completions(uri: &str, lib: &str) -> Vec<Value> {
+    let Some(resolver) = document_resolver(uri) else {
+        return vec![];
+    };
+    let segments: Vec<&str> = lib.split('.').collect();
+    let Some(path) = resolver.resolve(&segments) else {
+        return interpreter::embedded_library_functions(lib)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": lib }))
+            .collect();
+    };
+    let Ok(text) = fs::read_to_string(&path) else {
+        return vec![];
+    };
+
+    let name = path.to_string_lossy().to_string();
+    let functions = panic::catch_unwind(AssertUnwindSafe(|| {
+        analysis::library_functions(&name, &text)
+    }));
+    functions
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, sig)| json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": sig }))
+        .collect()
+}
+
+fn is_genz_uri(uri: &str) -> bool {
+    uri.ends_with(".unbx") || uri.ends_with(".unbxx")
+}
+
+/// Converts a line and a character, counted in UTF-16 code units, to a byte offset.
+fn to_offset(text: &str, line: usize, character: usize) -> usize {
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Converts a byte offset to a line and a character, counted in UTF-16 code units.
+fn to_position(text: &str, offset: usize) -> Value {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+fn to_range(text: &str, span: Span) -> Value {
+    let start = span.offset as usize;
+    json!({
+        "start": to_position(text, start),
+        "end": to_position(text, start + span.length),
+    })
+}
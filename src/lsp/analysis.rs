@@ -0,0 +1,466 @@
+//! Analyses a document for the language server: the errors in it, and the declaration each of
+//! its identifiers refers to.
+
+use std::sync::Arc;
+
+use miette::Report;
+
+use crate::{
+    ast::{
+        pretty_print::Printer, Ast, Closure, EnumDef, Expr, ExprKind, Fun, FunParam, Ident,
+        ImportKind, Local, Pat, PatKind, Stmt, StmtKind, StructDef, Ty, TyKind,
+    },
+    interpreter::{self, resolver::ModuleResolver},
+    parse::parser,
+    session::{Session, SourceFile},
+    span_encoding::{split_source_offset, Span, DUMMY_SP},
+    visitor::{self, Visitor},
+};
+
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The declarations, or `None` if the document cannot be parsed.
+    pub decls: Option<Vec<Decl>>,
+    /// The identifiers that refer to a declaration, with the index of the declaration.
+    pub refs: Vec<(Span, usize)>,
+}
+
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+    pub code: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DeclKind {
+    Variable,
+    Parameter,
+    Function,
+    Struct,
+    Enum,
+    Library,
+}
+
+#[derive(Clone)]
+pub struct Decl {
+    pub name: String,
+    pub kind: DeclKind,
+    /// The span of the identifier in the declaration.
+    pub span: Span,
+    /// How the declaration is shown on hover, e.g. `set mut x: int`.
+    pub detail: String,
+    /// The start and the end of the part of the document where the declaration is visible.
+    pub visible: (usize, usize),
+    /// The path of the library a `Library` declaration adds, e.g. `utils.strings` for
+    /// `add utils.strings as s;`.
+    pub lib_path: Option<String>,
+}
+
+/// Parses and type checks a document. The libraries it adds are looked up with `resolver`.
+pub fn analyze(name: &str, text: &str, resolver: &ModuleResolver) -> Analysis {
+    let contents = Arc::new(text.to_string());
+    let file = SourceFile::new(name, Arc::clone(&contents));
+    let mut session = Session::new_collecting(Arc::new(file));
+
+    let ast = parser::parse(&contents, &mut session);
+    if let Some(ast) = &ast {
+        interpreter::typecheck(ast, &session, resolver);
+    }
+
+    let diagnostics = session
+        .error_handler
+        .take_reports()
+        .iter()
+        .map(to_diagnostic)
+        .collect();
+    let (decls, refs) = match &ast {
+        Some(ast) => {
+            let (decls, refs) = Resolver::resolve(text, ast);
+            (Some(decls), refs)
+        }
+        None => (None, vec![]),
+    };
+
+    Analysis {
+        diagnostics,
+        decls,
+        refs,
+    }
+}
+
+/// Returns the signatures of the functions exported by a library file, or `None` if it cannot
+/// be parsed.
+pub fn library_functions(name: &str, text: &str) -> Option<Vec<(String, String)>> {
+    let contents = Arc::new(text.to_string());
+    let file = SourceFile::new(name, Arc::clone(&contents));
+    let mut session = Session::new_collecting(Arc::new(file));
+    let ast = parser::parse(&contents, &mut session)?;
+
+    let functions = ast
+        .stmts
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::FuncDecl(fun) if !fun.is_private => {
+                let mut printer = Printer::new(text);
+                printer.print_fun_sig(&fun.sig);
+                Some((fun.sig.name.name.to_string(), printer.output))
+            }
+            _ => None,
+        })
+        .collect();
+    Some(functions)
+}
+
+fn to_diagnostic(report: &Report) -> Diagnostic {
+    let labels: Vec<_> = report
+        .labels()
+        .map(|labels| labels.collect())
+        .unwrap_or_default();
+    let primary = labels
+        .iter()
+        .find(|label| label.primary())
+        .or(labels.first());
+
+    let mut message = report.to_string();
+    if let Some(label) = primary.and_then(|label| label.label()) {
+        message = format!("{}\n{}", message, label);
+    }
+    if let Some(help) = report.help() {
+        message = format!("{}\nhelp: {}", message, help);
+    }
+
+    let span = match primary {
+        Some(label) => {
+            let (file, offset) = split_source_offset(label.offset());
+            Span::new(offset as u32, (offset + label.len()) as u32, file)
+        }
+        None => DUMMY_SP,
+    };
+
+    Diagnostic {
+        span,
+        message,
+        code: report.code().map(|code| code.to_string()),
+    }
+}
+
+struct Scope {
+    decls: Vec<usize>,
+    /// The end of the scope, where its declarations stop being visible.
+    end: usize,
+    /// Whether this is the scope of the parameters of a named function, which hides the
+    /// variables of the scopes around it.
+    is_fun_boundary: bool,
+}
+
+/// Finds the declaration each identifier refers to, following the scoping rules of the
+/// interpreter.
+struct Resolver<'src> {
+    src: &'src str,
+    decls: Vec<Decl>,
+    refs: Vec<(Span, usize)>,
+    scopes: Vec<Scope>,
+}
+
+impl<'src> Resolver<'src> {
+    fn resolve(src: &'src str, ast: &Ast) -> (Vec<Decl>, Vec<(Span, usize)>) {
+        let mut resolver = Resolver {
+            src,
+            decls: vec![],
+            refs: vec![],
+            scopes: vec![],
+        };
+        resolver.visit_block(&ast.stmts, 0, src.len());
+        (resolver.decls, resolver.refs)
+    }
+
+    fn push_scope(&mut self, end: usize, is_fun_boundary: bool) {
+        self.scopes.push(Scope {
+            decls: vec![],
+            end,
+            is_fun_boundary,
+        });
+    }
+
+    fn declare(&mut self, ident: &Ident, kind: DeclKind, detail: String, visible_from: usize) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.decls.push(self.decls.len());
+        self.decls.push(Decl {
+            name: ident.name.to_string(),
+            kind,
+            span: ident.span,
+            detail,
+            visible: (visible_from, scope.end),
+            lib_path: None,
+        });
+    }
+
+    fn lookup(&mut self, ident: &Ident) {
+        let name = ident.name.as_str();
+        let mut is_var_hidden = false;
+        for scope in self.scopes.iter().rev() {
+            let found = scope.decls.iter().rev().find(|&&index| {
+                let decl = &self.decls[index];
+                let is_var = matches!(decl.kind, DeclKind::Variable | DeclKind::Parameter);
+                decl.name == name && !(is_var && is_var_hidden)
+            });
+            if let Some(&index) = found {
+                self.refs.push((ident.span, index));
+                return;
+            }
+
+            is_var_hidden |= scope.is_fun_boundary;
+        }
+    }
+
+    fn ty_to_string(&self, ty: &Ty) -> String {
+        let mut printer = Printer::new(self.src);
+        printer.visit_ty(ty);
+        printer.output
+    }
+
+    fn visit_block(&mut self, stmts: &Vec<Box<Stmt>>, start: usize, end: usize) {
+        self.push_scope(end, false);
+
+        // Functions and types can be used anywhere in their block.
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::FuncDecl(fun) => {
+                    let mut printer = Printer::new(self.src);
+                    printer.print_fun_sig(&fun.sig);
+                    self.declare(&fun.sig.name, DeclKind::Function, printer.output, start);
+                }
+                StmtKind::StructDecl(def) => {
+                    let detail = self.struct_detail(def);
+                    self.declare(&def.name, DeclKind::Struct, detail, start);
+                }
+                StmtKind::EnumDecl(def) => {
+                    let detail = self.enum_detail(def);
+                    self.declare(&def.name, DeclKind::Enum, detail, start);
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn struct_detail(&self, def: &StructDef) -> String {
+        let fields: Vec<String> = def
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.ident.name, self.ty_to_string(&field.ty)))
+            .collect();
+        format!("struct {} {{ {} }}", def.name.name, fields.join(", "))
+    }
+
+    fn enum_detail(&self, def: &EnumDef) -> String {
+        let variants: Vec<String> = def
+            .variants
+            .iter()
+            .map(|variant| {
+                if variant.fields.is_empty() {
+                    return variant.ident.name.to_string();
+                }
+                let fields: Vec<String> = variant
+                    .fields
+                    .iter()
+                    .map(|ty| self.ty_to_string(ty))
+                    .collect();
+                format!("{}({})", variant.ident.name, fields.join(", "))
+            })
+            .collect();
+        format!("enum {} {{ {} }}", def.name.name, variants.join(", "))
+    }
+
+    fn declare_params(&mut self, inputs: &Vec<FunParam>, visible_from: usize) {
+        for param in inputs {
+            self.visit_ty(&param.ty);
+            let is_mut = if param.is_mut { "mut " } else { "" };
+            let detail = format!(
+                "{}{}: {}",
+                is_mut,
+                param.ident.name,
+                self.ty_to_string(&param.ty)
+            );
+            self.declare(&param.ident, DeclKind::Parameter, detail, visible_from);
+        }
+    }
+
+    /// Declares the bindings of a pattern. `prefix` is put before each binding on hover, and
+    /// `ty` is the type of the whole pattern if it is known.
+    fn declare_pat(&mut self, pat: &Pat, ty: Option<&Ty>, prefix: &str, visible_from: usize) {
+        match &pat.kind {
+            PatKind::Ident(ident) => {
+                let detail = match ty {
+                    Some(ty) => format!("{}{}: {}", prefix, ident.name, self.ty_to_string(ty)),
+                    None => format!("{}{}", prefix, ident.name),
+                };
+                self.declare(ident, DeclKind::Variable, detail, visible_from);
+            }
+            PatKind::Tuple(pats) => {
+                let tys = match ty.map(|ty| &ty.kind) {
+                    Some(TyKind::Tuple(tys)) if tys.len() == pats.len() => Some(tys),
+                    _ => None,
+                };
+                for (i, pat) in pats.iter().enumerate() {
+                    let ty = tys.map(|tys| &tys[i]);
+                    self.declare_pat(pat, ty, prefix, visible_from);
+                }
+            }
+            PatKind::Variant(enum_name, _, pats) => {
+                self.lookup(enum_name);
+                for pat in pats {
+                    self.declare_pat(pat, None, prefix, visible_from);
+                }
+            }
+            PatKind::Lit(expr) => self.visit_expr(expr),
+            PatKind::Wild => {}
+        }
+    }
+
+    fn visit_closure(&mut self, closure: &Closure) {
+        let (start, end) = span_bounds(closure.body.span);
+        self.push_scope(end, false);
+        self.declare_params(&closure.inputs, start);
+        if let Some(output) = &closure.output {
+            self.visit_ty(output);
+        }
+        self.visit_stmt(&closure.body);
+        self.scopes.pop();
+    }
+}
+
+impl<'ast, 'src> Visitor<'ast> for Resolver<'src> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match &stmt.kind {
+            StmtKind::Block(stmts) => {
+                let (start, end) = span_bounds(stmt.span);
+                self.visit_block(stmts, start, end);
+            }
+            StmtKind::Import(import) => {
+                let mut printer = Printer::new(self.src);
+                printer.visit_stmt_import(import);
+                let detail = printer.output;
+                let visible_from = stmt.span.offset as usize;
+                match &import.kind {
+                    ImportKind::Select(names) => {
+                        for name in names {
+                            self.declare(name, DeclKind::Function, detail.clone(), visible_from);
+                        }
+                    }
+                    _ => {
+                        self.declare(import.name(), DeclKind::Library, detail, visible_from);
+                        let decl = self.decls.last_mut().unwrap();
+                        decl.lib_path = Some(import.segments().join("."));
+                    }
+                }
+            }
+            _ => visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_stmt_func_decl(&mut self, fun: &'ast Fun) {
+        let (start, end) = span_bounds(fun.body.span);
+        self.push_scope(end, true);
+        self.declare_params(&fun.sig.inputs, start);
+        if let Some(output) = &fun.sig.output {
+            self.visit_ty(output);
+        }
+        self.visit_stmt(&fun.body);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt_var(&mut self, local: &'ast Local) {
+        if let Some(ty) = &local.ty {
+            self.visit_ty(ty);
+        }
+        if let crate::ast::LocalKind::Init(expr) = &local.kind {
+            self.visit_expr(expr);
+        }
+
+        let set = crate::kw::to_string(crate::kw::Keyword::Set);
+        let prefix = if local.is_mut {
+            format!("{} {} ", set, crate::kw::to_string(crate::kw::Keyword::Mut))
+        } else {
+            format!("{} ", set)
+        };
+        let (_, visible_from) = span_bounds(local.span);
+        self.declare_pat(&local.pat, local.ty.as_ref(), &prefix, visible_from);
+    }
+
+    fn visit_stmt_for(&mut self, ident: &'ast Ident, expr: &'ast Expr, block: &'ast Stmt) {
+        self.visit_expr(expr);
+        let (start, end) = span_bounds(block.span);
+        self.push_scope(end, false);
+        let detail = format!(
+            "{} {}",
+            crate::kw::to_string(crate::kw::Keyword::For),
+            ident.name
+        );
+        self.declare(ident, DeclKind::Variable, detail, start);
+        self.visit_stmt(block);
+        self.scopes.pop();
+    }
+
+    fn visit_arm(&mut self, arm: &'ast crate::ast::Arm) {
+        let (start, end) = span_bounds(arm.span);
+        self.push_scope(end, false);
+        self.declare_pat(&arm.pat, None, "", start);
+        self.visit_stmt(&arm.body);
+        self.scopes.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match &expr.kind {
+            ExprKind::Identifier(ident) => self.lookup(ident),
+            ExprKind::Struct(name, fields) => {
+                self.lookup(name);
+                for field in fields {
+                    self.visit_expr(&field.expr);
+                }
+            }
+            ExprKind::Variant(enum_name, _, args) => {
+                self.lookup(enum_name);
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+            ExprKind::Closure(closure) => self.visit_closure(closure),
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &'ast Ty) {
+        match &ty.kind {
+            TyKind::Named(ident) => self.lookup(ident),
+            TyKind::Array(elem, len) => {
+                self.visit_ty(elem);
+                if let Some(len) = len {
+                    self.visit_expr(len);
+                }
+            }
+            TyKind::Tuple(tys) => {
+                for ty in tys {
+                    self.visit_ty(ty);
+                }
+            }
+            TyKind::Fun(inputs, output) => {
+                for (ty, _) in inputs {
+                    self.visit_ty(ty);
+                }
+                if let Some(output) = output {
+                    self.visit_ty(output);
+                }
+            }
+        }
+    }
+}
+
+fn span_bounds(span: Span) -> (usize, usize) {
+    let start = span.offset as usize;
+    (start, start + span.length)
+}
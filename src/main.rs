@@ -1,40 +1,17 @@
-mod ast;
-mod error_docs;
-mod error_handler;
-mod interpreter;
-#[path = "keyword.rs"]
-mod kw;
-mod lexer;
-mod parse;
-mod session;
-mod span_encoding;
-mod symbol;
-mod visitor;
-
 use std::{
     env, fs,
-    path::Path,
+    io::{self, Write},
+    path::{Path, PathBuf},
     process::{self, Command, Stdio},
     sync::Arc,
 };
 
-use session::SourceFile;
-
-use crate::error_handler::*;
-use once_cell::sync::Lazy;
-use std::io::Write;
-use std::sync::atomic::{AtomicBool, Ordering};
-
-// Global flag for Gen Z mode
-static GENZ_MODE: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
-
-pub fn enable_genz_mode() {
-    GENZ_MODE.store(true, Ordering::SeqCst);
-}
-
-pub fn is_genz_mode() -> bool {
-    GENZ_MODE.load(Ordering::SeqCst)
-}
+use pandora_interpreter::{
+    enable_genz_mode, error_docs, format,
+    interpreter::{self, resolver::ModuleResolver},
+    lsp, parse, repl,
+    session::{self, SourceFile},
+};
 
 fn main() {
     // collect args
@@ -64,9 +41,42 @@ fn main() {
     // verbose mode flag check
     let is_verbose = args.contains(&String::from("--verbose"));
 
+    // tree walker flag check
+    let is_tree_walk = args.contains(&String::from("--tree-walk"));
+
     // chaos mode flag check
     let is_genz = args.contains(&String::from("--wreck"));
 
+    if is_genz {
+        enable_genz_mode();
+    }
+
+    // library search path flag check
+    let lib_paths = lib_paths(&args);
+
+    if args[1] == "fmt" {
+        format::run(&args[2..]);
+    }
+
+    if args[1] == "lsp" {
+        lsp::run();
+    }
+
+    if args[1] == "repl" {
+        // The libraries of the interactive mode are looked up in the working directory.
+        let result = repl::run(
+            ModuleResolver::new(Some(PathBuf::from(".")), lib_paths),
+            is_verbose,
+            io::stdin().lock(),
+            &mut io::stdout(),
+        );
+        if let Err(e) = result {
+            eprintln!("Error: Failed to write output: {e}");
+            process::exit(1);
+        }
+        process::exit(0);
+    }
+
     let filename = &args[1];
 
     if is_genz {
@@ -88,7 +98,6 @@ fn main() {
     }
 
     if is_genz {
-        enable_genz_mode();
         println!("UNLEASH THE CHAOS!!!!!!!!");
     }
 
@@ -110,8 +119,41 @@ fn main() {
     }
     let ast = ast.unwrap();
 
+    // type check
+    let program_dir = Path::new(filename).parent().map(Path::to_path_buf);
+    let resolver = ModuleResolver::new(program_dir, lib_paths);
+    if !interpreter::typecheck(&ast, &session, &resolver) {
+        process::exit(1);
+    }
+
     // interpret
-    interpreter::interpret(&ast, &session, is_verbose);
+    let is_ok = if is_tree_walk {
+        interpreter::interpret(&ast, &session, resolver, is_verbose)
+    } else {
+        interpreter::run(&ast, &session, resolver, is_verbose)
+    };
+    process::exit(if is_ok { 0 } else { 1 });
+}
+
+/// Returns the directories given with `--lib-path`, which can be repeated.
+fn lib_paths(args: &Vec<String>) -> Vec<PathBuf> {
+    let mut lib_paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg != "--lib-path" {
+            continue;
+        }
+
+        match args.next() {
+            Some(dir) if !dir.starts_with("-") => lib_paths.push(PathBuf::from(dir)),
+            _ => {
+                eprintln!("Error: lib-path flag must be followed by a directory");
+                process::exit(1);
+            }
+        }
+    }
+
+    lib_paths
 }
 
 fn try_latest(args: &Vec<String>) {
@@ -259,13 +301,19 @@ fn help() {
     println!("Made by dungtl2003 and himarawi for school project.");
     println!("");
     println!("Usage: unbox <file.box> [--verbose | -v]");
+    println!("       unbox repl         Start the interactive mode");
+    println!("       unbox lsp          Start the language server over stdin/stdout");
+    println!("       unbox fmt [--check] <files...>  Format the files in place");
     println!("Options:");
     println!("  --version, -v      Display version information");
     println!("  --latest           Check for the latest version");
     println!("  --verbose          Enable verbose output");
+    println!("  --tree-walk        Run with the tree-walking interpreter instead of the VM");
     println!("  --help, -h         Display this help message");
     println!("  --wreck            Enable Gen Z mode ☠️☠️☠️");
     println!("  --explain [code]   Explain the error code");
+    println!("  --lib-path <dir>   Also look up libraries in the directory, can be repeated");
+    println!("  --check            With fmt, only check that the files are formatted");
     println!("");
 }
 
@@ -279,6 +327,9 @@ fn validate_options(args: &Vec<String>) {
         "--wreck".to_string(),
         "--explain".to_string(),
         "--latest".to_string(),
+        "--tree-walk".to_string(),
+        "--check".to_string(),
+        "--lib-path".to_string(),
     ];
 
     for arg in args {
@@ -18,7 +18,17 @@ pub fn lex_token_tree<'sess, 'src>(
     src: &'src str,
     session: &'sess mut Session,
 ) -> PResult<TokenStream> {
-    let string_reader = StringReader::new(src, session);
+    lex_token_tree_from(src, 0, session)
+}
+
+/// Lexes the source code from the given position, so that the spans of the tokens are still
+/// relative to the beginning of `src`.
+pub fn lex_token_tree_from(
+    src: &str,
+    start: BytePos,
+    session: &mut Session,
+) -> PResult<TokenStream> {
+    let string_reader = StringReader::new(src, start, session);
 
     let (tokenstream, res) = tokentrees::TokenTreesReader::lex_all_token_trees(string_reader);
 
@@ -37,11 +47,15 @@ struct StringReader<'sess, 'src> {
 }
 
 impl<'sess, 'src> StringReader<'sess, 'src> {
-    fn new(src: &'src str, session: &'sess mut Session) -> StringReader<'sess, 'src> {
+    fn new(
+        src: &'src str,
+        start: BytePos,
+        session: &'sess mut Session,
+    ) -> StringReader<'sess, 'src> {
         StringReader {
             src,
-            pos: 0,
-            cursor: Cursor::new(src),
+            pos: start,
+            cursor: Cursor::new(&src[start as usize..]),
             session,
         }
     }
@@ -144,6 +158,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
         Span {
             offset: start,
             length: (end - start) as usize,
+            file: self.session.file,
         }
     }
 
@@ -420,6 +435,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
             .build_unterminated_string_literal_error(Span {
                 offset: start_quote_pos,
                 length: (end - start_quote_pos) as usize,
+                file: self.session.file,
             })
             .into();
 
@@ -431,6 +447,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
         let span = Span {
             offset: start,
             length: (end - start) as usize,
+            file: self.session.file,
         };
         let report = self
             .session
@@ -445,6 +462,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
         let span = Span {
             offset: pos,
             length: len,
+            file: self.session.file,
         };
         let report = self
             .session
@@ -459,6 +477,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
         let span = Span {
             offset: pos,
             length: 1,
+            file: self.session.file,
         };
 
         let report = self
@@ -474,6 +493,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
         let span = Span {
             offset: pos,
             length: len,
+            file: self.session.file,
         };
         let report = self
             .session
@@ -525,10 +545,12 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                     Some(Span {
                         offset: start + nested_open_idx as BytePos,
                         length: 2,
+                        file: self.session.file,
                     }),
                     Some(Span {
                         offset: start + nested_close_idx as BytePos,
                         length: 2,
+                        file: self.session.file,
                     }),
                     None,
                 )
@@ -546,6 +568,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                     Some(Span {
                         offset: start,
                         length: tok_len as usize,
+                        file: self.session.file,
                     }),
                 )
                 .into();
@@ -562,6 +585,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
             .build_unterminated_character_literal_error(Span {
                 offset: start_quote_pos,
                 length: 1,
+                file: self.session.file,
             })
             .into();
 
@@ -607,6 +631,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                 Span {
                     offset: start,
                     length: (end - start) as usize,
+                    file: self.session.file,
                 },
             )
             .into();
@@ -625,6 +650,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                         Span {
                             offset: start,
                             length: (self.pos - start) as usize,
+                            file: self.session.file,
                         },
                     )
                     .into()
@@ -638,6 +664,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                 let start_span = Span {
                     offset: start,
                     length: 1,
+                    file: self.session.file,
                 };
                 let possible_terminator_span = if found > 0 {
                     let terminator_offset = possible_terminator_offset.unwrap();
@@ -653,6 +680,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                         possible_terminator_span.map(|value| Span {
                             offset: value.0 as BytePos,
                             length: value.1,
+                            file: self.session.file,
                         }),
                     )
                     .into()
@@ -666,6 +694,7 @@ impl<'sess, 'src> StringReader<'sess, 'src> {
                         Span {
                             offset: start,
                             length: (self.pos - start) as usize,
+                            file: self.session.file,
                         },
                     )
                     .into()
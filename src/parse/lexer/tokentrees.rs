@@ -55,7 +55,11 @@ impl<'sess, 'src> TokenTreesReader<'sess, 'src> {
                 TokenKind::Eof => {
                     if is_delimited {
                         // This is weird but for display.
-                        let span = Span::new(self.token.span.offset - 1, self.token.span.offset);
+                        let span = Span::new(
+                            self.token.span.offset - 1,
+                            self.token.span.offset,
+                            self.token.span.file,
+                        );
                         let err = PError::UnclosedDelimiter {
                             unclosed_delimiter_spans: self
                                 .open_delims
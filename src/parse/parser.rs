@@ -1,4 +1,5 @@
 mod expr;
+mod pat;
 mod stmt;
 mod ty;
 
@@ -12,7 +13,7 @@ use crate::{
         TokenTree, TokenTreeCursor,
     },
     kw::{self, Keyword},
-    session::Session,
+    session::{BytePos, Session},
     span_encoding::DUMMY_SP,
 };
 
@@ -20,7 +21,13 @@ use super::errors::PError;
 use super::{lexer, PResult};
 
 pub fn parse(contents: &str, session: &mut Session) -> Option<Ast> {
-    let tokens = lexer::lex_token_tree(contents, session);
+    parse_from(contents, 0, session)
+}
+
+/// Parses the source code from the given position, e.g. the last input of the REPL, whose
+/// spans are relative to the beginning of everything typed so far.
+pub fn parse_from(contents: &str, start: BytePos, session: &mut Session) -> Option<Ast> {
+    let tokens = lexer::lex_token_tree_from(contents, start, session);
 
     if !session.can_recover() {
         return None;
@@ -53,6 +60,11 @@ pub fn parse(contents: &str, session: &mut Session) -> Option<Ast> {
                 if need_recover {
                     parser.recover();
                 }
+                // A closing brace ends no statement at the top level, so it must be skipped to
+                // make progress.
+                if parser.token.is_close_delim(Delimiter::Brace) {
+                    parser.advance();
+                }
             }
         }
     }
@@ -82,6 +94,9 @@ pub struct Parser {
     pub prev_token: Token,
     expected_tokens: Vec<TokenType>,
     token_cursor: TokenCursor,
+    /// Whether a struct literal is forbidden in the current expression. This is needed for
+    /// conditions like `when x { ... }`, where `x { ... }` is not a struct literal.
+    no_struct_literal: bool,
 }
 
 impl Parser {
@@ -95,6 +110,7 @@ impl Parser {
                 tree_cursor: stream.into_trees(),
                 stack: Vec::new(),
             },
+            no_struct_literal: false,
         };
 
         // Make parser point to the first token.
@@ -117,6 +133,36 @@ impl Parser {
         }
     }
 
+    /// Parses the contents of the delimited group that begins at the current token with `f`, and
+    /// eats its closing delimiter. If `f` fails, the rest of the group is skipped, so that the
+    /// parser goes on after the group instead of stopping at a token inside it.
+    fn parse_delimited<T>(
+        &mut self,
+        delim: Delimiter,
+        f: impl FnOnce(&mut Self) -> PResult<T>,
+    ) -> PResult<T> {
+        self.expect(TokenKind::OpenDelim(delim))?;
+        // The group stays on the stack of the token cursor until its closing delimiter.
+        let depth = self.token_cursor.stack.len();
+        self.advance(); // Eat the opening delimiter
+
+        let result = f(self).and_then(|value| {
+            self.expect(TokenKind::CloseDelim(delim))?;
+            Ok(value)
+        });
+
+        if result.is_err() {
+            while self.token.kind != TokenKind::Eof && self.token_cursor.stack.len() >= depth {
+                self.advance();
+            }
+        }
+        if self.token.is_close_delim(delim) {
+            self.advance(); // Eat the closing delimiter
+        }
+
+        result
+    }
+
     fn is_synchronized(&self) -> bool {
         if self.token.is_keyword(Keyword::Set)
             || self.token.is_keyword(Keyword::When)
@@ -127,6 +173,10 @@ impl Parser {
             || self.token.is_keyword(Keyword::Add)
             || self.token.is_keyword(Keyword::Br)
             || self.token.is_keyword(Keyword::Skip)
+            || self.token.is_keyword(Keyword::Struct)
+            || self.token.is_keyword(Keyword::Enum)
+            || self.token.is_keyword(Keyword::Match)
+            || self.token.is_keyword(Keyword::Priv)
         {
             return true;
         }
@@ -151,6 +201,20 @@ impl Parser {
         self.expected_tokens.clear();
     }
 
+    /// Look ahead `dist` tokens past the current one without advancing the parser.
+    pub fn look_ahead<R>(&self, dist: usize, looker: impl FnOnce(&Token) -> R) -> R {
+        if dist == 0 {
+            return looker(&self.token);
+        }
+
+        let mut cursor = self.token_cursor.clone();
+        let mut token = Token::dummy();
+        for _ in 0..dist {
+            token = cursor.next().0;
+        }
+        looker(&token)
+    }
+
     fn expect(&mut self, expected: TokenKind) -> PResult<()> {
         if self.token.kind == expected {
             return Ok(());
@@ -281,3 +345,48 @@ impl TokenCursor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::session::SourceFile;
+
+    /// Parses a program and returns the messages of the errors found in it.
+    fn parse_errors(src: &str) -> Vec<String> {
+        let file = SourceFile::new("<test>", Arc::new(src.to_string()));
+        let mut session = Session::new_collecting(Arc::new(file));
+        let ast = parse(src, &mut session);
+        let reports = session.error_handler.take_reports();
+        assert_eq!(ast.is_some(), reports.is_empty());
+        reports.iter().map(|report| report.to_string()).collect()
+    }
+
+    #[test]
+    fn recovers_from_errors_in_struct_braces() {
+        assert!(!parse_errors("struct P { x: int y: int }").is_empty());
+        assert!(!parse_errors("set p: P = P { x: 1 y: 2 };").is_empty());
+        // The parser goes on after the braces, and finds errors in the next statements too.
+        let errors = parse_errors("struct P { x: int y: int } set p: P = P { x: 1 y: 2 }; set;");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+
+    #[test]
+    fn recovers_from_errors_in_enum_and_match_braces() {
+        assert!(!parse_errors("enum E { A B }").is_empty());
+        assert!(!parse_errors("enum E { A(int int) }").is_empty());
+        assert!(!parse_errors("match 1 { 1 {} }").is_empty());
+        let errors = parse_errors("enum E { A(int int), B } match 1 { 1 {} } set;");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+
+    #[test]
+    fn recovers_from_errors_in_import_selections() {
+        for src in ["add math.{sqrt pow};", "add math.{};", "add m.{f as g};"] {
+            assert!(!parse_errors(src).is_empty(), "{}", src);
+        }
+        let errors = parse_errors("add math.{}; add m.{f as g}; set;");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
     ast::{
-        BinOp, BinOpKind, BinOpToken, Delimiter, Expr, ExprKind, Lit, LitKind, TokenKind, Ty, UnOp,
+        BinOp, BinOpKind, BinOpToken, Closure, Delimiter, Expr, ExprField, ExprKind, Ident, Lit,
+        LitKind, RangeLimits, TokenKind, Ty, UnOp,
     },
+    kw::Keyword,
     parse::{
         errors::PError,
         util::parser::{AssocOp, Fixity},
@@ -14,8 +16,22 @@ use crate::span_encoding;
 
 impl Parser {
     pub fn parse_expr(&mut self) -> PResult<Box<Expr>> {
-        let lhs = self.parse_expr_prefix()?;
-        self.parse_expr_rest(0, lhs)
+        self.parse_expr_res(false)
+    }
+
+    /// Parses an expression in which a struct literal is not allowed at the top level.
+    /// E.g., the condition of `when`, where `{` starts the block instead.
+    pub fn parse_expr_no_struct(&mut self) -> PResult<Box<Expr>> {
+        self.parse_expr_res(true)
+    }
+
+    fn parse_expr_res(&mut self, no_struct_literal: bool) -> PResult<Box<Expr>> {
+        let old = std::mem::replace(&mut self.no_struct_literal, no_struct_literal);
+        let result = self
+            .parse_expr_prefix()
+            .and_then(|lhs| self.parse_expr_rest(0, lhs));
+        self.no_struct_literal = old;
+        result
     }
 
     fn parse_expr_rest(&mut self, min_prec: usize, mut lhs: Box<Expr>) -> PResult<Box<Expr>> {
@@ -45,7 +61,7 @@ impl Parser {
 
             let fixity = op_assoc.fixity();
             let next_prec = match fixity {
-                Fixity::Left => prec + 1,
+                Fixity::Left | Fixity::None => prec + 1,
                 Fixity::Right => prec,
             };
 
@@ -75,6 +91,12 @@ impl Parser {
                     let binary = self.mk_binary(span_encoding::respan(ast_op, op_span), lhs, rhs);
                     self.mk_expr(binary, span)
                 }
+                AssocOp::DotDot => {
+                    self.mk_expr(ExprKind::Range(lhs, rhs, RangeLimits::HalfOpen), span)
+                }
+                AssocOp::DotDotEq => {
+                    self.mk_expr(ExprKind::Range(lhs, rhs, RangeLimits::Closed), span)
+                }
                 AssocOp::Assign => self.mk_expr(ExprKind::Assign(lhs, rhs, op_span), span),
                 AssocOp::AssignOp(k) => {
                     let aop = match k {
@@ -93,6 +115,11 @@ impl Parser {
                     self.mk_expr(aopexpr, span)
                 }
                 AssocOp::As => unreachable!("AssocOp::As should be handled separately"),
+            };
+
+            // A non-associative operator cannot be chained, e.g. `a..b..c`.
+            if fixity == Fixity::None {
+                break;
             }
         }
 
@@ -174,14 +201,63 @@ impl Parser {
 
     fn parse_expr_dot(&mut self, base: Box<Expr>) -> PResult<Box<Expr>> {
         debug_assert!(self.token.is_kind(TokenKind::Dot));
-        let start = self.token.span;
         self.advance();
+
+        if let TokenKind::Literal(lit) = self.token.kind {
+            return self.parse_expr_tuple_field(base, lit);
+        }
+
         let field = self.parse_ident()?;
-        let span = start.to(field.span);
+        let span = self.mk_expr_sp(&base, field.span);
         let dot = ExprKind::LibAccess(base, field);
         Ok(self.mk_expr(dot, span))
     }
 
+    /// Parses the field index of a tuple field access, e.g. `foo.0`.
+    /// `foo.0.1` is lexed as `foo`, `.` and the float `0.1`, so we have to split the float.
+    fn parse_expr_tuple_field(&mut self, base: Box<Expr>, lit: Lit) -> PResult<Box<Expr>> {
+        let span = self.token.span;
+        let symbol = lit.symbol.as_str();
+
+        let fields: Vec<&str> = match lit.kind {
+            LitKind::Int => vec![symbol],
+            LitKind::Float => symbol.split('.').collect(),
+            _ => vec![],
+        };
+
+        let is_valid = !fields.is_empty()
+            && fields.len() <= 2
+            && fields.iter().all(|field| {
+                field.chars().all(|c| c.is_ascii_digit()) && field.parse::<usize>().is_ok()
+            });
+        if !is_valid {
+            let err = PError::ExpectedToken {
+                expected: vec![TokenType::Ident],
+                found: TokenType::Token(self.token.kind),
+                span,
+                prev_span: self.prev_token.span,
+            };
+            return Err(vec![err]);
+        }
+        self.advance();
+
+        let mut base = base;
+        let mut offset = span.offset;
+        for field in fields {
+            let field_span = Span {
+                offset,
+                length: field.len(),
+                file: span.file,
+            };
+            let index = field.parse::<usize>().unwrap();
+            let expr_span = self.mk_expr_sp(&base, field_span);
+            base = self.mk_expr(ExprKind::TupleField(base, index, field_span), expr_span);
+            offset += field.len() as u32 + 1; // skip the `.`
+        }
+
+        Ok(base)
+    }
+
     fn parse_expr_array_index(&mut self, base: Box<Expr>) -> PResult<Box<Expr>> {
         debug_assert!(self.token.is_open_delim(Delimiter::Bracket));
         let start = self.token.span;
@@ -239,6 +315,9 @@ impl Parser {
     fn parse_expr_bottom(&mut self) -> PResult<Box<Expr>> {
         match self.token.kind {
             TokenKind::Literal(_) => self.parse_expr_lit(),
+            TokenKind::Ident(_, _) if self.token.is_keyword(Keyword::Fun) => {
+                self.parse_expr_closure()
+            }
             TokenKind::Ident(_, _) => self.parse_expr_ident(),
             TokenKind::OpenDelim(Delimiter::Parenthesis) => {
                 self.parse_expr_grouped(Delimiter::Parenthesis)
@@ -262,6 +341,29 @@ impl Parser {
         }
     }
 
+    /// Parses an anonymous function.
+    /// Closure = 'fun' '(' [Param] ')' ('->' Type)? Block
+    fn parse_expr_closure(&mut self) -> PResult<Box<Expr>> {
+        debug_assert!(self.token.is_keyword(Keyword::Fun));
+        let start = self.token.span;
+        self.advance(); // eat 'fun'
+
+        let inputs = self.parse_fun_params()?;
+        let output = self.parse_fun_ret_ty()?;
+        let sig_span = start.to(self.prev_token.span);
+
+        let body = self.parse_stmt_block()?;
+        let span = start.to(self.prev_token.span);
+
+        let closure = Closure {
+            inputs,
+            output,
+            body,
+            span: sig_span,
+        };
+        Ok(self.mk_expr(ExprKind::Closure(Box::new(closure)), span))
+    }
+
     fn parse_expr_array(&mut self) -> PResult<Box<Expr>> {
         debug_assert!(self.token.is_open_delim(Delimiter::Bracket));
         let start = self.token.span;
@@ -325,22 +427,126 @@ impl Parser {
             self.parse_expr_lit()
         } else {
             let ident = self.parse_ident()?;
+            if self.token.is_open_delim(Delimiter::Brace) && !self.no_struct_literal {
+                return self.parse_expr_struct(ident);
+            }
+            if self.token.is_kind(TokenKind::PathSep) {
+                return self.parse_expr_variant(ident);
+            }
+
             let span = ident.span;
             let expr = ExprKind::Identifier(ident);
             Ok(self.mk_expr(expr, span))
         }
     }
 
+    /// Parses an enum variant.
+    /// Variant = Ident '::' Ident ('(' [Expr] ')')?
+    fn parse_expr_variant(&mut self, enum_name: Ident) -> PResult<Box<Expr>> {
+        debug_assert!(self.token.is_kind(TokenKind::PathSep));
+        self.advance(); // eat '::'
+
+        let variant = self.parse_ident()?;
+
+        let mut args = Vec::new();
+        if self.token.is_open_delim(Delimiter::Parenthesis) {
+            self.advance(); // eat '('
+            loop {
+                if self.token.is_close_delim(Delimiter::Parenthesis) {
+                    break;
+                }
+
+                args.push(self.parse_expr()?);
+
+                if !self.token.is_kind(TokenKind::Comma) {
+                    break;
+                }
+
+                self.advance(); // eat comma
+            }
+
+            self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
+            self.advance(); // eat ')'
+        }
+
+        let span = enum_name.span.to(self.prev_token.span);
+        Ok(self.mk_expr(ExprKind::Variant(enum_name, variant, args), span))
+    }
+
+    /// Parses a struct literal.
+    /// Struct = Ident '{' (Field (',' Field)* ','?)? '}'
+    /// Field = Ident (':' Expr)?
+    ///
+    /// `Point { x, y }` is a shorthand for `Point { x: x, y: y }`.
+    fn parse_expr_struct(&mut self, name: Ident) -> PResult<Box<Expr>> {
+        debug_assert!(self.token.is_open_delim(Delimiter::Brace));
+
+        let fields = self.parse_delimited(Delimiter::Brace, |this| {
+            let mut fields = Vec::new();
+            loop {
+                if this.token.is_close_delim(Delimiter::Brace) {
+                    break;
+                }
+
+                let ident = this.parse_ident()?;
+                let expr = if this.token.is_kind(TokenKind::Colon) {
+                    this.advance(); // eat ':'
+                    this.parse_expr()?
+                } else {
+                    this.mk_expr(ExprKind::Identifier(ident.clone()), ident.span)
+                };
+                let span = ident.span.to(expr.span);
+                fields.push(ExprField { ident, expr, span });
+
+                if !this.token.is_kind(TokenKind::Comma) {
+                    break;
+                }
+
+                this.advance(); // eat comma
+            }
+            Ok(fields)
+        })?;
+        let span = name.span.to(self.prev_token.span);
+
+        Ok(self.mk_expr(ExprKind::Struct(name, fields), span))
+    }
+
+    /// Parses a parenthesized expression or a tuple.
+    /// `()` is the unit value, `(expr)` is just `expr` and `(expr,)` is a tuple with one element.
     fn parse_expr_grouped(&mut self, delim: Delimiter) -> PResult<Box<Expr>> {
+        let start = self.token.span;
         self.advance();
-        let expr = self.parse_expr()?;
+
+        let mut exprs = Vec::new();
+        let mut trailing_comma = false;
+        loop {
+            if self.token.is_close_delim(delim) {
+                break;
+            }
+
+            exprs.push(self.parse_expr()?);
+
+            trailing_comma = self.token.is_kind(TokenKind::Comma);
+            if !trailing_comma {
+                break;
+            }
+
+            self.advance(); // eat comma
+        }
+
         self.expect(TokenKind::CloseDelim(delim))?;
+        let span = start.to(self.token.span);
         self.advance();
-        Ok(expr)
+
+        if exprs.len() == 1 && !trailing_comma {
+            return Ok(exprs.pop().unwrap());
+        }
+
+        Ok(self.mk_expr(ExprKind::Tuple(exprs), span))
     }
 
     /// Parses a literal expression. Lit = true | false | token_lit~
-    fn parse_expr_lit(&mut self) -> PResult<Box<Expr>> {
+    pub fn parse_expr_lit(&mut self) -> PResult<Box<Expr>> {
         let lit = self.parse_token_lit()?;
         let span = self.prev_token.span;
         let expr = self.mk_literal(lit);
@@ -0,0 +1,143 @@
+use crate::ast::{BinOpToken, Delimiter, Expr, ExprKind, Pat, PatKind, TokenKind, UnOp};
+
+use super::{PResult, Parser};
+
+impl Parser {
+    /// pattern = '_' | '-'? literal | identifier | path ('(' (pattern (',' pattern)* ','?)? ')')?
+    ///         | '(' (pattern (',' pattern)* ','?)? ')'
+    /// path = identifier '::' identifier
+    pub fn parse_pat(&mut self) -> PResult<Pat> {
+        self.parse_pat_with(true)
+    }
+
+    /// Parses the pattern of a variable declaration, which must always match.
+    /// local_pattern = '_' | identifier | '(' (local_pattern (',' local_pattern)* ','?)? ')'
+    pub fn parse_pat_local(&mut self) -> PResult<Pat> {
+        self.parse_pat_with(false)
+    }
+
+    fn parse_pat_with(&mut self, allow_refutable: bool) -> PResult<Pat> {
+        if self.token.is_open_delim(Delimiter::Parenthesis) {
+            self.parse_pat_tuple_or_parens(allow_refutable)
+        } else if self
+            .token
+            .is_non_raw_ident_where(|ident| ident.name.as_str() == "_")
+        {
+            let span = self.token.span;
+            self.advance(); // eat '_'
+            Ok(Pat {
+                kind: PatKind::Wild,
+                span,
+            })
+        } else if allow_refutable && self.token.can_begin_literal_maybe_minus() {
+            self.parse_pat_lit()
+        } else {
+            self.parse_pat_ident(allow_refutable)
+        }
+    }
+
+    fn parse_pat_lit(&mut self) -> PResult<Pat> {
+        let expr = if self.token.kind == TokenKind::BinOp(BinOpToken::Minus) {
+            let start = self.token.span;
+            self.advance(); // eat '-'
+            let lit = self.parse_expr_lit()?;
+            let span = start.to(lit.span);
+            Box::new(Expr {
+                kind: ExprKind::Unary(UnOp::Ne, lit),
+                span,
+            })
+        } else {
+            self.parse_expr_lit()?
+        };
+
+        let span = expr.span;
+        Ok(Pat {
+            kind: PatKind::Lit(expr),
+            span,
+        })
+    }
+
+    fn parse_pat_ident(&mut self, allow_refutable: bool) -> PResult<Pat> {
+        let ident = self.parse_ident()?;
+        if !allow_refutable || self.token.kind != TokenKind::PathSep {
+            let span = ident.span;
+            return Ok(Pat {
+                kind: PatKind::Ident(ident),
+                span,
+            });
+        }
+
+        self.advance(); // eat '::'
+        let variant = self.parse_ident()?;
+
+        let mut fields = Vec::new();
+        if self.token.is_open_delim(Delimiter::Parenthesis) {
+            self.advance(); // eat '('
+            loop {
+                if self.token.is_close_delim(Delimiter::Parenthesis) {
+                    break;
+                }
+
+                fields.push(self.parse_pat()?);
+
+                if self.token.kind != TokenKind::Comma {
+                    break;
+                }
+
+                self.advance(); // eat comma
+            }
+
+            self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
+            self.advance(); // eat ')'
+        }
+
+        let span = ident.span.to(self.prev_token.span);
+        Ok(Pat {
+            kind: PatKind::Variant(ident, variant, fields),
+            span,
+        })
+    }
+
+    /// `(p)` is just `p` while `(p,)` is a tuple pattern with one element.
+    fn parse_pat_tuple_or_parens(&mut self, allow_refutable: bool) -> PResult<Pat> {
+        debug_assert!(self.token.is_open_delim(Delimiter::Parenthesis));
+
+        let start = self.token.span;
+        self.advance();
+
+        let mut pats = Vec::new();
+        let mut trailing_comma = false;
+        loop {
+            if self.token.is_close_delim(Delimiter::Parenthesis) {
+                break;
+            }
+
+            pats.push(self.parse_pat_with(allow_refutable)?);
+
+            trailing_comma = self.token.kind == TokenKind::Comma;
+            if !trailing_comma {
+                break;
+            }
+
+            self.advance(); // eat comma
+        }
+
+        self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
+        self.advance();
+
+        let span = start.to(self.prev_token.span);
+
+        if pats.len() == 1 && !trailing_comma {
+            let pat = pats.pop().unwrap();
+            return Ok(Pat {
+                kind: pat.kind,
+                span,
+            });
+        }
+
+        Ok(Pat {
+            kind: PatKind::Tuple(pats),
+            span,
+        })
+    }
+}
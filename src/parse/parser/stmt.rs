@@ -1,5 +1,8 @@
 use super::{PResult, Parser, TokenType};
-use crate::ast::{Delimiter, Fun, FunParam, FunSig};
+use crate::ast::{
+    Arm, Delimiter, EnumDef, FieldDef, Fun, FunParam, FunSig, Ident, Import, ImportKind, PatKind,
+    StructDef, Ty, VariantDef,
+};
 use crate::kw;
 use crate::parse::errors::PError;
 use crate::{
@@ -24,7 +27,10 @@ impl Parser {
             self.parse_stmt_return()
         } else if self.token.kind == TokenKind::Semicolon {
             self.parse_stmt_empty()
-        } else if self.token.is_keyword(Keyword::Fun) {
+        } else if self.token.is_keyword(Keyword::Priv)
+            || self.token.is_keyword(Keyword::Fun)
+                && !self.look_ahead(1, |token| token.is_open_delim(Delimiter::Parenthesis))
+        {
             self.parse_stmt_func_decl()
         } else if self.token.is_keyword(Keyword::Add) {
             self.parse_stmt_import()
@@ -32,6 +38,12 @@ impl Parser {
             self.parse_stmt_break()
         } else if self.token.is_keyword(Keyword::Skip) {
             self.parse_stmt_continue()
+        } else if self.token.is_keyword(Keyword::Struct) {
+            self.parse_stmt_struct_decl()
+        } else if self.token.is_keyword(Keyword::Enum) {
+            self.parse_stmt_enum_decl()
+        } else if self.token.is_keyword(Keyword::Match) {
+            self.parse_stmt_match()
         } else if self.token.can_begin_expr() {
             self.parse_stmt_expr()
         } else {
@@ -44,6 +56,125 @@ impl Parser {
         }
     }
 
+    /// struct_declaration = 'struct' identifier '{' (field (',' field)* ','?)? '}'
+    /// field = identifier ':' type_specifier
+    fn parse_stmt_struct_decl(&mut self) -> PResult<Box<Stmt>> {
+        if !self.token.is_keyword(Keyword::Struct) {
+            let err = PError::ExpectedToken {
+                expected: vec![TokenType::Keyword(kw::to_symbol(Keyword::Struct))],
+                found: TokenType::Token(self.token.kind),
+                span: self.token.span,
+                prev_span: self.prev_token.span,
+            };
+
+            return Err(vec![err]);
+        }
+
+        let start_span = self.token.span;
+        self.advance(); // Eat "struct"
+
+        let name = self.parse_ident()?;
+
+        let fields = self.parse_delimited(Delimiter::Brace, |this| {
+            let mut fields: Vec<FieldDef> = Vec::new();
+            loop {
+                if this.token.is_close_delim(Delimiter::Brace) {
+                    break;
+                }
+
+                let ident = this.parse_ident()?;
+
+                this.expect(TokenKind::Colon)?;
+                this.advance(); // Eat ':'
+
+                let ty = this.parse_ty()?;
+                let span = ident.span.to(this.prev_token.span);
+                fields.push(FieldDef { ident, ty, span });
+
+                if this.token.kind != TokenKind::Comma {
+                    break;
+                }
+
+                this.advance(); // Eat ','
+            }
+            Ok(fields)
+        })?;
+        let span = start_span.to(self.prev_token.span);
+
+        let kind = StmtKind::StructDecl(Box::new(StructDef { name, fields }));
+        Ok(Box::new(Stmt { kind, span }))
+    }
+
+    /// enum_declaration = 'enum' identifier '{' (variant (',' variant)* ','?)? '}'
+    /// variant = identifier ('(' type_specifier (',' type_specifier)* ','? ')')?
+    fn parse_stmt_enum_decl(&mut self) -> PResult<Box<Stmt>> {
+        if !self.token.is_keyword(Keyword::Enum) {
+            let err = PError::ExpectedToken {
+                expected: vec![TokenType::Keyword(kw::to_symbol(Keyword::Enum))],
+                found: TokenType::Token(self.token.kind),
+                span: self.token.span,
+                prev_span: self.prev_token.span,
+            };
+
+            return Err(vec![err]);
+        }
+
+        let start_span = self.token.span;
+        self.advance(); // Eat "enum"
+
+        let name = self.parse_ident()?;
+
+        let variants = self.parse_delimited(Delimiter::Brace, |this| {
+            let mut variants: Vec<VariantDef> = Vec::new();
+            loop {
+                if this.token.is_close_delim(Delimiter::Brace) {
+                    break;
+                }
+
+                let ident = this.parse_ident()?;
+
+                let mut fields = Vec::new();
+                if this.token.is_open_delim(Delimiter::Parenthesis) {
+                    fields = this.parse_delimited(Delimiter::Parenthesis, |this| {
+                        let mut fields = Vec::new();
+                        loop {
+                            if this.token.is_close_delim(Delimiter::Parenthesis) {
+                                break;
+                            }
+
+                            fields.push(this.parse_ty()?);
+
+                            if this.token.kind != TokenKind::Comma {
+                                break;
+                            }
+
+                            this.advance(); // Eat ','
+                        }
+                        Ok(fields)
+                    })?;
+                }
+
+                let span = ident.span.to(this.prev_token.span);
+                variants.push(VariantDef {
+                    ident,
+                    fields,
+                    span,
+                });
+
+                if this.token.kind != TokenKind::Comma {
+                    break;
+                }
+
+                this.advance(); // Eat ','
+            }
+            Ok(variants)
+        })?;
+        let span = start_span.to(self.prev_token.span);
+
+        let kind = StmtKind::EnumDecl(Box::new(EnumDef { name, variants }));
+        Ok(Box::new(Stmt { kind, span }))
+    }
+
     fn parse_stmt_continue(&mut self) -> PResult<Box<Stmt>> {
         if !self.token.is_keyword(Keyword::Skip) {
             let err = PError::ExpectedToken {
@@ -94,6 +225,21 @@ impl Parser {
         }))
     }
 
+    /// Parses the functions selected from a library: '{' ident (',' ident)* '}'
+    fn parse_import_selection(&mut self) -> PResult<Vec<Ident>> {
+        self.parse_delimited(Delimiter::Brace, |this| {
+            let mut names = vec![this.parse_ident()?];
+            while this.token.kind == TokenKind::Comma {
+                this.advance(); // Eat ','
+                if this.token.is_close_delim(Delimiter::Brace) {
+                    break;
+                }
+                names.push(this.parse_ident()?);
+            }
+            Ok(names)
+        })
+    }
+
     fn parse_stmt_import(&mut self) -> PResult<Box<Stmt>> {
         if !self.token.is_keyword(Keyword::Add) {
             let err = PError::ExpectedToken {
@@ -109,18 +255,47 @@ impl Parser {
         let start_span = self.token.span;
         self.advance(); // Eat "import"
 
-        let path = self.parse_ident()?;
+        let mut path = vec![self.parse_ident()?];
+        let mut kind = ImportKind::Library;
+        while self.token.kind == TokenKind::Dot {
+            self.advance(); // Eat "."
+            if self.token.is_open_delim(Delimiter::Brace) {
+                kind = ImportKind::Select(self.parse_import_selection()?);
+                break;
+            }
+            path.push(self.parse_ident()?);
+        }
+        let path_span = path[0].span.to(path.last().unwrap().span);
+
+        if matches!(kind, ImportKind::Library) && self.token.is_keyword(Keyword::As) {
+            self.advance(); // Eat "as"
+            kind = ImportKind::Alias(self.parse_ident()?);
+        }
+
         self.expect(TokenKind::Semicolon)?;
         let span = start_span.to(self.token.span);
         self.advance();
 
-        let kind = StmtKind::Import(path);
+        let kind = StmtKind::Import(Box::new(Import {
+            path,
+            kind,
+            span: path_span,
+        }));
         let stmt = Box::new(Stmt { kind, span });
 
         Ok(stmt)
     }
 
+    /// function_declaration = 'priv'? 'fun' identifier function_parameters return_type statement
     fn parse_stmt_func_decl(&mut self) -> PResult<Box<Stmt>> {
+        let start_span = self.token.span;
+        let is_private = if self.token.is_keyword(Keyword::Priv) {
+            self.advance(); // Eat "priv"
+            true
+        } else {
+            false
+        };
+
         if !self.token.is_keyword(Keyword::Fun) {
             let err = PError::ExpectedToken {
                 expected: vec![TokenType::Keyword(kw::to_symbol(Keyword::Fun))],
@@ -131,7 +306,6 @@ impl Parser {
 
             return Err(vec![err]);
         }
-        let start_span = self.token.span;
         self.advance(); // Eat "fn"
 
         let sig = self.parse_stmt_func_sig()?;
@@ -139,7 +313,11 @@ impl Parser {
 
         let end_span = self.prev_token.span;
         let span = start_span.to(end_span);
-        let kind = StmtKind::FuncDecl(Box::new(Fun { sig, body }));
+        let kind = StmtKind::FuncDecl(Box::new(Fun {
+            is_private,
+            sig,
+            body,
+        }));
         let stmt = Box::new(Stmt { kind, span });
 
         Ok(stmt)
@@ -192,7 +370,7 @@ impl Parser {
         let start_span = self.token.span;
         self.advance();
 
-        let condition = self.parse_expr()?;
+        let condition = self.parse_expr_no_struct()?;
         let block = self.parse_stmt()?;
         let end_span = self.prev_token.span;
         let span = start_span.to(end_span);
@@ -232,7 +410,7 @@ impl Parser {
         }
 
         self.advance();
-        let expr = self.parse_expr()?;
+        let expr = self.parse_expr_no_struct()?;
         let block = self.parse_stmt()?;
         let end_span = self.prev_token.span;
         let span = start_span.to(end_span);
@@ -258,7 +436,7 @@ impl Parser {
         let start_span = self.token.span;
         self.advance(); // Eat token after "if"
                         // Parse the condition expression.
-        let condition = self.parse_expr()?;
+        let condition = self.parse_expr_no_struct()?;
         // Parse the block for the `if` statement.
         let if_block = self.parse_stmt()?;
 
@@ -279,6 +457,48 @@ impl Parser {
         Ok(stmt)
     }
 
+    /// match_statement = 'match' expression '{' (pattern '=>' block_statement ','?)* '}'
+    fn parse_stmt_match(&mut self) -> PResult<Box<Stmt>> {
+        if !self.token.is_keyword(Keyword::Match) {
+            let err = PError::ExpectedToken {
+                expected: vec![TokenType::Keyword(kw::to_symbol(Keyword::Match))],
+                found: TokenType::Token(self.token.kind),
+                span: self.token.span,
+                prev_span: self.prev_token.span,
+            };
+
+            return Err(vec![err]);
+        }
+
+        let start_span = self.token.span;
+        self.advance(); // Eat "match"
+
+        let scrutinee = self.parse_expr_no_struct()?;
+
+        let arms = self.parse_delimited(Delimiter::Brace, |this| {
+            let mut arms: Vec<Arm> = Vec::new();
+            while !this.token.is_close_delim(Delimiter::Brace) {
+                let pat = this.parse_pat()?;
+
+                this.expect(TokenKind::FatArrow)?;
+                this.advance(); // Eat '=>'
+
+                let body = this.parse_stmt_block()?;
+                let span = pat.span.to(body.span);
+                arms.push(Arm { pat, body, span });
+
+                if this.token.kind == TokenKind::Comma {
+                    this.advance(); // Eat ','
+                }
+            }
+            Ok(arms)
+        })?;
+        let span = start_span.to(self.prev_token.span);
+
+        let kind = StmtKind::Match(scrutinee, arms);
+        Ok(Box::new(Stmt { kind, span }))
+    }
+
     /// block_statement = '{' statement* '}'
     pub fn parse_stmt_block(&mut self) -> PResult<Box<Stmt>> {
         self.expect(TokenKind::OpenDelim(Delimiter::Brace))?;
@@ -334,7 +554,10 @@ impl Parser {
         Ok(stmt)
     }
 
-    /// variable_declaration = 'var' 'mut'? identifier: type_specifier ('=' expression)? ';'
+    /// variable_declaration = 'var' 'mut'? pattern (':' type_specifier)? ('=' expression)? ';'
+    ///
+    /// The type can only be omitted when the pattern is not a plain identifier, in which case the
+    /// initializer is mandatory.
     fn parse_stmt_var_decl(&mut self) -> PResult<Box<Stmt>> {
         if !self.token.is_keyword(Keyword::Set) {
             let err = PError::ExpectedToken {
@@ -357,12 +580,19 @@ impl Parser {
             false
         };
 
-        let ident = self.parse_ident()?;
-        self.expect(TokenKind::Colon)?;
-        self.advance(); // ':'
-        let ty = self.parse_ty()?;
+        let pat = self.parse_pat_local()?;
+        let is_destructuring = !matches!(pat.kind, PatKind::Ident(_));
+
+        let ty = if !is_destructuring || self.token.kind == TokenKind::Colon {
+            self.expect(TokenKind::Colon)?;
+            self.advance(); // ':'
+            Some(self.parse_ty()?)
+        } else {
+            None
+        };
 
-        let init = if self.token.kind == TokenKind::Eq {
+        let init = if self.token.kind == TokenKind::Eq || is_destructuring {
+            self.expect(TokenKind::Eq)?;
             self.advance(); // expr
             Some(self.parse_expr()?)
         } else {
@@ -382,7 +612,7 @@ impl Parser {
 
         let local = Local {
             is_mut,
-            ident,
+            pat,
             ty,
             kind,
             span,
@@ -407,7 +637,23 @@ impl Parser {
     fn parse_stmt_func_sig(&mut self) -> PResult<FunSig> {
         let start = self.token.span;
         let name = self.parse_ident()?;
+        let inputs = self.parse_fun_params()?;
+        let output = self.parse_fun_ret_ty()?;
+
+        let end = self.prev_token.span;
+        let span = start.to(end);
+
+        Ok(FunSig {
+            name,
+            inputs,
+            output,
+            span,
+        })
+    }
 
+    /// function_parameters = '(' (parameter (',' parameter)* ','?)? ')'
+    /// parameter = 'mut'? identifier ':' type_specifier
+    pub fn parse_fun_params(&mut self) -> PResult<Vec<FunParam>> {
         self.expect(TokenKind::OpenDelim(Delimiter::Parenthesis))?;
         self.advance();
 
@@ -450,21 +696,16 @@ impl Parser {
         self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
         self.advance(); // Eat ')'
 
-        let output = if self.token.kind == TokenKind::RArrow {
+        Ok(inputs)
+    }
+
+    /// return_type = ('->' type_specifier)?
+    pub fn parse_fun_ret_ty(&mut self) -> PResult<Option<Ty>> {
+        if self.token.kind == TokenKind::RArrow {
             self.advance(); // Eat '->'
-            Some(self.parse_ty()?)
+            Ok(Some(self.parse_ty()?))
         } else {
-            None
-        };
-
-        let end = self.prev_token.span;
-        let span = start.to(end);
-
-        Ok(FunSig {
-            name,
-            inputs,
-            output,
-            span,
-        })
+            Ok(None)
+        }
     }
 }
@@ -1,4 +1,7 @@
-use crate::ast::{Delimiter, Expr, TokenKind, Ty, TyKind};
+use crate::{
+    ast::{Delimiter, Expr, TokenKind, Ty, TyKind},
+    kw::Keyword,
+};
 
 use super::{PResult, Parser};
 
@@ -6,6 +9,10 @@ impl Parser {
     pub fn parse_ty(&mut self) -> PResult<Ty> {
         if self.token.is_open_delim(Delimiter::Bracket) {
             self.parse_ty_array()
+        } else if self.token.is_open_delim(Delimiter::Parenthesis) {
+            self.parse_ty_tuple_or_parens()
+        } else if self.token.is_keyword(Keyword::Fun) {
+            self.parse_ty_fun()
         } else {
             self.parse_ty_ident()
         }
@@ -20,6 +27,90 @@ impl Parser {
         Ok(Ty { kind, span })
     }
 
+    /// Parses a tuple type or a parenthesized type.
+    /// `()` is the unit type, `(T)` is just `T` and `(T,)` is a tuple with one element.
+    fn parse_ty_tuple_or_parens(&mut self) -> PResult<Ty> {
+        debug_assert!(self.token.is_open_delim(Delimiter::Parenthesis));
+
+        let start = self.token.span;
+        self.advance();
+
+        let mut tys = Vec::new();
+        let mut trailing_comma = false;
+        loop {
+            if self.token.is_close_delim(Delimiter::Parenthesis) {
+                break;
+            }
+
+            tys.push(self.parse_ty()?);
+
+            trailing_comma = self.token.kind == TokenKind::Comma;
+            if !trailing_comma {
+                break;
+            }
+
+            self.advance(); // eat comma
+        }
+
+        self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
+        self.advance();
+
+        let span = start.to(self.prev_token.span);
+
+        if tys.len() == 1 && !trailing_comma {
+            let ty = tys.pop().unwrap();
+            return Ok(Ty {
+                kind: ty.kind,
+                span,
+            });
+        }
+
+        let kind = TyKind::Tuple(tys);
+        Ok(Ty { kind, span })
+    }
+
+    /// Parses a function type.
+    /// FunType = 'fun' '(' ('mut'? Type (',' 'mut'? Type)* ','?)? ')' ('->' Type)?
+    fn parse_ty_fun(&mut self) -> PResult<Ty> {
+        debug_assert!(self.token.is_keyword(Keyword::Fun));
+
+        let start = self.token.span;
+        self.advance(); // eat 'fun'
+
+        self.expect(TokenKind::OpenDelim(Delimiter::Parenthesis))?;
+        self.advance();
+
+        let mut inputs = Vec::new();
+        loop {
+            if self.token.is_close_delim(Delimiter::Parenthesis) {
+                break;
+            }
+
+            let is_mut = if self.token.is_keyword(Keyword::Mut) {
+                self.advance(); // eat 'mut'
+                true
+            } else {
+                false
+            };
+            inputs.push((self.parse_ty()?, is_mut));
+
+            if self.token.kind != TokenKind::Comma {
+                break;
+            }
+
+            self.advance(); // eat comma
+        }
+
+        self.expect(TokenKind::CloseDelim(Delimiter::Parenthesis))?;
+        self.advance();
+
+        let output = self.parse_fun_ret_ty()?.map(Box::new);
+        let span = start.to(self.prev_token.span);
+
+        let kind = TyKind::Fun(inputs, output);
+        Ok(Ty { kind, span })
+    }
+
     fn parse_ty_array(&mut self) -> PResult<Ty> {
         debug_assert!(self.token.is_open_delim(Delimiter::Bracket));
 
@@ -9,6 +9,8 @@ pub enum Fixity {
     Left,
     /// The operator is right-associative
     Right,
+    /// The operator is not associative
+    None,
 }
 
 /// Associative operator with precedence.
@@ -58,6 +60,10 @@ pub enum AssocOp {
     AssignOp(BinOpToken),
     /// `as`
     As,
+    /// `..` range
+    DotDot,
+    /// `..=` range
+    DotDotEq,
 }
 
 impl AssocOp {
@@ -90,6 +96,8 @@ impl AssocOp {
             TokenKind::Ne => Some(NotEqual),
             TokenKind::AndAnd => Some(LAnd),
             TokenKind::OrOr => Some(LOr),
+            TokenKind::DotDot => Some(DotDot),
+            TokenKind::DotDotEq => Some(DotDotEq),
             _ => None,
         }
     }
@@ -98,16 +106,17 @@ impl AssocOp {
     pub fn precedence(&self) -> usize {
         use AssocOp::*;
         match *self {
-            As => 11,
-            Multiply | Divide | Modulus => 10,
-            Add | Subtract => 9,
-            ShiftLeft | ShiftRight => 8,
-            BitAnd => 7,
-            BitXor => 6,
-            BitOr => 5,
-            Less | Greater | LessEqual | GreaterEqual | Equal | NotEqual => 4,
-            LAnd => 3,
-            LOr => 2,
+            As => 12,
+            Multiply | Divide | Modulus => 11,
+            Add | Subtract => 10,
+            ShiftLeft | ShiftRight => 9,
+            BitAnd => 8,
+            BitXor => 7,
+            BitOr => 6,
+            Less | Greater | LessEqual | GreaterEqual | Equal | NotEqual => 5,
+            LAnd => 4,
+            LOr => 3,
+            DotDot | DotDotEq => 2,
             Assign | AssignOp(_) => 1,
         }
     }
@@ -121,6 +130,7 @@ impl AssocOp {
             Multiply | Divide | Modulus | Add | Subtract | ShiftLeft | ShiftRight | BitAnd
             | BitXor | BitOr | Less | Greater | LessEqual | GreaterEqual | Equal | NotEqual
             | LAnd | LOr | As => Fixity::Left,
+            DotDot | DotDotEq => Fixity::None,
         }
     }
 
@@ -145,7 +155,7 @@ impl AssocOp {
             BitOr => Some(BinOpKind::BitOr),
             LAnd => Some(BinOpKind::And),
             LOr => Some(BinOpKind::Or),
-            Assign | AssignOp(_) | As => None,
+            Assign | AssignOp(_) | As | DotDot | DotDotEq => None,
         }
     }
 }
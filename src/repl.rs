@@ -0,0 +1,314 @@
+//! The interactive mode, started with `unbox repl`.
+//!
+//! Every input is appended to the history of the session, and the history is the source code
+//! that errors point to. This way, an error raised by a function declared by an earlier input
+//! still points to the right place.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    sync::Arc,
+};
+
+use crate::{
+    ast::StmtKind,
+    interpreter::{resolver::ModuleResolver, terminate_last_statement, Repl},
+    parse::{errors::PError, lexer, parser},
+    session::{BytePos, Session, SourceFile},
+};
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+/// Reads and runs the inputs of the user from `input` until its end or `:quit`. The prompts
+/// and the results are written to `output`. The errors of the inputs are printed to the standard
+/// output, like those of a program run from a file, and the errors of the commands to the
+/// standard error.
+pub fn run(
+    resolver: ModuleResolver,
+    is_verbose: bool,
+    mut input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "Pandora {} REPL, type :help for help.",
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    let mut repl = Repl::new(resolver, is_verbose);
+    let mut history = String::new();
+    // The lines of an input that is not complete yet.
+    let mut buffer = String::new();
+    loop {
+        if buffer.is_empty() {
+            write!(output, "{}", PROMPT)?;
+        } else {
+            write!(output, "{}", CONTINUATION_PROMPT)?;
+        }
+        output.flush()?;
+
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) => {
+                writeln!(output)?;
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error: Failed to read input: {e}");
+                break;
+            }
+        }
+
+        if buffer.is_empty() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(command) = trimmed.strip_prefix(':') {
+                if !run_command(&mut repl, &mut history, command, output)? {
+                    break;
+                }
+                continue;
+            }
+        }
+
+        buffer.push_str(&line);
+        if eval(&mut repl, &mut history, &buffer, true, output)? {
+            buffer.clear();
+        }
+    }
+    Ok(())
+}
+
+/// Runs a meta-command. Returns `false` if the session should end.
+fn run_command(
+    repl: &mut Repl,
+    history: &mut String,
+    command: &str,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    let (name, arg) = match command.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (command, ""),
+    };
+
+    match name {
+        "quit" | "q" => return Ok(false),
+        "help" | "h" => help(output)?,
+        "env" => repl.print_env(output)?,
+        "type" | "t" if !arg.is_empty() => eval_type(repl, history, arg, output)?,
+        "load" | "l" if !arg.is_empty() => load(repl, history, arg, output)?,
+        "type" | "t" | "load" | "l" => {
+            eprintln!("Error: Missing argument for ':{}'", name);
+        }
+        _ => eprintln!(
+            "Error: Unknown command ':{}', type :help for the list of commands",
+            name
+        ),
+    }
+
+    Ok(true)
+}
+
+/// Appends an input to the history and runs it. If `can_continue` is set and the input ends
+/// inside a delimiter, nothing is done and `false` is returned, so that more lines can be
+/// read.
+fn eval(
+    repl: &mut Repl,
+    history: &mut String,
+    input: &str,
+    can_continue: bool,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    let start = history.len() as BytePos;
+    let mut contents = format!("{}{}", history, input);
+    terminate_last_statement(&mut contents, start as usize);
+
+    let (contents, mut session) = new_session(repl, contents);
+    let tokens = lexer::lex_token_tree_from(&contents, start, &mut session);
+    if let Err(errors) = tokens {
+        let is_unclosed = errors
+            .iter()
+            .all(|err| matches!(err, PError::UnclosedDelimiter { .. }));
+        if can_continue && is_unclosed && !session.has_error() {
+            return Ok(false);
+        }
+    }
+
+    *history = contents.to_string();
+    // The lexer reports its own errors.
+    if session.has_error() {
+        return Ok(true);
+    }
+
+    if let Some(ast) = parser::parse_from(&contents, start, &mut session) {
+        repl.eval(&ast, &session, output)?;
+    }
+    Ok(true)
+}
+
+/// Prints the type of an expression, without evaluating it.
+fn eval_type(
+    repl: &mut Repl,
+    history: &mut String,
+    expr: &str,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let start = history.len() as BytePos;
+    let (contents, mut session) = new_session(repl, format!("{}{};\n", history, expr));
+    *history = contents.to_string();
+
+    let ast = match parser::parse_from(&contents, start, &mut session) {
+        Some(ast) => ast,
+        None => return Ok(()),
+    };
+
+    match ast.stmts.as_slice() {
+        [stmt] => match &stmt.kind {
+            StmtKind::Expr(expr) => repl.print_type(expr, &session, output)?,
+            _ => eprintln!("Error: ':type' expects an expression"),
+        },
+        _ => eprintln!("Error: ':type' expects an expression"),
+    }
+    Ok(())
+}
+
+/// Runs the content of a file as if it was typed, e.g. to use the functions of a library
+/// without its name as a prefix.
+fn load(
+    repl: &mut Repl,
+    history: &mut String,
+    path: &str,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let extensions: &[&str] = if crate::is_genz_mode() {
+        &[".unbx", ".unbxx"]
+    } else {
+        &[".box", ".boxx"]
+    };
+    if !extensions.iter().any(|ext| path.ends_with(ext)) {
+        eprintln!(
+            "Error: File to load must have a {} extension",
+            extensions.join(" or ")
+        );
+        return Ok(());
+    }
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Error: Failed to read file '{}': {e}", path);
+            return Ok(());
+        }
+    };
+
+    eval(repl, history, &format!("{}\n", contents), false, output)?;
+    Ok(())
+}
+
+fn new_session(repl: &Repl, contents: String) -> (Arc<String>, Session) {
+    let contents = Arc::new(contents);
+    let file = SourceFile::new("<repl>", Arc::clone(&contents));
+    (contents, repl.session(file))
+}
+
+fn help(output: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        output,
+        "Type statements or expressions to run them. The value of an expression is printed,"
+    )?;
+    writeln!(
+        output,
+        "and a statement is continued on the next lines until its delimiters are closed."
+    )?;
+    writeln!(output, "Commands:")?;
+    writeln!(
+        output,
+        "  :type <expr>, :t   Print the type of an expression without evaluating it"
+    )?;
+    writeln!(
+        output,
+        "  :env               Print the declared libraries, types, functions and variables"
+    )?;
+    writeln!(
+        output,
+        "  :load <file>, :l   Run the statements of a file, e.g. a .boxx library"
+    )?;
+    writeln!(output, "  :help, :h          Display this help message")?;
+    writeln!(
+        output,
+        "  :quit, :q          Exit the REPL (or press Ctrl+D)"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the REPL on the given lines, returning what it writes after its banner.
+    fn run_script(input: &str) -> String {
+        let mut output = vec![];
+        run(
+            ModuleResolver::default(),
+            false,
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let (_banner, rest) = output.split_once('\n').unwrap();
+        rest.to_string()
+    }
+
+    #[test]
+    fn values_of_expressions_are_printed() {
+        let output = run_script("1 + 2\nset x: int = 4;\nx * 10\n\"a\" + \"b\";\n");
+        assert_eq!(output, ">> 3\n>> >> 40\n>> \"ab\"\n>> \n");
+    }
+
+    #[test]
+    fn expressions_may_end_with_a_comment() {
+        let output = run_script("1 + 2 // add\n3 * /* mul */ 4 /* done */\n");
+        assert_eq!(output, ">> 3\n>> 12\n>> \n");
+    }
+
+    #[test]
+    fn unclosed_delimiters_continue_on_the_next_lines() {
+        let output = run_script(
+            "fun sum(a: int,\nb: int) -> int {\nwhen a > 0 {\nyeet a + b;\n}\nyeet b;\n}\nsum(1, 2)\n",
+        );
+        assert_eq!(output, ">> .. .. .. .. .. .. >> 3\n>> \n");
+    }
+
+    #[test]
+    fn type_prints_the_type_without_evaluating() {
+        let output = run_script("set mut x: int = 1;\n:type x + 1\n:t (x, [true])\nx\n");
+        assert_eq!(output, ">> >> int\n>> (int, [bool; 1])\n>> 1\n>> \n");
+    }
+
+    #[test]
+    fn env_prints_the_declarations() {
+        let output = run_script(
+            "add math;\nstruct P { x: int }\nfun f(a: int) -> int { yeet a; }\nset mut x: int = 1;\nset x: bool = true;\nset y: str;\n:env\n",
+        );
+        assert_eq!(
+            output,
+            ">> >> >> >> >> >> >> add math;\nstruct P\nf: fun(int) -> int\nx: bool = true\ny: str\n>> \n"
+        );
+    }
+
+    #[test]
+    fn load_runs_a_file_as_if_it_was_typed() {
+        let dir = std::env::temp_dir().join(format!("pandora-repl-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.boxx");
+        fs::write(&path, "fun twice(x: int) -> int {\n    yeet x * 2;\n}\n").unwrap();
+
+        let output = run_script(&format!(":load {}\ntwice(21)\n", path.display()));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output, ">> >> 42\n>> \n");
+    }
+}
@@ -1,27 +1,135 @@
-use std::sync::Arc;
+use std::{cell::RefCell, rc::Rc, sync::Arc};
 
-use miette::NamedSource;
+use miette::{MietteError, MietteSpanContents, NamedSource, SourceCode, SourceSpan, SpanContents};
 
-use crate::{ErrorHandler, ErrorType};
+use crate::{span_encoding::split_source_offset, ErrorHandler, ErrorType};
 
 #[derive(Debug)]
 pub struct Session {
     pub error_type: ErrorType,
     pub error_handler: ErrorHandler,
+    /// The file being parsed.
+    pub file: FileId,
 }
 
 pub type BytePos = u32;
 
 pub type SourceFile = NamedSource<Arc<String>>;
 
+/// The id of a file in a [`SourceMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    /// The file of the program being run.
+    pub const MAIN: FileId = FileId(0);
+}
+
+/// The files of a program: the program being run, then the libraries it adds. A span knows the
+/// id of its file here, so that an error is shown in the file it was raised in.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    files: Rc<RefCell<Vec<Arc<SourceFile>>>>,
+}
+
+impl SourceMap {
+    pub fn new(main: Arc<SourceFile>) -> Self {
+        Self {
+            files: Rc::new(RefCell::new(vec![main])),
+        }
+    }
+
+    /// Adds a file, or returns the id of the same file if it was already added.
+    pub fn add_file(&self, file: Arc<SourceFile>) -> FileId {
+        let mut files = self.files.borrow_mut();
+        let same_file = files
+            .iter()
+            .position(|added| added.name() == file.name() && added.inner() == file.inner());
+        match same_file {
+            Some(index) => FileId(index as u32),
+            None => {
+                files.push(file);
+                FileId(files.len() as u32 - 1)
+            }
+        }
+    }
+
+    /// Replaces the program being run, e.g. with everything typed so far in the REPL.
+    pub fn set_main_file(&self, file: Arc<SourceFile>) {
+        self.files.borrow_mut()[FileId::MAIN.0 as usize] = file;
+    }
+
+    pub fn file(&self, id: FileId) -> Arc<SourceFile> {
+        Arc::clone(&self.files.borrow()[id.0 as usize])
+    }
+
+    /// The files added so far, to show the source code of a report.
+    pub fn files(&self) -> SourceFiles {
+        SourceFiles(self.files.borrow().clone())
+    }
+}
+
+/// The files of a [`SourceMap`] at some point. It reads the spans of a report in the file they
+/// belong to.
+#[derive(Debug, Clone)]
+pub struct SourceFiles(Vec<Arc<SourceFile>>);
+
+impl SourceCode for SourceFiles {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let (file, offset) = split_source_offset(span.offset());
+        let file = self
+            .0
+            .get(file.0 as usize)
+            .ok_or(MietteError::OutOfBounds)?;
+        let contents = file.read_span(
+            &(offset, span.len()).into(),
+            context_lines_before,
+            context_lines_after,
+        )?;
+        // The span of the contents is given back with the file id, like the labels.
+        let file_start = span.offset() - offset;
+        Ok(Box::new(MietteSpanContents::new_named(
+            file.name().to_string(),
+            contents.data(),
+            (file_start + contents.span().offset(), contents.span().len()).into(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
 impl Session {
     pub fn new(file: Arc<SourceFile>) -> Self {
+        Self::with_error_handler(ErrorHandler::new(SourceMap::new(file)), FileId::MAIN)
+    }
+
+    /// Creates a session whose errors are collected instead of being printed.
+    pub fn new_collecting(file: Arc<SourceFile>) -> Self {
+        let error_handler = ErrorHandler::new_collecting(SourceMap::new(file));
+        Self::with_error_handler(error_handler, FileId::MAIN)
+    }
+
+    /// Creates a session parsing the given file of the error handler's source map, e.g. a
+    /// library added by the program, whose errors are reported with the program's.
+    pub fn with_error_handler(error_handler: ErrorHandler, file: FileId) -> Self {
         Self {
             error_type: ErrorType::NoError,
-            error_handler: ErrorHandler::new(Arc::clone(&file)),
+            error_handler,
+            file,
         }
     }
 
+    /// Returns the source code of the file being parsed.
+    pub fn contents(&self) -> Arc<String> {
+        Arc::clone(self.error_handler.source_map.file(self.file).inner())
+    }
+
     pub fn set_error(&mut self, error_type: ErrorType) {
         self.error_type = error_type;
     }
@@ -3,27 +3,42 @@ use std::fmt::{Display, Formatter};
 
 use miette::SourceSpan;
 
-use crate::session::BytePos;
+use crate::session::{BytePos, FileId};
 
 pub const DUMMY_SP: Span = Span {
     offset: 0,
     length: 0,
+    file: FileId::MAIN,
 };
 
+/// The file of a span is kept in the high bits of the offset given to miette, so that the
+/// `SourceFiles` of a report can read it from the right file. A 64-bit offset is split in two
+/// halves. On smaller targets, files are limited to 16 MiB and a program to 256 files.
+#[cfg(target_pointer_width = "64")]
+const FILE_ID_SHIFT: u32 = 32;
+#[cfg(not(target_pointer_width = "64"))]
+const FILE_ID_SHIFT: u32 = 24;
+
+/// The bits of an offset given to miette that hold the offset in the file.
+const FILE_OFFSET_MASK: usize = (1 << FILE_ID_SHIFT) - 1;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Span {
     /// The start of the span.
     pub offset: BytePos,
     /// The total length of the span
     pub length: usize,
+    /// The file of the span in the source map.
+    pub file: FileId,
 }
 
 impl Span {
     /// Creates a new span from a start (inclusive) and end (exclusive) position.
-    pub fn new(start: BytePos, end: BytePos) -> Self {
+    pub fn new(start: BytePos, end: BytePos, file: FileId) -> Self {
         Span {
             offset: start,
             length: (end - start) as usize,
+            file,
         }
     }
 
@@ -31,11 +46,15 @@ impl Span {
         Span {
             offset: span.end(),
             length: 1,
+            file: span.file,
         }
     }
 
     pub fn to_source_span(&self) -> SourceSpan {
-        (self.offset as usize, self.length).into()
+        debug_assert!(self.offset as usize <= FILE_OFFSET_MASK);
+        debug_assert!((self.file.0 as usize) < 1 << (usize::BITS - FILE_ID_SHIFT));
+        let offset = ((self.file.0 as usize) << FILE_ID_SHIFT) | self.offset as usize;
+        (offset, self.length).into()
     }
 
     /// Returns the end of the span (exclusive).
@@ -52,10 +71,18 @@ impl Span {
         Span {
             offset: self.offset,
             length: (other.end() - self.offset) as usize,
+            file: self.file,
         }
     }
 }
 
+/// Splits an offset given to miette by `Span::to_source_span` into its file and its offset in
+/// the file.
+pub fn split_source_offset(offset: usize) -> (FileId, usize) {
+    let file = FileId((offset >> FILE_ID_SHIFT) as u32);
+    (file, offset & FILE_OFFSET_MASK)
+}
+
 impl Display for Span {
     /// Formats the span as `[offset (inclusive), end (exclusive)]`.
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
@@ -72,3 +99,24 @@ pub struct Spanned<T> {
 pub fn respan<T>(node: T, span: Span) -> Spanned<T> {
     Spanned { node, span }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_offsets_keep_the_file_of_the_span() {
+        let span = Span::new(1234, 1240, FileId(3));
+        let source_span = span.to_source_span();
+        assert_eq!(source_span.len(), 6);
+        assert_eq!(split_source_offset(source_span.offset()), (FileId(3), 1234));
+
+        let last = Span::new(
+            FILE_OFFSET_MASK as BytePos,
+            FILE_OFFSET_MASK as BytePos,
+            FileId(1),
+        );
+        let offset = last.to_source_span().offset();
+        assert_eq!(split_source_offset(offset), (FileId(1), FILE_OFFSET_MASK));
+    }
+}
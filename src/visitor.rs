@@ -1,4 +1,7 @@
-use crate::ast::{Expr, ExprKind, Fun, FunSig, Ident, Local, LocalKind, Stmt, StmtKind, Ty};
+use crate::ast::{
+    Arm, EnumDef, Expr, ExprKind, Fun, FunSig, Ident, Import, Local, LocalKind, Pat, PatKind, Stmt,
+    StmtKind, StructDef, Ty,
+};
 
 pub trait Visitor<'ast>: Sized {
     fn visit_expr(&mut self, expr: &'ast Expr) {
@@ -9,6 +12,10 @@ pub trait Visitor<'ast>: Sized {
         walk_ty(self, ty)
     }
 
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        walk_pat(self, pat)
+    }
+
     fn visit_stmt_block(&mut self, stmts: &'ast Vec<Box<Stmt>>) {
         walk_stmt_block(self, stmts)
     }
@@ -54,8 +61,24 @@ pub trait Visitor<'ast>: Sized {
         walk_stmt_empty(self);
     }
 
-    fn visit_stmt_import(&mut self, ident: &'ast Ident) {
-        walk_stmt_import(self, ident);
+    fn visit_stmt_import(&mut self, import: &'ast Import) {
+        walk_stmt_import(self, import);
+    }
+
+    fn visit_stmt_struct_decl(&mut self, def: &'ast StructDef) {
+        walk_stmt_struct_decl(self, def);
+    }
+
+    fn visit_stmt_enum_decl(&mut self, def: &'ast EnumDef) {
+        walk_stmt_enum_decl(self, def);
+    }
+
+    fn visit_stmt_match(&mut self, scrutinee: &'ast Expr, arms: &'ast Vec<Arm>) {
+        walk_stmt_match(self, scrutinee, arms);
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        walk_arm(self, arm);
     }
 }
 
@@ -89,16 +112,55 @@ pub fn walk_stmt<'ast, V: Visitor<'ast>>(visitor: &mut V, stmt: &'ast Stmt) {
         }
         StmtKind::Break => {}
         StmtKind::Continue => {}
-        StmtKind::Import(name) => {
-            visitor.visit_stmt_import(name);
+        StmtKind::Import(import) => {
+            visitor.visit_stmt_import(import);
+        }
+        StmtKind::StructDecl(def) => {
+            visitor.visit_stmt_struct_decl(def);
+        }
+        StmtKind::EnumDecl(def) => {
+            visitor.visit_stmt_enum_decl(def);
+        }
+        StmtKind::Match(scrutinee, arms) => {
+            visitor.visit_stmt_match(scrutinee, arms);
         }
     }
 }
 
-pub fn walk_stmt_import<'ast, V: Visitor<'ast>>(_visitor: &mut V, _ident: &'ast Ident) {}
+pub fn walk_stmt_enum_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, def: &'ast EnumDef) {
+    for variant in &def.variants {
+        for ty in &variant.fields {
+            visitor.visit_ty(ty);
+        }
+    }
+}
+
+pub fn walk_stmt_match<'ast, V: Visitor<'ast>>(
+    visitor: &mut V,
+    scrutinee: &'ast Expr,
+    arms: &'ast Vec<Arm>,
+) {
+    visitor.visit_expr(scrutinee);
+    for arm in arms {
+        visitor.visit_arm(arm);
+    }
+}
+
+pub fn walk_arm<'ast, V: Visitor<'ast>>(visitor: &mut V, arm: &'ast Arm) {
+    visitor.visit_pat(&arm.pat);
+    visitor.visit_stmt(&arm.body);
+}
+
+pub fn walk_stmt_struct_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, def: &'ast StructDef) {
+    for field in &def.fields {
+        visitor.visit_ty(&field.ty);
+    }
+}
+
+pub fn walk_stmt_import<'ast, V: Visitor<'ast>>(_visitor: &mut V, _import: &'ast Import) {}
 
 pub fn walk_stmt_func_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, fun: &'ast Fun) {
-    let Fun { sig, body } = fun;
+    let Fun { sig, body, .. } = fun;
     let FunSig {
         name: _,
         inputs,
@@ -171,13 +233,17 @@ pub fn walk_stmt_empty<'ast, V: Visitor<'ast>>(_visitor: &mut V) {}
 pub fn walk_stmt_var<'ast, V: Visitor<'ast>>(visitor: &mut V, local: &'ast Local) {
     let Local {
         is_mut: _,
-        ident: _,
+        pat,
         ty,
         kind,
         span: _,
     } = local;
 
-    visitor.visit_ty(ty);
+    visitor.visit_pat(pat);
+
+    if let Some(ty) = ty {
+        visitor.visit_ty(ty);
+    }
 
     match kind {
         LocalKind::Init(expr) => {
@@ -189,6 +255,18 @@ pub fn walk_stmt_var<'ast, V: Visitor<'ast>>(visitor: &mut V, local: &'ast Local
 
 pub fn walk_ty<'ast, V: Visitor<'ast>>(_visitor: &mut V, _ty: &'ast Ty) {}
 
+pub fn walk_pat<'ast, V: Visitor<'ast>>(visitor: &mut V, pat: &'ast Pat) {
+    match &pat.kind {
+        PatKind::Wild | PatKind::Ident(_) => {}
+        PatKind::Tuple(pats) | PatKind::Variant(_, _, pats) => {
+            for pat in pats {
+                visitor.visit_pat(pat);
+            }
+        }
+        PatKind::Lit(expr) => visitor.visit_expr(expr),
+    }
+}
+
 pub fn walk_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, expression: &'ast Expr) {
     let Expr { kind, span: _ } = expression;
 
@@ -244,5 +322,36 @@ pub fn walk_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, expression: &'ast Expr
             visitor.visit_expr(element);
             visitor.visit_expr(count);
         }
+        ExprKind::Range(start, end, _) => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+        ExprKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        ExprKind::TupleField(tuple, _index, _) => {
+            visitor.visit_expr(tuple);
+        }
+        ExprKind::Struct(_name, fields) => {
+            for field in fields {
+                visitor.visit_expr(&field.expr);
+            }
+        }
+        ExprKind::Variant(_enum_name, _variant, args) => {
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Closure(closure) => {
+            for input in &closure.inputs {
+                visitor.visit_ty(&input.ty);
+            }
+            if let Some(output) = &closure.output {
+                visitor.visit_ty(output);
+            }
+            visitor.visit_stmt(&closure.body);
+        }
     }
 }
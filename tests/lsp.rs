@@ -0,0 +1,267 @@
+//! Drives `unbox lsp` over its standard input and output, like an editor does.
+
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    process::{self, Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+/// How long to wait for a message before the server is considered stuck.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const URI: &str = "file:///pandora/main.box";
+
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_unbox"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("the server should start");
+        let stdin = server.stdin.take().unwrap();
+        let stdout = server.stdout.take().unwrap();
+
+        // The messages are read on another thread so that a stuck server fails the test
+        // instead of blocking it.
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut output) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Client {
+            server,
+            stdin,
+            messages,
+            next_id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let content = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Sends a request and returns the result of its response. The notifications sent in
+    /// between are skipped.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive(method);
+            if message["id"] == id {
+                return message["result"].clone();
+            }
+        }
+    }
+
+    /// Opens a document and returns the diagnostics published for it.
+    fn open(&mut self, text: &str) -> Vec<Value> {
+        self.open_uri(URI, text)
+    }
+
+    /// Opens the document with the given URI, like [`Client::open`].
+    fn open_uri(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "pandora", "version": 1, "text": text },
+            }),
+        );
+        loop {
+            let message = self.receive("textDocument/didOpen");
+            if message["method"] == "textDocument/publishDiagnostics" {
+                assert_eq!(message["params"]["uri"], uri);
+                return message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    fn receive(&self, waiting_for: &str) -> Value {
+        self.messages
+            .recv_timeout(TIMEOUT)
+            .unwrap_or_else(|_| panic!("the server did not answer `{}`", waiting_for))
+    }
+}
+
+impl Drop for Client {
+    /// Stops a server that is stuck or was not asked to exit, so that it does not outlive the
+    /// test.
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+    }
+}
+
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok()?;
+        }
+    }
+    let mut content = vec![0; length];
+    input.read_exact(&mut content).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn position(line: u64, character: u64) -> Value {
+    position_in(URI, line, character)
+}
+
+fn position_in(uri: &str, line: u64, character: u64) -> Value {
+    json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+}
+
+#[test]
+fn answers_requests_about_a_document() {
+    let mut client = Client::start();
+    let result = client.request("initialize", json!({ "capabilities": {} }));
+    assert!(result["capabilities"].is_object(), "{}", result);
+    client.notify("initialized", json!({}));
+
+    let diagnostics = client.open("set count: int = 1;\nset total: int = count + 1;\n");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let hover = client.request("textDocument/hover", position(1, 19));
+    let contents = hover["contents"]["value"].as_str().unwrap_or_default();
+    assert!(contents.contains("count: int"), "{}", hover);
+
+    let definition = client.request("textDocument/definition", position(1, 19));
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 0, "character": 4 })
+    );
+
+    let completion = client.request("textDocument/completion", position(2, 0));
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item["label"].as_str())
+        .collect();
+    assert!(
+        labels.contains(&"count") && labels.contains(&"total"),
+        "{:?}",
+        labels
+    );
+
+    let diagnostics = client.open("set count: int = true;\n");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}
+
+#[test]
+fn completes_the_functions_of_an_added_library() {
+    let mut client = Client::start();
+    client.request("initialize", json!({ "capabilities": {} }));
+
+    // The alias is followed back to the path of the library, and the incomplete call does not
+    // hide the declarations of the last version that could be parsed.
+    client.open("add math as m;\n");
+    client.open("add math as m;\nm.");
+    let completion = client.request("textDocument/completion", position(1, 2));
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item["label"].as_str())
+        .collect();
+    assert!(labels.contains(&"sqrt"), "{:?}", labels);
+    assert!(!labels.contains(&"println"), "{:?}", labels);
+}
+
+#[test]
+fn completes_libraries_next_to_documents_with_encoded_paths() {
+    let dir = env::temp_dir().join(format!("pandora lsp-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("mylib.boxx"), "fun hi() {}\n").unwrap();
+    // Editors percent-encode the characters of the path that are not allowed in a URI.
+    let uri = format!(
+        "file://{}/main.box",
+        dir.display().to_string().replace(' ', "%20")
+    );
+
+    let mut client = Client::start();
+    client.request("initialize", json!({ "capabilities": {} }));
+    client.open_uri(&uri, "add mylib;\nmylib.");
+    let completion = client.request("textDocument/completion", position_in(&uri, 1, 6));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item["label"].as_str())
+        .collect();
+    assert_eq!(labels, ["hi"]);
+}
+
+#[test]
+fn keeps_answering_after_malformed_documents() {
+    let mut client = Client::start();
+    client.request("initialize", json!({ "capabilities": {} }));
+
+    for text in [
+        "struct P { x: int y: int }",
+        "set p: P = P { x: 1 y: 2 };",
+        "enum E { A(int int), B }",
+        "match 1 { 1 {} }",
+        "add math.{sqrt pow};",
+        "fun f( {",
+        "set x: int = ",
+        "}}}",
+    ] {
+        let diagnostics = client.open(text);
+        assert!(!diagnostics.is_empty(), "{}", text);
+        // Requests about a document that does not parse are still answered.
+        client.request("textDocument/hover", position(0, 1));
+        client.request("textDocument/completion", position(0, 1));
+    }
+
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}